@@ -1,3 +1,7 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, VecDeque};
+
 #[derive(Debug, Clone, PartialEq)]
 #[allow(dead_code)]
 enum Genero {
@@ -77,6 +81,176 @@ impl Playlist {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(dead_code)]
+enum ModoRepeticion {
+    Ninguna,
+    Una,
+    Todas,
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+struct Reproductor {
+    canciones: Vec<Cancion>,
+    orden: Vec<usize>,
+    cursor: Option<usize>,
+    sonando: Option<Cancion>,
+    repeticion: ModoRepeticion,
+    aleatorio: bool,
+    cola: VecDeque<Cancion>,
+    historial: Vec<Cancion>,
+    rng: StdRng,
+}
+
+#[allow(dead_code)]
+impl Reproductor {
+    pub fn new(playlist: &Playlist, semilla: u64) -> Self {
+        Reproductor {
+            canciones: playlist.canciones.clone(),
+            orden: (0..playlist.canciones.len()).collect(),
+            cursor: None,
+            sonando: None,
+            repeticion: ModoRepeticion::Ninguna,
+            aleatorio: false,
+            cola: VecDeque::new(),
+            historial: vec![],
+            rng: StdRng::seed_from_u64(semilla),
+        }
+    }
+
+    pub fn actual(&self) -> Option<&Cancion> {
+        self.sonando.as_ref()
+    }
+
+    pub fn historial(&self) -> &[Cancion] {
+        &self.historial
+    }
+
+    pub fn set_repeticion(&mut self, modo: ModoRepeticion) {
+        self.repeticion = modo;
+    }
+
+    pub fn set_aleatorio(&mut self, aleatorio: bool) {
+        self.aleatorio = aleatorio;
+        let actual = self.cursor.map(|c| self.orden[c]);
+        if aleatorio {
+            // La cancion que esta sonando queda primera y el resto se mezcla detras
+            let pendientes: Vec<usize> = (0..self.canciones.len())
+                .filter(|i| Some(*i) != actual)
+                .collect();
+            let ultimo_artista = actual.map(|i| self.canciones[i].artista.clone());
+            let mezcla = self.mezclar(pendientes, ultimo_artista.as_deref());
+            self.orden = actual.into_iter().chain(mezcla).collect();
+            self.cursor = actual.map(|_| 0);
+        } else {
+            self.orden = (0..self.canciones.len()).collect();
+            self.cursor = actual;
+        }
+    }
+
+    pub fn agregar_a_continuacion(&mut self, cancion: Cancion) {
+        self.cola.push_back(cancion);
+    }
+
+    pub fn siguiente(&mut self) -> Option<&Cancion> {
+        // Lo pedido con "a continuacion" suena antes que la repeticion de la actual
+        if let Some(cancion) = self.cola.pop_front() {
+            return self.reproducir(cancion);
+        }
+        if self.repeticion == ModoRepeticion::Una
+            && let Some(c) = self.cursor
+        {
+            let cancion = self.canciones[self.orden[c]].clone();
+            return self.reproducir(cancion);
+        }
+        if self.orden.is_empty() {
+            return None;
+        }
+
+        let proximo = match self.cursor {
+            None => 0,
+            Some(c) if c + 1 < self.orden.len() => c + 1,
+            Some(_) => {
+                if self.repeticion != ModoRepeticion::Todas {
+                    self.sonando = None;
+                    return None;
+                }
+                if self.aleatorio {
+                    let ultimo_artista = self.sonando.as_ref().map(|c| c.artista.clone());
+                    let indices = (0..self.canciones.len()).collect();
+                    self.orden = self.mezclar(indices, ultimo_artista.as_deref());
+                }
+                0
+            }
+        };
+        self.cursor = Some(proximo);
+        let cancion = self.canciones[self.orden[proximo]].clone();
+        self.reproducir(cancion)
+    }
+
+    pub fn anterior(&mut self) -> Option<&Cancion> {
+        let anterior = match self.cursor {
+            Some(c) if c > 0 => c - 1,
+            Some(_) if self.repeticion == ModoRepeticion::Todas => self.orden.len() - 1,
+            _ => return None,
+        };
+        self.cursor = Some(anterior);
+        let cancion = self.canciones[self.orden[anterior]].clone();
+        self.reproducir(cancion)
+    }
+
+    fn reproducir(&mut self, cancion: Cancion) -> Option<&Cancion> {
+        self.historial.push(cancion.clone());
+        self.sonando = Some(cancion);
+        self.sonando.as_ref()
+    }
+
+    fn mezclar(&mut self, mut pendientes: Vec<usize>, ultimo_artista: Option<&str>) -> Vec<usize> {
+        let mut orden = vec![];
+        let mut ultimo = ultimo_artista.map(|a| a.to_string());
+
+        while !pendientes.is_empty() {
+            let mut conteo: HashMap<&str, usize> = HashMap::new();
+            for i in &pendientes {
+                *conteo
+                    .entry(self.canciones[*i].artista.as_str())
+                    .or_insert(0) += 1;
+            }
+
+            // Un candidato sirve si no repite artista y, despues de elegirlo,
+            // todavia se pueden ordenar las restantes sin dos seguidas del mismo artista
+            let restantes = pendientes.len() - 1;
+            let candidatos: Vec<usize> = (0..pendientes.len())
+                .filter(|pos| {
+                    let artista = self.canciones[pendientes[*pos]].artista.as_str();
+                    if Some(artista) == ultimo.as_deref() {
+                        return false;
+                    }
+                    conteo.iter().all(|(a, cantidad)| {
+                        if *a == artista {
+                            cantidad - 1 <= restantes / 2
+                        } else {
+                            *cantidad <= restantes.div_ceil(2)
+                        }
+                    })
+                })
+                .collect();
+
+            // Si no hay forma de evitar la repeticion se elige cualquiera
+            let pos = if candidatos.is_empty() {
+                self.rng.gen_range(0..pendientes.len())
+            } else {
+                candidatos[self.rng.gen_range(0..candidatos.len())]
+            };
+            let elegido = pendientes.remove(pos);
+            ultimo = Some(self.canciones[elegido].artista.clone());
+            orden.push(elegido);
+        }
+        orden
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -214,4 +388,104 @@ mod tests {
         );
         assert!(playlist.obtener_canciones_por_artista("asdf").is_empty());
     }
+
+    fn playlist_para_reproducir(nombre: &str) -> Playlist {
+        let mut playlist = Playlist::new(nombre);
+        playlist.canciones = vec![
+            crear_cancion("Cuello", "Los piojos", Genero::Rock),
+            crear_cancion("Manos", "Los piojos", Genero::Rock),
+            crear_cancion("Ruta 40", "Los piojos", Genero::Rock),
+            crear_cancion("Ojos", "Dolina", Genero::Jazz),
+            crear_cancion("Oreja", "Dolina", Genero::Jazz),
+            crear_cancion("Roxanne", "The police", Genero::Rock),
+        ];
+        playlist
+    }
+
+    #[test]
+    fn test_reproductor_siguiente_y_anterior() {
+        let playlist = playlist_para_reproducir("reproductor 1");
+        let mut reproductor = Reproductor::new(&playlist, 1);
+        assert!(reproductor.actual().is_none());
+        assert!(reproductor.anterior().is_none());
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Cuello");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Manos");
+        assert_eq!(reproductor.anterior().unwrap().titulo, "Cuello");
+        assert_eq!(reproductor.historial().len(), 3);
+    }
+
+    #[test]
+    fn test_reproductor_fin_sin_repeticion() {
+        let playlist = playlist_para_reproducir("reproductor 2");
+        let mut reproductor = Reproductor::new(&playlist, 1);
+        for _ in 0..6 {
+            assert!(reproductor.siguiente().is_some());
+        }
+        assert!(reproductor.siguiente().is_none());
+        assert!(reproductor.actual().is_none());
+    }
+
+    #[test]
+    fn test_reproductor_repetir_una_y_todas() {
+        let playlist = playlist_para_reproducir("reproductor 3");
+        let mut reproductor = Reproductor::new(&playlist, 1);
+        reproductor.siguiente();
+        reproductor.set_repeticion(ModoRepeticion::Una);
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Cuello");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Cuello");
+
+        reproductor.set_repeticion(ModoRepeticion::Todas);
+        for _ in 0..5 {
+            reproductor.siguiente();
+        }
+        assert_eq!(reproductor.actual().unwrap().titulo, "Roxanne");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Cuello");
+        assert_eq!(reproductor.anterior().unwrap().titulo, "Roxanne");
+    }
+
+    #[test]
+    fn test_reproductor_cola_a_continuacion() {
+        let playlist = playlist_para_reproducir("reproductor 4");
+        let mut reproductor = Reproductor::new(&playlist, 1);
+        reproductor.siguiente();
+        reproductor.agregar_a_continuacion(crear_cancion("Coca", "Ale Sanz", Genero::Pop));
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Coca");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Manos");
+
+        // Con repetir una, la cola suena primero y despues vuelve la cancion repetida
+        reproductor.set_repeticion(ModoRepeticion::Una);
+        reproductor.agregar_a_continuacion(crear_cancion("Coca", "Ale Sanz", Genero::Pop));
+        reproductor.agregar_a_continuacion(crear_cancion("Oreja", "Dolina", Genero::Jazz));
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Coca");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Oreja");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Manos");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Manos");
+    }
+
+    #[test]
+    fn test_reproductor_aleatorio_sin_artista_repetido() {
+        let playlist = playlist_para_reproducir("reproductor 5");
+        for semilla in 0..20 {
+            let mut reproductor = Reproductor::new(&playlist, semilla);
+            reproductor.set_aleatorio(true);
+            reproductor.set_repeticion(ModoRepeticion::Todas);
+            let mut artistas = vec![];
+            for _ in 0..18 {
+                artistas.push(reproductor.siguiente().unwrap().artista.clone());
+            }
+            assert!(artistas.windows(2).all(|par| par[0] != par[1]));
+        }
+    }
+
+    #[test]
+    fn test_reproductor_aleatorio_determinista() {
+        let playlist = playlist_para_reproducir("reproductor 6");
+        let mut uno = Reproductor::new(&playlist, 42);
+        let mut otro = Reproductor::new(&playlist, 42);
+        uno.set_aleatorio(true);
+        otro.set_aleatorio(true);
+        for _ in 0..6 {
+            assert_eq!(uno.siguiente().cloned(), otro.siguiente().cloned());
+        }
+    }
 }
@@ -1,6 +1,9 @@
 use crate::own_crates::fecha::Fecha;
-use rand::{Rng, distributions::Alphanumeric};
-use std::{collections::HashMap, fmt};
+use rand::{Rng, SeedableRng, distributions::Alphanumeric, rngs::StdRng};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt, fs,
+};
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 #[allow(dead_code)]
@@ -40,6 +43,20 @@ pub enum ErroresApp {
     CriptoYaExiste,
     CriptoNoExiste,
     RedCriptoInvalida,
+    CantidadInvalida,
+    OrdenInexistente,
+    OrdenNoCancelable,
+    SinLiquidez,
+    FuenteInvalida,
+    SinCotizacion,
+    AsientoDesbalanceado,
+    PrecisionEnUso,
+    LimiteDiarioExcedido,
+    LimiteMensualExcedido,
+    ConfiguracionInvalida,
+    TransaccionInexistente,
+    TransaccionFinalizada,
+    CadenaConPendientes,
 }
 
 impl fmt::Display for ErroresApp {
@@ -69,6 +86,58 @@ impl fmt::Display for ErroresApp {
             ErroresApp::RedCriptoInvalida => {
                 write!(f, "La criptomoneda seleccionada no opera en esa red")
             }
+            ErroresApp::CantidadInvalida => {
+                write!(f, "La cantidad o el precio de la orden no son validos")
+            }
+            ErroresApp::OrdenInexistente => {
+                write!(f, "La orden no existe para ese usuario")
+            }
+            ErroresApp::OrdenNoCancelable => {
+                write!(f, "La orden ya fue completada o cancelada")
+            }
+            ErroresApp::SinLiquidez => {
+                write!(
+                    f,
+                    "No hay ordenes contrarias para ejecutar la orden a mercado"
+                )
+            }
+            ErroresApp::FuenteInvalida => {
+                write!(f, "No se pudo leer la fuente de cotizaciones")
+            }
+            ErroresApp::SinCotizacion => {
+                write!(f, "No hay cotizacion registrada para ese momento")
+            }
+            ErroresApp::AsientoDesbalanceado => {
+                write!(f, "Los movimientos del asiento no suman cero")
+            }
+            ErroresApp::LimiteDiarioExcedido => {
+                write!(
+                    f,
+                    "La operacion supera el limite diario de retiro de su nivel"
+                )
+            }
+            ErroresApp::LimiteMensualExcedido => {
+                write!(
+                    f,
+                    "La operacion supera el limite mensual de retiro de su nivel"
+                )
+            }
+            ErroresApp::ConfiguracionInvalida => {
+                write!(f, "La configuracion de comisiones no es valida")
+            }
+            ErroresApp::TransaccionInexistente => write!(f, "La transaccion no existe"),
+            ErroresApp::TransaccionFinalizada => {
+                write!(f, "La transaccion ya fue confirmada o fallo")
+            }
+            ErroresApp::CadenaConPendientes => {
+                write!(f, "La blockchain tiene transacciones sin confirmar")
+            }
+            ErroresApp::PrecisionEnUso => {
+                write!(
+                    f,
+                    "No se puede cambiar la precision de una moneda con movimientos"
+                )
+            }
         }
     }
 }
@@ -133,19 +202,41 @@ impl<'a> Detalle for DetalleOperacion<'a> {
 #[allow(dead_code)]
 pub struct Operacion<'d> {
     id: String,
-    fecha: Fecha,
+    // Segundos unix, el mismo reloj que usan las cotizaciones y el libro mayor
+    instante: i64,
     usuario: u128,
     tipo: DetalleOperacion<'d>,
+    comision: Option<Comision<'d>>,
+    estado: EstadoOperacion,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct Comision<'c> {
+    concepto: String,
+    moneda: &'c str,
+    monto: f64,
 }
 
 impl<'a> fmt::Display for Operacion<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let comision = match &self.comision {
+            Some(c) => format!(" - Comision: {} {} ({})", c.monto, c.moneda, c.concepto),
+            None => String::new(),
+        };
+        let estado = match self.estado {
+            EstadoOperacion::Completada => "",
+            EstadoOperacion::Pendiente => " - Estado: PENDIENTE",
+            EstadoOperacion::Fallida => " - Estado: FALLIDA",
+        };
         write!(
             f,
-            "Fecha: {} - Usuario: {} - DETALLE -> [{}]",
-            self.fecha,
+            "Fecha: {} - Usuario: {} - DETALLE -> [{}{}{}]",
+            self.fecha(),
             self.usuario,
-            self.tipo.detalle()
+            self.tipo.detalle(),
+            comision,
+            estado
         )
     }
 }
@@ -162,15 +253,20 @@ impl<'a> Operacion<'a> {
             random_str
         }
 
-        let fecha = Fecha::hoy();
         let id = generar_random_id(10);
         Operacion {
             id,
-            fecha,
+            instante: chrono::Utc::now().timestamp(),
             usuario,
             tipo,
+            comision: None,
+            estado: EstadoOperacion::Completada,
         }
     }
+
+    pub fn fecha(&self) -> Fecha {
+        Fecha::desde_instante(self.instante)
+    }
 }
 #[derive(Clone, Copy, Debug, PartialEq)]
 #[allow(dead_code)]
@@ -179,7 +275,7 @@ pub struct Usuario<'c> {
     apellido: &'c str,
     email: &'c str,
     dni: u128,
-    validado: bool,
+    nivel_kyc: NivelKyc,
 }
 
 impl<'c> Usuario<'c> {
@@ -189,34 +285,21 @@ impl<'c> Usuario<'c> {
             apellido,
             email,
             dni,
-            validado: false,
+            nivel_kyc: NivelKyc::Ninguno,
         }
     }
 }
 
+// Foto de los saldos del usuario armada a partir del libro mayor, que es la unica fuente
 #[derive(Clone, Debug, PartialEq)]
 #[allow(dead_code)]
 pub struct Balance<'s> {
     id_usuario: u128,
-    balance_criptos: HashMap<&'s str, f64>,
-    balance_fiat: f64,
-}
-
-impl<'s> Balance<'s> {
-    pub fn new(id_usuario: u128, criptomonedas: Vec<&'s str>) -> Balance<'s> {
-        let balance_criptos: HashMap<&'s str, f64> = criptomonedas
-            .into_iter()
-            .map(|clave| (clave, 0.0))
-            .collect();
-
-        let balance_fiat = 0.0;
-
-        Balance {
-            id_usuario,
-            balance_criptos,
-            balance_fiat,
-        }
-    }
+    balance_criptos: HashMap<&'s str, Monto>,
+    balance_fiat: Monto,
+    // Fondos retenidos por ordenes abiertas, no disponibles para operar
+    criptos_retenidas: HashMap<&'s str, Monto>,
+    fiat_retenido: Monto,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -224,6 +307,8 @@ impl<'s> Balance<'s> {
 pub struct Blockchain<'g> {
     nombre: &'g str,
     prefijo: &'g str,
+    // Bloques que se esperan antes de dar por firme una transaccion
+    confirmaciones_requeridas: u32,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -238,9 +323,18 @@ pub struct Criptomoneda<'f> {
 #[allow(dead_code)]
 pub struct Plataforma<'p> {
     usuarios: HashMap<u128, Usuario<'p>>,
-    balances: HashMap<u128, Balance<'p>>,
     criptomonedas: HashMap<&'p str, Criptomoneda<'p>>,
     operaciones: Vec<Operacion<'p>>,
+    libros: HashMap<&'p str, LibroOrdenes<'p>>,
+    proxima_orden: u64,
+    historial_cotizaciones: HashMap<&'p str, Vec<PuntoCotizacion>>,
+    mayor: LibroMayor<'p>,
+    precisiones: HashMap<&'p str, u32>,
+    limites_kyc: HashMap<NivelKyc, LimitesRetiro>,
+    comisiones: ConfiguracionComisiones<'p>,
+    instante_simulado: Option<i64>,
+    cadenas: HashMap<&'p str, CadenaSimulada>,
+    transacciones: Vec<TransaccionCripto<'p>>,
 }
 
 #[allow(dead_code)]
@@ -249,13 +343,21 @@ impl<'p> Plataforma<'p> {
         let usuarios: HashMap<u128, Usuario<'p>> = HashMap::new();
         let operaciones: Vec<Operacion<'p>> = Vec::new();
         let criptomonedas: HashMap<&'p str, Criptomoneda<'p>> = HashMap::new();
-        let balances: HashMap<u128, Balance<'p>> = HashMap::new();
 
         Plataforma {
             usuarios,
-            balances,
             operaciones,
             criptomonedas,
+            libros: HashMap::new(),
+            proxima_orden: 1,
+            historial_cotizaciones: HashMap::new(),
+            mayor: LibroMayor::default(),
+            precisiones: HashMap::from([(MONEDA_FIAT, DECIMALES_FIAT)]),
+            limites_kyc: HashMap::new(),
+            comisiones: ConfiguracionComisiones::default(),
+            instante_simulado: None,
+            cadenas: HashMap::new(),
+            transacciones: Vec::new(),
         }
     }
 
@@ -266,6 +368,7 @@ impl<'p> Plataforma<'p> {
         let prefijo = criptomoneda.prefijo;
         if !self.criptomoneda_existe_en_sistema(prefijo) {
             self.criptomonedas.insert(prefijo, criptomoneda);
+            self.precisiones.insert(prefijo, DECIMALES_CRIPTO);
             return Ok(prefijo);
         }
         Err(ErroresApp::CriptoYaExiste)
@@ -283,12 +386,7 @@ impl<'p> Plataforma<'p> {
         };
 
         let nuevo_usuario: Usuario = Usuario::new(nombre, apellido, email, dni);
-
-        let criptomonedas_en_sistema = self.recuperar_criptomonedas_en_sistema();
-        let nuevo_balance: Balance = Balance::new(dni, criptomonedas_en_sistema);
-
         self.usuarios.insert(nuevo_usuario.dni, nuevo_usuario);
-        self.balances.insert(nuevo_usuario.dni, nuevo_balance);
 
         return Ok(nuevo_usuario.dni);
     }
@@ -298,23 +396,36 @@ impl<'p> Plataforma<'p> {
         dni: u128,
         cripto: &'p str,
         monto: f64,
-    ) -> Result<Operacion, ErroresApp> {
-        self.usuario_dispone_fondos_cripto(dni, cripto, monto)?;
+    ) -> Result<Operacion<'p>, ErroresApp> {
+        // Realizo chequeos de usuario
+        self.realizar_chequeos_de_usuario(dni, true, false)?;
 
-        // Recupero cotizacion de la criptomoneda
+        // Recupero cotizacion de la criptomoneda y paso los montos a unidades
         let cotizacion = self.recuperar_datos_criptomoneda(cripto)?.cotizacion;
-
-        // Recupero balance cripto del usuario y disminuyo el monto
-        let balance_cripto = self.recuperar_balance_cripto_de_usuario(dni, cripto)?;
-        *balance_cripto -= monto;
-
-        // Modifico balance fiat aumentando el monto
-        let cripto_a_fiat = monto * cotizacion;
-        self.ingresar_fiat(dni, cripto_a_fiat)?;
+        let cantidad = self.a_unidades(cripto, monto)?;
+        let importe = self.a_unidades(MONEDA_FIAT, monto * cotizacion)?;
+        let spread = self.comisiones.spread_venta;
+        let comision = a_unidades(monto * cotizacion * spread, self.precision_de(MONEDA_FIAT));
+        self.verificar_disponible(dni, cripto, cantidad)?;
 
         // Genero detalle y registro nueva operacion
         let detalle = DetalleOperacion::CRIPTOVENDER(cripto, monto, cotizacion);
-        Ok(self.registrar_operacion(dni, detalle))
+        let comision_detalle = self.comision_para("Spread de venta", MONEDA_FIAT, comision);
+        let operacion = self.registrar_operacion_con_comision(dni, detalle, comision_detalle);
+
+        // La mesa de la plataforma recibe la cripto y entrega el fiat menos el spread
+        self.asentar(
+            "Venta de cripto",
+            vec![operacion.id.clone()],
+            vec![
+                (Cuenta::Disponible(dni), cripto, -cantidad),
+                (Cuenta::Mesa, cripto, cantidad),
+                (Cuenta::Mesa, MONEDA_FIAT, -importe),
+                (Cuenta::Disponible(dni), MONEDA_FIAT, importe - comision),
+                (Cuenta::Comisiones, MONEDA_FIAT, comision),
+            ],
+        )?;
+        Ok(operacion)
     }
 
     pub fn comprar_cripto(
@@ -322,26 +433,36 @@ impl<'p> Plataforma<'p> {
         dni: u128,
         cripto: &'p str,
         monto: f64,
-    ) -> Result<Operacion, ErroresApp> {
+    ) -> Result<Operacion<'p>, ErroresApp> {
         // Realizo chequeos de usuario
         self.realizar_chequeos_de_usuario(dni, true, true)?;
-        self.usuario_dispone_fondos_fiat(dni, monto)?;
 
-        // Recupero datos de la criptomoneda
+        // Recupero cotizacion de la criptomoneda y paso los montos a unidades
         let cotizacion = self.recuperar_datos_criptomoneda(cripto)?.cotizacion;
-
-        // Modifico balance fiat aumentando el monto
-        let cripto_a_fiat = monto * cotizacion;
-        let medio = "Balanceo interno";
-        self.retirar_fiat(dni, cripto_a_fiat, medio)?;
-
-        // Recupero balance de la cripto y aumento el monto
-        let balance_cripto = self.recuperar_balance_cripto_de_usuario(dni, cripto)?;
-        *balance_cripto += monto;
+        let cantidad = self.a_unidades(cripto, monto)?;
+        let importe = self.a_unidades(MONEDA_FIAT, monto * cotizacion)?;
+        let spread = self.comisiones.spread_compra;
+        let comision = a_unidades(monto * cotizacion * spread, self.precision_de(MONEDA_FIAT));
+        self.verificar_disponible(dni, MONEDA_FIAT, importe + comision)?;
 
         // Genero detalle y registro nueva operacion
         let detalle = DetalleOperacion::CRIPTOCOMPRAR(cripto, monto, cotizacion);
-        Ok(self.registrar_operacion(dni, detalle))
+        let comision_detalle = self.comision_para("Spread de compra", MONEDA_FIAT, comision);
+        let operacion = self.registrar_operacion_con_comision(dni, detalle, comision_detalle);
+
+        // La mesa de la plataforma recibe el fiat y entrega la cripto
+        self.asentar(
+            "Compra de cripto",
+            vec![operacion.id.clone()],
+            vec![
+                (Cuenta::Disponible(dni), MONEDA_FIAT, -(importe + comision)),
+                (Cuenta::Mesa, MONEDA_FIAT, importe),
+                (Cuenta::Comisiones, MONEDA_FIAT, comision),
+                (Cuenta::Mesa, cripto, -cantidad),
+                (Cuenta::Disponible(dni), cripto, cantidad),
+            ],
+        )?;
+        Ok(operacion)
     }
 
     pub fn retirar_cripto(
@@ -350,31 +471,57 @@ impl<'p> Plataforma<'p> {
         cripto: &'p str,
         monto: f64,
         blockchain: &'p str,
-    ) -> Result<Operacion, ErroresApp> {
+    ) -> Result<Operacion<'p>, ErroresApp> {
         // Chequeo que la criptomoneda opere en la blockchain ingresada
         if !self.criptomoneda_opera_en_blockchain(cripto, blockchain)? {
             return Err(ErroresApp::RedCriptoInvalida);
         }
         self.realizar_chequeos_de_usuario(dni, true, true)?;
-        self.usuario_dispone_fondos_cripto(dni, cripto, monto)?;
+        let cantidad = self.a_unidades(cripto, monto)?;
 
-        // Recupero cotizacion de la cripto
+        // Recupero cotizacion de la cripto y controlo el limite del nivel KYC
         let cotizacion = self.recuperar_datos_criptomoneda(cripto)?.cotizacion;
+        self.verificar_limites_de_retiro(dni, true, monto * cotizacion)?;
 
-        // Recupero balance mutable de usuario
-        let balance_cripto = self.recuperar_balance_cripto_de_usuario(dni, cripto)?;
-        // Modifico balance cripto disminuyendo el monto
-        *balance_cripto -= monto;
+        // La comision de red se cobra en la misma cripto, ademas del monto enviado
+        let comision_red = self.comisiones.red.get(blockchain).copied().unwrap_or(0.0);
+        let comision = a_unidades(comision_red, self.precision_de(cripto));
+        self.verificar_disponible(dni, cripto, cantidad + comision)?;
 
         // Genero detalle y registro nueva operacion
-        let detalle = DetalleOperacion::CRIPTORETIRAR(
+        let hash = self.simular_envio_hash();
+        let detalle =
+            DetalleOperacion::CRIPTORETIRAR(cripto, monto, cotizacion, blockchain, hash.clone());
+        let concepto = format!("Red {}", blockchain);
+        let comision_detalle = self.comision_para(&concepto, cripto, comision);
+        let mut operacion = self.registrar_operacion_con_comision(dni, detalle, comision_detalle);
+
+        // Los fondos quedan bloqueados hasta que la transaccion sea firme
+        self.asentar(
+            "Retiro de cripto enviado",
+            vec![operacion.id.clone()],
+            transferencia(
+                Cuenta::Disponible(dni),
+                Cuenta::Retenido(dni),
+                cripto,
+                cantidad + comision,
+            ),
+        )?;
+        let transaccion = TransaccionCripto {
+            hash,
+            operacion: operacion.id.clone(),
+            dni,
             cripto,
-            monto,
-            cotizacion,
             blockchain,
-            self.simular_envio_hash(),
-        );
-        Ok(self.registrar_operacion(dni, detalle))
+            tipo: TipoTransaccion::Retiro,
+            unidades: cantidad,
+            comision,
+            confirmaciones: 0,
+            confirmaciones_requeridas: self.confirmaciones_requeridas(cripto, blockchain),
+            estado: EstadoTransaccion::Pendiente,
+        };
+        self.enviar_a_cadena(&mut operacion, transaccion)?;
+        Ok(operacion)
     }
 
     pub fn recibir_cripto(
@@ -383,7 +530,7 @@ impl<'p> Plataforma<'p> {
         cripto: &'p str,
         monto: f64,
         blockchain: &'p str,
-    ) -> Result<Operacion, ErroresApp> {
+    ) -> Result<Operacion<'p>, ErroresApp> {
         // Chequeo que la criptomoneda opere en la blockchain ingresada
         if !self.criptomoneda_opera_en_blockchain(cripto, blockchain)? {
             return Err(ErroresApp::RedCriptoInvalida);
@@ -391,17 +538,31 @@ impl<'p> Plataforma<'p> {
 
         // Realizo chequeos de usuario
         self.realizar_chequeos_de_usuario(dni, true, false)?;
+        let cantidad = self.a_unidades(cripto, monto)?;
 
         // Recupero cotizacion de la cripto
         let cotizacion = self.recuperar_datos_criptomoneda(cripto)?.cotizacion;
 
-        // Recupero balance cripto de usuario y lo aumento
-        let balance_cripto = self.recuperar_balance_cripto_de_usuario(dni, cripto)?;
-        *balance_cripto += monto;
-
         // Genero detalle y registro nueva operacion
         let detalle = DetalleOperacion::CRIPTORECIBIR(cripto, monto, cotizacion, blockchain);
-        Ok(self.registrar_operacion(dni, detalle))
+        let mut operacion = self.registrar_operacion(dni, detalle);
+
+        // El deposito se acredita recien cuando la transaccion es firme
+        let transaccion = TransaccionCripto {
+            hash: self.simular_envio_hash(),
+            operacion: operacion.id.clone(),
+            dni,
+            cripto,
+            blockchain,
+            tipo: TipoTransaccion::Deposito,
+            unidades: cantidad,
+            comision: 0,
+            confirmaciones: 0,
+            confirmaciones_requeridas: self.confirmaciones_requeridas(cripto, blockchain),
+            estado: EstadoTransaccion::Pendiente,
+        };
+        self.enviar_a_cadena(&mut operacion, transaccion)?;
+        Ok(operacion)
     }
 
     pub fn retirar_fiat(
@@ -409,36 +570,66 @@ impl<'p> Plataforma<'p> {
         dni: u128,
         monto: f64,
         medio: &'p str,
-    ) -> Result<Operacion, ErroresApp> {
+    ) -> Result<Operacion<'p>, ErroresApp> {
         // Realizo chequeos de usuario
         self.realizar_chequeos_de_usuario(dni, true, true)?;
-        self.usuario_dispone_fondos_fiat(dni, monto)?;
-
-        // Recupero balance fiat y lo modifico
-        let balance_usuario = self.recuperar_balance_de_usuario(dni)?;
-        balance_usuario.balance_fiat -= monto;
+        let importe = self.a_unidades(MONEDA_FIAT, monto)?;
+        self.verificar_limites_de_retiro(dni, false, monto)?;
+        let tasa = self.comisiones.retiro_fiat;
+        let comision = a_unidades(monto * tasa, self.precision_de(MONEDA_FIAT));
+        self.verificar_disponible(dni, MONEDA_FIAT, importe + comision)?;
 
         // Genero detalle y registro nueva operacion
         let detalle = DetalleOperacion::FIATRETIRAR(monto, medio);
-        Ok(self.registrar_operacion(dni, detalle))
+        let comision_detalle = self.comision_para("Retiro de fiat", MONEDA_FIAT, comision);
+        let operacion = self.registrar_operacion_con_comision(dni, detalle, comision_detalle);
+        self.asentar(
+            "Retiro de fiat",
+            vec![operacion.id.clone()],
+            vec![
+                (Cuenta::Disponible(dni), MONEDA_FIAT, -(importe + comision)),
+                (Cuenta::Externa, MONEDA_FIAT, importe),
+                (Cuenta::Comisiones, MONEDA_FIAT, comision),
+            ],
+        )?;
+        Ok(operacion)
     }
 
-    pub fn ingresar_fiat(&mut self, dni: u128, monto: f64) -> Result<Operacion, ErroresApp> {
+    pub fn ingresar_fiat(&mut self, dni: u128, monto: f64) -> Result<Operacion<'p>, ErroresApp> {
         // Realizo chequeos de usuario
         self.realizar_chequeos_de_usuario(dni, true, false)?;
-
-        // Recupero balance fiat y lo modifico
-        let balance_usuario = self.recuperar_balance_de_usuario(dni)?;
-        balance_usuario.balance_fiat += monto;
+        let importe = self.a_unidades(MONEDA_FIAT, monto)?;
 
         // Genero detalle y registro nueva operacion
         let detalle = DetalleOperacion::FIATINGRESAR(monto);
-        Ok(self.registrar_operacion(dni, detalle))
+        let operacion = self.registrar_operacion(dni, detalle);
+        self.asentar(
+            "Ingreso de fiat",
+            vec![operacion.id.clone()],
+            transferencia(
+                Cuenta::Externa,
+                Cuenta::Disponible(dni),
+                MONEDA_FIAT,
+                importe,
+            ),
+        )?;
+        Ok(operacion)
     }
 
     fn registrar_operacion(&mut self, dni: u128, detalle: DetalleOperacion<'p>) -> Operacion<'p> {
+        self.registrar_operacion_con_comision(dni, detalle, None)
+    }
+
+    fn registrar_operacion_con_comision(
+        &mut self,
+        dni: u128,
+        detalle: DetalleOperacion<'p>,
+        comision: Option<Comision<'p>>,
+    ) -> Operacion<'p> {
         // Genero nueva operacion y su detalle
-        let nueva_operacion = Operacion::new(dni, detalle);
+        let mut nueva_operacion = Operacion::new(dni, detalle);
+        nueva_operacion.instante = self.instante_actual();
+        nueva_operacion.comision = comision;
 
         // Genero nueva operacion y su detalle
         self.operaciones.push(nueva_operacion.clone());
@@ -452,53 +643,29 @@ impl<'p> Plataforma<'p> {
     fn usuario_validado(&self, dni: u128) -> bool {
         let usuario = self.usuarios.get(&dni);
         match usuario {
-            Some(usuario) => usuario.validado,
+            Some(usuario) => usuario.nivel_kyc != NivelKyc::Ninguno,
             _ => false,
         }
     }
 
-    fn usuario_dispone_fondos_fiat(&mut self, dni: u128, monto: f64) -> Result<bool, ErroresApp> {
-        let balance = self.recuperar_balance_de_usuario(dni)?;
-        let balance_fiat: f64 = balance.balance_fiat;
-        if balance_fiat < monto {
-            return Err(ErroresApp::MontoInsuficiente);
-        }
-        Ok(true)
-    }
-
-    fn usuario_dispone_fondos_cripto(
-        &mut self,
-        dni: u128,
-        criptomoneda: &'p str,
-        monto: f64,
-    ) -> Result<bool, ErroresApp> {
-        let balance = self.recuperar_balance_de_usuario(dni)?;
-        let balance_cripto: &f64 = balance.balance_criptos.get(criptomoneda).unwrap();
-        if *balance_cripto < monto {
-            return Err(ErroresApp::MontoInsuficiente);
-        }
-        Ok(true)
-    }
-
-    fn recuperar_balance_cripto_de_usuario(
-        &mut self,
-        dni: u128,
-        criptomoneda: &str,
-    ) -> Result<&mut f64, ErroresApp> {
-        let balance = self.recuperar_balance_de_usuario(dni)?;
-        let balance_cripto = balance.balance_criptos.get_mut(criptomoneda);
-        match balance_cripto {
-            Some(balance_cripto) => Ok(balance_cripto),
-            None => Err(ErroresApp::NoExisteBalance),
-        }
-    }
-
-    fn recuperar_balance_de_usuario(&mut self, dni: u128) -> Result<&mut Balance<'p>, ErroresApp> {
-        let balance = self.balances.get_mut(&dni);
-        match balance {
-            Some(balance) => Ok(balance),
-            None => Err(ErroresApp::NoExisteBalance),
+    pub fn balance_de(&self, dni: u128) -> Result<Balance<'p>, ErroresApp> {
+        if !self.usuario_existe(dni) {
+            return Err(ErroresApp::NoExisteBalance);
         }
+        let criptos = self.recuperar_criptomonedas_en_sistema();
+        Ok(Balance {
+            id_usuario: dni,
+            balance_criptos: criptos
+                .iter()
+                .map(|c| (*c, self.saldo_de(dni, c)))
+                .collect(),
+            balance_fiat: self.saldo_de(dni, MONEDA_FIAT),
+            criptos_retenidas: criptos
+                .iter()
+                .map(|c| (*c, self.saldo_retenido_de(dni, c)))
+                .collect(),
+            fiat_retenido: self.saldo_retenido_de(dni, MONEDA_FIAT),
+        })
     }
 
     fn criptomoneda_existe_en_sistema(&self, prefijo: &'p str) -> bool {
@@ -554,7 +721,7 @@ impl<'p> Plataforma<'p> {
 
     pub fn validar_usuario(&mut self, dni: u128) -> Result<u128, ErroresApp> {
         let usuario = self.recuperar_usuario(dni)?;
-        usuario.validado = true;
+        usuario.nivel_kyc = NivelKyc::Completo;
         Ok(usuario.dni)
     }
 
@@ -576,196 +743,1741 @@ impl<'p> Plataforma<'p> {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[allow(dead_code)]
+pub enum NivelKyc {
+    Ninguno,
+    Basico,
+    Intermedio,
+    // Es el nivel que otorga `validar_usuario`
+    Completo,
+}
 
-    fn instanciar_plataforma<'p>() -> Plataforma<'p> {
-        let mut plataforma = Plataforma::new();
-        let blockchain = Blockchain {
-            nombre: "Ethereum",
-            prefijo: "ETH",
-        };
-        let mut blockchains = HashMap::new();
-        blockchains.insert("ETH", blockchain);
-        let cripto = Criptomoneda {
-            nombre: "Bitcoin",
-            prefijo: "BTC",
-            cotizacion: 50000.0,
-            blockchain_disponibles: blockchains.clone(),
-        };
-        plataforma.agregar_criptomoneda(cripto).unwrap();
-        plataforma
-            .crear_usuario("Simon", "Bierozko", "asdf@gmail@.com", 123)
-            .unwrap();
-        plataforma.validar_usuario(123).unwrap();
-        plataforma.ingresar_fiat(123, 100000.0).unwrap();
-        plataforma
-    }
+// Topes de retiro valuados en fiat; None significa sin limite
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct LimitesRetiro {
+    fiat_diario: Option<f64>,
+    fiat_mensual: Option<f64>,
+    cripto_diario: Option<f64>,
+    cripto_mensual: Option<f64>,
+}
 
-    #[test]
-    fn test_usuario_existe() {
-        let plataforma = instanciar_plataforma();
-        assert!(plataforma.usuario_existe(123));
-        assert!(!plataforma.usuario_existe(999));
+#[allow(dead_code)]
+impl LimitesRetiro {
+    pub fn new(
+        fiat_diario: Option<f64>,
+        fiat_mensual: Option<f64>,
+        cripto_diario: Option<f64>,
+        cripto_mensual: Option<f64>,
+    ) -> Self {
+        LimitesRetiro {
+            fiat_diario,
+            fiat_mensual,
+            cripto_diario,
+            cripto_mensual,
+        }
     }
 
-    #[test]
-    fn test_usuario_validado() {
-        let plataforma = instanciar_plataforma();
-        assert!(plataforma.usuario_validado(123));
+    pub fn por_defecto(nivel: NivelKyc) -> Self {
+        match nivel {
+            NivelKyc::Ninguno => LimitesRetiro::new(Some(0.0), Some(0.0), Some(0.0), Some(0.0)),
+            NivelKyc::Basico => {
+                LimitesRetiro::new(Some(1000.0), Some(10000.0), Some(500.0), Some(5000.0))
+            }
+            NivelKyc::Intermedio => {
+                LimitesRetiro::new(Some(10000.0), Some(100000.0), Some(5000.0), Some(50000.0))
+            }
+            NivelKyc::Completo => LimitesRetiro::new(None, None, None, None),
+        }
     }
+}
 
-    #[test]
-    fn test_usuario_dispone_fondos_fiat() {
-        let mut plataforma = instanciar_plataforma();
-        assert!(plataforma.usuario_dispone_fondos_fiat(123, 10.0).is_ok());
-        assert!(
-            plataforma
-                .usuario_dispone_fondos_fiat(123, 200000.0)
-                .is_err()
-        );
-    }
+// Spreads y comisiones expresados como fraccion (0.01 = 1%); la de red va en
+// unidades de la cripto retirada y depende de la blockchain. Los spreads solo se
+// cobran al operar contra la mesa: las ejecuciones del libro de ordenes no tienen comision
+#[derive(Clone, Debug, PartialEq, Default)]
+#[allow(dead_code)]
+pub struct ConfiguracionComisiones<'c> {
+    spread_compra: f64,
+    spread_venta: f64,
+    retiro_fiat: f64,
+    red: HashMap<&'c str, f64>,
+}
 
-    #[test]
-    fn test_usuario_dispone_fondos_cripto() {
-        let mut plataforma = instanciar_plataforma();
-        plataforma.comprar_cripto(123, "BTC", 1.0).unwrap();
-        assert!(
-            plataforma
-                .usuario_dispone_fondos_cripto(123, "BTC", 1.0)
-                .is_ok()
-        );
-        assert!(
-            plataforma
-                .usuario_dispone_fondos_cripto(123, "BTC", 2.0)
-                .is_err()
-        );
+#[allow(dead_code)]
+impl<'c> ConfiguracionComisiones<'c> {
+    pub fn new(spread_compra: f64, spread_venta: f64, retiro_fiat: f64) -> Self {
+        ConfiguracionComisiones {
+            spread_compra,
+            spread_venta,
+            retiro_fiat,
+            red: HashMap::new(),
+        }
     }
 
-    #[test]
-    fn test_recuperar_balance_cripto_de_usuario() {
-        let mut plataforma = instanciar_plataforma();
-        assert!(
-            plataforma
-                .recuperar_balance_cripto_de_usuario(123, "BTC")
-                .is_ok()
-        );
-        assert!(
-            plataforma
-                .recuperar_balance_cripto_de_usuario(123, "ETH")
-                .is_err()
-        );
+    pub fn con_comision_de_red(mut self, blockchain: &'c str, monto: f64) -> Self {
+        self.red.insert(blockchain, monto);
+        self
     }
 
-    #[test]
-    fn test_recuperar_balance_de_usuario() {
-        let mut plataforma = instanciar_plataforma();
-        assert!(plataforma.recuperar_balance_de_usuario(123).is_ok());
-        assert!(plataforma.recuperar_balance_de_usuario(999).is_err());
+    fn es_valida(&self) -> bool {
+        let fraccion_valida = |f: f64| (0.0..1.0).contains(&f);
+        fraccion_valida(self.spread_compra)
+            && fraccion_valida(self.spread_venta)
+            && fraccion_valida(self.retiro_fiat)
+            && self.red.values().all(|m| m.is_finite() && *m >= 0.0)
     }
+}
 
-    #[test]
-    fn test_criptomoneda_existe_en_sistema() {
-        let plataforma = instanciar_plataforma();
-        assert!(plataforma.criptomoneda_existe_en_sistema("BTC"));
-        assert!(!plataforma.criptomoneda_existe_en_sistema("ETH"));
+#[allow(dead_code)]
+impl<'p> Plataforma<'p> {
+    pub fn asignar_nivel_kyc(&mut self, dni: u128, nivel: NivelKyc) -> Result<(), ErroresApp> {
+        let usuario = self.recuperar_usuario(dni)?;
+        usuario.nivel_kyc = nivel;
+        Ok(())
     }
 
-    #[test]
-    fn test_recuperar_criptomonedas_en_sistema() {
-        let plataforma = instanciar_plataforma();
-        let criptos = plataforma.recuperar_criptomonedas_en_sistema();
-        assert_eq!(criptos, vec!["BTC"]);
+    pub fn nivel_kyc_de(&self, dni: u128) -> Result<NivelKyc, ErroresApp> {
+        match self.usuarios.get(&dni) {
+            Some(usuario) => Ok(usuario.nivel_kyc),
+            None => Err(ErroresApp::UsuarioInexistente),
+        }
     }
 
-    #[test]
-    fn test_recuperar_datos_criptomoneda() {
-        let plataforma = instanciar_plataforma();
-        assert!(plataforma.recuperar_datos_criptomoneda("BTC").is_ok());
-        assert!(plataforma.recuperar_datos_criptomoneda("ETH").is_err());
+    pub fn definir_limites(&mut self, nivel: NivelKyc, limites: LimitesRetiro) {
+        self.limites_kyc.insert(nivel, limites);
     }
 
-    #[test]
-    fn test_criptomoneda_opera_en_blockchain() {
-        let plataforma = instanciar_plataforma();
-        assert!(
-            plataforma
-                .criptomoneda_opera_en_blockchain("BTC", "ETH")
-                .unwrap()
-        );
-        assert!(
-            !plataforma
-                .criptomoneda_opera_en_blockchain("BTC", "SOL")
-                .unwrap()
-        );
+    pub fn limites_de(&self, nivel: NivelKyc) -> LimitesRetiro {
+        self.limites_kyc
+            .get(&nivel)
+            .copied()
+            .unwrap_or(LimitesRetiro::por_defecto(nivel))
     }
 
-    #[test]
-    fn test_simular_envio_hash() {
-        let plataforma = instanciar_plataforma();
-        let hash = plataforma.simular_envio_hash();
-        assert_eq!(hash.len(), 20);
+    pub fn configurar_comisiones(
+        &mut self,
+        comisiones: ConfiguracionComisiones<'p>,
+    ) -> Result<(), ErroresApp> {
+        if !comisiones.es_valida() {
+            return Err(ErroresApp::ConfiguracionInvalida);
+        }
+        self.comisiones = comisiones;
+        Ok(())
     }
 
-    #[test]
-    fn test_recuperar_usuario() {
-        let mut plataforma = instanciar_plataforma();
-        assert!(plataforma.recuperar_usuario(123).is_ok());
-        assert!(plataforma.recuperar_usuario(999).is_err());
+    // Permite fijar el dia de las operaciones; None vuelve a la fecha del sistema
+    pub fn simular_fecha(&mut self, fecha: Option<Fecha>) {
+        self.simular_instante(fecha.map(|f| f.a_instante()));
     }
 
-    #[test]
-    fn test_realizar_chequeos_de_usuario() {
-        let plataforma = instanciar_plataforma();
-        assert!(
-            plataforma
-                .realizar_chequeos_de_usuario(123, true, true)
-                .is_ok()
-        );
-        assert!(
-            plataforma
-                .realizar_chequeos_de_usuario(999, true, false)
-                .is_err()
-        );
-        assert!(
-            plataforma
-                .realizar_chequeos_de_usuario(123, true, true)
-                .is_ok()
-        );
+    // Igual que `simular_fecha` pero con la precision de las cotizaciones
+    pub fn simular_instante(&mut self, instante: Option<i64>) {
+        self.instante_simulado = instante;
     }
 
-    #[test]
-    fn test_vender_cripto() {
-        let mut plataforma = instanciar_plataforma();
-        plataforma.ingresar_fiat(123, 100000000.0).unwrap();
-        plataforma.comprar_cripto(123, "BTC", 5.0).unwrap();
-        let op = plataforma.vender_cripto(123, "BTC", 2.0).unwrap();
-        assert_eq!(op.usuario, 123);
+    pub fn instante_actual(&self) -> i64 {
+        self.instante_simulado
+            .unwrap_or_else(|| chrono::Utc::now().timestamp())
     }
 
-    #[test]
-    fn test_comprar_cripto() {
-        let mut plataforma = instanciar_plataforma();
-        plataforma.ingresar_fiat(123, 9000000.0).unwrap();
-        let op = plataforma.comprar_cripto(123, "BTC", 3.0).unwrap();
-        assert_eq!(op.usuario, 123);
+    pub fn fecha_actual(&self) -> Fecha {
+        Fecha::desde_instante(self.instante_actual())
     }
 
-    #[test]
-    fn test_retirar_cripto() {
-        let mut plataforma = instanciar_plataforma();
-        plataforma.ingresar_fiat(123, 10000000.0).unwrap();
-        plataforma.comprar_cripto(123, "BTC", 5.0).unwrap();
-        let op = plataforma.retirar_cripto(123, "BTC", 2.0, "ETH").unwrap();
-        assert_eq!(op.usuario, 123);
+    // Retirado en el dia y en el mes de `fecha`, valuado en fiat
+    pub fn retirado_por(&self, dni: u128, cripto: bool, fecha: Fecha) -> (f64, f64) {
+        let mut en_el_dia = 0.0;
+        let mut en_el_mes = 0.0;
+        let operaciones = self.operaciones.iter().filter(|o| {
+            o.usuario == dni
+                && o.estado != EstadoOperacion::Fallida
+                && o.fecha().get_mes() == fecha.get_mes()
+                && o.fecha().get_ano() == fecha.get_ano()
+        });
+        for operacion in operaciones {
+            let valor = match (&operacion.tipo, cripto) {
+                (DetalleOperacion::FIATRETIRAR(monto, _), false) => *monto,
+                (DetalleOperacion::CRIPTORETIRAR(_, monto, cotizacion, _, _), true) => {
+                    monto * cotizacion
+                }
+                _ => continue,
+            };
+            en_el_mes += valor;
+            if operacion.fecha() == fecha {
+                en_el_dia += valor;
+            }
+        }
+        (en_el_dia, en_el_mes)
     }
 
-    #[test]
-    fn test_recibir_cripto() {
-        let mut plataforma = instanciar_plataforma();
-        let op = plataforma.recibir_cripto(123, "BTC", 2.0, "ETH").unwrap();
-        assert_eq!(op.usuario, 123);
+    fn verificar_limites_de_retiro(
+        &self,
+        dni: u128,
+        cripto: bool,
+        valor: f64,
+    ) -> Result<(), ErroresApp> {
+        let limites = self.limites_de(self.nivel_kyc_de(dni)?);
+        let (diario, mensual) = if cripto {
+            (limites.cripto_diario, limites.cripto_mensual)
+        } else {
+            (limites.fiat_diario, limites.fiat_mensual)
+        };
+        let (en_el_dia, en_el_mes) = self.retirado_por(dni, cripto, self.fecha_actual());
+        // Se compara en unidades de fiat para no depender del redondeo de f64
+        let decimales = self.precision_de(MONEDA_FIAT);
+        let excede = |usado: f64, limite: Option<f64>| {
+            limite.is_some_and(|l| a_unidades(usado + valor, decimales) > a_unidades(l, decimales))
+        };
+        if excede(en_el_dia, diario) {
+            return Err(ErroresApp::LimiteDiarioExcedido);
+        }
+        if excede(en_el_mes, mensual) {
+            return Err(ErroresApp::LimiteMensualExcedido);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum LadoOrden {
+    Compra,
+    Venta,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum TipoOrden {
+    Limite(f64),
+    Mercado,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum EstadoOrden {
+    Abierta,
+    Parcial,
+    Completada,
+    Cancelada,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct Orden<'o> {
+    id: u64,
+    dni: u128,
+    cripto: &'o str,
+    lado: LadoOrden,
+    tipo: TipoOrden,
+    // En la precision de la cripto, para que los llenados parciales no dejen restos
+    cantidad: Monto,
+    pendiente: Monto,
+    // Unidades de fiat (compras) o de cripto (ventas) que la orden todavia tiene retenidas
+    retenido: i128,
+    estado: EstadoOrden,
+}
+
+#[allow(dead_code)]
+impl<'o> Orden<'o> {
+    pub fn precio_limite(&self) -> Option<f64> {
+        match self.tipo {
+            TipoOrden::Limite(precio) => Some(precio),
+            TipoOrden::Mercado => None,
+        }
+    }
+
+    pub fn ejecutado(&self) -> Monto {
+        Monto::new(
+            self.cantidad.unidades - self.pendiente.unidades,
+            self.cantidad.decimales,
+        )
+    }
+
+    pub fn moneda_retenida(&self) -> &'o str {
+        match self.lado {
+            LadoOrden::Compra => MONEDA_FIAT,
+            LadoOrden::Venta => self.cripto,
+        }
+    }
+
+    pub fn esta_abierta(&self) -> bool {
+        matches!(self.estado, EstadoOrden::Abierta | EstadoOrden::Parcial)
+    }
+
+    // Una orden cruza si acepta el precio de la orden contraria en reposo
+    fn cruza_con(&self, precio: f64) -> bool {
+        match (self.lado, self.tipo) {
+            (_, TipoOrden::Mercado) => true,
+            (LadoOrden::Compra, TipoOrden::Limite(limite)) => precio <= limite,
+            (LadoOrden::Venta, TipoOrden::Limite(limite)) => precio >= limite,
+        }
+    }
+}
+
+// Libro de un par cripto/fiat. Cada lado queda ordenado por prioridad precio-tiempo
+#[derive(Clone, Debug, PartialEq, Default)]
+#[allow(dead_code)]
+pub struct LibroOrdenes<'l> {
+    compras: Vec<Orden<'l>>,
+    ventas: Vec<Orden<'l>>,
+    cerradas: Vec<Orden<'l>>,
+}
+
+#[allow(dead_code)]
+impl<'l> LibroOrdenes<'l> {
+    fn lado_mut(&mut self, lado: LadoOrden) -> &mut Vec<Orden<'l>> {
+        match lado {
+            LadoOrden::Compra => &mut self.compras,
+            LadoOrden::Venta => &mut self.ventas,
+        }
+    }
+
+    fn contrario_mut(&mut self, lado: LadoOrden) -> &mut Vec<Orden<'l>> {
+        match lado {
+            LadoOrden::Compra => &mut self.ventas,
+            LadoOrden::Venta => &mut self.compras,
+        }
+    }
+
+    fn insertar(&mut self, orden: Orden<'l>) {
+        let lado = orden.lado;
+        let ordenes = self.lado_mut(lado);
+        ordenes.push(orden);
+        // Mejor precio primero y, a igual precio, la orden mas vieja
+        ordenes.sort_by(|a, b| {
+            let (pa, pb) = (a.precio_limite().unwrap(), b.precio_limite().unwrap());
+            let por_precio = match lado {
+                LadoOrden::Compra => pb.total_cmp(&pa),
+                LadoOrden::Venta => pa.total_cmp(&pb),
+            };
+            por_precio.then(a.id.cmp(&b.id))
+        });
+    }
+
+    pub fn mejor_compra(&self) -> Option<f64> {
+        self.compras.first().and_then(|o| o.precio_limite())
+    }
+
+    pub fn mejor_venta(&self) -> Option<f64> {
+        self.ventas.first().and_then(|o| o.precio_limite())
+    }
+
+    // Cantidad acumulada por nivel de precio, en orden de prioridad
+    pub fn profundidad(&self, lado: LadoOrden) -> Vec<(f64, Monto)> {
+        let ordenes = match lado {
+            LadoOrden::Compra => &self.compras,
+            LadoOrden::Venta => &self.ventas,
+        };
+        let mut niveles: Vec<(f64, Monto)> = Vec::new();
+        for orden in ordenes {
+            let precio = orden.precio_limite().unwrap();
+            match niveles.last_mut() {
+                Some((p, cantidad)) if *p == precio => {
+                    cantidad.unidades += orden.pendiente.unidades
+                }
+                _ => niveles.push((precio, orden.pendiente)),
+            }
+        }
+        niveles
+    }
+
+    // Simula el barrido del lado contrario: (unidades ejecutables, costo en unidades de
+    // fiat). El costo de cada ejecucion se redondea por separado, igual que al ejecutar
+    fn simular_barrido(&self, orden: &Orden, decimales_fiat: u32) -> (i128, i128) {
+        let contrarias = match orden.lado {
+            LadoOrden::Compra => &self.ventas,
+            LadoOrden::Venta => &self.compras,
+        };
+        let decimales = orden.cantidad.decimales;
+        let mut restante = orden.cantidad.unidades;
+        let mut costo = 0;
+        for contraria in contrarias.iter().filter(|c| c.dni != orden.dni) {
+            let precio = contraria.precio_limite().unwrap();
+            if restante == 0 || !orden.cruza_con(precio) {
+                break;
+            }
+            let cantidad = restante.min(contraria.pendiente.unidades);
+            restante -= cantidad;
+            costo += a_unidades(
+                Monto::new(cantidad, decimales).a_f64() * precio,
+                decimales_fiat,
+            );
+        }
+        (orden.cantidad.unidades - restante, costo)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct ResultadoOrden<'r> {
+    orden: Orden<'r>,
+    operaciones: Vec<Operacion<'r>>,
+}
+
+#[allow(dead_code)]
+impl<'p> Plataforma<'p> {
+    // Las ejecuciones contra el libro son entre usuarios al precio pactado y no cobran comision
+    pub fn colocar_orden(
+        &mut self,
+        dni: u128,
+        cripto: &'p str,
+        lado: LadoOrden,
+        tipo: TipoOrden,
+        cantidad: f64,
+    ) -> Result<ResultadoOrden<'p>, ErroresApp> {
+        self.realizar_chequeos_de_usuario(dni, true, true)?;
+        self.recuperar_datos_criptomoneda(cripto)?;
+        let precio_valido = match tipo {
+            TipoOrden::Limite(precio) => precio.is_finite() && precio > 0.0,
+            TipoOrden::Mercado => true,
+        };
+        if !precio_valido {
+            return Err(ErroresApp::CantidadInvalida);
+        }
+        let cantidad = Monto::new(
+            self.a_unidades(cripto, cantidad)?,
+            self.precision_de(cripto),
+        );
+
+        let mut orden = Orden {
+            id: self.proxima_orden,
+            dni,
+            cripto,
+            lado,
+            tipo,
+            cantidad,
+            pendiente: cantidad,
+            retenido: 0,
+            estado: EstadoOrden::Abierta,
+        };
+
+        // Calculo cuanto hay que retener antes de tocar el libro
+        let decimales_fiat = self.precision_de(MONEDA_FIAT);
+        let (ejecutable, costo) = self
+            .libros
+            .get(cripto)
+            .map(|libro| libro.simular_barrido(&orden, decimales_fiat))
+            .unwrap_or((0, 0));
+        if tipo == TipoOrden::Mercado && ejecutable == 0 {
+            return Err(ErroresApp::SinLiquidez);
+        }
+        orden.retenido = match (lado, tipo) {
+            (LadoOrden::Compra, TipoOrden::Limite(precio)) => {
+                // Lo que cruza se paga al precio del libro, el resto al limite
+                let resto = Monto::new(cantidad.unidades - ejecutable, cantidad.decimales);
+                costo + a_unidades(resto.a_f64() * precio, decimales_fiat)
+            }
+            (LadoOrden::Compra, TipoOrden::Mercado) => costo,
+            (LadoOrden::Venta, _) => cantidad.unidades,
+        };
+        if orden.retenido <= 0 {
+            return Err(ErroresApp::CantidadInvalida);
+        }
+        self.retener_fondos(&orden)?;
+        self.proxima_orden += 1;
+
+        let operaciones = self.ejecutar_contra_libro(&mut orden)?;
+
+        let libro = self.libros.entry(cripto).or_default();
+        match orden.tipo {
+            TipoOrden::Limite(_) if orden.pendiente.unidades > 0 => {
+                orden.estado = if operaciones.is_empty() {
+                    EstadoOrden::Abierta
+                } else {
+                    EstadoOrden::Parcial
+                };
+                libro.insertar(orden.clone());
+            }
+            _ => {
+                // Lo que una orden a mercado no pudo ejecutar se cancela
+                orden.estado = if orden.pendiente.unidades > 0 {
+                    EstadoOrden::Cancelada
+                } else {
+                    EstadoOrden::Completada
+                };
+                let sobrante = orden.retenido;
+                orden.retenido = 0;
+                libro.cerradas.push(orden.clone());
+                self.liberar_fondos(&orden, sobrante)?;
+            }
+        }
+
+        Ok(ResultadoOrden { orden, operaciones })
+    }
+
+    pub fn cancelar_orden(&mut self, dni: u128, id: u64) -> Result<Orden<'p>, ErroresApp> {
+        let libro = self
+            .libros
+            .values_mut()
+            .find(|l| {
+                l.compras
+                    .iter()
+                    .chain(&l.ventas)
+                    .chain(&l.cerradas)
+                    .any(|o| o.id == id)
+            })
+            .ok_or(ErroresApp::OrdenInexistente)?;
+
+        let en_reposo = libro
+            .compras
+            .iter()
+            .position(|o| o.id == id)
+            .map(|i| (LadoOrden::Compra, i))
+            .or_else(|| {
+                libro
+                    .ventas
+                    .iter()
+                    .position(|o| o.id == id)
+                    .map(|i| (LadoOrden::Venta, i))
+            });
+        let (lado, indice) = match en_reposo {
+            Some(posicion) => posicion,
+            None => {
+                let cerrada = libro.cerradas.iter().find(|o| o.id == id).unwrap();
+                if cerrada.dni != dni {
+                    return Err(ErroresApp::OrdenInexistente);
+                }
+                return Err(ErroresApp::OrdenNoCancelable);
+            }
+        };
+        if libro.lado_mut(lado)[indice].dni != dni {
+            return Err(ErroresApp::OrdenInexistente);
+        }
+
+        let mut orden = libro.lado_mut(lado).remove(indice);
+        let liberado = orden.retenido;
+        orden.retenido = 0;
+        orden.estado = EstadoOrden::Cancelada;
+        libro.cerradas.push(orden.clone());
+        self.liberar_fondos(&orden, liberado)?;
+        Ok(orden)
+    }
+
+    pub fn ordenes_abiertas(&self, dni: u128) -> Vec<&Orden<'p>> {
+        let mut ordenes: Vec<&Orden<'p>> = self
+            .libros
+            .values()
+            .flat_map(|l| l.compras.iter().chain(&l.ventas))
+            .filter(|o| o.dni == dni)
+            .collect();
+        ordenes.sort_by_key(|o| o.id);
+        ordenes
+    }
+
+    pub fn buscar_orden(&self, id: u64) -> Option<&Orden<'p>> {
+        self.libros
+            .values()
+            .flat_map(|l| l.compras.iter().chain(&l.ventas).chain(&l.cerradas))
+            .find(|o| o.id == id)
+    }
+
+    pub fn libro_de(&self, cripto: &str) -> Option<&LibroOrdenes<'p>> {
+        self.libros.get(cripto)
+    }
+
+    // Cruza la orden entrante contra las ordenes en reposo del lado contrario
+    fn ejecutar_contra_libro(
+        &mut self,
+        orden: &mut Orden<'p>,
+    ) -> Result<Vec<Operacion<'p>>, ErroresApp> {
+        let mut operaciones = Vec::new();
+        let decimales_fiat = self.precision_de(MONEDA_FIAT);
+        loop {
+            if orden.pendiente.unidades == 0 {
+                break;
+            }
+            let Some(libro) = self.libros.get_mut(orden.cripto) else {
+                break;
+            };
+            let contrarias = libro.contrario_mut(orden.lado);
+            // No se cruzan ordenes del mismo usuario
+            let Some(indice) = contrarias.iter().position(|c| c.dni != orden.dni) else {
+                break;
+            };
+            let contraria = &mut contrarias[indice];
+            let precio = contraria.precio_limite().unwrap();
+            if !orden.cruza_con(precio) {
+                break;
+            }
+
+            let unidades = orden.pendiente.unidades.min(contraria.pendiente.unidades);
+            orden.pendiente.unidades -= unidades;
+            contraria.pendiente.unidades -= unidades;
+            let cantidad = Monto::new(unidades, orden.pendiente.decimales).a_f64();
+            let lado = orden.lado;
+            let (compra, venta) = match lado {
+                LadoOrden::Compra => (&mut *orden, &mut *contraria),
+                LadoOrden::Venta => (&mut *contraria, &mut *orden),
+            };
+            // La compra entrante paga cada ejecucion redondeada, como se calculo al retener.
+            // La que esta en reposo paga lo que le sobra de retencion sobre lo que le queda
+            // pendiente, asi el redondeo de sus ejecuciones suma justo lo retenido
+            let importe = match lado {
+                LadoOrden::Compra => a_unidades(cantidad * precio, decimales_fiat),
+                LadoOrden::Venta => {
+                    compra.retenido - a_unidades(compra.pendiente.a_f64() * precio, decimales_fiat)
+                }
+            };
+            compra.retenido -= importe;
+            venta.retenido -= unidades;
+            let (comprador, vendedor) = (compra.dni, venta.dni);
+
+            let mut completada = None;
+            if contraria.pendiente.unidades == 0 {
+                let mut completa = contrarias.remove(indice);
+                completa.estado = EstadoOrden::Completada;
+                let sobrante = completa.retenido;
+                completa.retenido = 0;
+                libro.cerradas.push(completa.clone());
+                completada = Some((completa, sobrante));
+            } else {
+                contraria.estado = EstadoOrden::Parcial;
+            }
+
+            let compra = self.registrar_operacion(
+                comprador,
+                DetalleOperacion::CRIPTOCOMPRAR(orden.cripto, cantidad, precio),
+            );
+            let venta = self.registrar_operacion(
+                vendedor,
+                DetalleOperacion::CRIPTOVENDER(orden.cripto, cantidad, precio),
+            );
+            self.asentar(
+                "Ejecucion de orden",
+                vec![compra.id.clone(), venta.id.clone()],
+                vec![
+                    (Cuenta::Retenido(comprador), MONEDA_FIAT, -importe),
+                    (Cuenta::Disponible(vendedor), MONEDA_FIAT, importe),
+                    (Cuenta::Retenido(vendedor), orden.cripto, -unidades),
+                    (Cuenta::Disponible(comprador), orden.cripto, unidades),
+                ],
+            )?;
+            if let Some((completa, sobrante)) = completada {
+                self.liberar_fondos(&completa, sobrante)?;
+            }
+            operaciones.push(compra);
+            operaciones.push(venta);
+            // La cotizacion pasa a ser el precio de la ultima ejecucion
+            if let Some(datos) = self.criptomonedas.get_mut(orden.cripto) {
+                datos.cotizacion = precio;
+            }
+        }
+        Ok(operaciones)
+    }
+
+    fn retener_fondos(&mut self, orden: &Orden<'p>) -> Result<(), ErroresApp> {
+        let moneda = orden.moneda_retenida();
+        self.verificar_disponible(orden.dni, moneda, orden.retenido)?;
+        self.asentar(
+            &format!("Retencion orden #{}", orden.id),
+            vec![],
+            transferencia(
+                Cuenta::Disponible(orden.dni),
+                Cuenta::Retenido(orden.dni),
+                moneda,
+                orden.retenido,
+            ),
+        )
+    }
+
+    fn liberar_fondos(&mut self, orden: &Orden<'p>, unidades: i128) -> Result<(), ErroresApp> {
+        if unidades == 0 {
+            return Ok(());
+        }
+        self.asentar(
+            &format!("Liberacion orden #{}", orden.id),
+            vec![],
+            transferencia(
+                Cuenta::Retenido(orden.dni),
+                Cuenta::Disponible(orden.dni),
+                orden.moneda_retenida(),
+                unidades,
+            ),
+        )
+    }
+}
+
+// Moneda de curso legal de la plataforma dentro del libro mayor
+pub const MONEDA_FIAT: &str = "FIAT";
+const DECIMALES_FIAT: u32 = 2;
+const DECIMALES_CRIPTO: u32 = 8;
+
+// Redondea un valor a unidades minimas de una moneda con `decimales` decimales
+fn a_unidades(valor: f64, decimales: u32) -> i128 {
+    (valor * 10f64.powi(decimales as i32)).round() as i128
+}
+
+// Importe en punto fijo: `unidades` son la minima fraccion de la moneda
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(dead_code)]
+pub struct Monto {
+    unidades: i128,
+    decimales: u32,
+}
+
+#[allow(dead_code)]
+impl Monto {
+    pub fn new(unidades: i128, decimales: u32) -> Self {
+        Monto {
+            unidades,
+            decimales,
+        }
+    }
+
+    pub fn desde_f64(valor: f64, decimales: u32) -> Self {
+        Monto::new(a_unidades(valor, decimales), decimales)
+    }
+
+    pub fn unidades(&self) -> i128 {
+        self.unidades
+    }
+
+    pub fn a_f64(&self) -> f64 {
+        self.unidades as f64 / 10f64.powi(self.decimales as i32)
+    }
+}
+
+impl fmt::Display for Monto {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let signo = if self.unidades < 0 { "-" } else { "" };
+        let absoluto = self.unidades.unsigned_abs();
+        if self.decimales == 0 {
+            return write!(f, "{}{}", signo, absoluto);
+        }
+        let base = 10u128.pow(self.decimales);
+        write!(
+            f,
+            "{}{}.{:0ancho$}",
+            signo,
+            absoluto / base,
+            absoluto % base,
+            ancho = self.decimales as usize
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[allow(dead_code)]
+pub enum Cuenta {
+    Disponible(u128),
+    Retenido(u128),
+    // Contraparte de depositos y retiros hacia fuera de la plataforma
+    Externa,
+    // Contraparte de las compras y ventas instantaneas a cotizacion
+    Mesa,
+    // Ingresos de la plataforma por spreads y comisiones
+    Comisiones,
+}
+
+#[allow(dead_code)]
+impl Cuenta {
+    pub fn dni(&self) -> Option<u128> {
+        match self {
+            Cuenta::Disponible(dni) | Cuenta::Retenido(dni) => Some(*dni),
+            Cuenta::Externa | Cuenta::Mesa | Cuenta::Comisiones => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct Movimiento<'m> {
+    cuenta: Cuenta,
+    moneda: &'m str,
+    // Positivo acredita la cuenta, negativo la debita
+    unidades: i128,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct Asiento<'a> {
+    numero: u64,
+    instante: i64,
+    concepto: String,
+    operaciones: Vec<String>,
+    movimientos: Vec<Movimiento<'a>>,
+}
+
+#[allow(dead_code)]
+impl<'a> Asiento<'a> {
+    // Monedas cuyos movimientos no suman cero
+    pub fn desbalances(&self) -> Vec<(&'a str, i128)> {
+        let mut sumas: Vec<(&'a str, i128)> = Vec::new();
+        for movimiento in &self.movimientos {
+            match sumas.iter_mut().find(|(m, _)| *m == movimiento.moneda) {
+                Some((_, suma)) => *suma += movimiento.unidades,
+                None => sumas.push((movimiento.moneda, movimiento.unidades)),
+            }
+        }
+        sumas.retain(|(_, suma)| *suma != 0);
+        sumas
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+#[allow(dead_code)]
+pub struct LibroMayor<'l> {
+    asientos: Vec<Asiento<'l>>,
+    saldos: HashMap<(Cuenta, &'l str), i128>,
+}
+
+#[allow(dead_code)]
+impl<'l> LibroMayor<'l> {
+    fn asentar(&mut self, asiento: Asiento<'l>) -> Result<(), ErroresApp> {
+        if !asiento.desbalances().is_empty() {
+            return Err(ErroresApp::AsientoDesbalanceado);
+        }
+        for movimiento in &asiento.movimientos {
+            *self
+                .saldos
+                .entry((movimiento.cuenta, movimiento.moneda))
+                .or_insert(0) += movimiento.unidades;
+        }
+        self.asientos.push(asiento);
+        Ok(())
+    }
+
+    pub fn saldo(&self, cuenta: Cuenta, moneda: &str) -> i128 {
+        self.saldos.get(&(cuenta, moneda)).copied().unwrap_or(0)
+    }
+
+    pub fn usa_moneda(&self, moneda: &str) -> bool {
+        self.saldos.keys().any(|(_, m)| *m == moneda)
+    }
+}
+
+// Doble partida de una transferencia simple entre dos cuentas
+fn transferencia(
+    desde: Cuenta,
+    hacia: Cuenta,
+    moneda: &str,
+    unidades: i128,
+) -> Vec<(Cuenta, &str, i128)> {
+    vec![(desde, moneda, -unidades), (hacia, moneda, unidades)]
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum Discrepancia<'d> {
+    AsientoDesbalanceado {
+        numero: u64,
+        moneda: &'d str,
+        diferencia: i128,
+    },
+    SaldoInconsistente {
+        cuenta: Cuenta,
+        moneda: &'d str,
+        registrado: i128,
+        recalculado: i128,
+    },
+}
+
+#[allow(dead_code)]
+impl<'p> Plataforma<'p> {
+    pub fn precision_de(&self, moneda: &str) -> u32 {
+        self.precisiones
+            .get(moneda)
+            .copied()
+            .unwrap_or(DECIMALES_CRIPTO)
+    }
+
+    // Solo se puede cambiar mientras la moneda no tenga movimientos
+    pub fn definir_precision(&mut self, moneda: &'p str, decimales: u32) -> Result<(), ErroresApp> {
+        if !self.precisiones.contains_key(moneda) {
+            return Err(ErroresApp::CriptoNoExiste);
+        }
+        if decimales > 18 {
+            return Err(ErroresApp::CantidadInvalida);
+        }
+        if self.mayor.usa_moneda(moneda) {
+            return Err(ErroresApp::PrecisionEnUso);
+        }
+        self.precisiones.insert(moneda, decimales);
+        Ok(())
+    }
+
+    pub fn saldo_de(&self, dni: u128, moneda: &str) -> Monto {
+        let unidades = self.mayor.saldo(Cuenta::Disponible(dni), moneda);
+        Monto::new(unidades, self.precision_de(moneda))
+    }
+
+    pub fn saldo_retenido_de(&self, dni: u128, moneda: &str) -> Monto {
+        let unidades = self.mayor.saldo(Cuenta::Retenido(dni), moneda);
+        Monto::new(unidades, self.precision_de(moneda))
+    }
+
+    pub fn asientos_de_operacion(&self, id: &str) -> Vec<&Asiento<'p>> {
+        self.mayor
+            .asientos
+            .iter()
+            .filter(|a| a.operaciones.iter().any(|o| o == id))
+            .collect()
+    }
+
+    // Recalcula todo desde los asientos y lo compara con los saldos registrados
+    pub fn conciliar(&self) -> Vec<Discrepancia<'p>> {
+        let mut discrepancias = Vec::new();
+        let mut recalculados: HashMap<(Cuenta, &'p str), i128> = HashMap::new();
+        for asiento in &self.mayor.asientos {
+            for (moneda, diferencia) in asiento.desbalances() {
+                discrepancias.push(Discrepancia::AsientoDesbalanceado {
+                    numero: asiento.numero,
+                    moneda,
+                    diferencia,
+                });
+            }
+            for movimiento in &asiento.movimientos {
+                *recalculados
+                    .entry((movimiento.cuenta, movimiento.moneda))
+                    .or_insert(0) += movimiento.unidades;
+            }
+        }
+
+        let mut claves: Vec<(Cuenta, &'p str)> = recalculados.keys().copied().collect();
+        claves.extend(
+            self.mayor
+                .saldos
+                .keys()
+                .filter(|c| !recalculados.contains_key(*c)),
+        );
+        for (cuenta, moneda) in claves {
+            let registrado = self.mayor.saldo(cuenta, moneda);
+            let recalculado = recalculados.get(&(cuenta, moneda)).copied().unwrap_or(0);
+            if registrado != recalculado {
+                discrepancias.push(Discrepancia::SaldoInconsistente {
+                    cuenta,
+                    moneda,
+                    registrado,
+                    recalculado,
+                });
+            }
+        }
+
+        discrepancias
+    }
+
+    // Convierte un monto de la moneda a unidades; tiene que quedar positivo
+    fn a_unidades(&self, moneda: &str, valor: f64) -> Result<i128, ErroresApp> {
+        if !valor.is_finite() {
+            return Err(ErroresApp::CantidadInvalida);
+        }
+        let unidades = a_unidades(valor, self.precision_de(moneda));
+        if unidades <= 0 {
+            return Err(ErroresApp::CantidadInvalida);
+        }
+        Ok(unidades)
+    }
+
+    fn verificar_disponible(
+        &self,
+        dni: u128,
+        moneda: &str,
+        unidades: i128,
+    ) -> Result<(), ErroresApp> {
+        if self.mayor.saldo(Cuenta::Disponible(dni), moneda) < unidades {
+            return Err(ErroresApp::MontoInsuficiente);
+        }
+        Ok(())
+    }
+
+    // Detalle de la comision para la operacion; None si no se cobro nada
+    fn comision_para(
+        &self,
+        concepto: &str,
+        moneda: &'p str,
+        unidades: i128,
+    ) -> Option<Comision<'p>> {
+        if unidades == 0 {
+            return None;
+        }
+        Some(Comision {
+            concepto: concepto.to_string(),
+            moneda,
+            monto: Monto::new(unidades, self.precision_de(moneda)).a_f64(),
+        })
+    }
+
+    fn asentar(
+        &mut self,
+        concepto: &str,
+        operaciones: Vec<String>,
+        movimientos: Vec<(Cuenta, &'p str, i128)>,
+    ) -> Result<(), ErroresApp> {
+        let asiento = Asiento {
+            numero: self.mayor.asientos.len() as u64 + 1,
+            instante: self.instante_actual(),
+            concepto: concepto.to_string(),
+            operaciones,
+            movimientos: movimientos
+                .into_iter()
+                .filter(|(_, _, unidades)| *unidades != 0)
+                .map(|(cuenta, moneda, unidades)| Movimiento {
+                    cuenta,
+                    moneda,
+                    unidades,
+                })
+                .collect(),
+        };
+        self.mayor.asentar(asiento)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum EstadoOperacion {
+    Completada,
+    Pendiente,
+    Fallida,
+}
+
+// Blockchain local: cada bloque incluye lo que esta en la mempool y una
+// transaccion puede fallar al azar (con semilla) o por una falla forzada
+#[derive(Clone, Debug)]
+#[allow(dead_code)]
+pub struct CadenaSimulada {
+    altura: u64,
+    rng: StdRng,
+    probabilidad_falla: f64,
+    mempool: Vec<String>,
+    incluidas: HashMap<String, u64>,
+    fallidas: Vec<String>,
+    a_fallar: Vec<String>,
+}
+
+#[allow(dead_code)]
+impl CadenaSimulada {
+    pub fn new(semilla: u64, probabilidad_falla: f64) -> Result<Self, ErroresApp> {
+        if !(0.0..=1.0).contains(&probabilidad_falla) {
+            return Err(ErroresApp::ConfiguracionInvalida);
+        }
+        Ok(CadenaSimulada::con_semilla(semilla, probabilidad_falla))
+    }
+
+    fn con_semilla(semilla: u64, probabilidad_falla: f64) -> Self {
+        CadenaSimulada {
+            altura: 0,
+            rng: StdRng::seed_from_u64(semilla),
+            probabilidad_falla,
+            mempool: Vec::new(),
+            incluidas: HashMap::new(),
+            fallidas: Vec::new(),
+            a_fallar: Vec::new(),
+        }
+    }
+
+    fn enviar(&mut self, hash: String) {
+        self.mempool.push(hash);
+    }
+
+    fn forzar_falla(&mut self, hash: &str) {
+        self.a_fallar.push(hash.to_string());
+    }
+
+    fn minar_bloque(&mut self) {
+        self.altura += 1;
+        for hash in std::mem::take(&mut self.a_fallar) {
+            self.incluidas.remove(&hash);
+            self.mempool.retain(|h| *h != hash);
+            self.fallidas.push(hash);
+        }
+        for hash in std::mem::take(&mut self.mempool) {
+            if self.rng.gen_bool(self.probabilidad_falla) {
+                self.fallidas.push(hash);
+            } else {
+                self.incluidas.insert(hash, self.altura);
+            }
+        }
+    }
+
+    pub fn altura(&self) -> u64 {
+        self.altura
+    }
+
+    pub fn confirmaciones(&self, hash: &str) -> u32 {
+        match self.incluidas.get(hash) {
+            Some(altura) => (self.altura - altura + 1) as u32,
+            None => 0,
+        }
+    }
+
+    pub fn fallo(&self, hash: &str) -> bool {
+        self.fallidas.iter().any(|h| h == hash)
+    }
+}
+
+impl Default for CadenaSimulada {
+    fn default() -> Self {
+        CadenaSimulada::con_semilla(0, 0.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum TipoTransaccion {
+    Retiro,
+    Deposito,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum EstadoTransaccion {
+    Pendiente,
+    Confirmada,
+    Fallida,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct TransaccionCripto<'t> {
+    hash: String,
+    operacion: String,
+    dni: u128,
+    cripto: &'t str,
+    blockchain: &'t str,
+    tipo: TipoTransaccion,
+    unidades: i128,
+    comision: i128,
+    confirmaciones: u32,
+    confirmaciones_requeridas: u32,
+    estado: EstadoTransaccion,
+}
+
+#[allow(dead_code)]
+impl<'p> Plataforma<'p> {
+    // Reemplazar la cadena perderia la mempool, asi que solo se permite sin pendientes
+    pub fn configurar_cadena(
+        &mut self,
+        blockchain: &'p str,
+        cadena: CadenaSimulada,
+    ) -> Result<(), ErroresApp> {
+        if self
+            .transacciones
+            .iter()
+            .any(|t| t.blockchain == blockchain && t.estado == EstadoTransaccion::Pendiente)
+        {
+            return Err(ErroresApp::CadenaConPendientes);
+        }
+        self.cadenas.insert(blockchain, cadena);
+        Ok(())
+    }
+
+    pub fn transaccion(&self, hash: &str) -> Option<&TransaccionCripto<'p>> {
+        self.transacciones.iter().find(|t| t.hash == hash)
+    }
+
+    pub fn transacciones_pendientes(&self, dni: u128) -> Vec<&TransaccionCripto<'p>> {
+        self.transacciones
+            .iter()
+            .filter(|t| t.dni == dni && t.estado == EstadoTransaccion::Pendiente)
+            .collect()
+    }
+
+    // La transaccion falla en el proximo bloque que se mine
+    pub fn simular_falla(&mut self, hash: &str) -> Result<(), ErroresApp> {
+        let transaccion = self
+            .transaccion(hash)
+            .ok_or(ErroresApp::TransaccionInexistente)?;
+        if transaccion.estado != EstadoTransaccion::Pendiente {
+            return Err(ErroresApp::TransaccionFinalizada);
+        }
+        let blockchain = transaccion.blockchain;
+        self.cadenas
+            .entry(blockchain)
+            .or_default()
+            .forzar_falla(hash);
+        Ok(())
+    }
+
+    // Mina bloques en la cadena y devuelve las transacciones que quedaron firmes o fallaron
+    pub fn minar_bloques(
+        &mut self,
+        blockchain: &'p str,
+        bloques: u32,
+    ) -> Result<Vec<TransaccionCripto<'p>>, ErroresApp> {
+        let mut finalizadas = Vec::new();
+        for _ in 0..bloques {
+            let cadena = self.cadenas.entry(blockchain).or_default();
+            cadena.minar_bloque();
+
+            let pendientes: Vec<usize> = (0..self.transacciones.len())
+                .filter(|i| {
+                    let t = &self.transacciones[*i];
+                    t.blockchain == blockchain && t.estado == EstadoTransaccion::Pendiente
+                })
+                .collect();
+            for indice in pendientes {
+                let cadena = &self.cadenas[blockchain];
+                let transaccion = &mut self.transacciones[indice];
+                let exito = if cadena.fallo(&transaccion.hash) {
+                    false
+                } else {
+                    transaccion.confirmaciones = cadena.confirmaciones(&transaccion.hash);
+                    if transaccion.confirmaciones < transaccion.confirmaciones_requeridas {
+                        continue;
+                    }
+                    true
+                };
+                finalizadas.push(self.finalizar_transaccion(indice, exito)?);
+            }
+        }
+        Ok(finalizadas)
+    }
+
+    fn enviar_a_cadena(
+        &mut self,
+        operacion: &mut Operacion<'p>,
+        transaccion: TransaccionCripto<'p>,
+    ) -> Result<(), ErroresApp> {
+        let blockchain = transaccion.blockchain;
+        let inmediata = transaccion.confirmaciones_requeridas == 0;
+        self.cadenas
+            .entry(blockchain)
+            .or_default()
+            .enviar(transaccion.hash.clone());
+        self.transacciones.push(transaccion);
+        operacion.estado = EstadoOperacion::Pendiente;
+        self.cambiar_estado_operacion(&operacion.id, EstadoOperacion::Pendiente);
+
+        // Una red sin confirmaciones requeridas se da por firme en el acto
+        if inmediata {
+            self.finalizar_transaccion(self.transacciones.len() - 1, true)?;
+            operacion.estado = EstadoOperacion::Completada;
+        }
+        Ok(())
+    }
+
+    fn finalizar_transaccion(
+        &mut self,
+        indice: usize,
+        exito: bool,
+    ) -> Result<TransaccionCripto<'p>, ErroresApp> {
+        let transaccion = self.transacciones[indice].clone();
+        let (dni, cripto) = (transaccion.dni, transaccion.cripto);
+        let operaciones = vec![transaccion.operacion.clone()];
+        let bloqueado = transaccion.unidades + transaccion.comision;
+        match (transaccion.tipo, exito) {
+            (TipoTransaccion::Retiro, true) => self.asentar(
+                "Retiro de cripto confirmado",
+                operaciones,
+                vec![
+                    (Cuenta::Retenido(dni), cripto, -bloqueado),
+                    (Cuenta::Externa, cripto, transaccion.unidades),
+                    (Cuenta::Comisiones, cripto, transaccion.comision),
+                ],
+            )?,
+            (TipoTransaccion::Retiro, false) => self.asentar(
+                "Reintegro por retiro fallido",
+                operaciones,
+                transferencia(
+                    Cuenta::Retenido(dni),
+                    Cuenta::Disponible(dni),
+                    cripto,
+                    bloqueado,
+                ),
+            )?,
+            (TipoTransaccion::Deposito, true) => self.asentar(
+                "Deposito de cripto",
+                operaciones,
+                transferencia(
+                    Cuenta::Externa,
+                    Cuenta::Disponible(dni),
+                    cripto,
+                    transaccion.unidades,
+                ),
+            )?,
+            // Un deposito que falla nunca se acredito
+            (TipoTransaccion::Deposito, false) => {}
+        }
+
+        let (estado, estado_operacion) = if exito {
+            (EstadoTransaccion::Confirmada, EstadoOperacion::Completada)
+        } else {
+            (EstadoTransaccion::Fallida, EstadoOperacion::Fallida)
+        };
+        self.transacciones[indice].estado = estado;
+        self.cambiar_estado_operacion(&transaccion.operacion, estado_operacion);
+        Ok(self.transacciones[indice].clone())
+    }
+
+    fn cambiar_estado_operacion(&mut self, id: &str, estado: EstadoOperacion) {
+        if let Some(operacion) = self.operaciones.iter_mut().find(|o| o.id == id) {
+            operacion.estado = estado;
+        }
+    }
+
+    fn confirmaciones_requeridas(&self, cripto: &str, blockchain: &str) -> u32 {
+        self.criptomonedas
+            .get(cripto)
+            .and_then(|c| c.blockchain_disponibles.get(blockchain))
+            .map(|b| b.confirmaciones_requeridas)
+            .unwrap_or(0)
+    }
+}
+
+// Cotizacion de una cripto en un instante (segundos unix)
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct PuntoCotizacion {
+    instante: i64,
+    precio: f64,
+}
+
+#[allow(dead_code)]
+impl PuntoCotizacion {
+    pub fn new(instante: i64, precio: f64) -> Self {
+        PuntoCotizacion { instante, precio }
+    }
+}
+
+#[allow(dead_code)]
+pub trait FuenteCotizaciones {
+    // Devuelve el prefijo de la cripto y su cotizacion, o None si no hay mas datos
+    fn proxima_cotizacion(&mut self) -> Option<(String, PuntoCotizacion)>;
+}
+
+// Reproduce cotizaciones grabadas con el formato "PREFIJO,instante,precio" por linea
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct FuenteArchivo {
+    cotizaciones: VecDeque<(String, PuntoCotizacion)>,
+}
+
+#[allow(dead_code)]
+impl FuenteArchivo {
+    pub fn desde_archivo(ruta: &str) -> Result<Self, ErroresApp> {
+        let contenido = fs::read_to_string(ruta).map_err(|_| ErroresApp::FuenteInvalida)?;
+        Self::desde_texto(&contenido)
+    }
+
+    pub fn desde_texto(contenido: &str) -> Result<Self, ErroresApp> {
+        let mut cotizaciones = VecDeque::new();
+        for linea in contenido.lines().map(str::trim) {
+            if linea.is_empty() || linea.starts_with('#') {
+                continue;
+            }
+            let campos: Vec<&str> = linea.split(',').map(str::trim).collect();
+            let [prefijo, instante, precio] = campos[..] else {
+                return Err(ErroresApp::FuenteInvalida);
+            };
+            let instante: i64 = instante.parse().map_err(|_| ErroresApp::FuenteInvalida)?;
+            let precio: f64 = precio.parse().map_err(|_| ErroresApp::FuenteInvalida)?;
+            if !precio.is_finite() || precio <= 0.0 {
+                return Err(ErroresApp::FuenteInvalida);
+            }
+            cotizaciones.push_back((prefijo.to_string(), PuntoCotizacion::new(instante, precio)));
+        }
+        Ok(FuenteArchivo { cotizaciones })
+    }
+}
+
+impl FuenteCotizaciones for FuenteArchivo {
+    fn proxima_cotizacion(&mut self) -> Option<(String, PuntoCotizacion)> {
+        self.cotizaciones.pop_front()
+    }
+}
+
+// Caminata aleatoria con semilla fija: misma semilla, misma serie de precios
+#[derive(Clone, Debug)]
+#[allow(dead_code)]
+pub struct SimuladorCotizaciones {
+    rng: StdRng,
+    precios: Vec<(String, f64)>,
+    instante: i64,
+    paso_segundos: i64,
+    volatilidad: f64,
+    pasos_restantes: u32,
+    siguiente: usize,
+}
+
+#[allow(dead_code)]
+impl SimuladorCotizaciones {
+    pub fn new(
+        semilla: u64,
+        precios_iniciales: Vec<(&str, f64)>,
+        inicio: i64,
+        paso_segundos: i64,
+        volatilidad: f64,
+        pasos: u32,
+    ) -> Result<Self, ErroresApp> {
+        // La variacion se sortea en [-volatilidad, volatilidad]
+        if !volatilidad.is_finite() || volatilidad < 0.0 {
+            return Err(ErroresApp::ConfiguracionInvalida);
+        }
+        Ok(SimuladorCotizaciones {
+            rng: StdRng::seed_from_u64(semilla),
+            precios: precios_iniciales
+                .into_iter()
+                .map(|(prefijo, precio)| (prefijo.to_string(), precio))
+                .collect(),
+            instante: inicio,
+            paso_segundos,
+            volatilidad,
+            pasos_restantes: pasos,
+            siguiente: 0,
+        })
+    }
+}
+
+impl FuenteCotizaciones for SimuladorCotizaciones {
+    fn proxima_cotizacion(&mut self) -> Option<(String, PuntoCotizacion)> {
+        if self.pasos_restantes == 0 || self.precios.is_empty() {
+            return None;
+        }
+        // Cada paso emite una cotizacion por cripto y despues avanza el reloj
+        let (prefijo, precio) = &mut self.precios[self.siguiente];
+        let variacion = self.rng.gen_range(-self.volatilidad..=self.volatilidad);
+        *precio = (*precio * (1.0 + variacion)).max(f64::MIN_POSITIVE);
+        let punto = PuntoCotizacion::new(self.instante, *precio);
+        let prefijo = prefijo.clone();
+
+        self.siguiente += 1;
+        if self.siguiente == self.precios.len() {
+            self.siguiente = 0;
+            self.instante += self.paso_segundos;
+            self.pasos_restantes -= 1;
+        }
+        Some((prefijo, punto))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum IntervaloVela {
+    Minuto,
+    Hora,
+    Dia,
+}
+
+#[allow(dead_code)]
+impl IntervaloVela {
+    pub fn segundos(&self) -> i64 {
+        match self {
+            IntervaloVela::Minuto => 60,
+            IntervaloVela::Hora => 60 * 60,
+            IntervaloVela::Dia => 60 * 60 * 24,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct Vela {
+    inicio: i64,
+    apertura: f64,
+    maximo: f64,
+    minimo: f64,
+    cierre: f64,
+}
+
+#[allow(dead_code)]
+impl<'p> Plataforma<'p> {
+    pub fn registrar_cotizacion(
+        &mut self,
+        cripto: &str,
+        punto: PuntoCotizacion,
+    ) -> Result<(), ErroresApp> {
+        let datos = self
+            .criptomonedas
+            .get_mut(cripto)
+            .ok_or(ErroresApp::CriptoNoExiste)?;
+        let serie = self
+            .historial_cotizaciones
+            .entry(datos.prefijo)
+            .or_default();
+        // La serie queda ordenada aunque la fuente entregue datos desordenados
+        let posicion = serie.partition_point(|p| p.instante <= punto.instante);
+        serie.insert(posicion, punto);
+        if posicion == serie.len() - 1 {
+            datos.cotizacion = punto.precio;
+        }
+        Ok(())
+    }
+
+    // Consume la fuente completa y devuelve cuantas cotizaciones se registraron
+    pub fn consumir_fuente(
+        &mut self,
+        fuente: &mut dyn FuenteCotizaciones,
+    ) -> Result<usize, ErroresApp> {
+        let mut registradas = 0;
+        while let Some((prefijo, punto)) = fuente.proxima_cotizacion() {
+            self.registrar_cotizacion(&prefijo, punto)?;
+            registradas += 1;
+        }
+        Ok(registradas)
+    }
+
+    pub fn historial_de(&self, cripto: &str) -> &[PuntoCotizacion] {
+        self.historial_cotizaciones
+            .get(cripto)
+            .map(|v| v.as_slice())
+            .unwrap_or(&[])
+    }
+
+    // Ultima cotizacion conocida al instante dado
+    pub fn cotizacion_al(&self, cripto: &str, instante: i64) -> Option<f64> {
+        let serie = self.historial_cotizaciones.get(cripto)?;
+        let hasta = serie.partition_point(|p| p.instante <= instante);
+        serie[..hasta].last().map(|p| p.precio)
+    }
+
+    pub fn velas(
+        &self,
+        cripto: &str,
+        intervalo: IntervaloVela,
+        desde: i64,
+        hasta: i64,
+    ) -> Vec<Vela> {
+        let segundos = intervalo.segundos();
+        let mut velas: Vec<Vela> = Vec::new();
+        let puntos = self
+            .historial_de(cripto)
+            .iter()
+            .filter(|p| p.instante >= desde && p.instante < hasta);
+        for punto in puntos {
+            let inicio = punto.instante.div_euclid(segundos) * segundos;
+            match velas.last_mut() {
+                Some(vela) if vela.inicio == inicio => {
+                    vela.maximo = vela.maximo.max(punto.precio);
+                    vela.minimo = vela.minimo.min(punto.precio);
+                    vela.cierre = punto.precio;
+                }
+                _ => velas.push(Vela {
+                    inicio,
+                    apertura: punto.precio,
+                    maximo: punto.precio,
+                    minimo: punto.precio,
+                    cierre: punto.precio,
+                }),
+            }
+        }
+        velas
+    }
+
+    // Valua en fiat lo que el usuario tenia (incluido lo retenido) al instante dado,
+    // reconstruyendo sus saldos con los asientos del libro mayor hasta ese momento
+    pub fn valuar_balance_al(&self, dni: u128, instante: i64) -> Result<f64, ErroresApp> {
+        if !self.usuario_existe(dni) {
+            return Err(ErroresApp::NoExisteBalance);
+        }
+        let mut tenencias: HashMap<&'p str, i128> = HashMap::new();
+        let movimientos = self
+            .mayor
+            .asientos
+            .iter()
+            .filter(|a| a.instante <= instante)
+            .flat_map(|a| a.movimientos.iter())
+            .filter(|m| m.cuenta.dni() == Some(dni));
+        for movimiento in movimientos {
+            *tenencias.entry(movimiento.moneda).or_insert(0) += movimiento.unidades;
+        }
+
+        let mut total = 0.0;
+        for (moneda, unidades) in tenencias {
+            let tenencia = Monto::new(unidades, self.precision_de(moneda)).a_f64();
+            if moneda == MONEDA_FIAT {
+                total += tenencia;
+                continue;
+            }
+            if unidades == 0 {
+                continue;
+            }
+            let precio = self
+                .cotizacion_al(moneda, instante)
+                .ok_or(ErroresApp::SinCotizacion)?;
+            total += tenencia * precio;
+        }
+        Ok(total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instanciar_plataforma<'p>() -> Plataforma<'p> {
+        let mut plataforma = Plataforma::new();
+        let blockchain = Blockchain {
+            nombre: "Ethereum",
+            prefijo: "ETH",
+            confirmaciones_requeridas: 12,
+        };
+        let mut blockchains = HashMap::new();
+        blockchains.insert("ETH", blockchain);
+        let cripto = Criptomoneda {
+            nombre: "Bitcoin",
+            prefijo: "BTC",
+            cotizacion: 50000.0,
+            blockchain_disponibles: blockchains.clone(),
+        };
+        plataforma.agregar_criptomoneda(cripto).unwrap();
+        plataforma
+            .crear_usuario("Simon", "Bierozko", "asdf@gmail@.com", 123)
+            .unwrap();
+        plataforma.validar_usuario(123).unwrap();
+        plataforma.ingresar_fiat(123, 100000.0).unwrap();
+        plataforma
+    }
+
+    #[test]
+    fn test_usuario_existe() {
+        let plataforma = instanciar_plataforma();
+        assert!(plataforma.usuario_existe(123));
+        assert!(!plataforma.usuario_existe(999));
+    }
+
+    #[test]
+    fn test_usuario_validado() {
+        let plataforma = instanciar_plataforma();
+        assert!(plataforma.usuario_validado(123));
+    }
+
+    #[test]
+    fn test_verificar_disponible() {
+        let mut plataforma = instanciar_plataforma();
+        plataforma.comprar_cripto(123, "BTC", 1.0).unwrap();
+        let fiat = |monto| a_unidades(monto, DECIMALES_FIAT);
+        let btc = |monto| a_unidades(monto, DECIMALES_CRIPTO);
+        assert!(
+            plataforma
+                .verificar_disponible(123, MONEDA_FIAT, fiat(10.0))
+                .is_ok()
+        );
+        assert!(
+            plataforma
+                .verificar_disponible(123, MONEDA_FIAT, fiat(200000.0))
+                .is_err()
+        );
+        assert!(
+            plataforma
+                .verificar_disponible(123, "BTC", btc(1.0))
+                .is_ok()
+        );
+        assert!(
+            plataforma
+                .verificar_disponible(123, "BTC", btc(2.0))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_balance_de() {
+        let mut plataforma = instanciar_plataforma();
+        plataforma.comprar_cripto(123, "BTC", 1.0).unwrap();
+        // El balance se arma con los saldos del libro mayor
+        let balance = plataforma.balance_de(123).unwrap();
+        assert_eq!(balance.balance_fiat, plataforma.saldo_de(123, MONEDA_FIAT));
+        assert_eq!(balance.balance_criptos["BTC"].a_f64(), 1.0);
+        assert_eq!(balance.criptos_retenidas["BTC"].a_f64(), 0.0);
+        assert!(!balance.balance_criptos.contains_key("ETH"));
+        assert_eq!(
+            plataforma.balance_de(999).unwrap_err(),
+            ErroresApp::NoExisteBalance
+        );
+    }
+
+    #[test]
+    fn test_criptomoneda_existe_en_sistema() {
+        let plataforma = instanciar_plataforma();
+        assert!(plataforma.criptomoneda_existe_en_sistema("BTC"));
+        assert!(!plataforma.criptomoneda_existe_en_sistema("ETH"));
+    }
+
+    #[test]
+    fn test_recuperar_criptomonedas_en_sistema() {
+        let plataforma = instanciar_plataforma();
+        let criptos = plataforma.recuperar_criptomonedas_en_sistema();
+        assert_eq!(criptos, vec!["BTC"]);
+    }
+
+    #[test]
+    fn test_recuperar_datos_criptomoneda() {
+        let plataforma = instanciar_plataforma();
+        assert!(plataforma.recuperar_datos_criptomoneda("BTC").is_ok());
+        assert!(plataforma.recuperar_datos_criptomoneda("ETH").is_err());
+    }
+
+    #[test]
+    fn test_criptomoneda_opera_en_blockchain() {
+        let plataforma = instanciar_plataforma();
+        assert!(
+            plataforma
+                .criptomoneda_opera_en_blockchain("BTC", "ETH")
+                .unwrap()
+        );
+        assert!(
+            !plataforma
+                .criptomoneda_opera_en_blockchain("BTC", "SOL")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_simular_envio_hash() {
+        let plataforma = instanciar_plataforma();
+        let hash = plataforma.simular_envio_hash();
+        assert_eq!(hash.len(), 20);
+    }
+
+    #[test]
+    fn test_recuperar_usuario() {
+        let mut plataforma = instanciar_plataforma();
+        assert!(plataforma.recuperar_usuario(123).is_ok());
+        assert!(plataforma.recuperar_usuario(999).is_err());
+    }
+
+    #[test]
+    fn test_realizar_chequeos_de_usuario() {
+        let plataforma = instanciar_plataforma();
+        assert!(
+            plataforma
+                .realizar_chequeos_de_usuario(123, true, true)
+                .is_ok()
+        );
+        assert!(
+            plataforma
+                .realizar_chequeos_de_usuario(999, true, false)
+                .is_err()
+        );
+        assert!(
+            plataforma
+                .realizar_chequeos_de_usuario(123, true, true)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_vender_cripto() {
+        let mut plataforma = instanciar_plataforma();
+        plataforma.ingresar_fiat(123, 100000000.0).unwrap();
+        plataforma.comprar_cripto(123, "BTC", 5.0).unwrap();
+        let op = plataforma.vender_cripto(123, "BTC", 2.0).unwrap();
+        assert_eq!(op.usuario, 123);
+    }
+
+    #[test]
+    fn test_comprar_cripto() {
+        let mut plataforma = instanciar_plataforma();
+        plataforma.ingresar_fiat(123, 9000000.0).unwrap();
+        let op = plataforma.comprar_cripto(123, "BTC", 3.0).unwrap();
+        assert_eq!(op.usuario, 123);
+    }
+
+    #[test]
+    fn test_retirar_cripto() {
+        let mut plataforma = instanciar_plataforma();
+        plataforma.ingresar_fiat(123, 10000000.0).unwrap();
+        plataforma.comprar_cripto(123, "BTC", 5.0).unwrap();
+        let op = plataforma.retirar_cripto(123, "BTC", 2.0, "ETH").unwrap();
+        assert_eq!(op.usuario, 123);
+    }
+
+    #[test]
+    fn test_recibir_cripto() {
+        let mut plataforma = instanciar_plataforma();
+        let op = plataforma.recibir_cripto(123, "BTC", 2.0, "ETH").unwrap();
+        assert_eq!(op.usuario, 123);
     }
 
     #[test]
@@ -791,6 +2503,7 @@ mod tests {
         let blockchain = Blockchain {
             nombre: "Solana",
             prefijo: "SOL",
+            confirmaciones_requeridas: 32,
         };
         let mut blockchains = HashMap::new();
         blockchains.insert("SOL", blockchain);
@@ -810,6 +2523,7 @@ mod tests {
         let blockchain = Blockchain {
             nombre: "Ethereum",
             prefijo: "ETH",
+            confirmaciones_requeridas: 12,
         };
         let mut blockchains = HashMap::new();
         blockchains.insert("ETH", blockchain);
@@ -830,4 +2544,874 @@ mod tests {
         let res = plataforma.validar_usuario(123);
         assert_eq!(res.unwrap(), 123);
     }
+
+    #[test]
+    fn test_orden_limite_queda_en_libro_y_retiene_fondos() {
+        let mut plataforma = instanciar_plataforma();
+        plataforma
+            .crear_usuario("Cosme", "Fulanito", "cosme@gmail.com", 456)
+            .unwrap();
+        plataforma.validar_usuario(456).unwrap();
+        plataforma.recibir_cripto(456, "BTC", 5.0, "ETH").unwrap();
+        plataforma.minar_bloques("ETH", 12).unwrap();
+        let res = plataforma
+            .colocar_orden(
+                123,
+                "BTC",
+                LadoOrden::Compra,
+                TipoOrden::Limite(40000.0),
+                2.0,
+            )
+            .unwrap();
+        assert_eq!(res.orden.estado, EstadoOrden::Abierta);
+        assert!(res.operaciones.is_empty());
+
+        let balance = plataforma.balance_de(123).unwrap();
+        assert_eq!(balance.balance_fiat.a_f64(), 20000.0);
+        assert_eq!(balance.fiat_retenido.a_f64(), 80000.0);
+        assert_eq!(
+            plataforma.libro_de("BTC").unwrap().mejor_compra(),
+            Some(40000.0)
+        );
+
+        // No alcanza el fiat disponible para una segunda orden
+        assert_eq!(
+            plataforma
+                .colocar_orden(
+                    123,
+                    "BTC",
+                    LadoOrden::Compra,
+                    TipoOrden::Limite(40000.0),
+                    1.0
+                )
+                .unwrap_err(),
+            ErroresApp::MontoInsuficiente
+        );
+    }
+
+    #[test]
+    fn test_prioridad_precio_tiempo_y_llenado_parcial() {
+        let mut plataforma = instanciar_plataforma();
+        plataforma
+            .crear_usuario("Cosme", "Fulanito", "cosme@gmail.com", 456)
+            .unwrap();
+        plataforma.validar_usuario(456).unwrap();
+        plataforma.recibir_cripto(456, "BTC", 5.0, "ETH").unwrap();
+        plataforma.minar_bloques("ETH", 12).unwrap();
+        let cara = plataforma
+            .colocar_orden(
+                456,
+                "BTC",
+                LadoOrden::Venta,
+                TipoOrden::Limite(45000.0),
+                1.0,
+            )
+            .unwrap();
+        let primera = plataforma
+            .colocar_orden(
+                456,
+                "BTC",
+                LadoOrden::Venta,
+                TipoOrden::Limite(40000.0),
+                1.0,
+            )
+            .unwrap();
+        let segunda = plataforma
+            .colocar_orden(
+                456,
+                "BTC",
+                LadoOrden::Venta,
+                TipoOrden::Limite(40000.0),
+                1.0,
+            )
+            .unwrap();
+        assert_eq!(
+            plataforma
+                .libro_de("BTC")
+                .unwrap()
+                .profundidad(LadoOrden::Venta),
+            vec![
+                (40000.0, Monto::desde_f64(2.0, DECIMALES_CRIPTO)),
+                (45000.0, Monto::desde_f64(1.0, DECIMALES_CRIPTO))
+            ]
+        );
+
+        // Compra 1.5 a 42000: llena la primera a 40000 y la mitad de la segunda
+        let res = plataforma
+            .colocar_orden(
+                123,
+                "BTC",
+                LadoOrden::Compra,
+                TipoOrden::Limite(42000.0),
+                1.5,
+            )
+            .unwrap();
+        assert_eq!(res.orden.estado, EstadoOrden::Completada);
+        assert_eq!(res.operaciones.len(), 4);
+        assert_eq!(
+            res.operaciones[0].tipo,
+            DetalleOperacion::CRIPTOCOMPRAR("BTC", 1.0, 40000.0)
+        );
+        assert_eq!(
+            res.operaciones[3].tipo,
+            DetalleOperacion::CRIPTOVENDER("BTC", 0.5, 40000.0)
+        );
+        assert_eq!(
+            plataforma.buscar_orden(primera.orden.id).unwrap().estado,
+            EstadoOrden::Completada
+        );
+        let parcial = plataforma.buscar_orden(segunda.orden.id).unwrap();
+        assert_eq!(parcial.estado, EstadoOrden::Parcial);
+        assert_eq!(parcial.pendiente.a_f64(), 0.5);
+        assert_eq!(
+            plataforma.buscar_orden(cara.orden.id).unwrap().estado,
+            EstadoOrden::Abierta
+        );
+
+        // El comprador paga 60000 y no le queda nada retenido por la mejora de precio
+        let comprador = plataforma.balance_de(123).unwrap();
+        assert_eq!(comprador.balance_fiat.a_f64(), 40000.0);
+        assert_eq!(comprador.fiat_retenido.a_f64(), 0.0);
+        assert_eq!(comprador.balance_criptos["BTC"].a_f64(), 1.5);
+        let vendedor = plataforma.balance_de(456).unwrap();
+        assert_eq!(vendedor.balance_fiat.a_f64(), 60000.0);
+        assert_eq!(vendedor.criptos_retenidas["BTC"].a_f64(), 1.5);
+        assert_eq!(vendedor.balance_criptos["BTC"].a_f64(), 2.0);
+        assert_eq!(plataforma.criptomonedas["BTC"].cotizacion, 40000.0);
+    }
+
+    #[test]
+    fn test_ejecuciones_del_libro_no_cobran_comision() {
+        let mut plataforma = instanciar_plataforma();
+        plataforma
+            .configurar_comisiones(ConfiguracionComisiones::new(0.01, 0.02, 0.005))
+            .unwrap();
+        plataforma
+            .crear_usuario("Cosme", "Fulanito", "cosme@gmail.com", 456)
+            .unwrap();
+        plataforma.validar_usuario(456).unwrap();
+        plataforma.recibir_cripto(456, "BTC", 5.0, "ETH").unwrap();
+        plataforma.minar_bloques("ETH", 12).unwrap();
+        plataforma
+            .colocar_orden(
+                456,
+                "BTC",
+                LadoOrden::Venta,
+                TipoOrden::Limite(40000.0),
+                1.0,
+            )
+            .unwrap();
+        let res = plataforma
+            .colocar_orden(123, "BTC", LadoOrden::Compra, TipoOrden::Mercado, 1.0)
+            .unwrap();
+
+        assert!(res.operaciones.iter().all(|o| o.comision.is_none()));
+        assert_eq!(plataforma.saldo_de(123, MONEDA_FIAT).a_f64(), 60000.0);
+        assert_eq!(plataforma.saldo_de(456, MONEDA_FIAT).a_f64(), 40000.0);
+        assert_eq!(plataforma.mayor.saldo(Cuenta::Comisiones, MONEDA_FIAT), 0);
+    }
+
+    #[test]
+    fn test_orden_a_mercado() {
+        let mut plataforma = instanciar_plataforma();
+        plataforma
+            .crear_usuario("Cosme", "Fulanito", "cosme@gmail.com", 456)
+            .unwrap();
+        plataforma.validar_usuario(456).unwrap();
+        plataforma.recibir_cripto(456, "BTC", 5.0, "ETH").unwrap();
+        plataforma.minar_bloques("ETH", 12).unwrap();
+        assert_eq!(
+            plataforma
+                .colocar_orden(123, "BTC", LadoOrden::Compra, TipoOrden::Mercado, 1.0)
+                .unwrap_err(),
+            ErroresApp::SinLiquidez
+        );
+        plataforma
+            .colocar_orden(
+                456,
+                "BTC",
+                LadoOrden::Venta,
+                TipoOrden::Limite(30000.0),
+                1.0,
+            )
+            .unwrap();
+        plataforma
+            .colocar_orden(
+                456,
+                "BTC",
+                LadoOrden::Venta,
+                TipoOrden::Limite(35000.0),
+                1.0,
+            )
+            .unwrap();
+
+        // Pide 3 pero solo hay 2 en el libro: el resto se cancela
+        let res = plataforma
+            .colocar_orden(123, "BTC", LadoOrden::Compra, TipoOrden::Mercado, 3.0)
+            .unwrap();
+        assert_eq!(res.orden.estado, EstadoOrden::Cancelada);
+        assert_eq!(res.orden.ejecutado().a_f64(), 2.0);
+        let comprador = plataforma.balance_de(123).unwrap();
+        assert_eq!(comprador.balance_fiat.a_f64(), 35000.0);
+        assert_eq!(comprador.fiat_retenido.a_f64(), 0.0);
+        assert!(plataforma.libro_de("BTC").unwrap().ventas.is_empty());
+    }
+
+    #[test]
+    fn test_redondeo_de_ejecuciones_cuadra_con_lo_retenido() {
+        let mut plataforma = instanciar_plataforma();
+        let vendedores = [456, 789, 101];
+        for dni in vendedores {
+            plataforma
+                .crear_usuario("Cosme", "Fulanito", "cosme@gmail.com", dni)
+                .unwrap();
+            plataforma.validar_usuario(dni).unwrap();
+            plataforma.recibir_cripto(dni, "BTC", 1.0, "ETH").unwrap();
+        }
+        plataforma.minar_bloques("ETH", 12).unwrap();
+        let fiat_de = |p: &Plataforma, dni| p.saldo_de(dni, MONEDA_FIAT).unidades;
+        let iniciales: Vec<i128> = vendedores
+            .iter()
+            .map(|d| fiat_de(&plataforma, *d))
+            .collect();
+
+        // Cada ejecucion vale 0.125: la compra entrante paga 0.13 a cada vendedor
+        for dni in vendedores {
+            plataforma
+                .colocar_orden(
+                    dni,
+                    "BTC",
+                    LadoOrden::Venta,
+                    TipoOrden::Limite(50000.0),
+                    0.0000025,
+                )
+                .unwrap();
+        }
+        let antes = fiat_de(&plataforma, 123);
+        let res = plataforma
+            .colocar_orden(
+                123,
+                "BTC",
+                LadoOrden::Compra,
+                TipoOrden::Limite(50000.0),
+                0.0000075,
+            )
+            .unwrap();
+        assert_eq!(res.orden.estado, EstadoOrden::Completada);
+        for (dni, inicial) in vendedores.iter().zip(&iniciales) {
+            assert_eq!(fiat_de(&plataforma, *dni) - inicial, 13);
+        }
+        assert_eq!(antes - fiat_de(&plataforma, 123), 39);
+        assert_eq!(plataforma.saldo_retenido_de(123, MONEDA_FIAT).unidades, 0);
+
+        // Con la compra en reposo se paga en total lo retenido: 0.375 redondeado
+        let antes = fiat_de(&plataforma, 123);
+        plataforma
+            .colocar_orden(
+                123,
+                "BTC",
+                LadoOrden::Compra,
+                TipoOrden::Limite(50000.0),
+                0.0000075,
+            )
+            .unwrap();
+        let iniciales: Vec<i128> = vendedores
+            .iter()
+            .map(|d| fiat_de(&plataforma, *d))
+            .collect();
+        for dni in vendedores {
+            plataforma
+                .colocar_orden(
+                    dni,
+                    "BTC",
+                    LadoOrden::Venta,
+                    TipoOrden::Limite(50000.0),
+                    0.0000025,
+                )
+                .unwrap();
+        }
+        let cobrado: i128 = vendedores
+            .iter()
+            .zip(&iniciales)
+            .map(|(dni, inicial)| fiat_de(&plataforma, *dni) - inicial)
+            .sum();
+        assert_eq!(cobrado, 38);
+        assert_eq!(antes - fiat_de(&plataforma, 123), 38);
+        assert_eq!(plataforma.saldo_retenido_de(123, MONEDA_FIAT).unidades, 0);
+        assert!(plataforma.libro_de("BTC").unwrap().compras.is_empty());
+    }
+
+    #[test]
+    fn test_cancelar_orden_libera_fondos() {
+        let mut plataforma = instanciar_plataforma();
+        plataforma
+            .crear_usuario("Cosme", "Fulanito", "cosme@gmail.com", 456)
+            .unwrap();
+        plataforma.validar_usuario(456).unwrap();
+        plataforma.recibir_cripto(456, "BTC", 5.0, "ETH").unwrap();
+        plataforma.minar_bloques("ETH", 12).unwrap();
+        let venta = plataforma
+            .colocar_orden(
+                456,
+                "BTC",
+                LadoOrden::Venta,
+                TipoOrden::Limite(60000.0),
+                2.0,
+            )
+            .unwrap();
+        let id = venta.orden.id;
+        assert_eq!(plataforma.ordenes_abiertas(456).len(), 1);
+        assert_eq!(
+            plataforma.cancelar_orden(123, id).unwrap_err(),
+            ErroresApp::OrdenInexistente
+        );
+
+        let cancelada = plataforma.cancelar_orden(456, id).unwrap();
+        assert_eq!(cancelada.estado, EstadoOrden::Cancelada);
+        let vendedor = plataforma.balance_de(456).unwrap();
+        assert_eq!(vendedor.balance_criptos["BTC"].a_f64(), 5.0);
+        assert_eq!(vendedor.criptos_retenidas["BTC"].a_f64(), 0.0);
+        assert!(plataforma.ordenes_abiertas(456).is_empty());
+        assert_eq!(
+            plataforma.cancelar_orden(456, id).unwrap_err(),
+            ErroresApp::OrdenNoCancelable
+        );
+        assert_eq!(
+            plataforma
+                .colocar_orden(456, "BTC", LadoOrden::Venta, TipoOrden::Limite(-1.0), 1.0)
+                .unwrap_err(),
+            ErroresApp::CantidadInvalida
+        );
+    }
+
+    #[test]
+    fn test_fuente_archivo_y_cotizacion_al() {
+        let ruta = std::env::temp_dir().join("tp4_cotizaciones_btc.csv");
+        fs::write(
+            &ruta,
+            "# prefijo,instante,precio\nBTC,120,52000\nBTC,0,50000\nBTC,60,51000\n",
+        )
+        .unwrap();
+        let mut fuente = FuenteArchivo::desde_archivo(ruta.to_str().unwrap()).unwrap();
+        let mut plataforma = instanciar_plataforma();
+        assert_eq!(plataforma.consumir_fuente(&mut fuente).unwrap(), 3);
+
+        // La serie queda ordenada y la cotizacion vigente es la mas reciente
+        let instantes: Vec<i64> = plataforma
+            .historial_de("BTC")
+            .iter()
+            .map(|p| p.instante)
+            .collect();
+        assert_eq!(instantes, vec![0, 60, 120]);
+        assert_eq!(plataforma.criptomonedas["BTC"].cotizacion, 52000.0);
+        assert_eq!(plataforma.cotizacion_al("BTC", 90), Some(51000.0));
+        assert_eq!(plataforma.cotizacion_al("BTC", -1), None);
+
+        assert_eq!(
+            FuenteArchivo::desde_texto("BTC,abc,1").unwrap_err(),
+            ErroresApp::FuenteInvalida
+        );
+        let mut desconocida = FuenteArchivo::desde_texto("DOGE,0,1").unwrap();
+        assert_eq!(
+            plataforma.consumir_fuente(&mut desconocida).unwrap_err(),
+            ErroresApp::CriptoNoExiste
+        );
+    }
+
+    #[test]
+    fn test_simulador_es_reproducible() {
+        let serie = |semilla| {
+            let mut plataforma = instanciar_plataforma();
+            let mut simulador =
+                SimuladorCotizaciones::new(semilla, vec![("BTC", 50000.0)], 0, 60, 0.01, 10)
+                    .unwrap();
+            assert_eq!(plataforma.consumir_fuente(&mut simulador).unwrap(), 10);
+            plataforma.historial_de("BTC").to_vec()
+        };
+        let serie_a = serie(7);
+        assert_eq!(serie_a, serie(7));
+        assert_ne!(serie_a, serie(8));
+        assert_eq!(serie_a[9].instante, 540);
+        // Cada paso se mueve a lo sumo un 1%
+        assert!(
+            serie_a
+                .windows(2)
+                .all(|w| (w[1].precio / w[0].precio - 1.0).abs() <= 0.01 + 1e-12)
+        );
+        for volatilidad in [-0.01, f64::NAN, f64::INFINITY] {
+            assert_eq!(
+                SimuladorCotizaciones::new(7, vec![("BTC", 50000.0)], 0, 60, volatilidad, 10)
+                    .unwrap_err(),
+                ErroresApp::ConfiguracionInvalida
+            );
+        }
+    }
+
+    #[test]
+    fn test_velas_por_intervalo() {
+        let mut plataforma = instanciar_plataforma();
+        let mut fuente = FuenteArchivo::desde_texto(
+            "BTC,0,100\nBTC,20,120\nBTC,40,90\nBTC,59,110\nBTC,60,115\nBTC,3600,130",
+        )
+        .unwrap();
+        plataforma.consumir_fuente(&mut fuente).unwrap();
+
+        let velas = plataforma.velas("BTC", IntervaloVela::Minuto, 0, 3600);
+        assert_eq!(velas.len(), 2);
+        assert_eq!(
+            velas[0],
+            Vela {
+                inicio: 0,
+                apertura: 100.0,
+                maximo: 120.0,
+                minimo: 90.0,
+                cierre: 110.0
+            }
+        );
+        assert_eq!(velas[1].apertura, 115.0);
+
+        let por_hora = plataforma.velas("BTC", IntervaloVela::Hora, 0, 7200);
+        assert_eq!(por_hora.len(), 2);
+        assert_eq!(por_hora[0].cierre, 115.0);
+        assert_eq!(por_hora[1].inicio, 3600);
+    }
+
+    #[test]
+    fn test_valuar_balance_al() {
+        let mut plataforma = instanciar_plataforma();
+        let inicio = Fecha::new(1, 1, 2100).a_instante();
+        plataforma.simular_instante(Some(inicio));
+        plataforma.comprar_cripto(123, "BTC", 1.0).unwrap();
+        assert_eq!(
+            plataforma.valuar_balance_al(123, inicio).unwrap_err(),
+            ErroresApp::SinCotizacion
+        );
+        let cotizaciones = format!("BTC,{},40000\nBTC,{},60000", inicio, inicio + 86400);
+        let mut fuente = FuenteArchivo::desde_texto(&cotizaciones).unwrap();
+        plataforma.consumir_fuente(&mut fuente).unwrap();
+
+        // Antes de la compra solo tenia el fiat depositado
+        assert_eq!(
+            plataforma.valuar_balance_al(123, inicio - 1).unwrap(),
+            100000.0
+        );
+        // 50000 de fiat restante mas 1 BTC a la cotizacion de cada momento
+        assert_eq!(
+            plataforma.valuar_balance_al(123, inicio + 100).unwrap(),
+            90000.0
+        );
+        assert_eq!(
+            plataforma.valuar_balance_al(123, inicio + 86400).unwrap(),
+            110000.0
+        );
+        // Vender despues no cambia la valuacion de un momento anterior
+        plataforma.simular_instante(Some(inicio + 2 * 86400));
+        plataforma.vender_cripto(123, "BTC", 1.0).unwrap();
+        assert_eq!(
+            plataforma.valuar_balance_al(123, inicio + 100).unwrap(),
+            90000.0
+        );
+        assert_eq!(
+            plataforma
+                .valuar_balance_al(123, inicio + 2 * 86400)
+                .unwrap(),
+            110000.0
+        );
+        assert_eq!(
+            plataforma.valuar_balance_al(999, 0).unwrap_err(),
+            ErroresApp::NoExisteBalance
+        );
+    }
+
+    #[test]
+    fn test_operaciones_generan_asientos_balanceados() {
+        let mut plataforma = instanciar_plataforma();
+        plataforma
+            .crear_usuario("Cosme", "Fulanito", "cosme@gmail.com", 456)
+            .unwrap();
+        plataforma.validar_usuario(456).unwrap();
+        plataforma.recibir_cripto(456, "BTC", 5.0, "ETH").unwrap();
+        plataforma.minar_bloques("ETH", 12).unwrap();
+        let compra = plataforma.comprar_cripto(123, "BTC", 1.0).unwrap();
+        plataforma.vender_cripto(456, "BTC", 0.5).unwrap();
+        plataforma
+            .retirar_fiat(123, 1000.0, "Transferencia")
+            .unwrap();
+        plataforma
+            .colocar_orden(
+                456,
+                "BTC",
+                LadoOrden::Venta,
+                TipoOrden::Limite(45000.0),
+                1.0,
+            )
+            .unwrap();
+        plataforma
+            .colocar_orden(
+                123,
+                "BTC",
+                LadoOrden::Compra,
+                TipoOrden::Limite(45000.0),
+                0.4,
+            )
+            .unwrap();
+
+        let asientos = plataforma.asientos_de_operacion(&compra.id);
+        assert_eq!(asientos.len(), 1);
+        assert_eq!(asientos[0].movimientos.len(), 4);
+        assert!(
+            plataforma
+                .mayor
+                .asientos
+                .iter()
+                .all(|a| a.desbalances().is_empty())
+        );
+        assert!(plataforma.conciliar().is_empty());
+
+        // La mesa entrego 1 BTC y recibio 0.5
+        assert_eq!(plataforma.mayor.saldo(Cuenta::Mesa, "BTC"), -50_000_000);
+        assert_eq!(plataforma.saldo_de(123, "BTC").to_string(), "1.40000000");
+        assert_eq!(
+            plataforma.saldo_retenido_de(456, "BTC").to_string(),
+            "0.60000000"
+        );
+        assert_eq!(
+            plataforma.saldo_de(123, MONEDA_FIAT).to_string(),
+            "31000.00"
+        );
+    }
+
+    #[test]
+    fn test_precision_por_moneda_sin_deriva() {
+        let mut plataforma = instanciar_plataforma();
+        // Diez depositos de 0.1 suman exactamente 1
+        for _ in 0..10 {
+            plataforma.ingresar_fiat(123, 0.1).unwrap();
+        }
+        assert_eq!(
+            plataforma.saldo_de(123, MONEDA_FIAT).to_string(),
+            "100001.00"
+        );
+        assert_eq!(
+            plataforma.balance_de(123).unwrap().balance_fiat.a_f64(),
+            100001.0
+        );
+
+        plataforma.definir_precision("BTC", 4).unwrap();
+        plataforma
+            .recibir_cripto(123, "BTC", 1.23456789, "ETH")
+            .unwrap();
+        plataforma.minar_bloques("ETH", 12).unwrap();
+        assert_eq!(plataforma.saldo_de(123, "BTC"), Monto::new(12346, 4));
+        assert_eq!(
+            plataforma.comprar_cripto(123, "BTC", 0.00001).unwrap_err(),
+            ErroresApp::CantidadInvalida
+        );
+        assert_eq!(
+            plataforma.definir_precision("BTC", 8).unwrap_err(),
+            ErroresApp::PrecisionEnUso
+        );
+        assert_eq!(
+            plataforma.definir_precision("DOGE", 2).unwrap_err(),
+            ErroresApp::CriptoNoExiste
+        );
+        assert_eq!(Monto::new(-5, 2).to_string(), "-0.05");
+    }
+
+    #[test]
+    fn test_conciliacion_detecta_diferencias() {
+        let mut plataforma = instanciar_plataforma();
+        plataforma.comprar_cripto(123, "BTC", 1.0).unwrap();
+        assert!(plataforma.conciliar().is_empty());
+
+        // Un asiento desbalanceado cargado sin pasar por la validacion
+        plataforma.mayor.asientos.push(Asiento {
+            numero: 99,
+            instante: 0,
+            concepto: "Error manual".to_string(),
+            operaciones: vec![],
+            movimientos: vec![Movimiento {
+                cuenta: Cuenta::Mesa,
+                moneda: MONEDA_FIAT,
+                unidades: 100,
+            }],
+        });
+
+        let discrepancias = plataforma.conciliar();
+        assert!(discrepancias.contains(&Discrepancia::AsientoDesbalanceado {
+            numero: 99,
+            moneda: MONEDA_FIAT,
+            diferencia: 100
+        }));
+        assert!(discrepancias.iter().any(|d| matches!(
+            d,
+            Discrepancia::SaldoInconsistente {
+                cuenta: Cuenta::Mesa,
+                ..
+            }
+        )));
+        assert_eq!(
+            plataforma
+                .asentar("Desbalanceado", vec![], vec![(Cuenta::Mesa, "BTC", 1)])
+                .unwrap_err(),
+            ErroresApp::AsientoDesbalanceado
+        );
+    }
+
+    #[test]
+    fn test_limites_de_retiro_por_nivel_kyc() {
+        let mut plataforma = instanciar_plataforma();
+        assert_eq!(plataforma.nivel_kyc_de(123).unwrap(), NivelKyc::Completo);
+        plataforma.asignar_nivel_kyc(123, NivelKyc::Basico).unwrap();
+        plataforma.definir_limites(
+            NivelKyc::Basico,
+            LimitesRetiro::new(Some(1000.0), Some(1500.0), Some(500.0), Some(1000.0)),
+        );
+
+        plataforma.simular_fecha(Some(Fecha::new(1, 3, 2025)));
+        plataforma
+            .retirar_fiat(123, 800.0, "Transferencia")
+            .unwrap();
+        assert_eq!(
+            plataforma
+                .retirar_fiat(123, 300.0, "Transferencia")
+                .unwrap_err(),
+            ErroresApp::LimiteDiarioExcedido
+        );
+        plataforma.simular_fecha(Some(Fecha::new(2, 3, 2025)));
+        plataforma
+            .retirar_fiat(123, 600.0, "Transferencia")
+            .unwrap();
+        plataforma.simular_fecha(Some(Fecha::new(3, 3, 2025)));
+        assert_eq!(
+            plataforma
+                .retirar_fiat(123, 200.0, "Transferencia")
+                .unwrap_err(),
+            ErroresApp::LimiteMensualExcedido
+        );
+        // En el mes siguiente el cupo se renueva
+        plataforma.simular_fecha(Some(Fecha::new(1, 4, 2025)));
+        plataforma
+            .retirar_fiat(123, 200.0, "Transferencia")
+            .unwrap();
+
+        // Los retiros de cripto se valuan a la cotizacion (0.02 BTC = 1000)
+        plataforma.comprar_cripto(123, "BTC", 0.1).unwrap();
+        assert_eq!(
+            plataforma
+                .retirar_cripto(123, "BTC", 0.02, "ETH")
+                .unwrap_err(),
+            ErroresApp::LimiteDiarioExcedido
+        );
+        plataforma.retirar_cripto(123, "BTC", 0.005, "ETH").unwrap();
+
+        plataforma
+            .asignar_nivel_kyc(123, NivelKyc::Ninguno)
+            .unwrap();
+        assert_eq!(
+            plataforma
+                .retirar_fiat(123, 1.0, "Transferencia")
+                .unwrap_err(),
+            ErroresApp::UsuarioNoValidado
+        );
+    }
+
+    #[test]
+    fn test_comisiones_por_tipo_de_operacion() {
+        let mut plataforma = instanciar_plataforma();
+        let comisiones =
+            ConfiguracionComisiones::new(0.01, 0.02, 0.005).con_comision_de_red("ETH", 0.0001);
+        plataforma.configurar_comisiones(comisiones).unwrap();
+
+        // Compra de 1 BTC a 50000 con 1% de spread
+        let compra = plataforma.comprar_cripto(123, "BTC", 1.0).unwrap();
+        assert_eq!(
+            plataforma.saldo_de(123, MONEDA_FIAT).to_string(),
+            "49500.00"
+        );
+        assert!(
+            compra
+                .to_string()
+                .contains("Comision: 500 FIAT (Spread de compra)")
+        );
+
+        // Venta de 0.5 BTC con 2% de spread
+        plataforma.vender_cripto(123, "BTC", 0.5).unwrap();
+        assert_eq!(
+            plataforma.saldo_de(123, MONEDA_FIAT).to_string(),
+            "74000.00"
+        );
+
+        let retiro = plataforma.retirar_cripto(123, "BTC", 0.1, "ETH").unwrap();
+        assert_eq!(plataforma.saldo_de(123, "BTC").to_string(), "0.39990000");
+        assert!(
+            retiro
+                .to_string()
+                .contains("Comision: 0.0001 BTC (Red ETH)")
+        );
+
+        plataforma
+            .retirar_fiat(123, 1000.0, "Transferencia")
+            .unwrap();
+        assert_eq!(
+            plataforma.saldo_de(123, MONEDA_FIAT).to_string(),
+            "72995.00"
+        );
+        plataforma.minar_bloques("ETH", 12).unwrap();
+
+        assert_eq!(
+            plataforma.mayor.saldo(Cuenta::Comisiones, MONEDA_FIAT),
+            100_500
+        );
+        assert_eq!(plataforma.mayor.saldo(Cuenta::Comisiones, "BTC"), 10_000);
+        assert!(plataforma.conciliar().is_empty());
+
+        // La comision se suma al monto: no alcanza para retirar todo el saldo
+        assert_eq!(
+            plataforma
+                .retirar_fiat(123, 72995.0, "Transferencia")
+                .unwrap_err(),
+            ErroresApp::MontoInsuficiente
+        );
+        assert_eq!(
+            plataforma
+                .configurar_comisiones(ConfiguracionComisiones::new(1.5, 0.0, 0.0))
+                .unwrap_err(),
+            ErroresApp::ConfiguracionInvalida
+        );
+    }
+
+    #[test]
+    fn test_retiro_cripto_queda_pendiente_hasta_confirmarse() {
+        let mut plataforma = instanciar_plataforma();
+        let comisiones = ConfiguracionComisiones::default().con_comision_de_red("ETH", 0.0001);
+        plataforma.configurar_comisiones(comisiones).unwrap();
+        plataforma.comprar_cripto(123, "BTC", 1.0).unwrap();
+
+        let retiro = plataforma.retirar_cripto(123, "BTC", 0.5, "ETH").unwrap();
+        assert_eq!(retiro.estado, EstadoOperacion::Pendiente);
+        assert!(retiro.to_string().contains("Estado: PENDIENTE"));
+        assert_eq!(plataforma.saldo_de(123, "BTC").to_string(), "0.49990000");
+        assert_eq!(
+            plataforma.saldo_retenido_de(123, "BTC").to_string(),
+            "0.50010000"
+        );
+        let hash = plataforma.transacciones_pendientes(123)[0].hash.clone();
+
+        assert!(plataforma.minar_bloques("ETH", 11).unwrap().is_empty());
+        let transaccion = plataforma.transaccion(&hash).unwrap();
+        assert_eq!(transaccion.confirmaciones, 11);
+        assert_eq!(transaccion.estado, EstadoTransaccion::Pendiente);
+        assert_eq!(plataforma.mayor.saldo(Cuenta::Externa, "BTC"), 0);
+
+        let finalizadas = plataforma.minar_bloques("ETH", 1).unwrap();
+        assert_eq!(finalizadas.len(), 1);
+        assert_eq!(finalizadas[0].estado, EstadoTransaccion::Confirmada);
+        assert_eq!(plataforma.saldo_retenido_de(123, "BTC").unidades(), 0);
+        assert_eq!(plataforma.mayor.saldo(Cuenta::Externa, "BTC"), 50_000_000);
+        assert_eq!(plataforma.mayor.saldo(Cuenta::Comisiones, "BTC"), 10_000);
+        assert_eq!(
+            plataforma.operaciones.last().unwrap().estado,
+            EstadoOperacion::Completada
+        );
+        assert!(plataforma.transacciones_pendientes(123).is_empty());
+        assert!(plataforma.conciliar().is_empty());
+        assert_eq!(
+            plataforma.simular_falla(&hash).unwrap_err(),
+            ErroresApp::TransaccionFinalizada
+        );
+    }
+
+    #[test]
+    fn test_retiro_cripto_fallido_reintegra_fondos() {
+        let mut plataforma = instanciar_plataforma();
+        let comisiones = ConfiguracionComisiones::default().con_comision_de_red("ETH", 0.0001);
+        plataforma.configurar_comisiones(comisiones).unwrap();
+        plataforma.comprar_cripto(123, "BTC", 1.0).unwrap();
+        plataforma.retirar_cripto(123, "BTC", 0.5, "ETH").unwrap();
+        let hash = plataforma.transacciones_pendientes(123)[0].hash.clone();
+
+        plataforma.minar_bloques("ETH", 3).unwrap();
+        plataforma.simular_falla(&hash).unwrap();
+        let finalizadas = plataforma.minar_bloques("ETH", 1).unwrap();
+        assert_eq!(finalizadas[0].estado, EstadoTransaccion::Fallida);
+
+        // Se devuelve el monto y la comision de red
+        assert_eq!(plataforma.saldo_de(123, "BTC").to_string(), "1.00000000");
+        assert_eq!(plataforma.saldo_retenido_de(123, "BTC").unidades(), 0);
+        assert_eq!(plataforma.mayor.saldo(Cuenta::Comisiones, "BTC"), 0);
+        assert!(
+            plataforma
+                .operaciones
+                .last()
+                .unwrap()
+                .to_string()
+                .contains("Estado: FALLIDA")
+        );
+        assert!(plataforma.conciliar().is_empty());
+        assert_eq!(
+            plataforma.simular_falla("inexistente").unwrap_err(),
+            ErroresApp::TransaccionInexistente
+        );
+    }
+
+    #[test]
+    fn test_deposito_cripto_se_acredita_al_confirmarse() {
+        let mut plataforma = instanciar_plataforma();
+        plataforma.recibir_cripto(123, "BTC", 2.0, "ETH").unwrap();
+        assert_eq!(plataforma.saldo_de(123, "BTC").unidades(), 0);
+        assert_eq!(plataforma.transacciones_pendientes(123).len(), 1);
+
+        plataforma.minar_bloques("ETH", 12).unwrap();
+        assert_eq!(plataforma.saldo_de(123, "BTC").to_string(), "2.00000000");
+        assert!(plataforma.conciliar().is_empty());
+
+        // Una cadena que rechaza todo hace fallar el deposito sin acreditar nada
+        plataforma
+            .configurar_cadena("ETH", CadenaSimulada::new(7, 1.0).unwrap())
+            .unwrap();
+        plataforma.recibir_cripto(123, "BTC", 1.0, "ETH").unwrap();
+        // Con el deposito en la mempool no se puede cambiar la cadena
+        assert_eq!(
+            plataforma
+                .configurar_cadena("ETH", CadenaSimulada::default())
+                .unwrap_err(),
+            ErroresApp::CadenaConPendientes
+        );
+        let finalizadas = plataforma.minar_bloques("ETH", 1).unwrap();
+        assert_eq!(finalizadas[0].estado, EstadoTransaccion::Fallida);
+        assert_eq!(plataforma.saldo_de(123, "BTC").to_string(), "2.00000000");
+        assert!(
+            plataforma
+                .configurar_cadena("ETH", CadenaSimulada::default())
+                .is_ok()
+        );
+        for probabilidad in [-0.1, 1.5, f64::NAN] {
+            assert_eq!(
+                CadenaSimulada::new(7, probabilidad).unwrap_err(),
+                ErroresApp::ConfiguracionInvalida
+            );
+        }
+    }
+
+    #[test]
+    fn test_red_sin_confirmaciones_es_inmediata() {
+        let mut plataforma = instanciar_plataforma();
+        let blockchain = Blockchain {
+            nombre: "Lightning",
+            prefijo: "LN",
+            confirmaciones_requeridas: 0,
+        };
+        plataforma
+            .criptomonedas
+            .get_mut("BTC")
+            .unwrap()
+            .blockchain_disponibles
+            .insert("LN", blockchain);
+
+        let deposito = plataforma.recibir_cripto(123, "BTC", 1.0, "LN").unwrap();
+        assert_eq!(deposito.estado, EstadoOperacion::Completada);
+        assert_eq!(plataforma.saldo_de(123, "BTC").to_string(), "1.00000000");
+        assert!(plataforma.transacciones_pendientes(123).is_empty());
+    }
 }
@@ -123,6 +123,18 @@ impl Fecha {
         }
     }
 
+    pub fn get_dia(&self) -> u32 {
+        self.dia
+    }
+
+    pub fn get_mes(&self) -> u32 {
+        self.mes
+    }
+
+    pub fn get_ano(&self) -> u32 {
+        self.ano
+    }
+
     pub fn set_fecha(&mut self, dia: u32, mes: u32, ano: u32) {
         self.dia = dia;
         self.mes = mes;
@@ -139,6 +151,22 @@ impl Fecha {
     pub fn to_string(&self) -> String {
         format!("{}/{}/{}", self.dia, self.mes, self.ano)
     }
+
+    // Dia (UTC) que corresponde a un instante en segundos unix
+    pub fn desde_instante(segundos: i64) -> Self {
+        let dia = DateTime::from_timestamp(segundos, 0)
+            .unwrap_or_default()
+            .date_naive();
+        Fecha::new(dia.day(), dia.month(), dia.year() as u32)
+    }
+
+    // Segundos unix al comienzo del dia (UTC)
+    pub fn a_instante(&self) -> i64 {
+        NaiveDate::from_ymd_opt(self.ano as i32, self.mes, self.dia)
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .map(|d| d.and_utc().timestamp())
+            .unwrap_or(0)
+    }
 }
 
 impl fmt::Display for Fecha {
@@ -1,7 +1,64 @@
+use crate::ejercicios::fecha::Fecha;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufReader, Write};
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum ErroresApp {
+    ErrorDeArchivo,
+    ConfiguracionInvalida,
+    VinInvalido,
+    VinDuplicado,
+    CapacidadExcedida,
+    UnidadInexistente,
+    CambioDeEstadoInvalido,
+    UnidadNoDisponible,
+    SenaInvalida,
+    ReservaInexistente,
+    ReservaVencida,
+    VentaInexistente,
+    CuotasNoDisponibles,
+    UsadoSuperaPrecio,
+    TasaInvalida,
+}
+
+impl fmt::Display for ErroresApp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErroresApp::ErrorDeArchivo => write!(f, "No fue posible acceder al archivo"),
+            ErroresApp::ConfiguracionInvalida => {
+                write!(f, "El archivo de configuracion no es valido")
+            }
+            ErroresApp::VinInvalido => write!(f, "El VIN ingresado no es valido"),
+            ErroresApp::VinDuplicado => write!(f, "Ya existe una unidad con ese VIN"),
+            ErroresApp::CapacidadExcedida => {
+                write!(f, "La concesionaria no tiene lugar para otra unidad")
+            }
+            ErroresApp::UnidadInexistente => write!(f, "No existe una unidad con ese VIN"),
+            ErroresApp::CambioDeEstadoInvalido => {
+                write!(f, "La unidad no puede pasar a ese estado")
+            }
+            ErroresApp::UnidadNoDisponible => write!(f, "La unidad no esta disponible"),
+            ErroresApp::SenaInvalida => write!(f, "El monto de la seña no es valido"),
+            ErroresApp::ReservaInexistente => write!(f, "No existe una reserva con ese numero"),
+            ErroresApp::ReservaVencida => write!(f, "La reserva esta vencida"),
+            ErroresApp::VentaInexistente => write!(f, "No existe una venta con ese numero"),
+            ErroresApp::CuotasNoDisponibles => {
+                write!(f, "No hay una tasa definida para esa cantidad de cuotas")
+            }
+            ErroresApp::UsadoSuperaPrecio => {
+                write!(f, "La tasacion del usado supera el precio de la unidad")
+            }
+            ErroresApp::TasaInvalida => {
+                write!(f, "La cantidad de cuotas o la tasa no es valida")
+            }
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Color {
@@ -13,6 +70,33 @@ pub enum Color {
     NEGRO,
 }
 
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum EstadoUnidad {
+    #[default]
+    EnStock,
+    Reservado,
+    Vendido,
+}
+
+#[allow(dead_code)]
+impl EstadoUnidad {
+    // Las reservadas siguen en el salon hasta que se entregan
+    pub fn ocupa_lugar(&self) -> bool {
+        *self != EstadoUnidad::Vendido
+    }
+
+    pub fn puede_pasar_a(&self, nuevo: EstadoUnidad) -> bool {
+        matches!(
+            (self, nuevo),
+            (EstadoUnidad::EnStock, EstadoUnidad::Reservado)
+                | (EstadoUnidad::EnStock, EstadoUnidad::Vendido)
+                | (EstadoUnidad::Reservado, EstadoUnidad::EnStock)
+                | (EstadoUnidad::Reservado, EstadoUnidad::Vendido)
+        )
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Auto {
@@ -21,6 +105,53 @@ pub struct Auto {
     pub ano: u16,
     pub color: Color,
     pub precio_bruto: f64,
+    #[serde(default)]
+    pub vin: String,
+    #[serde(default)]
+    pub estado: EstadoUnidad,
+    #[serde(default)]
+    pub kilometraje: u32,
+    #[serde(default)]
+    pub fecha_ingreso: Option<Fecha>,
+}
+
+// Digito verificador del VIN (posicion 9) segun la norma ISO 3779 / NHTSA
+#[allow(dead_code)]
+pub fn vin_es_valido(vin: &str) -> bool {
+    const PESOS: [u32; 17] = [8, 7, 6, 5, 4, 3, 2, 10, 0, 9, 8, 7, 6, 5, 4, 3, 2];
+    fn valor(c: char) -> Option<u32> {
+        match c {
+            '0'..='9' => c.to_digit(10),
+            'A' | 'J' => Some(1),
+            'B' | 'K' | 'S' => Some(2),
+            'C' | 'L' | 'T' => Some(3),
+            'D' | 'M' | 'U' => Some(4),
+            'E' | 'N' | 'V' => Some(5),
+            'F' | 'W' => Some(6),
+            'G' | 'P' | 'X' => Some(7),
+            'H' | 'Y' => Some(8),
+            'R' | 'Z' => Some(9),
+            // I, O y Q no se usan para no confundirlas con 1 y 0
+            _ => None,
+        }
+    }
+
+    let caracteres: Vec<char> = vin.chars().collect();
+    if caracteres.len() != 17 {
+        return false;
+    }
+    let mut suma = 0;
+    for (c, peso) in caracteres.iter().zip(PESOS) {
+        match valor(*c) {
+            Some(v) => suma += v * peso,
+            None => return false,
+        }
+    }
+    let esperado = match suma % 11 {
+        10 => 'X',
+        resto => char::from_digit(resto, 10).unwrap(),
+    };
+    caracteres[8] == esperado
 }
 
 #[allow(dead_code)]
@@ -32,43 +163,642 @@ impl Auto {
             ano,
             color,
             precio_bruto,
+            vin: String::new(),
+            estado: EstadoUnidad::EnStock,
+            kilometraje: 0,
+            fecha_ingreso: None,
         }
     }
 
+    pub fn como_unidad(
+        mut self,
+        vin: &str,
+        kilometraje: u32,
+        fecha_ingreso: Fecha,
+    ) -> Result<Auto, ErroresApp> {
+        let vin = vin.trim().to_uppercase();
+        if !vin_es_valido(&vin) {
+            return Err(ErroresApp::VinInvalido);
+        }
+        self.vin = vin;
+        self.kilometraje = kilometraje;
+        self.fecha_ingreso = Some(fecha_ingreso);
+        Ok(self)
+    }
+
+    // Usa las reglas por defecto; dentro de una concesionaria usar Concesionaria::calcular_precio
     pub fn calcular_precio(&mut self) -> f64 {
-        let mut precio_final: f64 = self.precio_bruto;
+        self.calcular_precio_con(&MotorPrecios::por_defecto())
+    }
 
-        precio_final += self.percepciones_por_color();
-        precio_final += self.percepciones_por_marca();
-        precio_final += self.percepciones_por_ano();
+    pub fn calcular_precio_con(&self, motor: &MotorPrecios) -> f64 {
+        motor.calcular(self).precio_final
+    }
 
-        precio_final
+    pub fn to_string(&self) -> String {
+        format!("{:?}", self)
     }
+}
 
-    fn percepciones_por_color(&self) -> f64 {
-        let primarios: [Color; 3] = [Color::ROJO, Color::AZUL, Color::AMARILLO];
-        if primarios.contains(&self.color) {
-            return self.precio_bruto * 0.25;
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CondicionPrecio {
+    Siempre,
+    Marca(String),
+    Modelo(String),
+    AnoMenorA(u16),
+    AnoDesde(u16),
+    ColorEn(Vec<Color>),
+    PrecioMayorA(f64),
+    PrecioMenorA(f64),
+    No(Box<CondicionPrecio>),
+    Todas(Vec<CondicionPrecio>),
+    Alguna(Vec<CondicionPrecio>),
+}
+
+#[allow(dead_code)]
+impl CondicionPrecio {
+    pub fn cumple(&self, auto: &Auto) -> bool {
+        match self {
+            CondicionPrecio::Siempre => true,
+            CondicionPrecio::Marca(marca) => auto.marca == *marca,
+            CondicionPrecio::Modelo(modelo) => auto.modelo == *modelo,
+            CondicionPrecio::AnoMenorA(ano) => auto.ano < *ano,
+            CondicionPrecio::AnoDesde(ano) => auto.ano >= *ano,
+            CondicionPrecio::ColorEn(colores) => colores.contains(&auto.color),
+            CondicionPrecio::PrecioMayorA(precio) => auto.precio_bruto > *precio,
+            CondicionPrecio::PrecioMenorA(precio) => auto.precio_bruto < *precio,
+            CondicionPrecio::No(condicion) => !condicion.cumple(auto),
+            CondicionPrecio::Todas(condiciones) => condiciones.iter().all(|c| c.cumple(auto)),
+            CondicionPrecio::Alguna(condiciones) => condiciones.iter().any(|c| c.cumple(auto)),
         }
-        -1.0 * self.precio_bruto * 0.10
     }
+}
 
-    fn percepciones_por_marca(&self) -> f64 {
-        if self.marca == "BMW" {
-            return self.precio_bruto * 0.15;
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Ajuste {
+    // Porcentaje sobre el precio bruto (15.0 es +15%, -10.0 es -10%)
+    Porcentaje(f64),
+    Fijo(f64),
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ModoAcumulacion {
+    Acumulable,
+    // Si aplica, no se evalua ninguna regla de menor prioridad
+    Exclusiva,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReglaPrecio {
+    pub nombre: String,
+    pub condicion: CondicionPrecio,
+    pub ajuste: Ajuste,
+    pub prioridad: u32,
+    pub modo: ModoAcumulacion,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineaDesglose {
+    pub regla: String,
+    pub ajuste: Ajuste,
+    pub monto: f64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DesglosePrecio {
+    pub precio_bruto: f64,
+    pub lineas: Vec<LineaDesglose>,
+    pub precio_final: f64,
+}
+
+impl fmt::Display for DesglosePrecio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Precio bruto: {:.2}", self.precio_bruto)?;
+        for linea in &self.lineas {
+            let detalle = match linea.ajuste {
+                Ajuste::Porcentaje(p) => format!("{:+}%", p),
+                Ajuste::Fijo(_) => "fijo".to_string(),
+            };
+            writeln!(f, "  {} ({}): {:+.2}", linea.regla, detalle, linea.monto)?;
+        }
+        write!(f, "Precio final: {:.2}", self.precio_final)
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MotorPrecios {
+    pub reglas: Vec<ReglaPrecio>,
+}
+
+#[allow(dead_code)]
+impl MotorPrecios {
+    // Las mismas percepciones que antes estaban fijas en Auto::calcular_precio
+    pub fn por_defecto() -> Self {
+        let primarios = vec![Color::ROJO, Color::AZUL, Color::AMARILLO];
+        let regla = |nombre: &str, condicion, ajuste, prioridad| ReglaPrecio {
+            nombre: nombre.to_string(),
+            condicion,
+            ajuste,
+            prioridad,
+            modo: ModoAcumulacion::Acumulable,
+        };
+        MotorPrecios {
+            reglas: vec![
+                regla(
+                    "Color primario",
+                    CondicionPrecio::ColorEn(primarios.clone()),
+                    Ajuste::Porcentaje(25.0),
+                    40,
+                ),
+                regla(
+                    "Color no primario",
+                    CondicionPrecio::No(Box::new(CondicionPrecio::ColorEn(primarios))),
+                    Ajuste::Porcentaje(-10.0),
+                    30,
+                ),
+                regla(
+                    "Marca BMW",
+                    CondicionPrecio::Marca("BMW".to_string()),
+                    Ajuste::Porcentaje(15.0),
+                    20,
+                ),
+                regla(
+                    "Anterior al 2000",
+                    CondicionPrecio::AnoMenorA(2000),
+                    Ajuste::Porcentaje(-5.0),
+                    10,
+                ),
+            ],
         }
-        0.0
     }
 
-    fn percepciones_por_ano(&self) -> f64 {
-        if self.ano < 2000 {
-            return -1.0 * self.precio_bruto * 0.05;
+    pub fn desde_archivo(ruta: &str) -> Result<Self, ErroresApp> {
+        let file = File::open(ruta).map_err(|_| ErroresApp::ErrorDeArchivo)?;
+        serde_json::from_reader(BufReader::new(file)).map_err(|_| ErroresApp::ConfiguracionInvalida)
+    }
+
+    pub fn guardar_en_archivo(&self, ruta: &str) -> Result<(), ErroresApp> {
+        let json =
+            serde_json::to_string_pretty(self).map_err(|_| ErroresApp::ConfiguracionInvalida)?;
+        File::create(ruta)
+            .and_then(|mut f| f.write_all(json.as_bytes()))
+            .map_err(|_| ErroresApp::ErrorDeArchivo)
+    }
+
+    pub fn calcular(&self, auto: &Auto) -> DesglosePrecio {
+        // Mayor prioridad primero; a igual prioridad se respeta el orden de carga
+        let mut reglas: Vec<&ReglaPrecio> = self.reglas.iter().collect();
+        reglas.sort_by_key(|r| Reverse(r.prioridad));
+
+        let mut lineas = vec![];
+        let mut precio_final = auto.precio_bruto;
+        for regla in reglas {
+            if !regla.condicion.cumple(auto) {
+                continue;
+            }
+            let monto = match regla.ajuste {
+                Ajuste::Porcentaje(porcentaje) => auto.precio_bruto * porcentaje / 100.0,
+                Ajuste::Fijo(monto) => monto,
+            };
+            precio_final += monto;
+            lineas.push(LineaDesglose {
+                regla: regla.nombre.clone(),
+                ajuste: regla.ajuste,
+                monto,
+            });
+            if regla.modo == ModoAcumulacion::Exclusiva {
+                break;
+            }
+        }
+
+        DesglosePrecio {
+            precio_bruto: auto.precio_bruto,
+            lineas,
+            precio_final,
         }
-        0.0
     }
+}
 
-    pub fn to_string(&self) -> String {
-        format!("{:?}", self)
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrdenAutos {
+    PrecioFinal,
+    Ano,
+}
+
+// Los campos en None (o colores vacio) no filtran
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConsultaAutos {
+    pub precio_final_min: Option<f64>,
+    pub precio_final_max: Option<f64>,
+    pub ano_min: Option<u16>,
+    pub ano_max: Option<u16>,
+    pub marca_contiene: Option<String>,
+    pub modelo_contiene: Option<String>,
+    pub colores: Vec<Color>,
+    pub orden: Option<(OrdenAutos, bool)>,
+    // Paginas desde 0; sin tamaño de pagina se devuelve todo
+    pub pagina: usize,
+    pub por_pagina: Option<usize>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoCotizado<'a> {
+    pub auto: &'a Auto,
+    pub precio_final: f64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultadoConsulta<'a> {
+    pub autos: Vec<AutoCotizado<'a>>,
+    pub total: usize,
+    pub pagina: usize,
+    pub total_paginas: usize,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct EstadisticaMarca {
+    pub marca: String,
+    pub cantidad: usize,
+    pub precio_final_promedio: f64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum EstadoConservacion {
+    Excelente,
+    Bueno,
+    Regular,
+    Malo,
+}
+
+#[allow(dead_code)]
+impl EstadoConservacion {
+    pub fn factor(&self) -> f64 {
+        match self {
+            EstadoConservacion::Excelente => 1.0,
+            EstadoConservacion::Bueno => 0.9,
+            EstadoConservacion::Regular => 0.75,
+            EstadoConservacion::Malo => 0.55,
+        }
+    }
+}
+
+// Porcentaje del precio que conserva el auto segun su antiguedad en años:
+// retencion[0] es el primer año; pasado el final se usa el ultimo valor
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurvaDepreciacion {
+    pub marca: Option<String>,
+    pub retencion: Vec<f64>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValuadorUsados {
+    pub curvas: Vec<CurvaDepreciacion>,
+    pub km_anuales_esperados: u32,
+    // Porcentaje que se descuenta (o suma) cada 10.000 km de diferencia
+    pub ajuste_cada_10000_km: f64,
+    pub ajuste_km_maximo: f64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tasacion {
+    pub vin: String,
+    pub valor_base: f64,
+    pub retencion: f64,
+    pub ajuste_kilometraje: f64,
+    pub factor_conservacion: f64,
+    pub valor: f64,
+}
+
+#[allow(dead_code)]
+impl CurvaDepreciacion {
+    pub fn retencion_para(&self, antiguedad: u16) -> f64 {
+        let indice = (antiguedad as usize).min(self.retencion.len().saturating_sub(1));
+        self.retencion.get(indice).copied().unwrap_or(1.0)
+    }
+}
+
+#[allow(dead_code)]
+impl ValuadorUsados {
+    pub fn por_defecto() -> Self {
+        ValuadorUsados {
+            curvas: vec![
+                CurvaDepreciacion {
+                    marca: None,
+                    retencion: vec![0.85, 0.75, 0.67, 0.6, 0.54, 0.49, 0.45, 0.41, 0.38, 0.35],
+                },
+                CurvaDepreciacion {
+                    marca: Some("BMW".to_string()),
+                    retencion: vec![0.8, 0.68, 0.58, 0.5, 0.44, 0.39, 0.35, 0.31, 0.28, 0.25],
+                },
+            ],
+            km_anuales_esperados: 15000,
+            ajuste_cada_10000_km: 3.0,
+            ajuste_km_maximo: 20.0,
+        }
+    }
+
+    pub fn desde_archivo(ruta: &str) -> Result<Self, ErroresApp> {
+        let file = File::open(ruta).map_err(|_| ErroresApp::ErrorDeArchivo)?;
+        serde_json::from_reader(BufReader::new(file)).map_err(|_| ErroresApp::ConfiguracionInvalida)
+    }
+
+    pub fn guardar_en_archivo(&self, ruta: &str) -> Result<(), ErroresApp> {
+        let json =
+            serde_json::to_string_pretty(self).map_err(|_| ErroresApp::ConfiguracionInvalida)?;
+        File::create(ruta)
+            .and_then(|mut f| f.write_all(json.as_bytes()))
+            .map_err(|_| ErroresApp::ErrorDeArchivo)
+    }
+
+    // La curva de la marca si existe; si no, la general (sin marca)
+    pub fn curva_para(&self, marca: &str) -> Option<&CurvaDepreciacion> {
+        self.curvas
+            .iter()
+            .find(|c| {
+                c.marca
+                    .as_deref()
+                    .is_some_and(|m| m.eq_ignore_ascii_case(marca))
+            })
+            .or_else(|| self.curvas.iter().find(|c| c.marca.is_none()))
+    }
+
+    pub fn valuar(
+        &self,
+        auto: &Auto,
+        conservacion: EstadoConservacion,
+        ano_actual: u16,
+    ) -> Tasacion {
+        let antiguedad = ano_actual.saturating_sub(auto.ano);
+        let retencion = self
+            .curva_para(&auto.marca)
+            .map(|c| c.retencion_para(antiguedad))
+            .unwrap_or(1.0);
+
+        // Menos km que lo esperado para la antiguedad suma, mas km resta
+        let esperados = self.km_anuales_esperados as f64 * (antiguedad.max(1)) as f64;
+        let diferencia = (esperados - auto.kilometraje as f64) / 10000.0;
+        let ajuste_kilometraje = (diferencia * self.ajuste_cada_10000_km)
+            .clamp(-self.ajuste_km_maximo, self.ajuste_km_maximo);
+
+        let valor = auto.precio_bruto
+            * retencion
+            * (1.0 + ajuste_kilometraje / 100.0)
+            * conservacion.factor();
+        Tasacion {
+            vin: auto.vin.clone(),
+            valor_base: auto.precio_bruto,
+            retencion,
+            ajuste_kilometraje,
+            factor_conservacion: conservacion.factor(),
+            valor,
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comprador {
+    pub nombre: String,
+    pub dni: String,
+    pub telefono: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reserva {
+    pub numero: u32,
+    pub vin: String,
+    pub comprador: Comprador,
+    // Precio pactado al reservar; no cambia aunque cambien las reglas de precio
+    pub precio_final: f64,
+    pub sena: f64,
+    pub fecha: Fecha,
+    pub vencimiento: Fecha,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TasaFinanciacion {
+    pub cuotas: u32,
+    // Tasa nominal anual, en porcentaje
+    pub tna: f64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfiguracionFinanciacion {
+    pub tasas: Vec<TasaFinanciacion>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CuotaFinanciacion {
+    pub numero: u32,
+    pub cuota: f64,
+    pub interes: f64,
+    pub amortizacion: f64,
+    pub saldo: f64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanFinanciacion {
+    pub monto: f64,
+    pub tna: f64,
+    pub cuotas: Vec<CuotaFinanciacion>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Venta {
+    pub numero: u32,
+    pub auto: Auto,
+    pub comprador: Comprador,
+    pub fecha: Fecha,
+    pub precio_final: f64,
+    pub sena: f64,
+    pub usado: Option<Tasacion>,
+    pub financiacion: Option<PlanFinanciacion>,
+}
+
+// Formato del archivo: las reservas y ventas se guardan junto al estado de las unidades.
+// Se siguen aceptando los archivos viejos que solo tenian la lista de autos
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ArchivoConcesionaria {
+    Completo {
+        autos: Vec<Auto>,
+        reservas: Vec<Reserva>,
+        ventas: Vec<Venta>,
+        proximo_numero: u32,
+    },
+    SoloAutos(Vec<Auto>),
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReporteMensual {
+    pub mes: u32,
+    pub ano: u32,
+    pub cantidad_ventas: usize,
+    pub total_vendido: f64,
+    pub total_financiado: f64,
+}
+
+#[allow(dead_code)]
+impl Default for ConfiguracionFinanciacion {
+    fn default() -> Self {
+        ConfiguracionFinanciacion {
+            tasas: vec![
+                TasaFinanciacion {
+                    cuotas: 6,
+                    tna: 0.0,
+                },
+                TasaFinanciacion {
+                    cuotas: 12,
+                    tna: 40.0,
+                },
+                TasaFinanciacion {
+                    cuotas: 24,
+                    tna: 50.0,
+                },
+                TasaFinanciacion {
+                    cuotas: 36,
+                    tna: 60.0,
+                },
+            ],
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl ConfiguracionFinanciacion {
+    pub fn definir_tasa(&mut self, cuotas: u32, tna: f64) -> Result<(), ErroresApp> {
+        if cuotas == 0 || !tna.is_finite() || tna < 0.0 {
+            return Err(ErroresApp::TasaInvalida);
+        }
+        match self.tasas.iter_mut().find(|t| t.cuotas == cuotas) {
+            Some(t) => t.tna = tna,
+            None => self.tasas.push(TasaFinanciacion { cuotas, tna }),
+        }
+        Ok(())
+    }
+
+    // Sistema frances: cuota constante, el interes se calcula sobre el saldo
+    pub fn cotizar(&self, monto: f64, cuotas: u32) -> Result<PlanFinanciacion, ErroresApp> {
+        let tasa = self
+            .tasas
+            .iter()
+            .find(|t| t.cuotas == cuotas)
+            .ok_or(ErroresApp::CuotasNoDisponibles)?;
+        let i = tasa.tna / 100.0 / 12.0;
+        let n = cuotas as f64;
+        let cuota = if i == 0.0 {
+            monto / n
+        } else {
+            monto * i / (1.0 - (1.0 + i).powf(-n))
+        };
+
+        let mut saldo = monto;
+        let mut detalle = vec![];
+        for numero in 1..=cuotas {
+            let interes = saldo * i;
+            let amortizacion = cuota - interes;
+            saldo -= amortizacion;
+            detalle.push(CuotaFinanciacion {
+                numero,
+                cuota,
+                interes,
+                amortizacion,
+                saldo: saldo.max(0.0),
+            });
+        }
+        Ok(PlanFinanciacion {
+            monto,
+            tna: tasa.tna,
+            cuotas: detalle,
+        })
+    }
+}
+
+#[allow(dead_code)]
+impl PlanFinanciacion {
+    pub fn total_a_pagar(&self) -> f64 {
+        self.cuotas.iter().map(|c| c.cuota).sum()
+    }
+
+    pub fn total_intereses(&self) -> f64 {
+        self.cuotas.iter().map(|c| c.interes).sum()
+    }
+}
+
+#[allow(dead_code)]
+impl Venta {
+    pub fn credito_usado(&self) -> f64 {
+        self.usado.as_ref().map(|t| t.valor).unwrap_or(0.0)
+    }
+
+    pub fn saldo_al_entregar(&self) -> f64 {
+        let saldo = self.precio_final - self.sena - self.credito_usado();
+        match &self.financiacion {
+            Some(plan) => saldo - plan.monto,
+            None => saldo,
+        }
+    }
+}
+
+impl fmt::Display for Venta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Recibo de venta N° {}", self.numero)?;
+        writeln!(f, "Fecha: {}", self.fecha)?;
+        writeln!(
+            f,
+            "Comprador: {} (DNI {})",
+            self.comprador.nombre, self.comprador.dni
+        )?;
+        writeln!(
+            f,
+            "Unidad: {} {} {} - VIN {}",
+            self.auto.marca, self.auto.modelo, self.auto.ano, self.auto.vin
+        )?;
+        writeln!(f, "Precio final: {:.2}", self.precio_final)?;
+        if self.sena > 0.0 {
+            writeln!(f, "Seña: -{:.2}", self.sena)?;
+        }
+        if let Some(usado) = &self.usado {
+            writeln!(
+                f,
+                "Usado entregado (VIN {}): -{:.2}",
+                usado.vin, usado.valor
+            )?;
+        }
+        if let Some(plan) = &self.financiacion {
+            writeln!(
+                f,
+                "Financiado: {:.2} en {} cuotas de {:.2} (TNA {:.2}%)",
+                plan.monto,
+                plan.cuotas.len(),
+                plan.cuotas.first().map(|c| c.cuota).unwrap_or(0.0),
+                plan.tna
+            )?;
+        }
+        write!(f, "Saldo abonado: {:.2}", self.saldo_al_entregar())
     }
 }
 
@@ -80,6 +810,12 @@ pub struct Concesionaria {
     pub capacidad_max: usize,
     pub autos_en_stock: Vec<Auto>,
     pub ruta_archivo: String,
+    pub motor_precios: MotorPrecios,
+    pub financiacion: ConfiguracionFinanciacion,
+    pub valuador: ValuadorUsados,
+    pub reservas: Vec<Reserva>,
+    pub ventas: Vec<Venta>,
+    pub proximo_numero: u32,
 }
 
 #[allow(dead_code)]
@@ -97,13 +833,37 @@ impl Concesionaria {
             capacidad_max,
             autos_en_stock,
             ruta_archivo,
+            motor_precios: MotorPrecios::por_defecto(),
+            financiacion: ConfiguracionFinanciacion::default(),
+            valuador: ValuadorUsados::por_defecto(),
+            reservas: vec![],
+            ventas: vec![],
+            proximo_numero: 1,
         };
-        c.autos_en_stock = c.leer_de_archivo();
+        c.leer_de_archivo();
         c
     }
 
+    pub fn cargar_reglas_de_precio(&mut self, ruta: &str) -> Result<usize, ErroresApp> {
+        self.motor_precios = MotorPrecios::desde_archivo(ruta)?;
+        Ok(self.motor_precios.reglas.len())
+    }
+
+    pub fn desglose_precio(&self, auto: &Auto) -> DesglosePrecio {
+        self.motor_precios.calcular(auto)
+    }
+
+    pub fn calcular_precio(&self, auto: &Auto) -> f64 {
+        auto.calcular_precio_con(&self.motor_precios)
+    }
+
     pub fn agregar_auto(&mut self, nuevo_auto: Auto) -> bool {
-        if self.autos_en_stock.len() >= self.capacidad_max {
+        if self.lugares_ocupados() >= self.capacidad_max {
+            return false;
+        }
+        if !nuevo_auto.vin.is_empty()
+            && (!vin_es_valido(&nuevo_auto.vin) || self.vin_en_uso(&nuevo_auto.vin))
+        {
             return false;
         }
         self.autos_en_stock.push(nuevo_auto);
@@ -111,52 +871,544 @@ impl Concesionaria {
         true
     }
 
-    pub fn eliminar_auto(&mut self, marca: String, modelo: String, ano: u16, color: Color) -> bool {
-        if self.autos_en_stock.is_empty() {
-            return false;
-        }
-        if let Some(indice) = self.obtener_posicion_auto(&marca, &modelo, ano, &color) {
-            self.autos_en_stock.remove(indice);
+    pub fn agregar_unidad(&mut self, unidad: Auto) -> Result<(), ErroresApp> {
+        if !vin_es_valido(&unidad.vin) {
+            return Err(ErroresApp::VinInvalido);
+        }
+        if self.vin_en_uso(&unidad.vin) {
+            return Err(ErroresApp::VinDuplicado);
+        }
+        if self.lugares_ocupados() >= self.capacidad_max {
+            return Err(ErroresApp::CapacidadExcedida);
+        }
+        self.autos_en_stock.push(unidad);
+        self.guardar_en_archivo();
+        Ok(())
+    }
+
+    pub fn lugares_ocupados(&self) -> usize {
+        self.autos_en_stock
+            .iter()
+            .filter(|a| a.estado.ocupa_lugar())
+            .count()
+    }
+
+    // Un VIN vendido puede volver a ingresar, asi que se prefiere la unidad que sigue en el salon
+    fn posicion_unidad(&self, vin: &str) -> Option<usize> {
+        let coincide = |a: &Auto| !a.vin.is_empty() && a.vin.eq_ignore_ascii_case(vin);
+        self.autos_en_stock
+            .iter()
+            .position(|a| a.estado.ocupa_lugar() && coincide(a))
+            .or_else(|| self.autos_en_stock.iter().position(coincide))
+    }
+
+    fn vin_en_uso(&self, vin: &str) -> bool {
+        self.buscar_unidad(vin)
+            .is_some_and(|a| a.estado.ocupa_lugar())
+    }
+
+    pub fn buscar_unidad(&self, vin: &str) -> Option<&Auto> {
+        self.posicion_unidad(vin)
+            .map(|indice| &self.autos_en_stock[indice])
+    }
+
+    pub fn eliminar_unidad(&mut self, vin: &str) -> Option<Auto> {
+        let indice = self.posicion_unidad(vin)?;
+        let unidad = self.autos_en_stock.remove(indice);
+        self.guardar_en_archivo();
+        Some(unidad)
+    }
+
+    pub fn cambiar_estado(&mut self, vin: &str, nuevo: EstadoUnidad) -> Result<(), ErroresApp> {
+        let indice = self
+            .posicion_unidad(vin)
+            .ok_or(ErroresApp::UnidadInexistente)?;
+        let unidad = &mut self.autos_en_stock[indice];
+        if !unidad.estado.puede_pasar_a(nuevo) {
+            return Err(ErroresApp::CambioDeEstadoInvalido);
+        }
+        unidad.estado = nuevo;
+        self.guardar_en_archivo();
+        Ok(())
+    }
+
+    pub fn eliminar_auto(&mut self, marca: String, modelo: String, ano: u16, color: Color) -> bool {
+        if self.autos_en_stock.is_empty() {
+            return false;
+        }
+        // Las unidades vendidas quedan como historial y no se eliminan
+        let indice = self.autos_en_stock.iter().position(|a| {
+            a.estado.ocupa_lugar()
+                && a.marca == marca
+                && a.modelo == modelo
+                && a.ano == ano
+                && a.color == color
+        });
+        if let Some(indice) = indice {
+            self.autos_en_stock.remove(indice);
+            self.guardar_en_archivo();
+            return true;
+        }
+        false
+    }
+
+    pub fn buscar_auto(&self, marca: String, modelo: String, ano: u16, color: Color) -> String {
+        if self.autos_en_stock.is_empty() {
+            return "No hay autos en stock".to_string();
+        }
+        if let Some(indice) = self.obtener_posicion_auto(&marca, &modelo, ano, &color) {
+            return self.autos_en_stock[indice].to_string();
+        }
+        "No se encontro auto con esas caracteristicas".to_string()
+    }
+
+    pub fn consultar(&self, consulta: &ConsultaAutos) -> ResultadoConsulta<'_> {
+        let contiene = |texto: &str, buscado: &Option<String>| match buscado {
+            Some(buscado) => texto.to_lowercase().contains(&buscado.to_lowercase()),
+            None => true,
+        };
+
+        let mut autos: Vec<AutoCotizado> = self
+            .autos_en_stock
+            .iter()
+            .filter(|auto| auto.estado == EstadoUnidad::EnStock)
+            .map(|auto| AutoCotizado {
+                auto,
+                precio_final: self.calcular_precio(auto),
+            })
+            .filter(|a| {
+                consulta
+                    .precio_final_min
+                    .is_none_or(|min| a.precio_final >= min)
+                    && consulta
+                        .precio_final_max
+                        .is_none_or(|max| a.precio_final <= max)
+                    && consulta.ano_min.is_none_or(|min| a.auto.ano >= min)
+                    && consulta.ano_max.is_none_or(|max| a.auto.ano <= max)
+                    && contiene(&a.auto.marca, &consulta.marca_contiene)
+                    && contiene(&a.auto.modelo, &consulta.modelo_contiene)
+                    && (consulta.colores.is_empty() || consulta.colores.contains(&a.auto.color))
+            })
+            .collect();
+
+        if let Some((orden, ascendente)) = consulta.orden {
+            autos.sort_by(|a, b| {
+                let resultado = match orden {
+                    OrdenAutos::PrecioFinal => a.precio_final.total_cmp(&b.precio_final),
+                    OrdenAutos::Ano => a.auto.ano.cmp(&b.auto.ano),
+                };
+                if ascendente {
+                    resultado
+                } else {
+                    resultado.reverse()
+                }
+            });
+        }
+
+        let total = autos.len();
+        let (autos, total_paginas) = match consulta.por_pagina {
+            Some(por_pagina) if por_pagina > 0 => {
+                let pagina = autos
+                    .into_iter()
+                    .skip(consulta.pagina.saturating_mul(por_pagina))
+                    .take(por_pagina)
+                    .collect();
+                (pagina, total.div_ceil(por_pagina))
+            }
+            // Sin paginar hay una sola pagina, salvo que no haya resultados
+            _ => {
+                let paginas = if autos.is_empty() { 0 } else { 1 };
+                (autos, paginas)
+            }
+        };
+
+        ResultadoConsulta {
+            autos,
+            total,
+            pagina: consulta.pagina,
+            total_paginas,
+        }
+    }
+
+    pub fn estadisticas_por_marca(&self) -> Vec<EstadisticaMarca> {
+        let mut estadisticas: Vec<EstadisticaMarca> = vec![];
+        let disponibles = self
+            .autos_en_stock
+            .iter()
+            .filter(|a| a.estado == EstadoUnidad::EnStock);
+        for auto in disponibles {
+            let precio_final = self.calcular_precio(auto);
+            match estadisticas.iter_mut().find(|e| e.marca == auto.marca) {
+                // Se acumula el total en el promedio y se divide al final
+                Some(estadistica) => {
+                    estadistica.cantidad += 1;
+                    estadistica.precio_final_promedio += precio_final;
+                }
+                None => estadisticas.push(EstadisticaMarca {
+                    marca: auto.marca.clone(),
+                    cantidad: 1,
+                    precio_final_promedio: precio_final,
+                }),
+            }
+        }
+        for estadistica in &mut estadisticas {
+            estadistica.precio_final_promedio /= estadistica.cantidad as f64;
+        }
+        estadisticas.sort_by(|a, b| a.marca.cmp(&b.marca));
+        estadisticas
+    }
+
+    fn obtener_posicion_auto(
+        &self,
+        marca: &str,
+        modelo: &str,
+        ano: u16,
+        color: &Color,
+    ) -> Option<usize> {
+        self.autos_en_stock.iter().position(|a| {
+            a.marca == marca && a.modelo == modelo && a.ano == ano && &a.color == color
+        })
+    }
+
+    pub fn reservar(
+        &mut self,
+        vin: &str,
+        comprador: Comprador,
+        sena: f64,
+        fecha: Fecha,
+        vencimiento: Fecha,
+    ) -> Result<u32, ErroresApp> {
+        let unidad = self
+            .buscar_unidad(vin)
+            .ok_or(ErroresApp::UnidadInexistente)?;
+        if unidad.estado != EstadoUnidad::EnStock {
+            return Err(ErroresApp::UnidadNoDisponible);
+        }
+        let precio_final = self.calcular_precio(unidad);
+        if sena <= 0.0 || sena > precio_final || vencimiento < fecha {
+            return Err(ErroresApp::SenaInvalida);
+        }
+        let vin = unidad.vin.clone();
+        self.cambiar_estado(&vin, EstadoUnidad::Reservado)?;
+
+        let numero = self.siguiente_numero();
+        self.reservas.push(Reserva {
+            numero,
+            vin,
+            comprador,
+            precio_final,
+            sena,
+            fecha,
+            vencimiento,
+        });
+        self.guardar_en_archivo();
+        Ok(numero)
+    }
+
+    pub fn cancelar_reserva(&mut self, numero: u32) -> Result<Reserva, ErroresApp> {
+        let indice = self
+            .reservas
+            .iter()
+            .position(|r| r.numero == numero)
+            .ok_or(ErroresApp::ReservaInexistente)?;
+        let reserva = self.reservas.remove(indice);
+        self.cambiar_estado(&reserva.vin, EstadoUnidad::EnStock)?;
+        self.guardar_en_archivo();
+        Ok(reserva)
+    }
+
+    // Devuelve a stock las unidades con reserva vencida; la seña no se reintegra
+    pub fn liberar_reservas_vencidas(&mut self, hoy: Fecha) -> Vec<Reserva> {
+        let (vencidas, vigentes): (Vec<Reserva>, Vec<Reserva>) =
+            self.reservas.drain(..).partition(|r| r.vencimiento < hoy);
+        self.reservas = vigentes;
+        for reserva in &vencidas {
+            let _ = self.cambiar_estado(&reserva.vin, EstadoUnidad::EnStock);
+        }
+        self.guardar_en_archivo();
+        vencidas
+    }
+
+    pub fn cotizar_financiacion(
+        &self,
+        monto: f64,
+        cuotas: u32,
+    ) -> Result<PlanFinanciacion, ErroresApp> {
+        self.financiacion.cotizar(monto, cuotas)
+    }
+
+    pub fn vender(
+        &mut self,
+        vin: &str,
+        comprador: Comprador,
+        fecha: Fecha,
+        cuotas: Option<u32>,
+    ) -> Result<u32, ErroresApp> {
+        let unidad = self
+            .buscar_unidad(vin)
+            .ok_or(ErroresApp::UnidadInexistente)?;
+        if unidad.estado != EstadoUnidad::EnStock {
+            return Err(ErroresApp::UnidadNoDisponible);
+        }
+        let venta = Venta {
+            numero: 0,
+            auto: unidad.clone(),
+            comprador,
+            fecha,
+            precio_final: self.calcular_precio(unidad),
+            sena: 0.0,
+            usado: None,
+            financiacion: None,
+        };
+        self.registrar_venta(venta, cuotas)
+    }
+
+    pub fn tasar_usado(
+        &self,
+        usado: &Auto,
+        conservacion: EstadoConservacion,
+        ano_actual: u16,
+    ) -> Tasacion {
+        self.valuador.valuar(usado, conservacion, ano_actual)
+    }
+
+    // El usado se toma como parte de pago e ingresa al stock con el valor de la tasacion
+    pub fn vender_con_usado(
+        &mut self,
+        vin: &str,
+        comprador: Comprador,
+        fecha: Fecha,
+        usado: Auto,
+        conservacion: EstadoConservacion,
+        cuotas: Option<u32>,
+    ) -> Result<u32, ErroresApp> {
+        let unidad = self
+            .buscar_unidad(vin)
+            .ok_or(ErroresApp::UnidadInexistente)?;
+        if unidad.estado != EstadoUnidad::EnStock {
+            return Err(ErroresApp::UnidadNoDisponible);
+        }
+        let (usado, tasacion) = self.preparar_usado(usado, conservacion, fecha)?;
+        let venta = Venta {
+            numero: 0,
+            auto: unidad.clone(),
+            comprador,
+            fecha,
+            precio_final: self.calcular_precio(unidad),
+            sena: 0.0,
+            usado: Some(tasacion),
+            financiacion: None,
+        };
+        self.registrar_venta_con_usado(venta, cuotas, Some(usado))
+    }
+
+    pub fn concretar_reserva(
+        &mut self,
+        numero_reserva: u32,
+        fecha: Fecha,
+        cuotas: Option<u32>,
+    ) -> Result<u32, ErroresApp> {
+        self.concretar(numero_reserva, fecha, None, cuotas)
+    }
+
+    pub fn concretar_reserva_con_usado(
+        &mut self,
+        numero_reserva: u32,
+        fecha: Fecha,
+        usado: Auto,
+        conservacion: EstadoConservacion,
+        cuotas: Option<u32>,
+    ) -> Result<u32, ErroresApp> {
+        self.concretar(numero_reserva, fecha, Some((usado, conservacion)), cuotas)
+    }
+
+    fn concretar(
+        &mut self,
+        numero_reserva: u32,
+        fecha: Fecha,
+        usado: Option<(Auto, EstadoConservacion)>,
+        cuotas: Option<u32>,
+    ) -> Result<u32, ErroresApp> {
+        let reserva = self
+            .reservas
+            .iter()
+            .find(|r| r.numero == numero_reserva)
+            .ok_or(ErroresApp::ReservaInexistente)?
+            .clone();
+        if reserva.vencimiento < fecha {
+            return Err(ErroresApp::ReservaVencida);
+        }
+        let auto = self
+            .buscar_unidad(&reserva.vin)
+            .ok_or(ErroresApp::UnidadInexistente)?
+            .clone();
+        let (usado, tasacion) = match usado {
+            Some((usado, conservacion)) => {
+                let (usado, tasacion) = self.preparar_usado(usado, conservacion, fecha)?;
+                (Some(usado), Some(tasacion))
+            }
+            None => (None, None),
+        };
+        let venta = Venta {
+            numero: 0,
+            auto,
+            comprador: reserva.comprador,
+            fecha,
+            precio_final: reserva.precio_final,
+            sena: reserva.sena,
+            usado: tasacion,
+            financiacion: None,
+        };
+        let numero = self.registrar_venta_con_usado(venta, cuotas, usado)?;
+        self.reservas.retain(|r| r.numero != numero_reserva);
+        self.guardar_en_archivo();
+        Ok(numero)
+    }
+
+    // Valida el usado y lo deja listo para ingresar al stock con el valor de la tasacion
+    fn preparar_usado(
+        &self,
+        usado: Auto,
+        conservacion: EstadoConservacion,
+        fecha: Fecha,
+    ) -> Result<(Auto, Tasacion), ErroresApp> {
+        let mut usado = usado;
+        usado.vin = usado.vin.trim().to_uppercase();
+        if !vin_es_valido(&usado.vin) {
+            return Err(ErroresApp::VinInvalido);
+        }
+        if self.vin_en_uso(&usado.vin) {
+            return Err(ErroresApp::VinDuplicado);
+        }
+        let tasacion = self.tasar_usado(&usado, conservacion, fecha.get_ano() as u16);
+        usado.precio_bruto = tasacion.valor;
+        usado.estado = EstadoUnidad::EnStock;
+        usado.fecha_ingreso = Some(fecha);
+        Ok((usado, tasacion))
+    }
+
+    // La unidad vendida libera su lugar, asi que el usado siempre entra
+    fn registrar_venta_con_usado(
+        &mut self,
+        venta: Venta,
+        cuotas: Option<u32>,
+        usado: Option<Auto>,
+    ) -> Result<u32, ErroresApp> {
+        let numero = self.registrar_venta(venta, cuotas)?;
+        if let Some(usado) = usado {
+            self.autos_en_stock.push(usado);
             self.guardar_en_archivo();
-            return true;
         }
-        false
+        Ok(numero)
     }
 
-    pub fn buscar_auto(&self, marca: String, modelo: String, ano: u16, color: Color) -> String {
-        if self.autos_en_stock.is_empty() {
-            return "No hay autos en stock".to_string();
+    // Completa el numero y la financiacion de la venta y la registra
+    fn registrar_venta(&mut self, venta: Venta, cuotas: Option<u32>) -> Result<u32, ErroresApp> {
+        let mut venta = venta;
+        let credito = venta.usado.as_ref().map(|t| t.valor).unwrap_or(0.0);
+        if venta.sena + credito > venta.precio_final {
+            return Err(ErroresApp::UsadoSuperaPrecio);
         }
-        if let Some(indice) = self.obtener_posicion_auto(&marca, &modelo, ano, &color) {
-            return self.autos_en_stock[indice].to_string();
+        // Se financia lo que queda despues de la seña y del usado
+        venta.financiacion = match cuotas {
+            Some(cuotas) => Some(
+                self.financiacion
+                    .cotizar(venta.precio_final - venta.sena - credito, cuotas)?,
+            ),
+            None => None,
+        };
+        self.cambiar_estado(&venta.auto.vin, EstadoUnidad::Vendido)?;
+
+        venta.numero = self.siguiente_numero();
+        venta.auto.estado = EstadoUnidad::Vendido;
+        let numero = venta.numero;
+        self.ventas.push(venta);
+        self.guardar_en_archivo();
+        Ok(numero)
+    }
+
+    pub fn buscar_venta(&self, numero: u32) -> Option<&Venta> {
+        self.ventas.iter().find(|v| v.numero == numero)
+    }
+
+    pub fn recibo_de_venta(&self, numero: u32) -> Result<String, ErroresApp> {
+        self.buscar_venta(numero)
+            .map(|v| v.to_string())
+            .ok_or(ErroresApp::VentaInexistente)
+    }
+
+    pub fn reporte_mensual(&self, mes: u32, ano: u32) -> ReporteMensual {
+        let mut reporte = ReporteMensual {
+            mes,
+            ano,
+            cantidad_ventas: 0,
+            total_vendido: 0.0,
+            total_financiado: 0.0,
+        };
+        for venta in &self.ventas {
+            if venta.fecha.get_mes() != mes || venta.fecha.get_ano() != ano {
+                continue;
+            }
+            reporte.cantidad_ventas += 1;
+            reporte.total_vendido += venta.precio_final;
+            if let Some(plan) = &venta.financiacion {
+                reporte.total_financiado += plan.monto;
+            }
         }
-        "No se encontro auto con esas caracteristicas".to_string()
+        reporte
     }
 
-    fn obtener_posicion_auto(
-        &self,
-        marca: &str,
-        modelo: &str,
-        ano: u16,
-        color: &Color,
-    ) -> Option<usize> {
-        self.autos_en_stock.iter().position(|a| {
-            a.marca == marca && a.modelo == modelo && a.ano == ano && &a.color == color
-        })
+    // Un reporte por cada mes con ventas, del mas antiguo al mas reciente
+    pub fn reportes_mensuales(&self) -> Vec<ReporteMensual> {
+        let mut meses: Vec<(u32, u32)> = self
+            .ventas
+            .iter()
+            .map(|v| (v.fecha.get_ano(), v.fecha.get_mes()))
+            .collect();
+        meses.sort();
+        meses.dedup();
+        meses
+            .into_iter()
+            .map(|(ano, mes)| self.reporte_mensual(mes, ano))
+            .collect()
+    }
+
+    fn siguiente_numero(&mut self) -> u32 {
+        let numero = self.proximo_numero;
+        self.proximo_numero += 1;
+        numero
     }
 
     fn guardar_en_archivo(&self) {
-        if let Ok(json) = serde_json::to_string_pretty(&self.autos_en_stock) {
+        let archivo = serde_json::json!({
+            "autos": &self.autos_en_stock,
+            "reservas": &self.reservas,
+            "ventas": &self.ventas,
+            "proximo_numero": self.proximo_numero,
+        });
+        if let Ok(json) = serde_json::to_string_pretty(&archivo) {
             let _ = File::create(&self.ruta_archivo).and_then(|mut f| f.write_all(json.as_bytes()));
         }
     }
 
-    pub fn leer_de_archivo(&self) -> Vec<Auto> {
-        if let Ok(file) = File::open(&self.ruta_archivo) {
-            let reader = BufReader::new(file);
-            serde_json::from_reader(reader).unwrap_or_default()
-        } else {
-            vec![]
+    pub fn leer_de_archivo(&mut self) {
+        let archivo = File::open(&self.ruta_archivo)
+            .ok()
+            .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok());
+        match archivo {
+            Some(ArchivoConcesionaria::Completo {
+                autos,
+                reservas,
+                ventas,
+                proximo_numero,
+            }) => {
+                self.autos_en_stock = autos;
+                self.reservas = reservas;
+                self.ventas = ventas;
+                self.proximo_numero = proximo_numero;
+            }
+            Some(ArchivoConcesionaria::SoloAutos(autos)) => self.autos_en_stock = autos,
+            None => self.autos_en_stock = vec![],
         }
     }
 }
@@ -330,10 +1582,807 @@ mod tests {
             capacidad_max: 10,
             autos_en_stock: autos.clone(),
             ruta_archivo: ruta.into(),
+            motor_precios: MotorPrecios::por_defecto(),
+            financiacion: ConfiguracionFinanciacion::default(),
+            valuador: ValuadorUsados::por_defecto(),
+            reservas: vec![],
+            ventas: vec![],
+            proximo_numero: 1,
         };
         c.guardar_en_archivo();
         let contenido = fs::read_to_string(ruta).unwrap();
         assert!(contenido.contains("clio"));
         let _ = fs::remove_file(ruta);
     }
+
+    #[test]
+    fn test_desglose_precio_por_defecto() {
+        let auto = crear_auto("BMW", "M3", 1995, Color::AZUL, 10000.0);
+        let desglose = MotorPrecios::por_defecto().calcular(&auto);
+        let reglas: Vec<&str> = desglose.lineas.iter().map(|l| l.regla.as_str()).collect();
+        assert_eq!(
+            reglas,
+            vec!["Color primario", "Marca BMW", "Anterior al 2000"]
+        );
+        assert_eq!(desglose.precio_final, 10000.0 + 2500.0 + 1500.0 - 500.0);
+        let texto = desglose.to_string();
+        assert!(texto.contains("Marca BMW (+15%): +1500.00"));
+        assert!(texto.ends_with("Precio final: 13500.00"));
+    }
+
+    #[test]
+    fn test_marca_distingue_mayusculas() {
+        let auto = crear_auto("bmw", "M3", 1995, Color::AZUL, 10000.0);
+        let desglose = MotorPrecios::por_defecto().calcular(&auto);
+        assert!(!desglose.lineas.iter().any(|l| l.regla == "Marca BMW"));
+        assert_eq!(desglose.precio_final, 10000.0 + 2500.0 - 500.0);
+    }
+
+    #[test]
+    fn test_regla_exclusiva_corta_las_de_menor_prioridad() {
+        let mut motor = MotorPrecios::por_defecto();
+        motor.reglas.push(ReglaPrecio {
+            nombre: "Liquidacion Fiat".to_string(),
+            condicion: CondicionPrecio::Todas(vec![
+                CondicionPrecio::Marca("Fiat".to_string()),
+                CondicionPrecio::PrecioMayorA(5000.0),
+            ]),
+            ajuste: Ajuste::Fijo(-3000.0),
+            prioridad: 100,
+            modo: ModoAcumulacion::Exclusiva,
+        });
+        let fiat = crear_auto("Fiat", "Uno", 2010, Color::ROJO, 10000.0);
+        assert_eq!(fiat.calcular_precio_con(&motor), 7000.0);
+        let ford = crear_auto("Ford", "Ka", 2010, Color::ROJO, 10000.0);
+        assert_eq!(ford.calcular_precio_con(&motor), 12500.0);
+    }
+
+    #[test]
+    fn test_cargar_reglas_de_archivo() {
+        let ruta_reglas = "test_cargar_reglas_de_archivo_reglas.json";
+        let ruta = "test_cargar_reglas_de_archivo.json";
+        let motor = MotorPrecios {
+            reglas: vec![ReglaPrecio {
+                nombre: "Recargo modelo".to_string(),
+                condicion: CondicionPrecio::Modelo("Gol".to_string()),
+                ajuste: Ajuste::Porcentaje(10.0),
+                prioridad: 1,
+                modo: ModoAcumulacion::Acumulable,
+            }],
+        };
+        motor.guardar_en_archivo(ruta_reglas).unwrap();
+
+        let mut c = Concesionaria::new("A".into(), "B".into(), 5, vec![], ruta.into());
+        assert_eq!(c.cargar_reglas_de_precio(ruta_reglas).unwrap(), 1);
+        let gol = crear_auto("VW", "Gol", 2018, Color::ROJO, 10000.0);
+        assert_eq!(c.desglose_precio(&gol).precio_final, 11000.0);
+        assert_eq!(c.calcular_precio(&gol), 11000.0);
+        assert_ne!(gol.clone().calcular_precio(), 11000.0);
+
+        fs::write(ruta_reglas, "{ no es json").unwrap();
+        assert_eq!(
+            c.cargar_reglas_de_precio(ruta_reglas).unwrap_err(),
+            ErroresApp::ConfiguracionInvalida
+        );
+        assert_eq!(
+            c.cargar_reglas_de_precio("no_existe.json").unwrap_err(),
+            ErroresApp::ErrorDeArchivo
+        );
+        let _ = fs::remove_file(ruta_reglas);
+    }
+
+    fn concesionaria_para_consultas(ruta: &str) -> Concesionaria {
+        let mut c = Concesionaria::new("Consultas".into(), "asdf".into(), 10, vec![], ruta.into());
+        c.autos_en_stock = vec![
+            crear_auto("Ford", "Focus", 2020, Color::NEGRO, 20000.0),
+            crear_auto("Ford", "Fiesta", 1998, Color::ROJO, 10000.0),
+            crear_auto("BMW", "Serie 3", 2022, Color::AZUL, 40000.0),
+            crear_auto("Fiat", "Uno", 2010, Color::BLANCO, 8000.0),
+            crear_auto("Fiat", "Palio", 2015, Color::ROJO, 9000.0),
+        ];
+        c
+    }
+
+    #[test]
+    fn test_consultar_sin_filtros() {
+        let c = concesionaria_para_consultas("test_consultar_sin_filtros.json");
+        let resultado = c.consultar(&ConsultaAutos::default());
+        assert_eq!(resultado.total, 5);
+        assert_eq!(resultado.autos.len(), 5);
+        assert_eq!(resultado.total_paginas, 1);
+        assert_eq!(resultado.autos[0].precio_final, 18000.0);
+    }
+
+    #[test]
+    fn test_consultar_con_filtros_y_orden() {
+        let c = concesionaria_para_consultas("test_consultar_con_filtros_y_orden.json");
+        let consulta = ConsultaAutos {
+            marca_contiene: Some("f".into()),
+            colores: vec![Color::ROJO, Color::NEGRO],
+            ano_min: Some(1999),
+            orden: Some((OrdenAutos::PrecioFinal, true)),
+            ..Default::default()
+        };
+        let resultado = c.consultar(&consulta);
+        let modelos: Vec<&str> = resultado
+            .autos
+            .iter()
+            .map(|a| a.auto.modelo.as_str())
+            .collect();
+        assert_eq!(modelos, vec!["Palio", "Focus"]);
+
+        let consulta = ConsultaAutos {
+            precio_final_min: Some(10000.0),
+            precio_final_max: Some(20000.0),
+            modelo_contiene: Some("FI".into()),
+            ..Default::default()
+        };
+        let resultado = c.consultar(&consulta);
+        assert_eq!(resultado.total, 1);
+        assert_eq!(resultado.autos[0].auto.modelo, "Fiesta");
+        assert_eq!(resultado.autos[0].precio_final, 12000.0);
+    }
+
+    #[test]
+    fn test_consultar_paginado() {
+        let c = concesionaria_para_consultas("test_consultar_paginado.json");
+        let mut consulta = ConsultaAutos {
+            orden: Some((OrdenAutos::Ano, false)),
+            por_pagina: Some(2),
+            pagina: 2,
+            ..Default::default()
+        };
+        let resultado = c.consultar(&consulta);
+        assert_eq!(resultado.total, 5);
+        assert_eq!(resultado.total_paginas, 3);
+        assert_eq!(resultado.autos.len(), 1);
+        assert_eq!(resultado.autos[0].auto.ano, 1998);
+
+        consulta.pagina = 5;
+        assert!(c.consultar(&consulta).autos.is_empty());
+
+        consulta.pagina = usize::MAX;
+        assert!(c.consultar(&consulta).autos.is_empty());
+    }
+
+    #[test]
+    fn test_consultar_sin_resultados_no_tiene_paginas() {
+        let c = concesionaria_para_consultas("test_consultar_sin_resultados.json");
+        let mut consulta = ConsultaAutos {
+            marca_contiene: Some("Tesla".into()),
+            ..Default::default()
+        };
+        assert_eq!(c.consultar(&consulta).total_paginas, 0);
+        consulta.por_pagina = Some(2);
+        assert_eq!(c.consultar(&consulta).total_paginas, 0);
+    }
+
+    #[test]
+    fn test_estadisticas_por_marca() {
+        let c = concesionaria_para_consultas("test_estadisticas_por_marca.json");
+        let estadisticas = c.estadisticas_por_marca();
+        let marcas: Vec<&str> = estadisticas.iter().map(|e| e.marca.as_str()).collect();
+        assert_eq!(marcas, vec!["BMW", "Fiat", "Ford"]);
+        assert_eq!(estadisticas[1].cantidad, 2);
+        assert_eq!(
+            estadisticas[1].precio_final_promedio,
+            (7200.0 + 11250.0) / 2.0
+        );
+        assert_eq!(estadisticas[0].precio_final_promedio, 56000.0);
+    }
+
+    const VIN_1: &str = "1M8GDM9AXKP042788";
+    const VIN_2: &str = "11111111111111111";
+
+    fn crear_unidad(vin: &str) -> Auto {
+        crear_auto("Ford", "Focus", 2020, Color::NEGRO, 20000.0)
+            .como_unidad(vin, 15000, Fecha::new(2, 1, 2025))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_vin_es_valido() {
+        assert!(vin_es_valido(VIN_1));
+        assert!(vin_es_valido(VIN_2));
+        assert!(!vin_es_valido("1M8GDM9A1KP042788"));
+        assert!(!vin_es_valido("1M8GDM9AXKP04278"));
+        assert!(!vin_es_valido("IM8GDM9AXKP042788"));
+        let auto = crear_auto("Ford", "Ka", 2018, Color::VERDE, 12000.0);
+        assert_eq!(
+            auto.como_unidad("123", 0, Fecha::new(1, 1, 2025))
+                .unwrap_err(),
+            ErroresApp::VinInvalido
+        );
+    }
+
+    #[test]
+    fn test_unidades_iguales_se_distinguen_por_vin() {
+        let ruta = "test_unidades_iguales_se_distinguen_por_vin.json";
+        let mut c = Concesionaria::new("Test".into(), "asdf".into(), 5, vec![], ruta.into());
+        c.agregar_unidad(crear_unidad(VIN_1)).unwrap();
+        c.agregar_unidad(crear_unidad(&VIN_2.to_lowercase()))
+            .unwrap();
+        assert_eq!(
+            c.agregar_unidad(crear_unidad(VIN_1)).unwrap_err(),
+            ErroresApp::VinDuplicado
+        );
+
+        let eliminada = c.eliminar_unidad(VIN_2).unwrap();
+        assert_eq!(eliminada.vin, VIN_2);
+        assert_eq!(c.autos_en_stock.len(), 1);
+        assert_eq!(c.autos_en_stock[0].vin, VIN_1);
+        assert!(c.eliminar_unidad(VIN_2).is_none());
+        let _ = fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_agregar_auto_valida_vin() {
+        let ruta = "test_agregar_auto_valida_vin.json";
+        let mut c = Concesionaria::new("Test".into(), "asdf".into(), 5, vec![], ruta.into());
+        let mut invalida = crear_unidad(VIN_1);
+        invalida.vin = "1M8GDM9A1KP042788".to_string();
+        assert!(!c.agregar_auto(invalida));
+        assert!(c.agregar_auto(crear_unidad(VIN_1)));
+        assert!(!c.agregar_auto(crear_unidad(VIN_1)));
+        assert_eq!(c.autos_en_stock.len(), 1);
+        let _ = fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_eliminar_auto_no_borra_vendidos() {
+        let ruta = "test_eliminar_auto_no_borra_vendidos.json";
+        let mut c = Concesionaria::new("Test".into(), "asdf".into(), 5, vec![], ruta.into());
+        c.agregar_unidad(crear_unidad(VIN_1)).unwrap();
+        c.agregar_unidad(crear_unidad(VIN_2)).unwrap();
+        c.cambiar_estado(VIN_1, EstadoUnidad::Vendido).unwrap();
+
+        assert!(c.eliminar_auto("Ford".into(), "Focus".into(), 2020, Color::NEGRO));
+        assert_eq!(c.autos_en_stock.len(), 1);
+        assert_eq!(c.autos_en_stock[0].vin, VIN_1);
+        assert!(!c.eliminar_auto("Ford".into(), "Focus".into(), 2020, Color::NEGRO));
+        let _ = fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_capacidad_cuenta_solo_unidades_en_salon() {
+        let ruta = "test_capacidad_cuenta_solo_unidades_en_salon.json";
+        let mut c = Concesionaria::new("Test".into(), "asdf".into(), 1, vec![], ruta.into());
+        c.agregar_unidad(crear_unidad(VIN_1)).unwrap();
+        assert_eq!(
+            c.agregar_unidad(crear_unidad(VIN_2)).unwrap_err(),
+            ErroresApp::CapacidadExcedida
+        );
+
+        c.cambiar_estado(VIN_1, EstadoUnidad::Reservado).unwrap();
+        assert_eq!(c.lugares_ocupados(), 1);
+        c.cambiar_estado(VIN_1, EstadoUnidad::Vendido).unwrap();
+        assert_eq!(c.lugares_ocupados(), 0);
+        c.agregar_unidad(crear_unidad(VIN_2)).unwrap();
+        assert_eq!(c.autos_en_stock.len(), 2);
+        let _ = fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_cambiar_estado_invalido() {
+        let ruta = "test_cambiar_estado_invalido.json";
+        let mut c = Concesionaria::new("Test".into(), "asdf".into(), 5, vec![], ruta.into());
+        c.agregar_unidad(crear_unidad(VIN_1)).unwrap();
+        c.cambiar_estado(VIN_1, EstadoUnidad::Vendido).unwrap();
+        assert_eq!(
+            c.cambiar_estado(VIN_1, EstadoUnidad::EnStock).unwrap_err(),
+            ErroresApp::CambioDeEstadoInvalido
+        );
+        assert_eq!(
+            c.cambiar_estado(VIN_2, EstadoUnidad::Reservado)
+                .unwrap_err(),
+            ErroresApp::UnidadInexistente
+        );
+        assert_eq!(c.consultar(&ConsultaAutos::default()).total, 0);
+        let _ = fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_unidad_persiste_datos_de_stock() {
+        let ruta = "test_unidad_persiste_datos_de_stock.json";
+        let mut c = Concesionaria::new("Test".into(), "asdf".into(), 5, vec![], ruta.into());
+        c.agregar_unidad(crear_unidad(VIN_1)).unwrap();
+        let leida = Concesionaria::new("Test".into(), "asdf".into(), 5, vec![], ruta.into());
+        let unidad = leida.buscar_unidad(VIN_1).unwrap();
+        assert_eq!(unidad.kilometraje, 15000);
+        assert_eq!(unidad.fecha_ingreso, Some(Fecha::new(2, 1, 2025)));
+        assert_eq!(unidad.estado, EstadoUnidad::EnStock);
+        let _ = fs::remove_file(ruta);
+    }
+
+    fn crear_comprador(nombre: &str) -> Comprador {
+        Comprador {
+            nombre: nombre.into(),
+            dni: "30111222".into(),
+            telefono: "221-555-0000".into(),
+        }
+    }
+
+    fn concesionaria_para_ventas(ruta: &str) -> Concesionaria {
+        let _ = fs::remove_file(ruta);
+        let mut c = Concesionaria::new("Test".into(), "asdf".into(), 5, vec![], ruta.into());
+        c.agregar_unidad(crear_unidad(VIN_1)).unwrap();
+        c.agregar_unidad(crear_unidad(VIN_2)).unwrap();
+        c
+    }
+
+    #[test]
+    fn test_financiacion_sistema_frances() {
+        let config = ConfiguracionFinanciacion::default();
+        let plan = config.cotizar(12000.0, 12).unwrap();
+        let i: f64 = 0.4 / 12.0;
+        let cuota_esperada = 12000.0 * i / (1.0 - (1.0 + i).powf(-12.0));
+        assert_eq!(plan.cuotas.len(), 12);
+        assert!(
+            plan.cuotas
+                .iter()
+                .all(|c| (c.cuota - cuota_esperada).abs() < 1e-6)
+        );
+        // El interes baja y la amortizacion sube cuota a cuota
+        assert!(plan.cuotas[0].interes > plan.cuotas[11].interes);
+        assert!((plan.cuotas[0].interes - 400.0).abs() < 1e-6);
+        assert!(plan.cuotas[11].saldo.abs() < 1e-6);
+        let amortizado: f64 = plan.cuotas.iter().map(|c| c.amortizacion).sum();
+        assert!((amortizado - 12000.0).abs() < 1e-6);
+
+        let sin_interes = config.cotizar(6000.0, 6).unwrap();
+        assert_eq!(sin_interes.cuotas[0].cuota, 1000.0);
+        assert_eq!(sin_interes.total_intereses(), 0.0);
+        assert_eq!(
+            config.cotizar(6000.0, 18).unwrap_err(),
+            ErroresApp::CuotasNoDisponibles
+        );
+    }
+
+    #[test]
+    fn test_definir_tasa() {
+        let mut config = ConfiguracionFinanciacion::default();
+        config.definir_tasa(12, 0.0).unwrap();
+        config.definir_tasa(18, 30.0).unwrap();
+        assert_eq!(
+            config.definir_tasa(0, 30.0).unwrap_err(),
+            ErroresApp::TasaInvalida
+        );
+        assert_eq!(
+            config.definir_tasa(24, -1.0).unwrap_err(),
+            ErroresApp::TasaInvalida
+        );
+        assert_eq!(
+            config.definir_tasa(24, f64::NAN).unwrap_err(),
+            ErroresApp::TasaInvalida
+        );
+        assert_eq!(config.cotizar(1200.0, 12).unwrap().total_a_pagar(), 1200.0);
+        assert!(config.cotizar(1200.0, 18).unwrap().total_intereses() > 0.0);
+    }
+
+    #[test]
+    fn test_reservar_y_concretar_venta() {
+        let ruta = "test_reservar_y_concretar_venta.json";
+        let mut c = concesionaria_para_ventas(ruta);
+        let reserva = c
+            .reservar(
+                VIN_1,
+                crear_comprador("Ana"),
+                1000.0,
+                Fecha::new(1, 3, 2025),
+                Fecha::new(15, 3, 2025),
+            )
+            .unwrap();
+        assert_eq!(
+            c.buscar_unidad(VIN_1).unwrap().estado,
+            EstadoUnidad::Reservado
+        );
+        assert_eq!(
+            c.vender(VIN_1, crear_comprador("Otro"), Fecha::new(2, 3, 2025), None)
+                .unwrap_err(),
+            ErroresApp::UnidadNoDisponible
+        );
+
+        let venta = c
+            .concretar_reserva(reserva, Fecha::new(10, 3, 2025), Some(12))
+            .unwrap();
+        assert!(c.reservas.is_empty());
+        assert_eq!(
+            c.buscar_unidad(VIN_1).unwrap().estado,
+            EstadoUnidad::Vendido
+        );
+
+        // Focus 2020 negro: solo el descuento por color no primario
+        let venta = c.buscar_venta(venta).unwrap();
+        assert_eq!(venta.precio_final, 18000.0);
+        assert_eq!(venta.financiacion.as_ref().unwrap().monto, 17000.0);
+        assert_eq!(venta.saldo_al_entregar(), 0.0);
+        let recibo = c.recibo_de_venta(venta.numero).unwrap();
+        assert!(recibo.contains("Ana"));
+        assert!(recibo.contains(VIN_1));
+        assert!(recibo.contains("12 cuotas"));
+        assert_eq!(
+            c.recibo_de_venta(99).unwrap_err(),
+            ErroresApp::VentaInexistente
+        );
+        let _ = fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_reservas_y_ventas_persisten() {
+        let ruta = "test_reservas_y_ventas_persisten.json";
+        let mut c = concesionaria_para_ventas(ruta);
+        let reserva = c
+            .reservar(
+                VIN_1,
+                crear_comprador("Ana"),
+                1000.0,
+                Fecha::new(1, 3, 2025),
+                Fecha::new(15, 3, 2025),
+            )
+            .unwrap();
+        let venta = c
+            .vender(VIN_2, crear_comprador("Luis"), Fecha::new(2, 3, 2025), None)
+            .unwrap();
+
+        let mut reabierta =
+            Concesionaria::new("Test".into(), "asdf".into(), 5, vec![], ruta.into());
+        assert_eq!(reabierta.reservas, c.reservas);
+        assert_eq!(reabierta.ventas, c.ventas);
+        assert_eq!(
+            reabierta.buscar_unidad(VIN_1).unwrap().estado,
+            EstadoUnidad::Reservado
+        );
+        let otra = reabierta
+            .concretar_reserva(reserva, Fecha::new(10, 3, 2025), None)
+            .unwrap();
+        assert!(otra > venta);
+        let _ = fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_reserva_congela_el_precio() {
+        let ruta = "test_reserva_congela_el_precio.json";
+        let mut c = concesionaria_para_ventas(ruta);
+        let reserva = c
+            .reservar(
+                VIN_1,
+                crear_comprador("Ana"),
+                1000.0,
+                Fecha::new(1, 3, 2025),
+                Fecha::new(15, 3, 2025),
+            )
+            .unwrap();
+        assert_eq!(c.reservas[0].precio_final, 18000.0);
+
+        // Sin reglas el Focus vale su precio bruto
+        c.motor_precios.reglas.clear();
+        let venta = c
+            .concretar_reserva(reserva, Fecha::new(10, 3, 2025), None)
+            .unwrap();
+        assert_eq!(c.buscar_venta(venta).unwrap().precio_final, 18000.0);
+        let otra = c
+            .vender(
+                VIN_2,
+                crear_comprador("Luis"),
+                Fecha::new(10, 3, 2025),
+                None,
+            )
+            .unwrap();
+        assert_eq!(c.buscar_venta(otra).unwrap().precio_final, 20000.0);
+        let _ = fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_reserva_vencida() {
+        let ruta = "test_reserva_vencida.json";
+        let mut c = concesionaria_para_ventas(ruta);
+        assert_eq!(
+            c.reservar(
+                VIN_1,
+                crear_comprador("Ana"),
+                0.0,
+                Fecha::new(1, 3, 2025),
+                Fecha::new(15, 3, 2025)
+            )
+            .unwrap_err(),
+            ErroresApp::SenaInvalida
+        );
+        let reserva = c
+            .reservar(
+                VIN_1,
+                crear_comprador("Ana"),
+                500.0,
+                Fecha::new(1, 3, 2025),
+                Fecha::new(15, 3, 2025),
+            )
+            .unwrap();
+        assert_eq!(
+            c.concretar_reserva(reserva, Fecha::new(16, 3, 2025), None)
+                .unwrap_err(),
+            ErroresApp::ReservaVencida
+        );
+
+        assert!(
+            c.liberar_reservas_vencidas(Fecha::new(15, 3, 2025))
+                .is_empty()
+        );
+        let liberadas = c.liberar_reservas_vencidas(Fecha::new(16, 3, 2025));
+        assert_eq!(liberadas.len(), 1);
+        assert_eq!(
+            c.buscar_unidad(VIN_1).unwrap().estado,
+            EstadoUnidad::EnStock
+        );
+        assert_eq!(
+            c.concretar_reserva(reserva, Fecha::new(16, 3, 2025), None)
+                .unwrap_err(),
+            ErroresApp::ReservaInexistente
+        );
+        let _ = fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_reportes_mensuales() {
+        let ruta = "test_reportes_mensuales.json";
+        let mut c = concesionaria_para_ventas(ruta);
+        c.vender(
+            VIN_2,
+            crear_comprador("Luis"),
+            Fecha::new(20, 4, 2025),
+            None,
+        )
+        .unwrap();
+        c.vender(
+            VIN_1,
+            crear_comprador("Ana"),
+            Fecha::new(3, 2, 2025),
+            Some(6),
+        )
+        .unwrap();
+
+        let reporte = c.reporte_mensual(2, 2025);
+        assert_eq!(reporte.cantidad_ventas, 1);
+        assert_eq!(reporte.total_vendido, 18000.0);
+        assert_eq!(reporte.total_financiado, 18000.0);
+        assert_eq!(c.reporte_mensual(3, 2025).cantidad_ventas, 0);
+
+        let reportes = c.reportes_mensuales();
+        assert_eq!(reportes.len(), 2);
+        assert_eq!((reportes[0].mes, reportes[1].mes), (2, 4));
+        assert_eq!(reportes[1].total_financiado, 0.0);
+        let _ = fs::remove_file(ruta);
+    }
+
+    fn crear_usado(marca: &str, ano: u16, kilometraje: u32) -> Auto {
+        let mut usado = crear_auto(marca, "Usado", ano, Color::BLANCO, 10000.0);
+        usado.vin = VIN_2.to_string();
+        usado.kilometraje = kilometraje;
+        usado
+    }
+
+    #[test]
+    fn test_valuar_usado() {
+        let valuador = ValuadorUsados::por_defecto();
+        // 3 años con los km esperados: solo depreciacion
+        let t = valuador.valuar(
+            &crear_usado("Ford", 2022, 45000),
+            EstadoConservacion::Excelente,
+            2025,
+        );
+        assert_eq!(t.retencion, 0.6);
+        assert!(t.ajuste_kilometraje.abs() < 1e-9);
+        assert!((t.valor - 6000.0).abs() < 1e-6);
+
+        // La curva de BMW deprecia mas rapido
+        let bmw = valuador.valuar(
+            &crear_usado("bmw", 2022, 45000),
+            EstadoConservacion::Excelente,
+            2025,
+        );
+        assert_eq!(bmw.retencion, 0.5);
+
+        // 20.000 km de mas restan 6%, y el estado regular un 25%
+        let t = valuador.valuar(
+            &crear_usado("Ford", 2022, 65000),
+            EstadoConservacion::Regular,
+            2025,
+        );
+        assert!((t.ajuste_kilometraje + 6.0).abs() < 1e-9);
+        assert!((t.valor - 10000.0 * 0.6 * 0.94 * 0.75).abs() < 1e-6);
+
+        // Pasado el final de la curva se mantiene el ultimo valor
+        let viejo = valuador.valuar(
+            &crear_usado("Ford", 1990, 0),
+            EstadoConservacion::Bueno,
+            2025,
+        );
+        assert_eq!(viejo.retencion, 0.35);
+        assert_eq!(viejo.ajuste_kilometraje, 20.0);
+    }
+
+    #[test]
+    fn test_valuador_desde_archivo() {
+        let ruta = "test_valuador_desde_archivo.json";
+        let mut valuador = ValuadorUsados::por_defecto();
+        valuador.curvas.push(CurvaDepreciacion {
+            marca: Some("Ford".into()),
+            retencion: vec![0.9],
+        });
+        valuador.guardar_en_archivo(ruta).unwrap();
+
+        let leido = ValuadorUsados::desde_archivo(ruta).unwrap();
+        assert_eq!(leido, valuador);
+        assert_eq!(leido.curva_para("FORD").unwrap().retencion_para(7), 0.9);
+        assert!(leido.curva_para("Reno").unwrap().marca.is_none());
+        let _ = fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_vender_con_usado() {
+        let ruta = "test_vender_con_usado.json";
+        let _ = fs::remove_file(ruta);
+        let mut c = Concesionaria::new("Test".into(), "asdf".into(), 1, vec![], ruta.into());
+        c.agregar_unidad(crear_unidad(VIN_1)).unwrap();
+
+        let usado = crear_usado("Ford", 2022, 45000);
+        let venta = c
+            .vender_con_usado(
+                VIN_1,
+                crear_comprador("Ana"),
+                Fecha::new(5, 5, 2025),
+                usado,
+                EstadoConservacion::Excelente,
+                Some(12),
+            )
+            .unwrap();
+
+        // Precio 18000 menos 6000 del usado
+        let venta = c.buscar_venta(venta).unwrap();
+        assert!((venta.credito_usado() - 6000.0).abs() < 1e-6);
+        assert!((venta.financiacion.as_ref().unwrap().monto - 12000.0).abs() < 1e-6);
+        assert!(venta.saldo_al_entregar().abs() < 1e-6);
+        assert!(
+            c.recibo_de_venta(venta.numero)
+                .unwrap()
+                .contains("Usado entregado")
+        );
+
+        // El usado ocupa el lugar que libero la unidad vendida
+        let ingresado = c.buscar_unidad(VIN_2).unwrap();
+        assert_eq!(ingresado.estado, EstadoUnidad::EnStock);
+        assert_eq!(ingresado.fecha_ingreso, Some(Fecha::new(5, 5, 2025)));
+        assert!((ingresado.precio_bruto - 6000.0).abs() < 1e-6);
+        assert_eq!(c.lugares_ocupados(), 1);
+        let _ = fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_vender_con_usado_invalido() {
+        let ruta = "test_vender_con_usado_invalido.json";
+        let mut c = concesionaria_para_ventas(ruta);
+        let mut sin_vin = crear_usado("Ford", 2022, 45000);
+        sin_vin.vin = String::new();
+        let resultado = c.vender_con_usado(
+            VIN_1,
+            crear_comprador("Ana"),
+            Fecha::new(5, 5, 2025),
+            sin_vin,
+            EstadoConservacion::Bueno,
+            None,
+        );
+        assert_eq!(resultado.unwrap_err(), ErroresApp::VinInvalido);
+
+        let mut caro = crear_usado("Ford", 2025, 0);
+        caro.vin = "2".repeat(17);
+        caro.precio_bruto = 100000.0;
+        let resultado = c.vender_con_usado(
+            VIN_1,
+            crear_comprador("Ana"),
+            Fecha::new(5, 5, 2025),
+            caro,
+            EstadoConservacion::Excelente,
+            None,
+        );
+        assert_eq!(resultado.unwrap_err(), ErroresApp::UsadoSuperaPrecio);
+        assert_eq!(
+            c.buscar_unidad(VIN_1).unwrap().estado,
+            EstadoUnidad::EnStock
+        );
+        assert!(c.ventas.is_empty());
+        assert_eq!(c.autos_en_stock.len(), 2);
+        let _ = fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_recibir_como_usado_un_auto_vendido() {
+        let ruta = "test_recibir_como_usado_un_auto_vendido.json";
+        let _ = fs::remove_file(ruta);
+        let mut c = Concesionaria::new("Test".into(), "asdf".into(), 2, vec![], ruta.into());
+        c.agregar_unidad(crear_unidad(VIN_1)).unwrap();
+        c.agregar_unidad(crear_unidad(VIN_2)).unwrap();
+        c.vender(VIN_2, crear_comprador("Luis"), Fecha::new(1, 5, 2025), None)
+            .unwrap();
+
+        // El VIN vuelve en minusculas como parte de pago de otra venta
+        let mut usado = crear_usado("Ford", 2022, 45000);
+        usado.vin = VIN_2.to_lowercase();
+        c.vender_con_usado(
+            VIN_1,
+            crear_comprador("Ana"),
+            Fecha::new(5, 5, 2025),
+            usado,
+            EstadoConservacion::Excelente,
+            None,
+        )
+        .unwrap();
+        let ingresado = c.buscar_unidad(VIN_2).unwrap();
+        assert_eq!(ingresado.vin, VIN_2);
+        assert_eq!(ingresado.estado, EstadoUnidad::EnStock);
+        assert_eq!(c.autos_en_stock.len(), 3);
+
+        // Ahora si esta en el salon y no puede volver a ingresar
+        let mut c2 = concesionaria_para_ventas("test_recibir_como_usado_duplicado.json");
+        let resultado = c2.vender_con_usado(
+            VIN_1,
+            crear_comprador("Ana"),
+            Fecha::new(5, 5, 2025),
+            crear_usado("Ford", 2022, 45000),
+            EstadoConservacion::Excelente,
+            None,
+        );
+        assert_eq!(resultado.unwrap_err(), ErroresApp::VinDuplicado);
+        assert!(c2.ventas.is_empty());
+        let _ = fs::remove_file(ruta);
+        let _ = fs::remove_file("test_recibir_como_usado_duplicado.json");
+    }
+
+    #[test]
+    fn test_concretar_reserva_con_usado() {
+        let ruta = "test_concretar_reserva_con_usado.json";
+        let _ = fs::remove_file(ruta);
+        let mut c = Concesionaria::new("Test".into(), "asdf".into(), 1, vec![], ruta.into());
+        c.agregar_unidad(crear_unidad(VIN_1)).unwrap();
+        let reserva = c
+            .reservar(
+                VIN_1,
+                crear_comprador("Ana"),
+                1000.0,
+                Fecha::new(1, 5, 2025),
+                Fecha::new(15, 5, 2025),
+            )
+            .unwrap();
+
+        let mut invalido = crear_usado("Ford", 2022, 45000);
+        invalido.vin = "123".into();
+        assert_eq!(
+            c.concretar_reserva_con_usado(
+                reserva,
+                Fecha::new(5, 5, 2025),
+                invalido,
+                EstadoConservacion::Excelente,
+                None,
+            )
+            .unwrap_err(),
+            ErroresApp::VinInvalido
+        );
+        assert_eq!(c.reservas.len(), 1);
+
+        let venta = c
+            .concretar_reserva_con_usado(
+                reserva,
+                Fecha::new(5, 5, 2025),
+                crear_usado("Ford", 2022, 45000),
+                EstadoConservacion::Excelente,
+                Some(12),
+            )
+            .unwrap();
+        // Precio 18000 menos la seña de 1000 y los 6000 del usado
+        let venta = c.buscar_venta(venta).unwrap();
+        assert!((venta.financiacion.as_ref().unwrap().monto - 11000.0).abs() < 1e-6);
+        assert!(c.reservas.is_empty());
+        assert_eq!(
+            c.buscar_unidad(VIN_2).unwrap().estado,
+            EstadoUnidad::EnStock
+        );
+        let _ = fs::remove_file(ruta);
+    }
 }
@@ -1,105 +1,1589 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
 use std::fs::File;
 use std::io::{BufReader, Write};
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[allow(dead_code)]
 enum Genero {
     Rock,
     Pop,
     Rap,
     Jazz,
+    #[default]
     Otros,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[allow(dead_code)]
 struct Cancion {
     titulo: String,
     artista: String,
     genero: Genero,
+    #[serde(default)]
+    duracion_segundos: u32,
+    #[serde(default)]
+    album: String,
+    #[serde(default)]
+    ano: u16,
+    #[serde(default)]
+    numero_pista: u32,
+    // De 1 a 5; 0 significa que no fue puntuada
+    #[serde(default)]
+    puntuacion: u8,
+    // Ruta o URL del archivo de audio, necesaria para M3U/PLS/XSPF
+    #[serde(default)]
+    ubicacion: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(dead_code)]
+enum CampoOrden {
+    Titulo,
+    Artista,
+    Genero,
+    Duracion,
+    Album,
+    Ano,
+    NumeroPista,
+    Puntuacion,
+}
+
+#[allow(dead_code)]
+impl CampoOrden {
+    pub fn comparar(&self, a: &Cancion, b: &Cancion) -> Ordering {
+        match self {
+            CampoOrden::Titulo => a.titulo.to_lowercase().cmp(&b.titulo.to_lowercase()),
+            CampoOrden::Artista => a.artista.to_lowercase().cmp(&b.artista.to_lowercase()),
+            CampoOrden::Genero => a.genero.cmp(&b.genero),
+            CampoOrden::Duracion => a.duracion_segundos.cmp(&b.duracion_segundos),
+            CampoOrden::Album => a.album.to_lowercase().cmp(&b.album.to_lowercase()),
+            CampoOrden::Ano => a.ano.cmp(&b.ano),
+            CampoOrden::NumeroPista => a.numero_pista.cmp(&b.numero_pista),
+            CampoOrden::Puntuacion => a.puntuacion.cmp(&b.puntuacion),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
-struct Playlist {
-    nombre: String,
-    canciones: Vec<Cancion>,
-    #[serde(skip)]
-    ruta_archivo: String,
+struct Playlist {
+    nombre: String,
+    canciones: Vec<Cancion>,
+    #[serde(skip)]
+    ruta_archivo: String,
+}
+
+#[allow(dead_code)]
+impl Playlist {
+    pub fn new(nombre: &str) -> Self {
+        Playlist {
+            nombre: nombre.to_string(),
+            canciones: Vec::new(),
+            ruta_archivo: format!("{}.json", nombre),
+        }
+    }
+
+    pub fn agregar_cancion(&mut self, cancion: Cancion) {
+        self.canciones.push(cancion);
+        self.guardar_en_archivo();
+    }
+
+    pub fn eliminar_cancion(&mut self, titulo: &str) {
+        self.canciones.retain(|c| c.titulo != titulo);
+        self.guardar_en_archivo();
+    }
+
+    pub fn mover_cancion(&mut self, titulo: &str, nueva_pos: usize) {
+        if let Some(indice) = self.canciones.iter().position(|c| c.titulo == titulo) {
+            if nueva_pos < self.canciones.len() {
+                let cancion = self.canciones.remove(indice);
+                self.canciones.insert(nueva_pos, cancion);
+                self.guardar_en_archivo();
+            }
+        }
+    }
+
+    pub fn modificar_titulo(&mut self, nuevo_nombre: &str) {
+        self.nombre = nuevo_nombre.to_string();
+    }
+
+    pub fn eliminar_todas(&mut self) {
+        self.canciones.clear();
+        self.guardar_en_archivo();
+    }
+
+    fn guardar_en_archivo(&self) {
+        if let Ok(json) = serde_json::to_string_pretty(&self) {
+            let _ = File::create(&self.ruta_archivo).and_then(|mut f| f.write_all(json.as_bytes()));
+        }
+    }
+
+    fn leer_de_archivo(&self) -> Vec<Cancion> {
+        if let Ok(file) = File::open(&self.ruta_archivo) {
+            let reader = BufReader::new(file);
+            if let Ok(playlist_leida) = serde_json::from_reader::<_, Playlist>(reader) {
+                return playlist_leida.canciones;
+            }
+        }
+        Vec::new()
+    }
+
+    pub fn buscar_cancion(&self, titulo: &str) -> Option<&Cancion> {
+        self.canciones.iter().find(|c| c.titulo == titulo)
+    }
+
+    pub fn obtener_canciones_por_genero(&self, genero: Genero) -> Vec<&Cancion> {
+        self.canciones
+            .iter()
+            .filter(|c| c.genero == genero)
+            .collect()
+    }
+
+    pub fn obtener_canciones_por_artista(&self, artista: &str) -> Vec<&Cancion> {
+        self.canciones
+            .iter()
+            .filter(|c| c.artista == artista)
+            .collect()
+    }
+
+    pub fn duracion_total(&self) -> u32 {
+        self.canciones.iter().map(|c| c.duracion_segundos).sum()
+    }
+
+    pub fn puntuacion_promedio(&self) -> Option<f64> {
+        let puntuadas: Vec<u8> = self
+            .canciones
+            .iter()
+            .map(|c| c.puntuacion)
+            .filter(|p| *p > 0)
+            .collect();
+        if puntuadas.is_empty() {
+            return None;
+        }
+        Some(puntuadas.iter().map(|p| *p as f64).sum::<f64>() / puntuadas.len() as f64)
+    }
+
+    pub fn generar_hasta_minutos(&self, nombre: &str, minutos: u32) -> Playlist {
+        let limite = minutos.saturating_mul(60);
+        let mut nueva = Playlist::new(nombre);
+        let mut acumulado: u32 = 0;
+        // Se recorre en orden y se saltean las que ya no entran
+        for cancion in &self.canciones {
+            if acumulado.saturating_add(cancion.duracion_segundos) <= limite {
+                acumulado += cancion.duracion_segundos;
+                nueva.canciones.push(cancion.clone());
+            }
+        }
+        nueva
+    }
+
+    pub fn ordenar_por(&mut self, campo: CampoOrden, ascendente: bool) {
+        self.canciones.sort_by(|a, b| {
+            let orden = campo.comparar(a, b);
+            if ascendente { orden } else { orden.reverse() }
+        });
+        self.guardar_en_archivo();
+    }
+
+    pub fn buscar_aproximado(&self, consulta: &str) -> Vec<&Cancion> {
+        let consulta = normalizar(consulta);
+        if consulta.is_empty() {
+            return vec![];
+        }
+
+        let mut encontradas: Vec<(usize, &Cancion)> = self
+            .canciones
+            .iter()
+            .filter_map(|c| {
+                let distancia = distancia_aproximada(&consulta, &normalizar(&c.titulo))
+                    .min(distancia_aproximada(&consulta, &normalizar(&c.artista)));
+                (distancia <= tolerancia(&consulta)).then_some((distancia, c))
+            })
+            .collect();
+        // Las coincidencias exactas quedan primero
+        encontradas.sort_by_key(|(distancia, _)| *distancia);
+        encontradas.into_iter().map(|(_, c)| c).collect()
+    }
+}
+
+// Pasa a minusculas y saca tildes para que "Canción" y "cancion" coincidan
+fn normalizar(texto: &str) -> String {
+    texto
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'ä' | 'â' => 'a',
+            'é' | 'è' | 'ë' | 'ê' => 'e',
+            'í' | 'ì' | 'ï' | 'î' => 'i',
+            'ó' | 'ò' | 'ö' | 'ô' => 'o',
+            'ú' | 'ù' | 'ü' | 'û' => 'u',
+            'ñ' => 'n',
+            _ => c,
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn tolerancia(consulta: &str) -> usize {
+    match consulta.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+// Menor distancia entre la consulta y cualquier tramo del texto con la misma cantidad de palabras
+fn distancia_aproximada(consulta: &str, texto: &str) -> usize {
+    if texto.contains(consulta) {
+        return 0;
+    }
+    let palabras_consulta = consulta.split(' ').count();
+    let palabras: Vec<&str> = texto.split(' ').collect();
+    if palabras.len() <= palabras_consulta {
+        return distancia_levenshtein(consulta, texto);
+    }
+    palabras
+        .windows(palabras_consulta)
+        .map(|tramo| distancia_levenshtein(consulta, &tramo.join(" ")))
+        .min()
+        .unwrap_or(usize::MAX)
+}
+
+fn distancia_levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut fila: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = fila[0];
+        fila[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let arriba = fila[j + 1];
+            fila[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(arriba).min(fila[j])
+            };
+            diagonal = arriba;
+        }
+    }
+    fila[b.len()]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(dead_code)]
+enum ModoRepeticion {
+    Ninguna,
+    Una,
+    Todas,
+}
+
+#[derive(Debug)]
+#[allow(dead_code)]
+struct Reproductor {
+    canciones: Vec<Cancion>,
+    orden: Vec<usize>,
+    cursor: Option<usize>,
+    sonando: Option<Cancion>,
+    repeticion: ModoRepeticion,
+    aleatorio: bool,
+    cola: VecDeque<Cancion>,
+    historial: Vec<Cancion>,
+    rng: StdRng,
+}
+
+#[allow(dead_code)]
+impl Reproductor {
+    pub fn new(playlist: &Playlist, semilla: u64) -> Self {
+        Reproductor {
+            canciones: playlist.canciones.clone(),
+            orden: (0..playlist.canciones.len()).collect(),
+            cursor: None,
+            sonando: None,
+            repeticion: ModoRepeticion::Ninguna,
+            aleatorio: false,
+            cola: VecDeque::new(),
+            historial: vec![],
+            rng: StdRng::seed_from_u64(semilla),
+        }
+    }
+
+    pub fn actual(&self) -> Option<&Cancion> {
+        self.sonando.as_ref()
+    }
+
+    pub fn historial(&self) -> &[Cancion] {
+        &self.historial
+    }
+
+    pub fn set_repeticion(&mut self, modo: ModoRepeticion) {
+        self.repeticion = modo;
+    }
+
+    pub fn set_aleatorio(&mut self, aleatorio: bool) {
+        self.aleatorio = aleatorio;
+        let actual = self.cursor.map(|c| self.orden[c]);
+        if aleatorio {
+            // La cancion que esta sonando queda primera y el resto se mezcla detras
+            let pendientes: Vec<usize> = (0..self.canciones.len())
+                .filter(|i| Some(*i) != actual)
+                .collect();
+            let ultimo_artista = actual.map(|i| self.canciones[i].artista.clone());
+            let mezcla = self.mezclar(pendientes, ultimo_artista.as_deref());
+            self.orden = actual.into_iter().chain(mezcla).collect();
+            self.cursor = actual.map(|_| 0);
+        } else {
+            self.orden = (0..self.canciones.len()).collect();
+            self.cursor = actual;
+        }
+    }
+
+    pub fn agregar_a_continuacion(&mut self, cancion: Cancion) {
+        self.cola.push_back(cancion);
+    }
+
+    pub fn siguiente(&mut self) -> Option<&Cancion> {
+        // Lo pedido con "a continuacion" suena antes que la repeticion de la actual
+        if let Some(cancion) = self.cola.pop_front() {
+            return self.reproducir(cancion);
+        }
+        if self.repeticion == ModoRepeticion::Una
+            && let Some(c) = self.cursor
+        {
+            let cancion = self.canciones[self.orden[c]].clone();
+            return self.reproducir(cancion);
+        }
+        if self.orden.is_empty() {
+            return None;
+        }
+
+        let proximo = match self.cursor {
+            None => 0,
+            Some(c) if c + 1 < self.orden.len() => c + 1,
+            Some(_) => {
+                if self.repeticion != ModoRepeticion::Todas {
+                    self.sonando = None;
+                    return None;
+                }
+                if self.aleatorio {
+                    let ultimo_artista = self.sonando.as_ref().map(|c| c.artista.clone());
+                    let indices = (0..self.canciones.len()).collect();
+                    self.orden = self.mezclar(indices, ultimo_artista.as_deref());
+                }
+                0
+            }
+        };
+        self.cursor = Some(proximo);
+        let cancion = self.canciones[self.orden[proximo]].clone();
+        self.reproducir(cancion)
+    }
+
+    pub fn anterior(&mut self) -> Option<&Cancion> {
+        let anterior = match self.cursor {
+            Some(c) if c > 0 => c - 1,
+            Some(_) if self.repeticion == ModoRepeticion::Todas => self.orden.len() - 1,
+            _ => return None,
+        };
+        self.cursor = Some(anterior);
+        let cancion = self.canciones[self.orden[anterior]].clone();
+        self.reproducir(cancion)
+    }
+
+    fn reproducir(&mut self, cancion: Cancion) -> Option<&Cancion> {
+        self.historial.push(cancion.clone());
+        self.sonando = Some(cancion);
+        self.sonando.as_ref()
+    }
+
+    fn mezclar(&mut self, mut pendientes: Vec<usize>, ultimo_artista: Option<&str>) -> Vec<usize> {
+        let mut orden = vec![];
+        let mut ultimo = ultimo_artista.map(|a| a.to_string());
+
+        while !pendientes.is_empty() {
+            let mut conteo: HashMap<&str, usize> = HashMap::new();
+            for i in &pendientes {
+                *conteo
+                    .entry(self.canciones[*i].artista.as_str())
+                    .or_insert(0) += 1;
+            }
+
+            // Un candidato sirve si no repite artista y, despues de elegirlo,
+            // todavia se pueden ordenar las restantes sin dos seguidas del mismo artista
+            let restantes = pendientes.len() - 1;
+            let candidatos: Vec<usize> = (0..pendientes.len())
+                .filter(|pos| {
+                    let artista = self.canciones[pendientes[*pos]].artista.as_str();
+                    if Some(artista) == ultimo.as_deref() {
+                        return false;
+                    }
+                    conteo.iter().all(|(a, cantidad)| {
+                        if *a == artista {
+                            cantidad - 1 <= restantes / 2
+                        } else {
+                            *cantidad <= restantes.div_ceil(2)
+                        }
+                    })
+                })
+                .collect();
+
+            // Si no hay forma de evitar la repeticion se elige cualquiera
+            let pos = if candidatos.is_empty() {
+                self.rng.gen_range(0..pendientes.len())
+            } else {
+                candidatos[self.rng.gen_range(0..candidatos.len())]
+            };
+            let elegido = pendientes.remove(pos);
+            ultimo = Some(self.canciones[elegido].artista.clone());
+            orden.push(elegido);
+        }
+        orden
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(dead_code)]
+enum ErroresImportacion {
+    EncabezadoFaltante { linea: usize },
+    LineaInvalida { linea: usize, contenido: String },
+    NumeroInvalido { linea: usize, contenido: String },
+    EntradaIncompleta { linea: usize },
+    XmlInvalido { linea: usize, detalle: String },
+    FormatoDesconocido,
+    ErrorDeArchivo,
+}
+
+impl fmt::Display for ErroresImportacion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErroresImportacion::EncabezadoFaltante { linea } => {
+                write!(f, "Linea {}: falta el encabezado de la playlist", linea)
+            }
+            ErroresImportacion::LineaInvalida { linea, contenido } => {
+                write!(f, "Linea {}: no se reconoce '{}'", linea, contenido)
+            }
+            ErroresImportacion::NumeroInvalido { linea, contenido } => {
+                write!(f, "Linea {}: '{}' no es un numero valido", linea, contenido)
+            }
+            ErroresImportacion::EntradaIncompleta { linea } => {
+                write!(f, "Linea {}: la entrada no indica el archivo", linea)
+            }
+            ErroresImportacion::XmlInvalido { linea, detalle } => {
+                write!(f, "Linea {}: XML invalido ({})", linea, detalle)
+            }
+            ErroresImportacion::FormatoDesconocido => {
+                write!(f, "El formato de la playlist no es soportado")
+            }
+            ErroresImportacion::ErrorDeArchivo => {
+                write!(f, "No fue posible acceder al archivo")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(dead_code)]
+enum FormatoPlaylist {
+    M3u,
+    Pls,
+    Xspf,
+}
+
+#[allow(dead_code)]
+impl FormatoPlaylist {
+    pub fn desde_ruta(ruta: &str) -> Option<FormatoPlaylist> {
+        // La extension sale del nombre del archivo, no de las carpetas
+        let archivo = ruta.rsplit(['/', '\\']).next()?;
+        let (_, extension) = archivo.rsplit_once('.')?;
+        match extension.to_lowercase().as_str() {
+            "m3u" | "m3u8" => Some(FormatoPlaylist::M3u),
+            "pls" => Some(FormatoPlaylist::Pls),
+            "xspf" => Some(FormatoPlaylist::Xspf),
+            _ => None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl Genero {
+    pub fn desde_texto(texto: &str) -> Genero {
+        match texto.trim().to_lowercase().as_str() {
+            "rock" => Genero::Rock,
+            "pop" => Genero::Pop,
+            "rap" => Genero::Rap,
+            "jazz" => Genero::Jazz,
+            _ => Genero::Otros,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl Cancion {
+    // Los reproductores guardan "Artista - Titulo" en un solo campo
+    fn nombre_para_mostrar(&self) -> String {
+        if self.artista.is_empty() {
+            self.titulo.clone()
+        } else {
+            format!("{} - {}", self.artista, self.titulo)
+        }
+    }
+
+    fn desde_nombre_para_mostrar(texto: &str) -> Cancion {
+        match texto.split_once(" - ") {
+            Some((artista, titulo)) => Cancion {
+                titulo: titulo.trim().to_string(),
+                artista: artista.trim().to_string(),
+                ..Default::default()
+            },
+            None => Cancion {
+                titulo: texto.trim().to_string(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl Playlist {
+    pub fn exportar(&self, formato: FormatoPlaylist) -> String {
+        match formato {
+            FormatoPlaylist::M3u => self.exportar_m3u(),
+            FormatoPlaylist::Pls => self.exportar_pls(),
+            FormatoPlaylist::Xspf => self.exportar_xspf(),
+        }
+    }
+
+    pub fn importar(
+        nombre: &str,
+        contenido: &str,
+        formato: FormatoPlaylist,
+    ) -> Result<Playlist, ErroresImportacion> {
+        let canciones = match formato {
+            FormatoPlaylist::M3u => importar_m3u(contenido)?,
+            FormatoPlaylist::Pls => importar_pls(contenido)?,
+            FormatoPlaylist::Xspf => importar_xspf(contenido)?,
+        };
+        let mut playlist = Playlist::new(nombre);
+        playlist.canciones = canciones;
+        Ok(playlist)
+    }
+
+    pub fn exportar_a_archivo(&self, ruta: &str) -> Result<(), ErroresImportacion> {
+        let formato =
+            FormatoPlaylist::desde_ruta(ruta).ok_or(ErroresImportacion::FormatoDesconocido)?;
+        File::create(ruta)
+            .and_then(|mut f| f.write_all(self.exportar(formato).as_bytes()))
+            .map_err(|_| ErroresImportacion::ErrorDeArchivo)
+    }
+
+    pub fn importar_de_archivo(nombre: &str, ruta: &str) -> Result<Playlist, ErroresImportacion> {
+        let formato =
+            FormatoPlaylist::desde_ruta(ruta).ok_or(ErroresImportacion::FormatoDesconocido)?;
+        let contenido =
+            std::fs::read_to_string(ruta).map_err(|_| ErroresImportacion::ErrorDeArchivo)?;
+        Playlist::importar(nombre, &contenido, formato)
+    }
+
+    fn exportar_m3u(&self) -> String {
+        let mut salida = String::from("#EXTM3U\n");
+        for cancion in &self.canciones {
+            salida.push_str(&format!(
+                "#EXTINF:{},{}\n",
+                cancion.duracion_segundos,
+                cancion.nombre_para_mostrar()
+            ));
+            if !cancion.album.is_empty() {
+                salida.push_str(&format!("#EXTALB:{}\n", cancion.album));
+            }
+            salida.push_str(&format!("#EXTGENRE:{:?}\n", cancion.genero));
+            salida.push_str(&format!("{}\n", cancion.ubicacion));
+        }
+        salida
+    }
+
+    fn exportar_pls(&self) -> String {
+        let mut salida = String::from("[playlist]\n");
+        for (i, cancion) in self.canciones.iter().enumerate() {
+            let n = i + 1;
+            salida.push_str(&format!("File{}={}\n", n, cancion.ubicacion));
+            salida.push_str(&format!("Title{}={}\n", n, cancion.nombre_para_mostrar()));
+            salida.push_str(&format!("Length{}={}\n", n, cancion.duracion_segundos));
+        }
+        salida.push_str(&format!("NumberOfEntries={}\n", self.canciones.len()));
+        salida.push_str("Version=2\n");
+        salida
+    }
+
+    fn exportar_xspf(&self) -> String {
+        let mut salida = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        salida.push_str("<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+        salida.push_str(&format!("  <title>{}</title>\n", escapar_xml(&self.nombre)));
+        salida.push_str("  <trackList>\n");
+        for cancion in &self.canciones {
+            salida.push_str("    <track>\n");
+            let campos = [
+                ("location", cancion.ubicacion.clone()),
+                ("title", cancion.titulo.clone()),
+                ("creator", cancion.artista.clone()),
+                ("album", cancion.album.clone()),
+                ("trackNum", cancion.numero_pista.to_string()),
+                // XSPF guarda la duracion en milisegundos
+                (
+                    "duration",
+                    (cancion.duracion_segundos as u64 * 1000).to_string(),
+                ),
+            ];
+            for (etiqueta, valor) in campos {
+                if !valor.is_empty() {
+                    salida.push_str(&format!(
+                        "      <{}>{}</{}>\n",
+                        etiqueta,
+                        escapar_xml(&valor),
+                        etiqueta
+                    ));
+                }
+            }
+            let metas = [
+                ("genero", format!("{:?}", cancion.genero)),
+                ("ano", cancion.ano.to_string()),
+                ("puntuacion", cancion.puntuacion.to_string()),
+            ];
+            for (rel, valor) in metas {
+                salida.push_str(&format!("      <meta rel=\"{}\">{}</meta>\n", rel, valor));
+            }
+            salida.push_str("    </track>\n");
+        }
+        salida.push_str("  </trackList>\n");
+        salida.push_str("</playlist>\n");
+        salida
+    }
+}
+
+fn parsear_numero<T: std::str::FromStr>(
+    texto: &str,
+    linea: usize,
+) -> Result<T, ErroresImportacion> {
+    texto
+        .trim()
+        .parse()
+        .map_err(|_| ErroresImportacion::NumeroInvalido {
+            linea,
+            contenido: texto.to_string(),
+        })
+}
+
+// -1 indica duracion desconocida (por ejemplo, streams); no se aceptan valores
+// que no entren en la duracion de la cancion
+fn parsear_duracion(texto: &str, linea: usize) -> Result<u32, ErroresImportacion> {
+    let duracion: i64 = parsear_numero(texto, linea)?;
+    if duracion == -1 {
+        return Ok(0);
+    }
+    u32::try_from(duracion).map_err(|_| ErroresImportacion::NumeroInvalido {
+        linea,
+        contenido: texto.to_string(),
+    })
+}
+
+fn importar_m3u(contenido: &str) -> Result<Vec<Cancion>, ErroresImportacion> {
+    let mut canciones = vec![];
+    // Datos de #EXTINF (y etiquetas extra) esperando la linea con el archivo
+    let mut pendiente: Option<(usize, Cancion)> = None;
+
+    for (i, linea) in contenido.lines().enumerate() {
+        let numero = i + 1;
+        let linea = linea.trim();
+        if linea.is_empty() || linea == "#EXTM3U" {
+            continue;
+        }
+
+        if let Some(info) = linea.strip_prefix("#EXTINF:") {
+            let (duracion, nombre) =
+                info.split_once(',')
+                    .ok_or_else(|| ErroresImportacion::LineaInvalida {
+                        linea: numero,
+                        contenido: linea.to_string(),
+                    })?;
+            // Un #EXTINF anterior que nunca recibio su archivo
+            if let Some((linea, _)) = pendiente {
+                return Err(ErroresImportacion::EntradaIncompleta { linea });
+            }
+            let mut cancion = Cancion::desde_nombre_para_mostrar(nombre);
+            cancion.duracion_segundos = parsear_duracion(duracion, numero)?;
+            pendiente = Some((numero, cancion));
+        } else if let Some(album) = linea.strip_prefix("#EXTALB:") {
+            if let Some((_, cancion)) = pendiente.as_mut() {
+                cancion.album = album.trim().to_string();
+            }
+        } else if let Some(genero) = linea.strip_prefix("#EXTGENRE:") {
+            if let Some((_, cancion)) = pendiente.as_mut() {
+                cancion.genero = Genero::desde_texto(genero);
+            }
+        } else if linea.starts_with('#') {
+            // Comentario o directiva no soportada
+            continue;
+        } else {
+            let mut cancion = match pendiente.take() {
+                Some((_, cancion)) => cancion,
+                None => {
+                    // M3U simple: el titulo sale del nombre del archivo
+                    let archivo = linea.rsplit(['/', '\\']).next().unwrap_or(linea);
+                    let titulo = archivo.rsplit_once('.').map_or(archivo, |(t, _)| t);
+                    Cancion::desde_nombre_para_mostrar(titulo)
+                }
+            };
+            cancion.ubicacion = linea.to_string();
+            canciones.push(cancion);
+        }
+    }
+
+    if let Some((linea, _)) = pendiente {
+        return Err(ErroresImportacion::EntradaIncompleta { linea });
+    }
+    Ok(canciones)
+}
+
+fn importar_pls(contenido: &str) -> Result<Vec<Cancion>, ErroresImportacion> {
+    // Por cada numero de entrada: linea donde aparece por primera vez y datos leidos
+    let mut entradas: HashMap<usize, (usize, Cancion, bool)> = HashMap::new();
+    let mut encabezado = false;
+    let mut cantidad_declarada: Option<(usize, usize)> = None;
+
+    for (i, linea) in contenido.lines().enumerate() {
+        let numero = i + 1;
+        let linea = linea.trim();
+        if linea.is_empty() || linea.starts_with(';') {
+            continue;
+        }
+        if !encabezado {
+            if !linea.eq_ignore_ascii_case("[playlist]") {
+                return Err(ErroresImportacion::EncabezadoFaltante { linea: numero });
+            }
+            encabezado = true;
+            continue;
+        }
+
+        let invalida = || ErroresImportacion::LineaInvalida {
+            linea: numero,
+            contenido: linea.to_string(),
+        };
+        let (clave, valor) = linea.split_once('=').ok_or_else(invalida)?;
+        let clave = clave.trim();
+        if clave.eq_ignore_ascii_case("NumberOfEntries") {
+            cantidad_declarada = Some((numero, parsear_numero(valor, numero)?));
+            continue;
+        }
+        if clave.eq_ignore_ascii_case("Version") {
+            continue;
+        }
+
+        let campo = clave.trim_end_matches(|c: char| c.is_ascii_digit());
+        let indice: usize = clave[campo.len()..].parse().map_err(|_| invalida())?;
+        let (_, cancion, tiene_archivo) =
+            entradas
+                .entry(indice)
+                .or_insert((numero, Cancion::default(), false));
+        match campo.to_lowercase().as_str() {
+            "file" => {
+                cancion.ubicacion = valor.trim().to_string();
+                *tiene_archivo = true;
+            }
+            "title" => {
+                let leida = Cancion::desde_nombre_para_mostrar(valor);
+                cancion.titulo = leida.titulo;
+                cancion.artista = leida.artista;
+            }
+            "length" => cancion.duracion_segundos = parsear_duracion(valor, numero)?,
+            _ => return Err(invalida()),
+        }
+    }
+
+    if !encabezado {
+        return Err(ErroresImportacion::EncabezadoFaltante { linea: 1 });
+    }
+    if let Some((linea, cantidad)) = cantidad_declarada
+        && cantidad != entradas.len()
+    {
+        return Err(ErroresImportacion::LineaInvalida {
+            linea,
+            contenido: format!("NumberOfEntries={}", cantidad),
+        });
+    }
+
+    let mut indices: Vec<usize> = entradas.keys().copied().collect();
+    indices.sort();
+    let mut canciones = vec![];
+    for indice in indices {
+        let (linea, cancion, tiene_archivo) = entradas.remove(&indice).unwrap();
+        if !tiene_archivo {
+            return Err(ErroresImportacion::EntradaIncompleta { linea });
+        }
+        canciones.push(cancion);
+    }
+    Ok(canciones)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum EventoXml {
+    Abre {
+        nombre: String,
+        atributos: Vec<(String, String)>,
+        linea: usize,
+    },
+    Cierra {
+        nombre: String,
+        linea: usize,
+    },
+    Texto {
+        contenido: String,
+        linea: usize,
+    },
+}
+
+fn escapar_xml(texto: &str) -> String {
+    texto
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn desescapar_xml(texto: &str) -> String {
+    texto
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// Lector XML minimo: alcanza para XSPF y verifica que las etiquetas cierren bien
+fn leer_eventos_xml(contenido: &str) -> Result<Vec<EventoXml>, ErroresImportacion> {
+    let mut eventos = vec![];
+    let mut abiertas: Vec<(String, usize)> = vec![];
+    let mut resto = contenido;
+    let mut linea = 1;
+
+    while !resto.is_empty() {
+        let Some(inicio) = resto.find('<') else {
+            if !resto.trim().is_empty() {
+                return Err(ErroresImportacion::XmlInvalido {
+                    linea,
+                    detalle: "texto fuera de la raiz".to_string(),
+                });
+            }
+            break;
+        };
+
+        let texto = &resto[..inicio];
+        if !texto.trim().is_empty() {
+            eventos.push(EventoXml::Texto {
+                contenido: desescapar_xml(texto.trim()),
+                linea,
+            });
+        }
+        linea += texto.matches('\n').count();
+        resto = &resto[inicio..];
+
+        let cierre_esperado = if resto.starts_with("<!--") {
+            "-->"
+        } else if resto.starts_with("<?") {
+            "?>"
+        } else {
+            ">"
+        };
+        let fin = resto
+            .find(cierre_esperado)
+            .ok_or_else(|| ErroresImportacion::XmlInvalido {
+                linea,
+                detalle: "etiqueta sin cerrar".to_string(),
+            })?
+            + cierre_esperado.len();
+        let etiqueta = &resto[..fin];
+        let linea_etiqueta = linea;
+        linea += etiqueta.matches('\n').count();
+        resto = &resto[fin..];
+
+        if cierre_esperado != ">" {
+            continue;
+        }
+        if let Some(nombre) = etiqueta.strip_prefix("</") {
+            let nombre = nombre.trim_end_matches('>').trim().to_string();
+            match abiertas.pop() {
+                Some((abierta, _)) if abierta == nombre => {}
+                _ => {
+                    return Err(ErroresImportacion::XmlInvalido {
+                        linea: linea_etiqueta,
+                        detalle: format!("cierre inesperado de <{}>", nombre),
+                    });
+                }
+            }
+            eventos.push(EventoXml::Cierra {
+                nombre,
+                linea: linea_etiqueta,
+            });
+            continue;
+        }
+
+        let autocerrada = etiqueta.ends_with("/>");
+        let interior = etiqueta[1..etiqueta.len() - if autocerrada { 2 } else { 1 }].trim();
+        let (nombre, atributos) = match interior.split_once(char::is_whitespace) {
+            Some((nombre, atributos)) => (nombre, leer_atributos(atributos, linea_etiqueta)?),
+            None => (interior, vec![]),
+        };
+        if nombre.is_empty() {
+            return Err(ErroresImportacion::XmlInvalido {
+                linea: linea_etiqueta,
+                detalle: "etiqueta sin nombre".to_string(),
+            });
+        }
+        eventos.push(EventoXml::Abre {
+            nombre: nombre.to_string(),
+            atributos,
+            linea: linea_etiqueta,
+        });
+        if autocerrada {
+            eventos.push(EventoXml::Cierra {
+                nombre: nombre.to_string(),
+                linea: linea_etiqueta,
+            });
+        } else {
+            abiertas.push((nombre.to_string(), linea_etiqueta));
+        }
+    }
+
+    if let Some((nombre, linea)) = abiertas.pop() {
+        return Err(ErroresImportacion::XmlInvalido {
+            linea,
+            detalle: format!("<{}> nunca se cierra", nombre),
+        });
+    }
+    Ok(eventos)
+}
+
+fn leer_atributos(texto: &str, linea: usize) -> Result<Vec<(String, String)>, ErroresImportacion> {
+    let mut atributos = vec![];
+    let mut resto = texto.trim();
+    while !resto.is_empty() {
+        let invalido = || ErroresImportacion::XmlInvalido {
+            linea,
+            detalle: format!("atributo invalido en '{}'", texto.trim()),
+        };
+        let (nombre, valor) = resto.split_once('=').ok_or_else(invalido)?;
+        let valor = valor.trim_start();
+        let comilla = valor.chars().next().filter(|c| *c == '"' || *c == '\'');
+        let comilla = comilla.ok_or_else(invalido)?;
+        let fin = valor[1..].find(comilla).ok_or_else(invalido)? + 1;
+        atributos.push((nombre.trim().to_string(), desescapar_xml(&valor[1..fin])));
+        resto = valor[fin + 1..].trim_start();
+    }
+    Ok(atributos)
+}
+
+fn importar_xspf(contenido: &str) -> Result<Vec<Cancion>, ErroresImportacion> {
+    let eventos = leer_eventos_xml(contenido)?;
+    match eventos.first() {
+        Some(EventoXml::Abre { nombre, .. }) if nombre == "playlist" => {}
+        Some(EventoXml::Abre { linea, .. })
+        | Some(EventoXml::Cierra { linea, .. })
+        | Some(EventoXml::Texto { linea, .. }) => {
+            return Err(ErroresImportacion::EncabezadoFaltante { linea: *linea });
+        }
+        None => return Err(ErroresImportacion::EncabezadoFaltante { linea: 1 }),
+    }
+
+    let mut canciones = vec![];
+    let mut actual: Option<(usize, Cancion, bool)> = None;
+    let mut campo: Option<(String, Option<String>)> = None;
+
+    for evento in eventos {
+        match evento {
+            EventoXml::Abre {
+                nombre,
+                atributos,
+                linea,
+            } => {
+                if nombre == "track" {
+                    if actual.is_some() {
+                        return Err(ErroresImportacion::XmlInvalido {
+                            linea,
+                            detalle: "<track> dentro de otro <track>".to_string(),
+                        });
+                    }
+                    actual = Some((linea, Cancion::default(), false));
+                } else if actual.is_some() {
+                    let rel = atributos
+                        .into_iter()
+                        .find(|(clave, _)| clave == "rel")
+                        .map(|(_, valor)| valor);
+                    campo = Some((nombre, rel));
+                }
+            }
+            EventoXml::Texto { contenido, linea } => {
+                let (Some((_, cancion, tiene_ubicacion)), Some((nombre, rel))) =
+                    (actual.as_mut(), campo.as_ref())
+                else {
+                    continue;
+                };
+                match (nombre.as_str(), rel.as_deref()) {
+                    ("location", _) => {
+                        cancion.ubicacion = contenido;
+                        *tiene_ubicacion = true;
+                    }
+                    ("title", _) => cancion.titulo = contenido,
+                    ("creator", _) => cancion.artista = contenido,
+                    ("album", _) => cancion.album = contenido,
+                    ("trackNum", _) => cancion.numero_pista = parsear_numero(&contenido, linea)?,
+                    ("duration", _) => {
+                        let milisegundos: u64 = parsear_numero(&contenido, linea)?;
+                        cancion.duracion_segundos =
+                            u32::try_from(milisegundos / 1000).map_err(|_| {
+                                ErroresImportacion::NumeroInvalido {
+                                    linea,
+                                    contenido: contenido.clone(),
+                                }
+                            })?;
+                    }
+                    ("meta", Some("genero")) => cancion.genero = Genero::desde_texto(&contenido),
+                    ("meta", Some("ano")) => cancion.ano = parsear_numero(&contenido, linea)?,
+                    ("meta", Some("puntuacion")) => {
+                        cancion.puntuacion = parsear_numero(&contenido, linea)?
+                    }
+                    _ => {}
+                }
+            }
+            EventoXml::Cierra { nombre, linea } => {
+                if nombre == "track" {
+                    let (linea, cancion, tiene_ubicacion) =
+                        actual.take().ok_or(ErroresImportacion::XmlInvalido {
+                            linea,
+                            detalle: "cierre de </track> sin apertura".to_string(),
+                        })?;
+                    if !tiene_ubicacion {
+                        return Err(ErroresImportacion::EntradaIncompleta { linea });
+                    }
+                    canciones.push(cancion);
+                }
+                campo = None;
+            }
+        }
+    }
+    Ok(canciones)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(dead_code)]
+enum ErroresBiblioteca {
+    PlaylistExistente,
+    PlaylistInexistente,
+    PlaylistInteligente,
+    CancionInexistente,
+}
+
+impl fmt::Display for ErroresBiblioteca {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErroresBiblioteca::PlaylistExistente => {
+                write!(f, "Ya existe una playlist con ese nombre")
+            }
+            ErroresBiblioteca::PlaylistInexistente => write!(f, "La playlist no existe"),
+            ErroresBiblioteca::PlaylistInteligente => {
+                write!(f, "Las playlists inteligentes no se editan a mano")
+            }
+            ErroresBiblioteca::CancionInexistente => {
+                write!(f, "La cancion no existe en la biblioteca")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(dead_code)]
+enum Condicion {
+    GeneroIgual(Genero),
+    ArtistaIgual(String),
+    AnoMayor(u16),
+    AnoMenor(u16),
+    PuntuacionMinima(u8),
+    DuracionMaxima(u32),
+    Y(Vec<Condicion>),
+    O(Vec<Condicion>),
+    No(Box<Condicion>),
+}
+
+#[allow(dead_code)]
+impl Condicion {
+    pub fn cumple(&self, cancion: &Cancion) -> bool {
+        match self {
+            Condicion::GeneroIgual(genero) => cancion.genero == *genero,
+            Condicion::ArtistaIgual(artista) => normalizar(&cancion.artista) == normalizar(artista),
+            Condicion::AnoMayor(ano) => cancion.ano > *ano,
+            Condicion::AnoMenor(ano) => cancion.ano < *ano,
+            Condicion::PuntuacionMinima(puntuacion) => cancion.puntuacion >= *puntuacion,
+            Condicion::DuracionMaxima(segundos) => cancion.duracion_segundos <= *segundos,
+            Condicion::Y(condiciones) => condiciones.iter().all(|c| c.cumple(cancion)),
+            Condicion::O(condiciones) => condiciones.iter().any(|c| c.cumple(cancion)),
+            Condicion::No(condicion) => !condicion.cumple(cancion),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(dead_code)]
+struct PlaylistInteligente {
+    condicion: Condicion,
+    orden: Option<(CampoOrden, bool)>,
+    limite: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+#[allow(dead_code)]
+struct BibliotecaMusical {
+    canciones: BTreeMap<u32, Cancion>,
+    proximo_id: u32,
+    playlists: HashMap<String, Vec<u32>>,
+    inteligentes: HashMap<String, PlaylistInteligente>,
+}
+
+#[allow(dead_code)]
+impl BibliotecaMusical {
+    pub fn new() -> Self {
+        BibliotecaMusical {
+            proximo_id: 1,
+            ..Default::default()
+        }
+    }
+
+    // Si la cancion ya estaba (mismo titulo y artista, sin importar tildes ni mayusculas)
+    // se devuelve el id existente y se completan los datos que le faltaban
+    pub fn agregar_cancion(&mut self, cancion: Cancion) -> u32 {
+        if let Some(id) = self.buscar_id(&cancion.titulo, &cancion.artista) {
+            if let Some(existente) = self.canciones.get_mut(&id) {
+                completar_metadatos(existente, cancion);
+            }
+            return id;
+        }
+        let id = self.proximo_id;
+        self.proximo_id += 1;
+        self.canciones.insert(id, cancion);
+        id
+    }
+
+    pub fn buscar_id(&self, titulo: &str, artista: &str) -> Option<u32> {
+        self.canciones
+            .iter()
+            .find(|(_, c)| misma_cancion(c, titulo, artista))
+            .map(|(id, _)| *id)
+    }
+
+    pub fn cancion(&self, id: u32) -> Option<&Cancion> {
+        self.canciones.get(&id)
+    }
+
+    pub fn eliminar_cancion(&mut self, id: u32) -> bool {
+        if self.canciones.remove(&id).is_none() {
+            return false;
+        }
+        for ids in self.playlists.values_mut() {
+            ids.retain(|i| *i != id);
+        }
+        true
+    }
+
+    pub fn crear_playlist(&mut self, nombre: &str) -> Result<(), ErroresBiblioteca> {
+        self.nombre_disponible(nombre)?;
+        self.playlists.insert(nombre.to_string(), vec![]);
+        Ok(())
+    }
+
+    pub fn crear_playlist_inteligente(
+        &mut self,
+        nombre: &str,
+        condicion: Condicion,
+        orden: Option<(CampoOrden, bool)>,
+        limite: Option<usize>,
+    ) -> Result<(), ErroresBiblioteca> {
+        self.nombre_disponible(nombre)?;
+        self.inteligentes.insert(
+            nombre.to_string(),
+            PlaylistInteligente {
+                condicion,
+                orden,
+                limite,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn eliminar_playlist(&mut self, nombre: &str) -> bool {
+        self.playlists.remove(nombre).is_some() || self.inteligentes.remove(nombre).is_some()
+    }
+
+    pub fn agregar_a_playlist(&mut self, nombre: &str, id: u32) -> Result<(), ErroresBiblioteca> {
+        if !self.canciones.contains_key(&id) {
+            return Err(ErroresBiblioteca::CancionInexistente);
+        }
+        self.ids_editables(nombre)?.push(id);
+        Ok(())
+    }
+
+    pub fn quitar_de_playlist(&mut self, nombre: &str, id: u32) -> Result<bool, ErroresBiblioteca> {
+        let ids = self.ids_editables(nombre)?;
+        let cantidad = ids.len();
+        ids.retain(|i| *i != id);
+        Ok(ids.len() != cantidad)
+    }
+
+    // Las inteligentes se recalculan en cada consulta, asi siguen a la biblioteca sin guardarse
+    pub fn canciones_de(&self, nombre: &str) -> Result<Vec<&Cancion>, ErroresBiblioteca> {
+        Ok(self
+            .ids_de(nombre)?
+            .iter()
+            .filter_map(|id| self.canciones.get(id))
+            .collect())
+    }
+
+    pub fn unir(&mut self, nombre_nueva: &str, nombres: &[&str]) -> Result<(), ErroresBiblioteca> {
+        self.nombre_disponible(nombre_nueva)?;
+        let mut ids: Vec<u32> = vec![];
+        for nombre in nombres {
+            for id in self.ids_de(nombre)? {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        }
+        self.playlists.insert(nombre_nueva.to_string(), ids);
+        Ok(())
+    }
+
+    // Canciones que estan en la primera playlist y no en la segunda
+    pub fn diferencia(&self, nombre: &str, otra: &str) -> Result<Vec<&Cancion>, ErroresBiblioteca> {
+        let excluidas = self.ids_de(otra)?;
+        Ok(self
+            .ids_de(nombre)?
+            .iter()
+            .filter(|id| !excluidas.contains(id))
+            .filter_map(|id| self.canciones.get(id))
+            .collect())
+    }
+
+    pub fn deduplicar_playlist(&mut self, nombre: &str) -> Result<usize, ErroresBiblioteca> {
+        let ids = self.ids_editables(nombre)?;
+        let cantidad = ids.len();
+        let mut vistos = vec![];
+        ids.retain(|id| {
+            if vistos.contains(id) {
+                return false;
+            }
+            vistos.push(*id);
+            true
+        });
+        Ok(cantidad - ids.len())
+    }
+
+    pub fn importar_playlist(&mut self, playlist: &Playlist) -> Result<(), ErroresBiblioteca> {
+        self.nombre_disponible(&playlist.nombre)?;
+        let ids = playlist
+            .canciones
+            .iter()
+            .map(|c| self.agregar_cancion(c.clone()))
+            .collect();
+        self.playlists.insert(playlist.nombre.clone(), ids);
+        Ok(())
+    }
+
+    pub fn exportar_playlist(&self, nombre: &str) -> Result<Playlist, ErroresBiblioteca> {
+        let mut playlist = Playlist::new(nombre);
+        playlist.canciones = self.canciones_de(nombre)?.into_iter().cloned().collect();
+        Ok(playlist)
+    }
+
+    fn nombre_disponible(&self, nombre: &str) -> Result<(), ErroresBiblioteca> {
+        if self.playlists.contains_key(nombre) || self.inteligentes.contains_key(nombre) {
+            return Err(ErroresBiblioteca::PlaylistExistente);
+        }
+        Ok(())
+    }
+
+    fn ids_editables(&mut self, nombre: &str) -> Result<&mut Vec<u32>, ErroresBiblioteca> {
+        if self.inteligentes.contains_key(nombre) {
+            return Err(ErroresBiblioteca::PlaylistInteligente);
+        }
+        self.playlists
+            .get_mut(nombre)
+            .ok_or(ErroresBiblioteca::PlaylistInexistente)
+    }
+
+    fn ids_de(&self, nombre: &str) -> Result<Vec<u32>, ErroresBiblioteca> {
+        if let Some(ids) = self.playlists.get(nombre) {
+            return Ok(ids.clone());
+        }
+        let inteligente = self
+            .inteligentes
+            .get(nombre)
+            .ok_or(ErroresBiblioteca::PlaylistInexistente)?;
+
+        let mut encontradas: Vec<(&u32, &Cancion)> = self
+            .canciones
+            .iter()
+            .filter(|(_, c)| inteligente.condicion.cumple(c))
+            .collect();
+        if let Some((campo, ascendente)) = inteligente.orden {
+            encontradas.sort_by(|(_, a), (_, b)| {
+                let orden = campo.comparar(a, b);
+                if ascendente { orden } else { orden.reverse() }
+            });
+        }
+        let limite = inteligente.limite.unwrap_or(encontradas.len());
+        Ok(encontradas
+            .into_iter()
+            .take(limite)
+            .map(|(id, _)| *id)
+            .collect())
+    }
+}
+
+fn misma_cancion(cancion: &Cancion, titulo: &str, artista: &str) -> bool {
+    normalizar(&cancion.titulo) == normalizar(titulo)
+        && normalizar(&cancion.artista) == normalizar(artista)
+}
+
+// Lo que ya tenia la cancion se respeta; solo se llenan los campos vacios
+fn completar_metadatos(existente: &mut Cancion, nueva: Cancion) {
+    if existente.genero == Genero::Otros {
+        existente.genero = nueva.genero;
+    }
+    if existente.duracion_segundos == 0 {
+        existente.duracion_segundos = nueva.duracion_segundos;
+    }
+    if existente.album.is_empty() {
+        existente.album = nueva.album;
+    }
+    if existente.ano == 0 {
+        existente.ano = nueva.ano;
+    }
+    if existente.numero_pista == 0 {
+        existente.numero_pista = nueva.numero_pista;
+    }
+    if existente.puntuacion == 0 {
+        existente.puntuacion = nueva.puntuacion;
+    }
+    if existente.ubicacion.is_empty() {
+        existente.ubicacion = nueva.ubicacion;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(dead_code)]
+enum ComandoPlaylist {
+    Agregar { cancion: Cancion, posicion: usize },
+    Eliminar { eliminadas: Vec<(usize, Cancion)> },
+    Mover { desde: usize, hasta: usize },
+    Renombrar { anterior: String, nuevo: String },
+    EliminarTodas { eliminadas: Vec<Cancion> },
+    Grupo(Vec<ComandoPlaylist>),
 }
 
 #[allow(dead_code)]
-impl Playlist {
-    pub fn new(nombre: &str) -> Self {
-        Playlist {
-            nombre: nombre.to_string(),
-            canciones: Vec::new(),
-            ruta_archivo: format!("{}.json", nombre),
+impl ComandoPlaylist {
+    fn aplicar(&self, playlist: &mut Playlist) {
+        match self {
+            ComandoPlaylist::Agregar { cancion, posicion } => {
+                playlist.canciones.insert(*posicion, cancion.clone());
+            }
+            ComandoPlaylist::Eliminar { eliminadas } => {
+                // De atras para adelante para que las posiciones guardadas sigan valiendo
+                for (posicion, _) in eliminadas.iter().rev() {
+                    playlist.canciones.remove(*posicion);
+                }
+            }
+            ComandoPlaylist::Mover { desde, hasta } => {
+                let cancion = playlist.canciones.remove(*desde);
+                playlist.canciones.insert(*hasta, cancion);
+            }
+            ComandoPlaylist::Renombrar { nuevo, .. } => playlist.nombre = nuevo.clone(),
+            ComandoPlaylist::EliminarTodas { .. } => playlist.canciones.clear(),
+            ComandoPlaylist::Grupo(comandos) => {
+                comandos.iter().for_each(|c| c.aplicar(playlist));
+            }
+        }
+    }
+
+    fn revertir(&self, playlist: &mut Playlist) {
+        match self {
+            ComandoPlaylist::Agregar { posicion, .. } => {
+                playlist.canciones.remove(*posicion);
+            }
+            ComandoPlaylist::Eliminar { eliminadas } => {
+                for (posicion, cancion) in eliminadas {
+                    playlist.canciones.insert(*posicion, cancion.clone());
+                }
+            }
+            ComandoPlaylist::Mover { desde, hasta } => {
+                let cancion = playlist.canciones.remove(*hasta);
+                playlist.canciones.insert(*desde, cancion);
+            }
+            ComandoPlaylist::Renombrar { anterior, .. } => playlist.nombre = anterior.clone(),
+            ComandoPlaylist::EliminarTodas { eliminadas } => {
+                playlist.canciones = eliminadas.clone();
+            }
+            ComandoPlaylist::Grupo(comandos) => {
+                comandos.iter().rev().for_each(|c| c.revertir(playlist));
+            }
+        }
+    }
+}
+
+// Edita una Playlist sin escribir el archivo en cada cambio: todo queda
+// en memoria, se puede deshacer/rehacer, y se persiste recien al confirmar
+#[derive(Debug)]
+#[allow(dead_code)]
+struct EditorPlaylist {
+    playlist: Playlist,
+    deshacer: VecDeque<ComandoPlaylist>,
+    rehacer: Vec<ComandoPlaylist>,
+    limite: usize,
+    grupo: Option<Vec<ComandoPlaylist>>,
+    cambios_sin_guardar: bool,
+}
+
+#[allow(dead_code)]
+impl EditorPlaylist {
+    pub fn new(playlist: Playlist, limite: usize) -> Self {
+        EditorPlaylist {
+            playlist,
+            deshacer: VecDeque::new(),
+            rehacer: vec![],
+            limite,
+            grupo: None,
+            cambios_sin_guardar: false,
         }
     }
 
+    pub fn playlist(&self) -> &Playlist {
+        &self.playlist
+    }
+
+    pub fn hay_cambios_sin_guardar(&self) -> bool {
+        self.cambios_sin_guardar
+    }
+
     pub fn agregar_cancion(&mut self, cancion: Cancion) {
-        self.canciones.push(cancion);
-        self.guardar_en_archivo();
+        let posicion = self.playlist.canciones.len();
+        self.ejecutar(ComandoPlaylist::Agregar { cancion, posicion });
     }
 
-    pub fn eliminar_cancion(&mut self, titulo: &str) {
-        self.canciones.retain(|c| c.titulo != titulo);
-        self.guardar_en_archivo();
+    pub fn eliminar_cancion(&mut self, titulo: &str) -> bool {
+        let eliminadas: Vec<(usize, Cancion)> = self
+            .playlist
+            .canciones
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.titulo == titulo)
+            .map(|(i, c)| (i, c.clone()))
+            .collect();
+        if eliminadas.is_empty() {
+            return false;
+        }
+        self.ejecutar(ComandoPlaylist::Eliminar { eliminadas });
+        true
     }
 
-    pub fn mover_cancion(&mut self, titulo: &str, nueva_pos: usize) {
-        if let Some(indice) = self.canciones.iter().position(|c| c.titulo == titulo) {
-            if nueva_pos < self.canciones.len() {
-                let cancion = self.canciones.remove(indice);
-                self.canciones.insert(nueva_pos, cancion);
-                self.guardar_en_archivo();
-            }
+    pub fn mover_cancion(&mut self, titulo: &str, nueva_pos: usize) -> bool {
+        let Some(desde) = self
+            .playlist
+            .canciones
+            .iter()
+            .position(|c| c.titulo == titulo)
+        else {
+            return false;
+        };
+        if nueva_pos >= self.playlist.canciones.len() || nueva_pos == desde {
+            return false;
         }
+        self.ejecutar(ComandoPlaylist::Mover {
+            desde,
+            hasta: nueva_pos,
+        });
+        true
     }
 
     pub fn modificar_titulo(&mut self, nuevo_nombre: &str) {
-        self.nombre = nuevo_nombre.to_string();
+        self.ejecutar(ComandoPlaylist::Renombrar {
+            anterior: self.playlist.nombre.clone(),
+            nuevo: nuevo_nombre.to_string(),
+        });
     }
 
     pub fn eliminar_todas(&mut self) {
-        self.canciones.clear();
-        self.guardar_en_archivo();
+        let eliminadas = self.playlist.canciones.clone();
+        self.ejecutar(ComandoPlaylist::EliminarTodas { eliminadas });
     }
 
-    fn guardar_en_archivo(&self) {
-        if let Ok(json) = serde_json::to_string_pretty(&self) {
-            let _ = File::create(&self.ruta_archivo).and_then(|mut f| f.write_all(json.as_bytes()));
+    // Todo lo que se haga hasta terminar_grupo se deshace en un solo paso
+    pub fn iniciar_grupo(&mut self) {
+        if self.grupo.is_none() {
+            self.grupo = Some(vec![]);
         }
     }
 
-    fn leer_de_archivo(&self) -> Vec<Cancion> {
-        if let Ok(file) = File::open(&self.ruta_archivo) {
-            let reader = BufReader::new(file);
-            if let Ok(playlist_leida) = serde_json::from_reader::<_, Playlist>(reader) {
-                return playlist_leida.canciones;
-            }
+    pub fn terminar_grupo(&mut self) {
+        if let Some(comandos) = self.grupo.take()
+            && !comandos.is_empty()
+        {
+            self.apilar(ComandoPlaylist::Grupo(comandos));
         }
-        Vec::new()
     }
 
-    pub fn buscar_cancion(&self, titulo: &str) -> Option<&Cancion> {
-        self.canciones.iter().find(|c| c.titulo == titulo)
+    pub fn deshacer(&mut self) -> bool {
+        self.terminar_grupo();
+        let Some(comando) = self.deshacer.pop_back() else {
+            return false;
+        };
+        comando.revertir(&mut self.playlist);
+        self.rehacer.push(comando);
+        self.cambios_sin_guardar = true;
+        true
     }
 
-    pub fn obtener_canciones_por_genero(&self, genero: Genero) -> Vec<&Cancion> {
-        self.canciones
-            .iter()
-            .filter(|c| c.genero == genero)
-            .collect()
+    pub fn rehacer(&mut self) -> bool {
+        self.terminar_grupo();
+        let Some(comando) = self.rehacer.pop() else {
+            return false;
+        };
+        comando.aplicar(&mut self.playlist);
+        self.deshacer.push_back(comando);
+        self.cambios_sin_guardar = true;
+        true
     }
 
-    pub fn obtener_canciones_por_artista(&self, artista: &str) -> Vec<&Cancion> {
-        self.canciones
-            .iter()
-            .filter(|c| c.artista == artista)
-            .collect()
+    pub fn confirmar(&mut self) {
+        self.terminar_grupo();
+        self.playlist.guardar_en_archivo();
+        self.cambios_sin_guardar = false;
+    }
+
+    fn ejecutar(&mut self, comando: ComandoPlaylist) {
+        comando.aplicar(&mut self.playlist);
+        self.cambios_sin_guardar = true;
+        self.rehacer.clear();
+        match self.grupo.as_mut() {
+            Some(grupo) => grupo.push(comando),
+            None => self.apilar(comando),
+        }
+    }
+
+    fn apilar(&mut self, comando: ComandoPlaylist) {
+        self.deshacer.push_back(comando);
+        if self.deshacer.len() > self.limite {
+            self.deshacer.pop_front();
+        }
     }
 }
 
@@ -112,9 +1596,41 @@ mod tests {
             titulo: titulo.to_string(),
             artista: artista.to_string(),
             genero,
+            ..Default::default()
+        }
+    }
+
+    fn crear_cancion_completa(
+        titulo: &str,
+        artista: &str,
+        duracion_segundos: u32,
+        ano: u16,
+        puntuacion: u8,
+    ) -> Cancion {
+        Cancion {
+            titulo: titulo.to_string(),
+            artista: artista.to_string(),
+            genero: Genero::Rock,
+            duracion_segundos,
+            album: format!("Grandes exitos de {}", artista),
+            ano,
+            numero_pista: 1,
+            puntuacion,
+            ubicacion: format!("/musica/{}.mp3", titulo),
         }
     }
 
+    fn playlist_con_metadatos(nombre: &str) -> Playlist {
+        let mut playlist = Playlist::new(nombre);
+        playlist.canciones = vec![
+            crear_cancion_completa("Canción para mañana", "Los piojos", 240, 1994, 4),
+            crear_cancion_completa("Ruta 40", "Los piojos", 300, 1998, 0),
+            crear_cancion_completa("Muchacha", "Luis Alberto Spinetta", 180, 1973, 5),
+            crear_cancion_completa("Ojos", "Dolina", 420, 2001, 3),
+        ];
+        playlist
+    }
+
     #[test]
     fn test_agregar_cancion() {
         let mut playlist = Playlist::new("asdf");
@@ -229,6 +1745,7 @@ mod tests {
             titulo: "Coca".into(),
             artista: "Ale Sanz".into(),
             genero: Genero::Jazz,
+            ..Default::default()
         };
 
         playlist.eliminar_todas();
@@ -247,6 +1764,7 @@ mod tests {
             titulo: "Coqui".into(),
             artista: "La banda del lechuga".into(),
             genero: Genero::Otros,
+            ..Default::default()
         });
         playlist.eliminar_cancion("Coqui");
 
@@ -264,11 +1782,13 @@ mod tests {
                 titulo: "asdf 1".into(),
                 artista: "asdf 1".into(),
                 genero: Genero::Rock,
+                ..Default::default()
             },
             Cancion {
                 titulo: "asdf 2".into(),
                 artista: "asdf 2".into(),
                 genero: Genero::Pop,
+                ..Default::default()
             },
         ];
         let playlist_serializada = Playlist {
@@ -292,4 +1812,586 @@ mod tests {
 
         let _ = std::fs::remove_file(ruta);
     }
+
+    fn playlist_para_reproducir(nombre: &str) -> Playlist {
+        let mut playlist = Playlist::new(nombre);
+        playlist.canciones = vec![
+            crear_cancion("Cuello", "Los piojos", Genero::Rock),
+            crear_cancion("Manos", "Los piojos", Genero::Rock),
+            crear_cancion("Ruta 40", "Los piojos", Genero::Rock),
+            crear_cancion("Ojos", "Dolina", Genero::Jazz),
+            crear_cancion("Oreja", "Dolina", Genero::Jazz),
+            crear_cancion("Roxanne", "The police", Genero::Rock),
+        ];
+        playlist
+    }
+
+    #[test]
+    fn test_reproductor_siguiente_y_anterior() {
+        let playlist = playlist_para_reproducir("reproductor 1");
+        let mut reproductor = Reproductor::new(&playlist, 1);
+        assert!(reproductor.actual().is_none());
+        assert!(reproductor.anterior().is_none());
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Cuello");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Manos");
+        assert_eq!(reproductor.anterior().unwrap().titulo, "Cuello");
+        assert_eq!(reproductor.historial().len(), 3);
+    }
+
+    #[test]
+    fn test_reproductor_fin_sin_repeticion() {
+        let playlist = playlist_para_reproducir("reproductor 2");
+        let mut reproductor = Reproductor::new(&playlist, 1);
+        for _ in 0..6 {
+            assert!(reproductor.siguiente().is_some());
+        }
+        assert!(reproductor.siguiente().is_none());
+        assert!(reproductor.actual().is_none());
+    }
+
+    #[test]
+    fn test_reproductor_repetir_una_y_todas() {
+        let playlist = playlist_para_reproducir("reproductor 3");
+        let mut reproductor = Reproductor::new(&playlist, 1);
+        reproductor.siguiente();
+        reproductor.set_repeticion(ModoRepeticion::Una);
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Cuello");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Cuello");
+
+        reproductor.set_repeticion(ModoRepeticion::Todas);
+        for _ in 0..5 {
+            reproductor.siguiente();
+        }
+        assert_eq!(reproductor.actual().unwrap().titulo, "Roxanne");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Cuello");
+        assert_eq!(reproductor.anterior().unwrap().titulo, "Roxanne");
+    }
+
+    #[test]
+    fn test_reproductor_cola_a_continuacion() {
+        let playlist = playlist_para_reproducir("reproductor 4");
+        let mut reproductor = Reproductor::new(&playlist, 1);
+        reproductor.siguiente();
+        reproductor.agregar_a_continuacion(crear_cancion("Coca", "Ale Sanz", Genero::Pop));
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Coca");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Manos");
+
+        // Con repetir una, la cola suena primero y despues vuelve la cancion repetida
+        reproductor.set_repeticion(ModoRepeticion::Una);
+        reproductor.agregar_a_continuacion(crear_cancion("Coca", "Ale Sanz", Genero::Pop));
+        reproductor.agregar_a_continuacion(crear_cancion("Oreja", "Dolina", Genero::Jazz));
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Coca");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Oreja");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Manos");
+        assert_eq!(reproductor.siguiente().unwrap().titulo, "Manos");
+    }
+
+    #[test]
+    fn test_reproductor_aleatorio_sin_artista_repetido() {
+        let playlist = playlist_para_reproducir("reproductor 5");
+        for semilla in 0..20 {
+            let mut reproductor = Reproductor::new(&playlist, semilla);
+            reproductor.set_aleatorio(true);
+            reproductor.set_repeticion(ModoRepeticion::Todas);
+            let mut artistas = vec![];
+            for _ in 0..18 {
+                artistas.push(reproductor.siguiente().unwrap().artista.clone());
+            }
+            assert!(artistas.windows(2).all(|par| par[0] != par[1]));
+        }
+    }
+
+    #[test]
+    fn test_reproductor_aleatorio_determinista() {
+        let playlist = playlist_para_reproducir("reproductor 6");
+        let mut uno = Reproductor::new(&playlist, 42);
+        let mut otro = Reproductor::new(&playlist, 42);
+        uno.set_aleatorio(true);
+        otro.set_aleatorio(true);
+        for _ in 0..6 {
+            assert_eq!(uno.siguiente().cloned(), otro.siguiente().cloned());
+        }
+    }
+
+    #[test]
+    fn test_duracion_total_y_puntuacion_promedio() {
+        let playlist = playlist_con_metadatos("metadatos 1");
+        assert_eq!(playlist.duracion_total(), 1140);
+        assert_eq!(playlist.puntuacion_promedio(), Some(4.0));
+        assert_eq!(Playlist::new("vacia").puntuacion_promedio(), None);
+    }
+
+    #[test]
+    fn test_generar_hasta_minutos() {
+        let playlist = playlist_con_metadatos("metadatos 2");
+        let corta = playlist.generar_hasta_minutos("corta", 12);
+        let titulos: Vec<&str> = corta.canciones.iter().map(|c| c.titulo.as_str()).collect();
+        assert_eq!(titulos, vec!["Canción para mañana", "Ruta 40", "Muchacha"]);
+        assert!(corta.duracion_total() <= 12 * 60);
+
+        let todas = playlist.generar_hasta_minutos("todas", u32::MAX);
+        assert_eq!(todas.canciones.len(), playlist.canciones.len());
+    }
+
+    #[test]
+    fn test_ordenar_por_campo() {
+        let mut playlist = playlist_con_metadatos("metadatos 3");
+        playlist.ordenar_por(CampoOrden::Ano, true);
+        assert_eq!(playlist.canciones[0].titulo, "Muchacha");
+        playlist.ordenar_por(CampoOrden::Duracion, false);
+        assert_eq!(playlist.canciones[0].titulo, "Ojos");
+        playlist.ordenar_por(CampoOrden::Puntuacion, false);
+        assert_eq!(playlist.canciones[0].titulo, "Muchacha");
+        playlist.ordenar_por(CampoOrden::Titulo, true);
+        assert_eq!(playlist.canciones[0].titulo, "Canción para mañana");
+        let _ = std::fs::remove_file(playlist.ruta_archivo.clone());
+    }
+
+    #[test]
+    fn test_buscar_aproximado_sin_tildes() {
+        let playlist = playlist_con_metadatos("metadatos 4");
+        let resultado = playlist.buscar_aproximado("cancion para manana");
+        assert_eq!(resultado.len(), 1);
+        assert_eq!(resultado[0].titulo, "Canción para mañana");
+    }
+
+    #[test]
+    fn test_buscar_aproximado_con_errores() {
+        let playlist = playlist_con_metadatos("metadatos 5");
+        let resultado = playlist.buscar_aproximado("spineta");
+        assert_eq!(resultado.len(), 1);
+        assert_eq!(resultado[0].artista, "Luis Alberto Spinetta");
+
+        let resultado = playlist.buscar_aproximado("los pijos");
+        assert_eq!(resultado.len(), 2);
+        assert!(playlist.buscar_aproximado("zzz").is_empty());
+        assert!(playlist.buscar_aproximado("   ").is_empty());
+    }
+
+    #[test]
+    fn test_m3u_ida_y_vuelta() {
+        let playlist = playlist_con_metadatos("formatos 1");
+        let m3u = playlist.exportar(FormatoPlaylist::M3u);
+        assert!(m3u.starts_with("#EXTM3U\n#EXTINF:240,Los piojos - Canción para mañana\n"));
+        let importada = Playlist::importar("copia", &m3u, FormatoPlaylist::M3u).unwrap();
+        assert_eq!(importada.canciones.len(), 4);
+        assert_eq!(importada.canciones[2].artista, "Luis Alberto Spinetta");
+        assert_eq!(importada.canciones[2].titulo, "Muchacha");
+        assert_eq!(
+            importada.canciones[2].album,
+            "Grandes exitos de Luis Alberto Spinetta"
+        );
+        assert_eq!(importada.canciones[2].genero, Genero::Rock);
+        assert_eq!(importada.canciones[2].duracion_segundos, 180);
+        assert_eq!(importada.canciones[2].ubicacion, "/musica/Muchacha.mp3");
+    }
+
+    #[test]
+    fn test_m3u_simple_y_errores() {
+        let simple = "cosas/Dolina - Ojos.mp3\n# comentario\nhttp://radio/stream\n";
+        let importada = Playlist::importar("simple", simple, FormatoPlaylist::M3u).unwrap();
+        assert_eq!(importada.canciones[0].artista, "Dolina");
+        assert_eq!(importada.canciones[0].titulo, "Ojos");
+
+        let sin_coma = "#EXTM3U\n#EXTINF:240 Los piojos\nruta.mp3\n";
+        assert_eq!(
+            Playlist::importar("x", sin_coma, FormatoPlaylist::M3u).unwrap_err(),
+            ErroresImportacion::LineaInvalida {
+                linea: 2,
+                contenido: "#EXTINF:240 Los piojos".to_string()
+            }
+        );
+        let mala_duracion = "#EXTM3U\n\n#EXTINF:abc,Los piojos - Ruta 40\nruta.mp3\n";
+        assert_eq!(
+            Playlist::importar("x", mala_duracion, FormatoPlaylist::M3u).unwrap_err(),
+            ErroresImportacion::NumeroInvalido {
+                linea: 3,
+                contenido: "abc".to_string()
+            }
+        );
+        let sin_archivo = "#EXTM3U\n#EXTINF:-1,Radio\n";
+        assert_eq!(
+            Playlist::importar("x", sin_archivo, FormatoPlaylist::M3u).unwrap_err(),
+            ErroresImportacion::EntradaIncompleta { linea: 2 }
+        );
+        let extinf_seguidos = "#EXTM3U\n#EXTINF:10,Radio\n#EXTINF:20,Otra\notra.mp3\n";
+        assert_eq!(
+            Playlist::importar("x", extinf_seguidos, FormatoPlaylist::M3u).unwrap_err(),
+            ErroresImportacion::EntradaIncompleta { linea: 2 }
+        );
+        let fuera_de_rango = "#EXTM3U\n#EXTINF:99999999999,Larga\nlarga.mp3\n";
+        assert_eq!(
+            Playlist::importar("x", fuera_de_rango, FormatoPlaylist::M3u).unwrap_err(),
+            ErroresImportacion::NumeroInvalido {
+                linea: 2,
+                contenido: "99999999999".to_string()
+            }
+        );
+        let negativa = "#EXTM3U\n#EXTINF:-5,Rara\nrara.mp3\n";
+        assert!(matches!(
+            Playlist::importar("x", negativa, FormatoPlaylist::M3u).unwrap_err(),
+            ErroresImportacion::NumeroInvalido { linea: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_pls_ida_y_vuelta() {
+        let playlist = playlist_con_metadatos("formatos 2");
+        let pls = playlist.exportar(FormatoPlaylist::Pls);
+        assert!(pls.contains("NumberOfEntries=4\n"));
+        let importada = Playlist::importar("copia", &pls, FormatoPlaylist::Pls).unwrap();
+        assert_eq!(importada.canciones.len(), 4);
+        assert_eq!(importada.canciones[3].titulo, "Ojos");
+        assert_eq!(importada.canciones[3].artista, "Dolina");
+        assert_eq!(importada.canciones[3].duracion_segundos, 420);
+        assert_eq!(importada.canciones[3].ubicacion, "/musica/Ojos.mp3");
+    }
+
+    #[test]
+    fn test_pls_errores() {
+        let sin_encabezado = "\nFile1=a.mp3\n";
+        assert_eq!(
+            Playlist::importar("x", sin_encabezado, FormatoPlaylist::Pls).unwrap_err(),
+            ErroresImportacion::EncabezadoFaltante { linea: 2 }
+        );
+        let clave_rara = "[playlist]\nFile1=a.mp3\nColor1=rojo\n";
+        assert_eq!(
+            Playlist::importar("x", clave_rara, FormatoPlaylist::Pls).unwrap_err(),
+            ErroresImportacion::LineaInvalida {
+                linea: 3,
+                contenido: "Color1=rojo".to_string()
+            }
+        );
+        let sin_archivo = "[playlist]\nFile1=a.mp3\nTitle2=Sin archivo\nNumberOfEntries=2\n";
+        assert_eq!(
+            Playlist::importar("x", sin_archivo, FormatoPlaylist::Pls).unwrap_err(),
+            ErroresImportacion::EntradaIncompleta { linea: 3 }
+        );
+        let fuera_de_rango = "[playlist]\nFile1=a.mp3\nLength1=4294967296\n";
+        assert!(matches!(
+            Playlist::importar("x", fuera_de_rango, FormatoPlaylist::Pls).unwrap_err(),
+            ErroresImportacion::NumeroInvalido { linea: 3, .. }
+        ));
+        let mal_contadas = "[playlist]\nFile1=a.mp3\nNumberOfEntries=3\n";
+        assert!(matches!(
+            Playlist::importar("x", mal_contadas, FormatoPlaylist::Pls).unwrap_err(),
+            ErroresImportacion::LineaInvalida { linea: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn test_xspf_ida_y_vuelta() {
+        let mut playlist = playlist_con_metadatos("formatos <3>");
+        playlist.canciones[0].artista = "Piojos & amigos".to_string();
+        let xspf = playlist.exportar(FormatoPlaylist::Xspf);
+        assert!(xspf.contains("<title>formatos &lt;3&gt;</title>"));
+        assert!(xspf.contains("<duration>240000</duration>"));
+        let importada = Playlist::importar("copia", &xspf, FormatoPlaylist::Xspf).unwrap();
+        assert_eq!(importada.canciones.len(), 4);
+        assert_eq!(importada.canciones[0].artista, "Piojos & amigos");
+        assert_eq!(importada.canciones, playlist.canciones);
+    }
+
+    #[test]
+    fn test_xspf_errores() {
+        let mal_cerrado = "<playlist>\n  <trackList>\n    <track>\n    </trackList>\n</playlist>\n";
+        assert_eq!(
+            Playlist::importar("x", mal_cerrado, FormatoPlaylist::Xspf).unwrap_err(),
+            ErroresImportacion::XmlInvalido {
+                linea: 4,
+                detalle: "cierre inesperado de <trackList>".to_string()
+            }
+        );
+        let sin_location = "<playlist>\n<trackList>\n<track>\n<title>Ojos</title>\n</track>\n</trackList>\n</playlist>";
+        assert_eq!(
+            Playlist::importar("x", sin_location, FormatoPlaylist::Xspf).unwrap_err(),
+            ErroresImportacion::EntradaIncompleta { linea: 3 }
+        );
+        let mala_duracion = "<playlist><trackList><track>\n<location>a.mp3</location>\n<duration>tres</duration>\n</track></trackList></playlist>";
+        assert_eq!(
+            Playlist::importar("x", mala_duracion, FormatoPlaylist::Xspf).unwrap_err(),
+            ErroresImportacion::NumeroInvalido {
+                linea: 3,
+                contenido: "tres".to_string()
+            }
+        );
+        assert_eq!(
+            Playlist::importar("x", "<lista/>", FormatoPlaylist::Xspf).unwrap_err(),
+            ErroresImportacion::EncabezadoFaltante { linea: 1 }
+        );
+        let anidado = "<playlist><trackList>\n<track>\n<track><location>a.mp3</location></track>\n</track>\n</trackList></playlist>";
+        assert_eq!(
+            Playlist::importar("x", anidado, FormatoPlaylist::Xspf).unwrap_err(),
+            ErroresImportacion::XmlInvalido {
+                linea: 3,
+                detalle: "<track> dentro de otro <track>".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_exportar_e_importar_archivo() {
+        let playlist = playlist_con_metadatos("formatos 3");
+        let ruta = "formatos_3.pls";
+        playlist.exportar_a_archivo(ruta).unwrap();
+        let importada = Playlist::importar_de_archivo("copia", ruta).unwrap();
+        assert_eq!(importada.canciones.len(), 4);
+        assert_eq!(
+            playlist.exportar_a_archivo("formatos_3.txt").unwrap_err(),
+            ErroresImportacion::FormatoDesconocido
+        );
+        assert_eq!(FormatoPlaylist::desde_ruta("m3u"), None);
+        assert_eq!(FormatoPlaylist::desde_ruta("listas.pls/m3u"), None);
+        assert_eq!(
+            FormatoPlaylist::desde_ruta("listas/Viaje.M3U8"),
+            Some(FormatoPlaylist::M3u)
+        );
+        let _ = std::fs::remove_file(ruta);
+    }
+
+    fn biblioteca_de_prueba() -> BibliotecaMusical {
+        let mut biblioteca = BibliotecaMusical::new();
+        biblioteca
+            .importar_playlist(&playlist_con_metadatos("clasicos"))
+            .unwrap();
+        let mut moderna = crear_cancion_completa("Nueva", "Los piojos", 200, 2005, 5);
+        moderna.genero = Genero::Rock;
+        let mut jazz = crear_cancion_completa("Blue", "Dolina", 200, 2010, 5);
+        jazz.genero = Genero::Jazz;
+        biblioteca.agregar_cancion(moderna);
+        biblioteca.agregar_cancion(jazz);
+        biblioteca
+    }
+
+    #[test]
+    fn test_biblioteca_guarda_cada_cancion_una_vez() {
+        let mut biblioteca = biblioteca_de_prueba();
+        assert_eq!(biblioteca.canciones.len(), 6);
+        biblioteca
+            .importar_playlist(&playlist_con_metadatos("otra copia"))
+            .unwrap();
+        assert_eq!(biblioteca.canciones.len(), 6);
+        assert_eq!(biblioteca.canciones_de("otra copia").unwrap().len(), 4);
+        assert_eq!(
+            biblioteca
+                .importar_playlist(&playlist_con_metadatos("clasicos"))
+                .unwrap_err(),
+            ErroresBiblioteca::PlaylistExistente
+        );
+    }
+
+    #[test]
+    fn test_biblioteca_editar_playlists() {
+        let mut biblioteca = biblioteca_de_prueba();
+        biblioteca.crear_playlist("favoritas").unwrap();
+        let id = biblioteca
+            .buscar_id("muchacha", "luis alberto spinetta")
+            .unwrap();
+        biblioteca.agregar_a_playlist("favoritas", id).unwrap();
+        assert_eq!(
+            biblioteca.agregar_a_playlist("favoritas", 999).unwrap_err(),
+            ErroresBiblioteca::CancionInexistente
+        );
+        assert_eq!(
+            biblioteca.agregar_a_playlist("no existe", id).unwrap_err(),
+            ErroresBiblioteca::PlaylistInexistente
+        );
+        assert!(biblioteca.quitar_de_playlist("favoritas", id).unwrap());
+        assert!(!biblioteca.quitar_de_playlist("favoritas", id).unwrap());
+
+        biblioteca.agregar_a_playlist("favoritas", id).unwrap();
+        assert!(biblioteca.eliminar_cancion(id));
+        assert!(biblioteca.canciones_de("favoritas").unwrap().is_empty());
+        assert_eq!(biblioteca.canciones_de("clasicos").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_playlist_inteligente_se_actualiza_sola() {
+        let mut biblioteca = biblioteca_de_prueba();
+        let rock_moderno = Condicion::Y(vec![
+            Condicion::GeneroIgual(Genero::Rock),
+            Condicion::AnoMayor(2000),
+        ]);
+        biblioteca
+            .crear_playlist_inteligente(
+                "rock moderno",
+                rock_moderno,
+                Some((CampoOrden::Puntuacion, false)),
+                None,
+            )
+            .unwrap();
+        let titulos: Vec<String> = biblioteca
+            .canciones_de("rock moderno")
+            .unwrap()
+            .iter()
+            .map(|c| c.titulo.clone())
+            .collect();
+        assert_eq!(titulos, vec!["Nueva", "Ojos"]);
+
+        let mut otra = crear_cancion_completa("Otra", "Divididos", 200, 2020, 1);
+        otra.genero = Genero::Rock;
+        biblioteca.agregar_cancion(otra);
+        let rock = biblioteca.canciones_de("rock moderno").unwrap();
+        assert_eq!(rock.len(), 3);
+        assert_eq!(rock[2].titulo, "Otra");
+
+        let id = biblioteca.buscar_id("Nueva", "Los piojos").unwrap();
+        assert_eq!(
+            biblioteca
+                .agregar_a_playlist("rock moderno", id)
+                .unwrap_err(),
+            ErroresBiblioteca::PlaylistInteligente
+        );
+    }
+
+    #[test]
+    fn test_biblioteca_unir_y_diferencia() {
+        let mut biblioteca = biblioteca_de_prueba();
+        biblioteca
+            .crear_playlist_inteligente("puntuadas", Condicion::PuntuacionMinima(5), None, Some(2))
+            .unwrap();
+        biblioteca.unir("todo", &["clasicos", "puntuadas"]).unwrap();
+        assert_eq!(biblioteca.canciones_de("todo").unwrap().len(), 5);
+
+        let solo_clasicos = biblioteca.diferencia("clasicos", "puntuadas").unwrap();
+        let titulos: Vec<&str> = solo_clasicos.iter().map(|c| c.titulo.as_str()).collect();
+        assert_eq!(titulos, vec!["Canción para mañana", "Ruta 40", "Ojos"]);
+        assert_eq!(
+            biblioteca.unir("todo", &["clasicos"]).unwrap_err(),
+            ErroresBiblioteca::PlaylistExistente
+        );
+    }
+
+    #[test]
+    fn test_biblioteca_deduplicar() {
+        let mut biblioteca = biblioteca_de_prueba();
+        biblioteca.crear_playlist("repetida").unwrap();
+        let id = biblioteca.buscar_id("Ojos", "Dolina").unwrap();
+        biblioteca.agregar_a_playlist("repetida", id).unwrap();
+        biblioteca.agregar_a_playlist("repetida", id).unwrap();
+        assert_eq!(biblioteca.deduplicar_playlist("repetida").unwrap(), 1);
+        assert_eq!(biblioteca.playlists["repetida"], vec![id]);
+    }
+
+    #[test]
+    fn test_biblioteca_completa_metadatos_de_repetidas() {
+        let mut biblioteca = biblioteca_de_prueba();
+        let id = biblioteca.buscar_id("Ruta 40", "Los piojos").unwrap();
+        let repetida = crear_cancion_completa("RUTA 40", "los piojos", 100, 2001, 4);
+        assert_eq!(biblioteca.agregar_cancion(repetida), id);
+        assert_eq!(biblioteca.canciones.len(), 6);
+
+        // Se conservan los datos que ya tenia y se agrega la puntuacion que faltaba
+        let cancion = biblioteca.cancion(id).unwrap();
+        assert_eq!(cancion.titulo, "Ruta 40");
+        assert_eq!(cancion.duracion_segundos, 300);
+        assert_eq!(cancion.ano, 1998);
+        assert_eq!(cancion.puntuacion, 4);
+    }
+
+    #[test]
+    fn test_biblioteca_exportar_playlist() {
+        let biblioteca = biblioteca_de_prueba();
+        let playlist = biblioteca.exportar_playlist("clasicos").unwrap();
+        assert_eq!(playlist.canciones, playlist_con_metadatos("x").canciones);
+        assert_eq!(playlist.ruta_archivo, "clasicos.json");
+    }
+
+    fn editor_de_prueba(ruta: &str, limite: usize) -> EditorPlaylist {
+        let mut playlist = playlist_con_metadatos("editable");
+        playlist.ruta_archivo = ruta.to_string();
+        EditorPlaylist::new(playlist, limite)
+    }
+
+    fn titulos(editor: &EditorPlaylist) -> Vec<String> {
+        editor
+            .playlist()
+            .canciones
+            .iter()
+            .map(|c| c.titulo.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_editor_deshacer_y_rehacer() {
+        let mut editor = editor_de_prueba("editor_1.json", 10);
+        let original = titulos(&editor);
+
+        assert!(editor.mover_cancion("Ojos", 0));
+        assert!(editor.eliminar_cancion("Ruta 40"));
+        editor.modificar_titulo("renombrada");
+        editor.agregar_cancion(crear_cancion("Nueva", "Alguien", Genero::Pop));
+        assert_eq!(titulos(&editor)[0], "Ojos");
+        assert_eq!(editor.playlist().canciones.len(), 4);
+
+        for _ in 0..4 {
+            assert!(editor.deshacer());
+        }
+        assert!(!editor.deshacer());
+        assert_eq!(titulos(&editor), original);
+        assert_eq!(editor.playlist().nombre, "editable");
+
+        assert!(editor.rehacer());
+        assert!(editor.rehacer());
+        assert_eq!(
+            titulos(&editor),
+            vec!["Ojos", "Canción para mañana", "Muchacha"]
+        );
+        assert!(!editor.mover_cancion("no existe", 0));
+        assert!(!editor.eliminar_cancion("no existe"));
+    }
+
+    #[test]
+    fn test_editor_nueva_edicion_borra_rehacer() {
+        let mut editor = editor_de_prueba("editor_2.json", 10);
+        editor.eliminar_cancion("Ojos");
+        editor.deshacer();
+        editor.modificar_titulo("otra");
+        assert!(!editor.rehacer());
+    }
+
+    #[test]
+    fn test_editor_historial_acotado() {
+        let mut editor = editor_de_prueba("editor_3.json", 2);
+        editor.modificar_titulo("uno");
+        editor.modificar_titulo("dos");
+        editor.modificar_titulo("tres");
+        assert!(editor.deshacer());
+        assert!(editor.deshacer());
+        assert!(!editor.deshacer());
+        assert_eq!(editor.playlist().nombre, "uno");
+    }
+
+    #[test]
+    fn test_editor_grupo_se_deshace_en_un_paso() {
+        let mut editor = editor_de_prueba("editor_4.json", 10);
+        let original = titulos(&editor);
+        editor.iniciar_grupo();
+        editor.eliminar_todas();
+        editor.agregar_cancion(crear_cancion("Unica", "Alguien", Genero::Pop));
+        editor.terminar_grupo();
+        assert_eq!(titulos(&editor), vec!["Unica"]);
+
+        assert!(editor.deshacer());
+        assert_eq!(titulos(&editor), original);
+        assert!(editor.rehacer());
+        assert_eq!(titulos(&editor), vec!["Unica"]);
+    }
+
+    #[test]
+    fn test_editor_persiste_solo_al_confirmar() {
+        let ruta = "editor_5.json";
+        let _ = std::fs::remove_file(ruta);
+        let mut editor = editor_de_prueba(ruta, 10);
+        editor.eliminar_todas();
+        assert!(editor.hay_cambios_sin_guardar());
+        assert!(std::fs::metadata(ruta).is_err());
+
+        editor.deshacer();
+        editor.confirmar();
+        assert!(!editor.hay_cambios_sin_guardar());
+        let json = std::fs::read_to_string(ruta).unwrap();
+        assert!(json.contains("Muchacha"));
+        let _ = std::fs::remove_file(ruta);
+    }
 }
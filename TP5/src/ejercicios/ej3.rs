@@ -1,10 +1,55 @@
 use crate::ejercicios::fecha::Fecha;
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
 use std::fs::File;
 use std::io::{BufReader, Write};
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum ErroresApp {
+    AtencionInexistente,
+    AtencionSinProcedimientos,
+    AtencionYaFacturada,
+    PrecioNoDefinido,
+    FacturaInexistente,
+    MontoInvalido,
+    ErrorDeArchivo,
+    MedicamentoInexistente,
+    StockInsuficiente,
+    CantidadInvalida,
+    LoteDuplicado,
+}
+
+impl fmt::Display for ErroresApp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErroresApp::AtencionInexistente => write!(f, "La atencion no existe"),
+            ErroresApp::AtencionSinProcedimientos => {
+                write!(f, "La atencion no registra procedimientos para facturar")
+            }
+            ErroresApp::AtencionYaFacturada => write!(f, "La atencion ya fue facturada"),
+            ErroresApp::PrecioNoDefinido => {
+                write!(f, "No hay precio definido para el procedimiento")
+            }
+            ErroresApp::FacturaInexistente => write!(f, "La factura no existe"),
+            ErroresApp::MontoInvalido => write!(f, "El monto ingresado no es valido"),
+            ErroresApp::ErrorDeArchivo => write!(f, "No fue posible escribir el archivo"),
+            ErroresApp::MedicamentoInexistente => {
+                write!(f, "El medicamento no existe en el inventario")
+            }
+            ErroresApp::StockInsuficiente => {
+                write!(f, "No hay stock suficiente del medicamento")
+            }
+            ErroresApp::CantidadInvalida => write!(f, "La cantidad debe ser mayor a cero"),
+            ErroresApp::LoteDuplicado => {
+                write!(f, "El lote ya fue ingresado para ese medicamento")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[allow(dead_code)]
 enum TipoAnimal {
     Perro,
@@ -30,6 +75,16 @@ struct Mascota {
     tutor: Tutor,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[allow(dead_code)]
+enum Procedimiento {
+    Consulta,
+    Vacunacion,
+    Desparasitacion,
+    Analisis,
+    Cirugia,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[allow(dead_code)]
 struct Atencion {
@@ -37,6 +92,354 @@ struct Atencion {
     diagnostico: String,
     tratamiento: String,
     proxima_visita: Option<Fecha>,
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    fecha: Option<Fecha>,
+    #[serde(default)]
+    procedimientos: Vec<Procedimiento>,
+    #[serde(default)]
+    recetas: Vec<Receta>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(dead_code)]
+struct Receta {
+    medicamento: String,
+    cantidad: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(dead_code)]
+struct Lote {
+    medicamento: String,
+    numero_lote: String,
+    vencimiento: Fecha,
+    cantidad: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(dead_code)]
+enum TipoMovimiento {
+    Ingreso,
+    Consumo { id_atencion: u32 },
+    BajaPorVencimiento,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(dead_code)]
+struct Movimiento {
+    fecha: Fecha,
+    medicamento: String,
+    numero_lote: String,
+    cantidad: u32,
+    tipo: TipoMovimiento,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(dead_code)]
+enum Alerta {
+    StockBajo {
+        medicamento: String,
+        disponible: u32,
+        minimo: u32,
+    },
+    ProximoVencimiento {
+        medicamento: String,
+        numero_lote: String,
+        vencimiento: Fecha,
+    },
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[allow(dead_code)]
+struct Farmacia {
+    lotes: Vec<Lote>,
+    stock_minimo: HashMap<String, u32>,
+    movimientos: Vec<Movimiento>,
+}
+
+#[allow(dead_code)]
+impl Farmacia {
+    pub fn ingresar_lote(
+        &mut self,
+        medicamento: &str,
+        numero_lote: &str,
+        vencimiento: Fecha,
+        cantidad: u32,
+        fecha: Fecha,
+    ) -> Result<(), ErroresApp> {
+        if cantidad == 0 {
+            return Err(ErroresApp::CantidadInvalida);
+        }
+        // Se mira el registro de ingresos porque los lotes agotados ya no estan en stock
+        if self.movimientos.iter().any(|m| {
+            m.tipo == TipoMovimiento::Ingreso
+                && m.medicamento == medicamento
+                && m.numero_lote == numero_lote
+        }) {
+            return Err(ErroresApp::LoteDuplicado);
+        }
+        self.lotes.push(Lote {
+            medicamento: medicamento.to_string(),
+            numero_lote: numero_lote.to_string(),
+            vencimiento,
+            cantidad,
+        });
+        self.movimientos.push(Movimiento {
+            fecha,
+            medicamento: medicamento.to_string(),
+            numero_lote: numero_lote.to_string(),
+            cantidad,
+            tipo: TipoMovimiento::Ingreso,
+        });
+        Ok(())
+    }
+
+    pub fn definir_stock_minimo(&mut self, medicamento: &str, minimo: u32) {
+        self.stock_minimo.insert(medicamento.to_string(), minimo);
+    }
+
+    pub fn stock_disponible(&self, medicamento: &str, hoy: Fecha) -> u32 {
+        self.lotes
+            .iter()
+            .filter(|l| l.medicamento == medicamento && l.vencimiento >= hoy)
+            .map(|l| l.cantidad)
+            .sum()
+    }
+
+    pub fn consumir(
+        &mut self,
+        medicamento: &str,
+        cantidad: u32,
+        fecha: Fecha,
+        id_atencion: u32,
+    ) -> Result<Vec<Movimiento>, ErroresApp> {
+        if cantidad == 0 {
+            return Err(ErroresApp::CantidadInvalida);
+        }
+        // Un medicamento con los lotes agotados existe, pero no tiene stock
+        if !self
+            .movimientos
+            .iter()
+            .any(|m| m.tipo == TipoMovimiento::Ingreso && m.medicamento == medicamento)
+        {
+            return Err(ErroresApp::MedicamentoInexistente);
+        }
+        if self.stock_disponible(medicamento, fecha) < cantidad {
+            return Err(ErroresApp::StockInsuficiente);
+        }
+
+        // FIFO por vencimiento: primero se consume el lote que vence antes
+        let mut lotes: Vec<&mut Lote> = self
+            .lotes
+            .iter_mut()
+            .filter(|l| l.medicamento == medicamento && l.vencimiento >= fecha && l.cantidad > 0)
+            .collect();
+        lotes.sort_by_key(|l| l.vencimiento);
+
+        let mut restante = cantidad;
+        let mut consumos = vec![];
+        for lote in lotes {
+            if restante == 0 {
+                break;
+            }
+            let tomado = restante.min(lote.cantidad);
+            lote.cantidad -= tomado;
+            restante -= tomado;
+            consumos.push(Movimiento {
+                fecha,
+                medicamento: medicamento.to_string(),
+                numero_lote: lote.numero_lote.clone(),
+                cantidad: tomado,
+                tipo: TipoMovimiento::Consumo { id_atencion },
+            });
+        }
+        self.lotes.retain(|l| l.cantidad > 0);
+        self.movimientos.extend(consumos.iter().cloned());
+        Ok(consumos)
+    }
+
+    pub fn dar_de_baja_vencidos(&mut self, hoy: Fecha) -> Vec<Movimiento> {
+        let bajas: Vec<Movimiento> = self
+            .lotes
+            .iter()
+            .filter(|l| l.vencimiento < hoy)
+            .map(|l| Movimiento {
+                fecha: hoy,
+                medicamento: l.medicamento.clone(),
+                numero_lote: l.numero_lote.clone(),
+                cantidad: l.cantidad,
+                tipo: TipoMovimiento::BajaPorVencimiento,
+            })
+            .collect();
+        self.lotes.retain(|l| l.vencimiento >= hoy);
+        self.movimientos.extend(bajas.iter().cloned());
+        bajas
+    }
+
+    pub fn alertas(&self, hoy: Fecha, dias_aviso: u32) -> Vec<Alerta> {
+        let mut alertas = vec![];
+
+        let mut minimos: Vec<(&String, &u32)> = self.stock_minimo.iter().collect();
+        minimos.sort();
+        for (medicamento, minimo) in minimos {
+            let disponible = self.stock_disponible(medicamento, hoy);
+            if disponible < *minimo {
+                alertas.push(Alerta::StockBajo {
+                    medicamento: medicamento.clone(),
+                    disponible,
+                    minimo: *minimo,
+                });
+            }
+        }
+
+        let mut limite = hoy;
+        limite.sumar_dias(dias_aviso);
+        for lote in &self.lotes {
+            if lote.vencimiento >= hoy && lote.vencimiento <= limite {
+                alertas.push(Alerta::ProximoVencimiento {
+                    medicamento: lote.medicamento.clone(),
+                    numero_lote: lote.numero_lote.clone(),
+                    vencimiento: lote.vencimiento,
+                });
+            }
+        }
+        alertas
+    }
+
+    pub fn movimientos_de(&self, medicamento: &str) -> Vec<&Movimiento> {
+        self.movimientos
+            .iter()
+            .filter(|m| m.medicamento == medicamento)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+#[allow(dead_code)]
+struct ListaPrecios {
+    precios: HashMap<(Procedimiento, TipoAnimal), f64>,
+}
+
+#[allow(dead_code)]
+impl ListaPrecios {
+    pub fn definir_precio(&mut self, procedimiento: Procedimiento, tipo: TipoAnimal, precio: f64) {
+        self.precios.insert((procedimiento, tipo), precio);
+    }
+
+    pub fn precio(&self, procedimiento: Procedimiento, tipo: TipoAnimal) -> Option<f64> {
+        self.precios.get(&(procedimiento, tipo)).copied()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(dead_code)]
+struct ItemFactura {
+    procedimiento: Procedimiento,
+    precio: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(dead_code)]
+struct Pago {
+    fecha: Fecha,
+    monto: f64,
+}
+
+// Diferencia por debajo de la cual un saldo se considera cancelado
+const TOLERANCIA_PAGO: f64 = 0.005;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[allow(dead_code)]
+struct Factura {
+    numero: u32,
+    id_atencion: u32,
+    fecha: Fecha,
+    tutor: Tutor,
+    nombre_mascota: String,
+    items: Vec<ItemFactura>,
+    pagos: Vec<Pago>,
+}
+
+#[allow(dead_code)]
+impl Factura {
+    pub fn total(&self) -> f64 {
+        self.items.iter().map(|i| i.precio).sum()
+    }
+
+    pub fn pagado(&self) -> f64 {
+        self.pagos.iter().map(|p| p.monto).sum()
+    }
+
+    pub fn saldo(&self) -> f64 {
+        self.total() - self.pagado()
+    }
+
+    pub fn esta_pagada(&self) -> bool {
+        self.saldo() <= TOLERANCIA_PAGO
+    }
+
+    pub fn recibo(&self) -> String {
+        let mut recibo = format!(
+            "Factura N° {}\nFecha: {}\nTutor: {} - Tel: {}\nMascota: {}\n",
+            self.numero, self.fecha, self.tutor.nombre, self.tutor.telefono, self.nombre_mascota
+        );
+        recibo.push_str("----------------------------------------\n");
+        for item in &self.items {
+            recibo.push_str(&format!(
+                "{:<28}{:>12.2}\n",
+                format!("{:?}", item.procedimiento),
+                item.precio
+            ));
+        }
+        recibo.push_str("----------------------------------------\n");
+        recibo.push_str(&format!("{:<28}{:>12.2}\n", "TOTAL", self.total()));
+        for pago in &self.pagos {
+            recibo.push_str(&format!(
+                "{:<28}{:>12.2}\n",
+                format!("Pago {}", pago.fecha),
+                pago.monto
+            ));
+        }
+        recibo.push_str(&format!("{:<28}{:>12.2}\n", "SALDO", self.saldo()));
+        recibo
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(dead_code)]
+struct EstadoDeCuenta {
+    tutor: Tutor,
+    facturas: Vec<Factura>,
+    total_facturado: f64,
+    total_pagado: f64,
+    saldo: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[allow(dead_code)]
+struct ReporteRecaudacion {
+    desde: Fecha,
+    hasta: Fecha,
+    cantidad_facturas: usize,
+    total_facturado: f64,
+    total_cobrado: f64,
+}
+
+// Formato del archivo: las facturas se guardan junto a las atenciones que facturan,
+// y la farmacia al lado. Se siguen aceptando los archivos viejos que solo tenian la
+// lista de atenciones o que no tenian farmacia
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ArchivoVeterinaria {
+    Completo {
+        atenciones: Vec<Atencion>,
+        facturas: Vec<Factura>,
+        #[serde(default)]
+        farmacia: Farmacia,
+    },
+    SoloAtenciones(Vec<Atencion>),
 }
 
 #[derive(Debug)]
@@ -48,6 +451,9 @@ struct Veterinaria {
     cola_atencion: VecDeque<Mascota>,
     historial_atenciones: Vec<Atencion>,
     ruta_archivo: String,
+    lista_precios: ListaPrecios,
+    facturas: Vec<Factura>,
+    farmacia: Farmacia,
 }
 
 #[allow(dead_code)]
@@ -60,8 +466,11 @@ impl Veterinaria {
             cola_atencion: VecDeque::new(),
             historial_atenciones: vec![],
             ruta_archivo: ruta_archivo.to_string(),
+            lista_precios: ListaPrecios::default(),
+            facturas: vec![],
+            farmacia: Farmacia::default(),
         };
-        vet.historial_atenciones = vet.leer_atenciones_de_archivo();
+        vet.leer_archivo();
         vet
     }
 
@@ -97,14 +506,46 @@ impl Veterinaria {
         tratamiento: &str,
         proxima_visita: Option<Fecha>,
     ) {
+        self.registrar_atencion_con_procedimientos(
+            mascota,
+            diagnostico,
+            tratamiento,
+            proxima_visita,
+            Fecha::hoy(),
+            vec![],
+        );
+    }
+
+    pub fn registrar_atencion_con_procedimientos(
+        &mut self,
+        mascota: Mascota,
+        diagnostico: &str,
+        tratamiento: &str,
+        proxima_visita: Option<Fecha>,
+        fecha: Fecha,
+        procedimientos: Vec<Procedimiento>,
+    ) -> u32 {
+        // El id es correlativo al mayor registrado, asi no se repite aunque se eliminen atenciones
+        let id = self
+            .historial_atenciones
+            .iter()
+            .map(|a| a.id)
+            .max()
+            .unwrap_or(0)
+            + 1;
         let atencion = Atencion {
             mascota,
             diagnostico: diagnostico.to_string(),
             tratamiento: tratamiento.to_string(),
             proxima_visita,
+            id,
+            fecha: Some(fecha),
+            procedimientos,
+            recetas: vec![],
         };
         self.historial_atenciones.push(atencion);
         self.escribir_atenciones_en_archivo();
+        id
     }
 
     pub fn buscar_atencion(
@@ -166,22 +607,199 @@ impl Veterinaria {
         false
     }
 
-    fn leer_atenciones_de_archivo(&self) -> Vec<Atencion> {
-        if let Ok(file) = File::open(&self.ruta_archivo) {
-            let reader = BufReader::new(file);
-            serde_json::from_reader(reader).unwrap_or_default()
-        } else {
-            vec![]
+    pub fn definir_precio(&mut self, procedimiento: Procedimiento, tipo: TipoAnimal, precio: f64) {
+        self.lista_precios
+            .definir_precio(procedimiento, tipo, precio);
+    }
+
+    pub fn facturar_atencion(&mut self, id_atencion: u32) -> Result<u32, ErroresApp> {
+        let atencion = self
+            .historial_atenciones
+            .iter()
+            .find(|a| a.id == id_atencion)
+            .ok_or(ErroresApp::AtencionInexistente)?;
+
+        if self.facturas.iter().any(|f| f.id_atencion == id_atencion) {
+            return Err(ErroresApp::AtencionYaFacturada);
+        }
+        if atencion.procedimientos.is_empty() {
+            return Err(ErroresApp::AtencionSinProcedimientos);
+        }
+
+        // Cada procedimiento se cotiza segun el tipo de animal atendido
+        let mut items = vec![];
+        for procedimiento in &atencion.procedimientos {
+            let precio = self
+                .lista_precios
+                .precio(*procedimiento, atencion.mascota.tipo)
+                .ok_or(ErroresApp::PrecioNoDefinido)?;
+            items.push(ItemFactura {
+                procedimiento: *procedimiento,
+                precio,
+            });
+        }
+
+        let numero = self.facturas.len() as u32 + 1;
+        let factura = Factura {
+            numero,
+            id_atencion,
+            fecha: atencion.fecha.unwrap_or_else(Fecha::hoy),
+            tutor: atencion.mascota.tutor.clone(),
+            nombre_mascota: atencion.mascota.nombre.clone(),
+            items,
+            pagos: vec![],
+        };
+        self.facturas.push(factura);
+        self.escribir_atenciones_en_archivo();
+        Ok(numero)
+    }
+
+    pub fn registrar_pago(
+        &mut self,
+        numero_factura: u32,
+        monto: f64,
+        fecha: Fecha,
+    ) -> Result<f64, ErroresApp> {
+        let factura = self
+            .facturas
+            .iter_mut()
+            .find(|f| f.numero == numero_factura)
+            .ok_or(ErroresApp::FacturaInexistente)?;
+
+        if monto <= 0.0 || monto > factura.saldo() + TOLERANCIA_PAGO {
+            return Err(ErroresApp::MontoInvalido);
+        }
+        factura.pagos.push(Pago { fecha, monto });
+        let saldo = factura.saldo();
+        self.escribir_atenciones_en_archivo();
+        Ok(saldo)
+    }
+
+    pub fn buscar_factura(&self, numero_factura: u32) -> Option<&Factura> {
+        self.facturas.iter().find(|f| f.numero == numero_factura)
+    }
+
+    pub fn estado_de_cuenta(&self, telefono_tutor: &str) -> Option<EstadoDeCuenta> {
+        let facturas: Vec<Factura> = self
+            .facturas
+            .iter()
+            .filter(|f| f.tutor.telefono == telefono_tutor)
+            .cloned()
+            .collect();
+        let tutor = facturas.first()?.tutor.clone();
+
+        let total_facturado: f64 = facturas.iter().map(|f| f.total()).sum();
+        let total_pagado: f64 = facturas.iter().map(|f| f.pagado()).sum();
+        Some(EstadoDeCuenta {
+            tutor,
+            facturas,
+            total_facturado,
+            total_pagado,
+            saldo: total_facturado - total_pagado,
+        })
+    }
+
+    pub fn recaudacion_por_periodo(&self, desde: Fecha, hasta: Fecha) -> ReporteRecaudacion {
+        let en_periodo = |fecha: &Fecha| *fecha >= desde && *fecha <= hasta;
+
+        let facturas_periodo: Vec<&Factura> = self
+            .facturas
+            .iter()
+            .filter(|f| en_periodo(&f.fecha))
+            .collect();
+        // Lo cobrado cuenta por la fecha del pago, no por la de la factura
+        let total_cobrado = self
+            .facturas
+            .iter()
+            .flat_map(|f| f.pagos.iter())
+            .filter(|p| en_periodo(&p.fecha))
+            .map(|p| p.monto)
+            .sum();
+
+        ReporteRecaudacion {
+            desde,
+            hasta,
+            cantidad_facturas: facturas_periodo.len(),
+            total_facturado: facturas_periodo.iter().map(|f| f.total()).sum(),
+            total_cobrado,
+        }
+    }
+
+    pub fn exportar_recibo(&self, numero_factura: u32, ruta: &str) -> Result<(), ErroresApp> {
+        let factura = self
+            .buscar_factura(numero_factura)
+            .ok_or(ErroresApp::FacturaInexistente)?;
+        File::create(ruta)
+            .and_then(|mut f| f.write_all(factura.recibo().as_bytes()))
+            .map_err(|_| ErroresApp::ErrorDeArchivo)
+    }
+
+    pub fn recetar_medicamento(
+        &mut self,
+        id_atencion: u32,
+        medicamento: &str,
+        cantidad: u32,
+        fecha: Fecha,
+    ) -> Result<Vec<Movimiento>, ErroresApp> {
+        let atencion = self
+            .historial_atenciones
+            .iter_mut()
+            .find(|a| a.id == id_atencion)
+            .ok_or(ErroresApp::AtencionInexistente)?;
+
+        let consumos = self
+            .farmacia
+            .consumir(medicamento, cantidad, fecha, id_atencion)?;
+        atencion.recetas.push(Receta {
+            medicamento: medicamento.to_string(),
+            cantidad,
+        });
+        self.escribir_atenciones_en_archivo();
+        Ok(consumos)
+    }
+
+    fn leer_archivo(&mut self) {
+        let archivo = File::open(&self.ruta_archivo)
+            .ok()
+            .and_then(|file| serde_json::from_reader(BufReader::new(file)).ok());
+        let (atenciones, facturas, farmacia) = match archivo {
+            Some(ArchivoVeterinaria::Completo {
+                atenciones,
+                facturas,
+                farmacia,
+            }) => (atenciones, facturas, farmacia),
+            Some(ArchivoVeterinaria::SoloAtenciones(atenciones)) => {
+                (atenciones, vec![], Farmacia::default())
+            }
+            None => (vec![], vec![], Farmacia::default()),
+        };
+        self.historial_atenciones = atenciones;
+        self.facturas = facturas;
+        self.farmacia = farmacia;
+
+        // Las atenciones guardadas antes de que existiera el id llegan con 0
+        let mut proximo_id = self
+            .historial_atenciones
+            .iter()
+            .map(|a| a.id)
+            .max()
+            .unwrap_or(0);
+        for atencion in &mut self.historial_atenciones {
+            if atencion.id == 0 {
+                proximo_id += 1;
+                atencion.id = proximo_id;
+            }
         }
     }
 
     fn escribir_atenciones_en_archivo(&self) {
+        let archivo = serde_json::json!({
+            "atenciones": &self.historial_atenciones,
+            "facturas": &self.facturas,
+            "farmacia": &self.farmacia,
+        });
         if let Ok(mut file) = File::create(&self.ruta_archivo) {
-            let _ = file.write_all(
-                serde_json::to_string_pretty(&self.historial_atenciones)
-                    .unwrap()
-                    .as_bytes(),
-            );
+            let _ = file.write_all(serde_json::to_string_pretty(&archivo).unwrap().as_bytes());
         }
     }
 }
@@ -321,4 +939,385 @@ mod tests {
         assert_eq!(a.diagnostico, "infeccion");
         let _ = std::fs::remove_file(ruta);
     }
+
+    #[test]
+    fn test_facturar_atencion() {
+        let ruta = "probatoria10.json";
+        let mut vet = Veterinaria::new(10, "la cuchita", "calle 7", ruta);
+        vet.definir_precio(Procedimiento::Consulta, TipoAnimal::Perro, 5000.0);
+        vet.definir_precio(Procedimiento::Vacunacion, TipoAnimal::Perro, 8000.0);
+        let id = vet.registrar_atencion_con_procedimientos(
+            nueva_mascota(),
+            "control anual",
+            "vacuna antirrabica",
+            None,
+            Fecha::new(3, 3, 2025),
+            vec![Procedimiento::Consulta, Procedimiento::Vacunacion],
+        );
+        let numero = vet.facturar_atencion(id).unwrap();
+        let factura = vet.buscar_factura(numero).unwrap();
+        assert_eq!(factura.items.len(), 2);
+        assert_eq!(factura.total(), 13000.0);
+        assert_eq!(
+            vet.facturar_atencion(id).unwrap_err(),
+            ErroresApp::AtencionYaFacturada
+        );
+        let _ = std::fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_facturar_atencion_sin_precio() {
+        let ruta = "probatoria11.json";
+        let mut vet = Veterinaria::new(10, "la cuchita", "calle 7", ruta);
+        vet.definir_precio(Procedimiento::Consulta, TipoAnimal::Perro, 5000.0);
+        let id = vet.registrar_atencion_con_procedimientos(
+            nueva_mascota(),
+            "fractura",
+            "yeso",
+            None,
+            Fecha::new(3, 3, 2025),
+            vec![Procedimiento::Cirugia],
+        );
+        assert_eq!(
+            vet.facturar_atencion(id).unwrap_err(),
+            ErroresApp::PrecioNoDefinido
+        );
+        assert_eq!(
+            vet.facturar_atencion(99).unwrap_err(),
+            ErroresApp::AtencionInexistente
+        );
+        let _ = std::fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_pagos_parciales_y_estado_de_cuenta() {
+        let ruta = "probatoria12.json";
+        let mut vet = Veterinaria::new(10, "la cuchita", "calle 7", ruta);
+        vet.definir_precio(Procedimiento::Consulta, TipoAnimal::Perro, 5000.0);
+        vet.definir_precio(Procedimiento::Vacunacion, TipoAnimal::Perro, 8000.0);
+        let id = vet.registrar_atencion_con_procedimientos(
+            nueva_mascota(),
+            "control",
+            "nada",
+            None,
+            Fecha::new(10, 4, 2025),
+            vec![Procedimiento::Consulta, Procedimiento::Vacunacion],
+        );
+        let numero = vet.facturar_atencion(id).unwrap();
+
+        let saldo = vet
+            .registrar_pago(numero, 3000.0, Fecha::new(10, 4, 2025))
+            .unwrap();
+        assert_eq!(saldo, 10000.0);
+        assert_eq!(
+            vet.registrar_pago(numero, 20000.0, Fecha::new(11, 4, 2025))
+                .unwrap_err(),
+            ErroresApp::MontoInvalido
+        );
+        let saldo = vet
+            .registrar_pago(numero, 10000.0, Fecha::new(2, 5, 2025))
+            .unwrap();
+        assert_eq!(saldo, 0.0);
+        assert!(vet.buscar_factura(numero).unwrap().esta_pagada());
+
+        let estado = vet.estado_de_cuenta("123456789").unwrap();
+        assert_eq!(estado.facturas.len(), 1);
+        assert_eq!(estado.total_pagado, 13000.0);
+        assert_eq!(estado.saldo, 0.0);
+        assert!(vet.estado_de_cuenta("000").is_none());
+        let _ = std::fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_recaudacion_por_periodo() {
+        let ruta = "probatoria13.json";
+        let mut vet = Veterinaria::new(10, "la cuchita", "calle 7", ruta);
+        vet.definir_precio(Procedimiento::Consulta, TipoAnimal::Perro, 5000.0);
+        let id = vet.registrar_atencion_con_procedimientos(
+            nueva_mascota(),
+            "control",
+            "nada",
+            None,
+            Fecha::new(28, 4, 2025),
+            vec![Procedimiento::Consulta],
+        );
+        let numero = vet.facturar_atencion(id).unwrap();
+        vet.registrar_pago(numero, 2000.0, Fecha::new(28, 4, 2025))
+            .unwrap();
+        vet.registrar_pago(numero, 3000.0, Fecha::new(5, 5, 2025))
+            .unwrap();
+
+        let abril = vet.recaudacion_por_periodo(Fecha::new(1, 4, 2025), Fecha::new(30, 4, 2025));
+        assert_eq!(abril.cantidad_facturas, 1);
+        assert_eq!(abril.total_facturado, 5000.0);
+        assert_eq!(abril.total_cobrado, 2000.0);
+
+        let mayo = vet.recaudacion_por_periodo(Fecha::new(1, 5, 2025), Fecha::new(31, 5, 2025));
+        assert_eq!(mayo.cantidad_facturas, 0);
+        assert_eq!(mayo.total_cobrado, 3000.0);
+        let _ = std::fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_exportar_recibo() {
+        let ruta = "probatoria14.json";
+        let ruta_recibo = "recibo_probatoria14.txt";
+        let mut vet = Veterinaria::new(10, "la cuchita", "calle 7", ruta);
+        vet.definir_precio(Procedimiento::Consulta, TipoAnimal::Perro, 5000.0);
+        let id = vet.registrar_atencion_con_procedimientos(
+            nueva_mascota(),
+            "control",
+            "nada",
+            None,
+            Fecha::new(1, 6, 2025),
+            vec![Procedimiento::Consulta],
+        );
+        let numero = vet.facturar_atencion(id).unwrap();
+        vet.exportar_recibo(numero, ruta_recibo).unwrap();
+        let contenido = std::fs::read_to_string(ruta_recibo).unwrap();
+        assert!(contenido.contains("Juan Carlos Pelotudo"));
+        assert!(contenido.contains("Consulta"));
+        assert!(contenido.contains("5000.00"));
+        assert_eq!(
+            vet.exportar_recibo(99, ruta_recibo).unwrap_err(),
+            ErroresApp::FacturaInexistente
+        );
+        let _ = std::fs::remove_file(ruta);
+        let _ = std::fs::remove_file(ruta_recibo);
+    }
+
+    #[test]
+    fn test_facturas_persisten_con_las_atenciones() {
+        let ruta = "probatoria19.json";
+        let mut vet = Veterinaria::new(12, "la cuchita", "calle 7", ruta);
+        vet.definir_precio(Procedimiento::Consulta, TipoAnimal::Perro, 5000.0);
+        let id = vet.registrar_atencion_con_procedimientos(
+            nueva_mascota(),
+            "control",
+            "nada",
+            None,
+            Fecha::new(1, 6, 2025),
+            vec![Procedimiento::Consulta],
+        );
+        let numero = vet.facturar_atencion(id).unwrap();
+        vet.registrar_pago(numero, 2000.0, Fecha::new(1, 6, 2025))
+            .unwrap();
+
+        let mut reabierta = Veterinaria::new(12, "la cuchita", "calle 7", ruta);
+        let factura = reabierta.buscar_factura(numero).unwrap();
+        assert_eq!(factura.saldo(), 3000.0);
+        assert_eq!(
+            reabierta.facturar_atencion(id).unwrap_err(),
+            ErroresApp::AtencionYaFacturada
+        );
+        let _ = std::fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_archivo_viejo_asigna_ids() {
+        let ruta = "probatoria20.json";
+        let atencion = serde_json::json!({
+            "mascota": nueva_mascota(),
+            "diagnostico": "resfrio",
+            "tratamiento": "jarabe",
+            "proxima_visita": null,
+        });
+        let viejo = serde_json::to_string(&vec![atencion.clone(), atencion]).unwrap();
+        std::fs::write(ruta, viejo).unwrap();
+
+        let mut vet = Veterinaria::new(13, "el refugio", "los hornos", ruta);
+        let ids: Vec<u32> = vet.historial_atenciones.iter().map(|a| a.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert!(vet.facturas.is_empty());
+        vet.registrar_atencion(nueva_mascota(), "chequeo", "nada", None);
+        assert_eq!(vet.historial_atenciones.last().unwrap().id, 3);
+        let _ = std::fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_pago_con_tolerancia_de_redondeo() {
+        let ruta = "probatoria21.json";
+        let mut vet = Veterinaria::new(14, "la cuchita", "calle 7", ruta);
+        vet.definir_precio(Procedimiento::Consulta, TipoAnimal::Perro, 0.3);
+        let id = vet.registrar_atencion_con_procedimientos(
+            nueva_mascota(),
+            "control",
+            "nada",
+            None,
+            Fecha::new(1, 6, 2025),
+            vec![Procedimiento::Consulta],
+        );
+        let numero = vet.facturar_atencion(id).unwrap();
+        vet.registrar_pago(numero, 0.1, Fecha::new(1, 6, 2025))
+            .unwrap();
+        vet.registrar_pago(numero, 0.1, Fecha::new(1, 6, 2025))
+            .unwrap();
+        // 0.3 - 0.1 - 0.1 no da exactamente 0.1 en f64
+        vet.registrar_pago(numero, 0.1, Fecha::new(1, 6, 2025))
+            .unwrap();
+        assert!(vet.buscar_factura(numero).unwrap().esta_pagada());
+        assert_eq!(
+            vet.registrar_pago(numero, 0.01, Fecha::new(1, 6, 2025))
+                .unwrap_err(),
+            ErroresApp::MontoInvalido
+        );
+        let _ = std::fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_recetar_consume_fifo_por_vencimiento() {
+        let ruta = "probatoria15.json";
+        let mut vet = Veterinaria::new(11, "farmacia animal", "calle 50", ruta);
+        let ingreso = Fecha::new(1, 1, 2025);
+        let farmacia = &mut vet.farmacia;
+        farmacia
+            .ingresar_lote("amoxicilina", "L-002", Fecha::new(30, 9, 2025), 10, ingreso)
+            .unwrap();
+        farmacia
+            .ingresar_lote("amoxicilina", "L-001", Fecha::new(31, 7, 2025), 5, ingreso)
+            .unwrap();
+        farmacia
+            .ingresar_lote("amoxicilina", "L-000", Fecha::new(1, 2, 2025), 50, ingreso)
+            .unwrap();
+        let fecha = Fecha::new(1, 6, 2025);
+        let id = vet.registrar_atencion_con_procedimientos(
+            nueva_mascota(),
+            "otitis",
+            "antibiotico",
+            None,
+            fecha,
+            vec![Procedimiento::Consulta],
+        );
+
+        // El lote L-000 esta vencido, asi que se empieza por L-001
+        let consumos = vet
+            .recetar_medicamento(id, "amoxicilina", 7, fecha)
+            .unwrap();
+        assert_eq!(consumos.len(), 2);
+        assert_eq!(consumos[0].numero_lote, "L-001");
+        assert_eq!(consumos[0].cantidad, 5);
+        assert_eq!(consumos[1].numero_lote, "L-002");
+        assert_eq!(consumos[1].cantidad, 2);
+        assert_eq!(vet.farmacia.stock_disponible("amoxicilina", fecha), 8);
+        assert_eq!(vet.historial_atenciones[0].recetas.len(), 1);
+
+        // La farmacia se guarda en el archivo junto con las atenciones
+        let reabierta = Veterinaria::new(11, "farmacia animal", "calle 50", ruta);
+        assert_eq!(reabierta.farmacia.stock_disponible("amoxicilina", fecha), 8);
+        assert_eq!(reabierta.farmacia.movimientos_de("amoxicilina").len(), 5);
+        let _ = std::fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_recetar_sin_stock() {
+        let ruta = "probatoria16.json";
+        let mut vet = Veterinaria::new(11, "farmacia animal", "calle 50", ruta);
+        let fecha = Fecha::new(1, 6, 2025);
+        vet.farmacia
+            .ingresar_lote("amoxicilina", "L-001", Fecha::new(31, 7, 2025), 15, fecha)
+            .unwrap();
+        let id = vet.registrar_atencion_con_procedimientos(
+            nueva_mascota(),
+            "otitis",
+            "antibiotico",
+            None,
+            fecha,
+            vec![],
+        );
+        assert_eq!(
+            vet.recetar_medicamento(id, "amoxicilina", 20, fecha)
+                .unwrap_err(),
+            ErroresApp::StockInsuficiente
+        );
+        assert_eq!(
+            vet.recetar_medicamento(id, "ibuprofeno", 1, fecha)
+                .unwrap_err(),
+            ErroresApp::MedicamentoInexistente
+        );
+        assert_eq!(vet.farmacia.stock_disponible("amoxicilina", fecha), 15);
+        assert_eq!(
+            vet.recetar_medicamento(id, "amoxicilina", 0, fecha)
+                .unwrap_err(),
+            ErroresApp::CantidadInvalida
+        );
+        assert!(vet.historial_atenciones[0].recetas.is_empty());
+        let _ = std::fs::remove_file(ruta);
+    }
+
+    #[test]
+    fn test_alertas_de_farmacia() {
+        let mut farmacia = Farmacia::default();
+        let ingreso = Fecha::new(1, 1, 2025);
+        farmacia
+            .ingresar_lote("amoxicilina", "L-002", Fecha::new(30, 9, 2025), 10, ingreso)
+            .unwrap();
+        farmacia
+            .ingresar_lote("amoxicilina", "L-001", Fecha::new(31, 7, 2025), 5, ingreso)
+            .unwrap();
+        farmacia.definir_stock_minimo("amoxicilina", 20);
+        let alertas = farmacia.alertas(Fecha::new(15, 7, 2025), 30);
+        assert!(alertas.contains(&Alerta::StockBajo {
+            medicamento: "amoxicilina".to_string(),
+            disponible: 15,
+            minimo: 20,
+        }));
+        assert!(alertas.contains(&Alerta::ProximoVencimiento {
+            medicamento: "amoxicilina".to_string(),
+            numero_lote: "L-001".to_string(),
+            vencimiento: Fecha::new(31, 7, 2025),
+        }));
+        assert_eq!(alertas.len(), 2);
+    }
+
+    #[test]
+    fn test_dar_de_baja_vencidos_y_movimientos() {
+        let mut farmacia = Farmacia::default();
+        let ingreso = Fecha::new(1, 1, 2025);
+        farmacia
+            .ingresar_lote("amoxicilina", "L-001", Fecha::new(31, 7, 2025), 5, ingreso)
+            .unwrap();
+        farmacia
+            .ingresar_lote("amoxicilina", "L-000", Fecha::new(1, 2, 2025), 50, ingreso)
+            .unwrap();
+        let bajas = farmacia.dar_de_baja_vencidos(Fecha::new(1, 3, 2025));
+        assert_eq!(bajas.len(), 1);
+        assert_eq!(bajas[0].numero_lote, "L-000");
+        assert_eq!(bajas[0].tipo, TipoMovimiento::BajaPorVencimiento);
+
+        let movimientos = farmacia.movimientos_de("amoxicilina");
+        assert_eq!(movimientos.len(), 3);
+        assert_eq!(movimientos[0].tipo, TipoMovimiento::Ingreso);
+    }
+
+    #[test]
+    fn test_ingresar_lote_invalido() {
+        let mut farmacia = Farmacia::default();
+        let ingreso = Fecha::new(1, 1, 2025);
+        let vencimiento = Fecha::new(31, 7, 2025);
+        assert_eq!(
+            farmacia
+                .ingresar_lote("amoxicilina", "L-001", vencimiento, 0, ingreso)
+                .unwrap_err(),
+            ErroresApp::CantidadInvalida
+        );
+        farmacia
+            .ingresar_lote("amoxicilina", "L-001", vencimiento, 5, ingreso)
+            .unwrap();
+        farmacia.consumir("amoxicilina", 5, ingreso, 1).unwrap();
+        assert_eq!(
+            farmacia.consumir("amoxicilina", 1, ingreso, 2).unwrap_err(),
+            ErroresApp::StockInsuficiente
+        );
+
+        // Aunque el lote se haya agotado, su numero no se puede volver a usar
+        assert_eq!(
+            farmacia
+                .ingresar_lote("amoxicilina", "L-001", vencimiento, 5, ingreso)
+                .unwrap_err(),
+            ErroresApp::LoteDuplicado
+        );
+        farmacia
+            .ingresar_lote("ibuprofeno", "L-001", vencimiento, 5, ingreso)
+            .unwrap();
+    }
 }
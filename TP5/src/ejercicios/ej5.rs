@@ -57,22 +57,37 @@ pub enum TipoSuscripcion {
     CLASICA,
     SUPER,
 }
+// Planes fijos de este ejercicio, ordenados de menor a mayor con su costo mensual.
+// No es un catalogo configurable: los precios no cambian sin recompilar
+static PLANES: [(TipoSuscripcion, f64); 3] = [
+    (TipoSuscripcion::BASICA, 100.0),
+    (TipoSuscripcion::CLASICA, 200.0),
+    (TipoSuscripcion::SUPER, 300.0),
+];
+
 #[allow(dead_code)]
 impl TipoSuscripcion {
+    fn posicion(&self) -> usize {
+        PLANES
+            .iter()
+            .position(|(tipo, _)| tipo == self)
+            .unwrap_or(0)
+    }
+
     pub fn superior(&self) -> &TipoSuscripcion {
-        match self {
-            TipoSuscripcion::BASICA => &TipoSuscripcion::CLASICA,
-            TipoSuscripcion::CLASICA => &TipoSuscripcion::SUPER,
-            TipoSuscripcion::SUPER => &TipoSuscripcion::SUPER,
-        }
+        &PLANES[(self.posicion() + 1).min(PLANES.len() - 1)].0
     }
 
     pub fn inferior(&self) -> &TipoSuscripcion {
-        match self {
-            TipoSuscripcion::BASICA => &TipoSuscripcion::BASICA,
-            TipoSuscripcion::CLASICA => &TipoSuscripcion::BASICA,
-            TipoSuscripcion::SUPER => &TipoSuscripcion::CLASICA,
-        }
+        &PLANES[self.posicion().saturating_sub(1)].0
+    }
+
+    pub fn es_el_mayor(&self) -> bool {
+        self.posicion() == PLANES.len() - 1
+    }
+
+    pub fn es_el_menor(&self) -> bool {
+        self.posicion() == 0
     }
 
     pub fn to_string(&self) -> String {
@@ -84,11 +99,7 @@ impl TipoSuscripcion {
     }
 
     pub fn get_costo(&self) -> f64 {
-        match self {
-            TipoSuscripcion::BASICA => 100.0,
-            TipoSuscripcion::CLASICA => 200.0,
-            TipoSuscripcion::SUPER => 300.0,
-        }
+        PLANES[self.posicion()].1
     }
 }
 
@@ -239,7 +250,7 @@ impl<'d> StreamingRust<'d> {
             Some(suscripcion_actual) => {
                 suscripcion_actual.cancelar(); // Cancelo la suscripcion actual
 
-                if suscripcion_actual.tipo_suscripcion == TipoSuscripcion::SUPER {
+                if suscripcion_actual.tipo_suscripcion.es_el_mayor() {
                     return Err(ErroresApp::MejorSuscripcionDisponible);
                 } //Si el usuario tiene la mayor suscripcion posible elevo error
 
@@ -272,8 +283,8 @@ impl<'d> StreamingRust<'d> {
         match suscripcion_actual {
             Some(suscripcion_actual) => {
                 suscripcion_actual.cancelar(); // Cancelo la suscripcion actual
-                if suscripcion_actual.tipo_suscripcion == TipoSuscripcion::BASICA {
-                    return Ok(TipoSuscripcion::BASICA);
+                if suscripcion_actual.tipo_suscripcion.es_el_menor() {
+                    return Ok(suscripcion_actual.tipo_suscripcion);
                 } //Si el usuario tiene la mayor suscripcion posible elevo error
 
                 let siguiente = suscripcion_actual.tipo_suscripcion.inferior(); //Obtengo la suscripcion inferior
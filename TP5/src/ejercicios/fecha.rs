@@ -1,8 +1,9 @@
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fmt;
 
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Fecha {
     dia: u32,
     mes: u32,
@@ -124,6 +125,18 @@ impl Fecha {
         }
     }
 
+    pub fn get_dia(&self) -> u32 {
+        self.dia
+    }
+
+    pub fn get_mes(&self) -> u32 {
+        self.mes
+    }
+
+    pub fn get_ano(&self) -> u32 {
+        self.ano
+    }
+
     pub fn set_fecha(&mut self, dia: u32, mes: u32, ano: u32) {
         self.dia = dia;
         self.mes = mes;
@@ -148,6 +161,18 @@ impl fmt::Display for Fecha {
     }
 }
 
+impl Ord for Fecha {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.ano, self.mes, self.dia).cmp(&(other.ano, other.mes, other.dia))
+    }
+}
+
+impl PartialOrd for Fecha {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 #[test]
 fn test_fecha_es_bisiesto() {
     let fecha: Fecha = Fecha::new(29, 02, 2024);
@@ -231,6 +256,13 @@ fn test_fecha_to_string() {
     assert_eq!("1/1/2025".to_string(), fecha.to_string());
 }
 
+#[test]
+fn test_fecha_orden() {
+    assert!(Fecha::new(1, 1, 2025) > Fecha::new(31, 12, 2024));
+    assert!(Fecha::new(28, 2, 2024) < Fecha::new(1, 3, 2024));
+    assert!(Fecha::new(15, 6, 2024) <= Fecha::new(15, 6, 2024));
+}
+
 #[test]
 fn test_fecha_restar_dias() {
     let mut fecha: Fecha = Fecha::new(1, 02, 2024);
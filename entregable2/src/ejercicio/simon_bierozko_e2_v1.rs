@@ -1,8 +1,10 @@
 use chrono::prelude::*;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::{self};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Fecha {
     dia: u32,
     mes: u32,
@@ -140,6 +142,32 @@ impl Fecha {
     pub fn to_string(&self) -> String {
         format!("{}/{}/{}", self.dia, self.mes, self.ano)
     }
+
+    pub fn dias_del_mes(&self) -> u32 {
+        if self.mes == 2 {
+            if self.es_bisiesto() { 29 } else { 28 }
+        } else if self.es_mes_corto() {
+            30
+        } else {
+            31
+        }
+    }
+
+    pub fn dias_hasta(&self, otra: Fecha) -> i64 {
+        let desde =
+            NaiveDate::from_ymd_opt(self.ano as i32, self.mes, self.dia).unwrap_or_default();
+        let hasta =
+            NaiveDate::from_ymd_opt(otra.ano as i32, otra.mes, otra.dia).unwrap_or_default();
+        (hasta - desde).num_days()
+    }
+
+    // Mismo dia n meses despues; si ese mes es mas corto queda en su ultimo dia
+    pub fn sumar_meses(&self, meses: u32) -> Fecha {
+        let total = self.mes - 1 + meses;
+        let mut fecha = Fecha::new(1, total % 12 + 1, self.ano + total / 12);
+        fecha.dia = self.dia.min(fecha.dias_del_mes());
+        fecha
+    }
 }
 
 impl fmt::Display for Fecha {
@@ -148,6 +176,18 @@ impl fmt::Display for Fecha {
     }
 }
 
+impl Ord for Fecha {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.ano, self.mes, self.dia).cmp(&(other.ano, other.mes, other.dia))
+    }
+}
+
+impl PartialOrd for Fecha {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 #[allow(dead_code)]
 pub enum ErroresApp {
@@ -163,6 +203,25 @@ pub enum ErroresApp {
     SinSuscripcionesActivas,
     SinSuscripciones,
     SinMediosPagoActivos,
+    PagoRechazado,
+    TiempoAgotado,
+    UsuarioConDeuda,
+    UsuarioNoSuspendido,
+    PlanInexistente,
+    ConfiguracionInvalida,
+    LimiteDePerfilesAlcanzado,
+    LimiteDePantallasAlcanzado,
+    UsuarioConSuscripcionPropia,
+    UsuarioYaEsMiembro,
+    InvitacionInexistente,
+    MiembroInexistente,
+    SesionInexistente,
+    CodigoExistente,
+    CodigoInexistente,
+    CodigoFueraDeVigencia,
+    CodigoAgotado,
+    CodigoYaCanjeado,
+    PromocionEnCurso,
 }
 
 impl fmt::Display for ErroresApp {
@@ -197,49 +256,264 @@ impl fmt::Display for ErroresApp {
                     "No se encontraron suscripciones activas con medios de pago"
                 )
             }
+            ErroresApp::PagoRechazado => write!(f, "El pago fue rechazado"),
+            ErroresApp::TiempoAgotado => {
+                write!(f, "No se obtuvo respuesta del procesador de pagos")
+            }
+            ErroresApp::UsuarioConDeuda => write!(f, "El usuario tiene facturas impagas"),
+            ErroresApp::UsuarioNoSuspendido => write!(f, "El usuario no esta suspendido"),
+            ErroresApp::PlanInexistente => write!(f, "El plan no esta en el catalogo"),
+            ErroresApp::ConfiguracionInvalida => {
+                write!(f, "La configuracion del catalogo no es valida")
+            }
+            ErroresApp::LimiteDePerfilesAlcanzado => {
+                write!(f, "El plan no admite mas perfiles en la cuenta")
+            }
+            ErroresApp::LimiteDePantallasAlcanzado => {
+                write!(f, "Se alcanzo el maximo de pantallas simultaneas del plan")
+            }
+            ErroresApp::UsuarioConSuscripcionPropia => {
+                write!(f, "El usuario ya tiene una suscripcion activa propia")
+            }
+            ErroresApp::UsuarioYaEsMiembro => write!(f, "El usuario ya pertenece a una cuenta"),
+            ErroresApp::InvitacionInexistente => write!(f, "No existe esa invitacion"),
+            ErroresApp::MiembroInexistente => {
+                write!(f, "El usuario no es miembro de la cuenta")
+            }
+            ErroresApp::SesionInexistente => write!(f, "No existe esa sesion"),
+            ErroresApp::CodigoExistente => write!(f, "Ya existe un codigo promocional igual"),
+            ErroresApp::CodigoInexistente => write!(f, "El codigo promocional no existe"),
+            ErroresApp::CodigoFueraDeVigencia => {
+                write!(f, "El codigo promocional no esta vigente")
+            }
+            ErroresApp::CodigoAgotado => write!(f, "El codigo promocional ya no tiene usos"),
+            ErroresApp::CodigoYaCanjeado => write!(f, "El usuario ya uso este codigo"),
+            ErroresApp::PromocionEnCurso => write!(f, "El usuario ya tiene una promocion en curso"),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-#[allow(dead_code)]
-pub enum TipoSuscripcion {
-    BASICA,
-    CLASICA,
-    SUPER,
+const LARGO_CODIGO_PLAN: usize = 16;
+
+// Codigo con el que el catalogo identifica a un plan. Los tres planes originales tienen
+// constantes, pero cualquier otro codigo se puede definir como dato en el catalogo
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TipoSuscripcion {
+    codigo: [u8; LARGO_CODIGO_PLAN],
 }
+
 #[allow(dead_code)]
 impl TipoSuscripcion {
-    pub fn superior(&self) -> &TipoSuscripcion {
-        match self {
-            TipoSuscripcion::BASICA => &TipoSuscripcion::CLASICA,
-            TipoSuscripcion::CLASICA => &TipoSuscripcion::SUPER,
-            TipoSuscripcion::SUPER => &TipoSuscripcion::SUPER,
+    pub const BASICA: TipoSuscripcion = TipoSuscripcion::constante("BASICA");
+    pub const CLASICA: TipoSuscripcion = TipoSuscripcion::constante("CLASICA");
+    pub const SUPER: TipoSuscripcion = TipoSuscripcion::constante("SUPER");
+
+    const fn constante(codigo: &str) -> Self {
+        let bytes = codigo.as_bytes();
+        let mut buffer = [0; LARGO_CODIGO_PLAN];
+        let mut i = 0;
+        while i < bytes.len() {
+            buffer[i] = bytes[i];
+            i += 1;
         }
+        TipoSuscripcion { codigo: buffer }
     }
 
-    pub fn inferior(&self) -> &TipoSuscripcion {
-        match self {
-            TipoSuscripcion::BASICA => &TipoSuscripcion::BASICA,
-            TipoSuscripcion::CLASICA => &TipoSuscripcion::BASICA,
-            TipoSuscripcion::SUPER => &TipoSuscripcion::CLASICA,
+    // Mayusculas, digitos y guiones bajos, hasta 16 caracteres
+    pub fn new(codigo: &str) -> Result<Self, ErroresApp> {
+        let valido = !codigo.is_empty()
+            && codigo.len() <= LARGO_CODIGO_PLAN
+            && codigo
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        if !valido {
+            return Err(ErroresApp::ConfiguracionInvalida);
         }
+        Ok(TipoSuscripcion::constante(codigo))
+    }
+
+    pub fn codigo(&self) -> &str {
+        let largo = self
+            .codigo
+            .iter()
+            .position(|b| *b == 0)
+            .unwrap_or(LARGO_CODIGO_PLAN);
+        std::str::from_utf8(&self.codigo[..largo]).unwrap_or_default()
     }
 
     pub fn to_string(&self) -> String {
-        match self {
-            TipoSuscripcion::BASICA => "BASICA".to_string(),
-            TipoSuscripcion::CLASICA => "CLASICA".to_string(),
-            TipoSuscripcion::SUPER => "SUPER".to_string(),
+        self.codigo().to_string()
+    }
+}
+
+impl fmt::Debug for TipoSuscripcion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.codigo())
+    }
+}
+
+impl Serialize for TipoSuscripcion {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.codigo())
+    }
+}
+
+impl<'de> Deserialize<'de> for TipoSuscripcion {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let codigo = String::deserialize(deserializer)?;
+        TipoSuscripcion::new(&codigo)
+            .map_err(|_| serde::de::Error::custom(format!("codigo de plan invalido: {}", codigo)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[allow(dead_code)]
+pub struct PrecioPlan {
+    desde: Fecha,
+    precio: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[allow(dead_code)]
+pub struct Plan {
+    tipo: TipoSuscripcion,
+    nombre: String,
+    orden: u8,
+    precios: Vec<PrecioPlan>,
+    pantallas: u8,
+    hd: bool,
+    descargas: bool,
+    // Perfiles por cuenta contando al titular; sin configurar, solo el titular
+    #[serde(default)]
+    perfiles: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[allow(dead_code)]
+pub struct CatalogoPlanes {
+    planes: Vec<Plan>,
+}
+
+impl Default for CatalogoPlanes {
+    fn default() -> Self {
+        CatalogoPlanes::por_defecto()
+    }
+}
+
+#[allow(dead_code)]
+impl CatalogoPlanes {
+    pub fn por_defecto() -> Self {
+        let plan = |tipo, nombre: &str, orden, precio, pantallas, hd, descargas, perfiles| Plan {
+            tipo,
+            nombre: nombre.to_string(),
+            orden,
+            precios: vec![PrecioPlan {
+                desde: Fecha::new(1, 1, 2000),
+                precio,
+            }],
+            pantallas,
+            hd,
+            descargas,
+            perfiles,
+        };
+        CatalogoPlanes {
+            planes: vec![
+                plan(
+                    TipoSuscripcion::BASICA,
+                    "Basica",
+                    1,
+                    100.0,
+                    1,
+                    false,
+                    false,
+                    1,
+                ),
+                plan(
+                    TipoSuscripcion::CLASICA,
+                    "Clasica",
+                    2,
+                    200.0,
+                    2,
+                    true,
+                    false,
+                    3,
+                ),
+                plan(TipoSuscripcion::SUPER, "Super", 3, 300.0, 4, true, true, 5),
+            ],
         }
     }
 
-    pub fn get_costo(&self) -> f64 {
-        match self {
-            TipoSuscripcion::BASICA => 100.0,
-            TipoSuscripcion::CLASICA => 200.0,
-            TipoSuscripcion::SUPER => 300.0,
+    pub fn desde_json(json: &str) -> Result<Self, ErroresApp> {
+        let mut catalogo: CatalogoPlanes =
+            serde_json::from_str(json).map_err(|_| ErroresApp::ConfiguracionInvalida)?;
+        for (i, plan) in catalogo.planes.iter().enumerate() {
+            let repetido = catalogo.planes[..i]
+                .iter()
+                .any(|otro| otro.tipo == plan.tipo || otro.orden == plan.orden);
+            if plan.precios.is_empty() || repetido {
+                return Err(ErroresApp::ConfiguracionInvalida);
+            }
+        }
+        for plan in &mut catalogo.planes {
+            plan.precios.sort_by_key(|p| p.desde);
         }
+        Ok(catalogo)
+    }
+
+    pub fn desde_archivo(ruta: &str) -> Result<Self, ErroresApp> {
+        let json = std::fs::read_to_string(ruta).map_err(|_| ErroresApp::ConfiguracionInvalida)?;
+        CatalogoPlanes::desde_json(&json)
+    }
+
+    pub fn plan(&self, tipo: TipoSuscripcion) -> Option<&Plan> {
+        self.planes.iter().find(|p| p.tipo == tipo)
+    }
+
+    // El ultimo precio que empezo a regir hasta esa fecha
+    pub fn precio_vigente(&self, tipo: TipoSuscripcion, fecha: Fecha) -> Result<f64, ErroresApp> {
+        let plan = self.plan(tipo).ok_or(ErroresApp::PlanInexistente)?;
+        plan.precios
+            .iter()
+            .rev()
+            .find(|p| p.desde <= fecha)
+            .map(|p| p.precio)
+            .ok_or(ErroresApp::PlanInexistente)
+    }
+
+    // Solo afecta a las suscripciones nuevas: las existentes conservan su precio
+    pub fn programar_precio(
+        &mut self,
+        tipo: TipoSuscripcion,
+        desde: Fecha,
+        precio: f64,
+    ) -> Result<(), ErroresApp> {
+        let plan = self
+            .planes
+            .iter_mut()
+            .find(|p| p.tipo == tipo)
+            .ok_or(ErroresApp::PlanInexistente)?;
+        plan.precios.retain(|p| p.desde != desde);
+        plan.precios.push(PrecioPlan { desde, precio });
+        plan.precios.sort_by_key(|p| p.desde);
+        Ok(())
+    }
+
+    pub fn superior(&self, tipo: TipoSuscripcion) -> Option<TipoSuscripcion> {
+        let orden = self.plan(tipo)?.orden;
+        self.planes
+            .iter()
+            .filter(|p| p.orden > orden)
+            .min_by_key(|p| p.orden)
+            .map(|p| p.tipo)
+    }
+
+    pub fn inferior(&self, tipo: TipoSuscripcion) -> Option<TipoSuscripcion> {
+        let orden = self.plan(tipo)?.orden;
+        self.planes
+            .iter()
+            .filter(|p| p.orden < orden)
+            .max_by_key(|p| p.orden)
+            .map(|p| p.tipo)
     }
 }
 
@@ -252,6 +526,248 @@ pub enum TipoMedioPago<'a> {
     TRANSFERENCIA { cbu: &'a str },
     CRIPTO { wallet: &'a str },
 }
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum RespuestaPasarela {
+    Aprobado,
+    Rechazado,
+    SinRespuesta,
+}
+
+pub trait Pasarela {
+    fn procesar(&mut self, medio: &str, monto: f64) -> RespuestaPasarela;
+}
+
+// Pasarela para pruebas: devuelve las respuestas encoladas y despues la de por defecto
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct PasarelaSimulada {
+    respuestas: VecDeque<RespuestaPasarela>,
+    por_defecto: RespuestaPasarela,
+    intentos: usize,
+}
+#[allow(dead_code)]
+impl PasarelaSimulada {
+    pub fn new(por_defecto: RespuestaPasarela) -> Self {
+        Self {
+            respuestas: VecDeque::new(),
+            por_defecto,
+            intentos: 0,
+        }
+    }
+
+    pub fn encolar(&mut self, respuesta: RespuestaPasarela) {
+        self.respuestas.push_back(respuesta);
+    }
+
+    pub fn intentos(&self) -> usize {
+        self.intentos
+    }
+}
+
+impl Pasarela for PasarelaSimulada {
+    fn procesar(&mut self, _medio: &str, _monto: f64) -> RespuestaPasarela {
+        self.intentos += 1;
+        self.respuestas.pop_front().unwrap_or(self.por_defecto)
+    }
+}
+
+// Reintentos inmediatos cuando la pasarela no responde
+const REINTENTOS_SIN_RESPUESTA: usize = 2;
+// Cobros rechazados antes de suspender al usuario
+const MAX_INTENTOS_COBRO: u8 = 3;
+
+pub trait ProcesadorPago {
+    fn nombre(&self) -> &str;
+
+    fn validar(&self) -> Result<(), ErroresApp>;
+
+    fn cobrar(&self, monto: f64, pasarela: &mut dyn Pasarela) -> Result<(), ErroresApp> {
+        self.validar()?;
+        if monto <= 0.0 {
+            return Err(ErroresApp::DatosInvalidos);
+        }
+        for _ in 0..=REINTENTOS_SIN_RESPUESTA {
+            match pasarela.procesar(self.nombre(), monto) {
+                RespuestaPasarela::Aprobado => return Ok(()),
+                RespuestaPasarela::Rechazado => return Err(ErroresApp::PagoRechazado),
+                RespuestaPasarela::SinRespuesta => continue,
+            }
+        }
+        Err(ErroresApp::TiempoAgotado)
+    }
+}
+
+#[allow(dead_code)]
+pub struct ProcesadorEfectivo {
+    disponible: f64,
+}
+
+#[allow(dead_code)]
+pub struct ProcesadorMercadoPago<'a> {
+    cvu: &'a str,
+}
+
+#[allow(dead_code)]
+pub struct ProcesadorCredito<'a> {
+    nro_cuenta: &'a str,
+}
+
+#[allow(dead_code)]
+pub struct ProcesadorTransferencia<'a> {
+    cbu: &'a str,
+}
+
+#[allow(dead_code)]
+pub struct ProcesadorCripto<'a> {
+    wallet: &'a str,
+}
+
+impl ProcesadorPago for ProcesadorEfectivo {
+    fn nombre(&self) -> &str {
+        "EFECTIVO"
+    }
+
+    fn validar(&self) -> Result<(), ErroresApp> {
+        if self.disponible < 0.0 {
+            return Err(ErroresApp::DatosInvalidos);
+        }
+        Ok(())
+    }
+
+    // El efectivo no pasa por la pasarela
+    fn cobrar(&self, monto: f64, _pasarela: &mut dyn Pasarela) -> Result<(), ErroresApp> {
+        self.validar()?;
+        if monto > self.disponible {
+            return Err(ErroresApp::MontoInsuficiente);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ProcesadorPago for ProcesadorMercadoPago<'a> {
+    fn nombre(&self) -> &str {
+        "MERCADOPAGO"
+    }
+
+    fn validar(&self) -> Result<(), ErroresApp> {
+        if !cvu_es_valido(self.cvu) {
+            return Err(ErroresApp::DatosInvalidos);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ProcesadorPago for ProcesadorCredito<'a> {
+    fn nombre(&self) -> &str {
+        "CREDITO"
+    }
+
+    fn validar(&self) -> Result<(), ErroresApp> {
+        if !luhn_es_valido(self.nro_cuenta) {
+            return Err(ErroresApp::DatosInvalidos);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ProcesadorPago for ProcesadorTransferencia<'a> {
+    fn nombre(&self) -> &str {
+        "TRANSFERENCIA"
+    }
+
+    fn validar(&self) -> Result<(), ErroresApp> {
+        if !cbu_es_valido(self.cbu) {
+            return Err(ErroresApp::DatosInvalidos);
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ProcesadorPago for ProcesadorCripto<'a> {
+    fn nombre(&self) -> &str {
+        "CRIPTO"
+    }
+
+    fn validar(&self) -> Result<(), ErroresApp> {
+        let largo_valido = (26..=62).contains(&self.wallet.len());
+        if !largo_valido || !self.wallet.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(ErroresApp::DatosInvalidos);
+        }
+        Ok(())
+    }
+}
+
+#[allow(dead_code)]
+impl<'a> TipoMedioPago<'a> {
+    pub fn nombre(&self) -> &'static str {
+        match self {
+            TipoMedioPago::EFECTIVO(_) => "EFECTIVO",
+            TipoMedioPago::MERCADOPAGO { .. } => "MERCADOPAGO",
+            TipoMedioPago::CREDITO { .. } => "CREDITO",
+            TipoMedioPago::TRANSFERENCIA { .. } => "TRANSFERENCIA",
+            TipoMedioPago::CRIPTO { .. } => "CRIPTO",
+        }
+    }
+
+    pub fn procesador(&self) -> Box<dyn ProcesadorPago + 'a> {
+        match *self {
+            TipoMedioPago::EFECTIVO(disponible) => Box::new(ProcesadorEfectivo { disponible }),
+            TipoMedioPago::MERCADOPAGO { cvu } => Box::new(ProcesadorMercadoPago { cvu }),
+            TipoMedioPago::CREDITO { nro_cuenta } => Box::new(ProcesadorCredito { nro_cuenta }),
+            TipoMedioPago::TRANSFERENCIA { cbu } => Box::new(ProcesadorTransferencia { cbu }),
+            TipoMedioPago::CRIPTO { wallet } => Box::new(ProcesadorCripto { wallet }),
+        }
+    }
+}
+
+pub fn luhn_es_valido(numero: &str) -> bool {
+    let digitos: Vec<u32> = numero
+        .chars()
+        .filter(|c| *c != ' ')
+        .map(|c| c.to_digit(10))
+        .collect::<Option<Vec<u32>>>()
+        .unwrap_or_default();
+    if !(12..=19).contains(&digitos.len()) {
+        return false;
+    }
+    // Desde la derecha, se duplica uno de cada dos digitos
+    let suma: u32 = digitos
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| match (i % 2, d * 2) {
+            (1, doble) if doble > 9 => doble - 9,
+            (1, doble) => doble,
+            _ => *d,
+        })
+        .sum();
+    suma.is_multiple_of(10)
+}
+
+// 22 digitos en dos bloques (banco/sucursal y cuenta), cada uno con su verificador al final
+pub fn cbu_es_valido(cbu: &str) -> bool {
+    fn bloque_valido(digitos: &[u32], pesos: &[u32]) -> bool {
+        let suma: u32 = digitos.iter().zip(pesos).map(|(d, p)| d * p).sum();
+        (10 - suma % 10) % 10 == digitos[digitos.len() - 1]
+    }
+
+    let digitos: Option<Vec<u32>> = cbu.chars().map(|c| c.to_digit(10)).collect();
+    match digitos {
+        Some(d) if d.len() == 22 => {
+            bloque_valido(&d[..8], &[7, 1, 3, 9, 7, 1, 3])
+                && bloque_valido(&d[8..], &[3, 9, 7, 1, 3, 9, 7, 1, 3, 9, 7, 1, 3])
+        }
+        _ => false,
+    }
+}
+
+// El CVU usa el formato y los verificadores del CBU, pero en lugar del codigo de
+// banco empieza con 000 seguido del codigo del proveedor de servicios de pago
+pub fn cvu_es_valido(cvu: &str) -> bool {
+    cvu.starts_with("000") && cbu_es_valido(cvu)
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 #[allow(dead_code)]
 pub struct Suscripcion<'c> {
@@ -260,34 +776,96 @@ pub struct Suscripcion<'c> {
     costo_mensual: f64,
     medio_pago: TipoMedioPago<'c>,
     fecha_inicio: Fecha,
+    // Los periodos se cuentan desde aca; despues de un cambio de plan a mitad de
+    // periodo es anterior a `fecha_inicio`, para conservar el dia de facturacion
+    inicio_periodos: Fecha,
     duracion_en_meses: u8,
     activa: bool,
+    renovacion_automatica: bool,
+    periodos_facturados: u8,
+    cambio_desde: Option<TipoSuscripcion>,
+    suspendida: bool,
+    fecha_baja: Option<Fecha>,
+    prueba: bool,
 }
 #[allow(dead_code)]
 impl<'c> Suscripcion<'c> {
     pub fn new(
         nombre_usuario: &'c str,
         tipo_suscripcion: TipoSuscripcion,
+        costo_mensual: f64,
         medio_pago: TipoMedioPago<'c>,
         fecha_inicio: Fecha,
     ) -> Self {
         Self {
             nombre_usuario,
             tipo_suscripcion,
-            costo_mensual: tipo_suscripcion.get_costo(),
+            costo_mensual,
             medio_pago,
             fecha_inicio,
+            inicio_periodos: fecha_inicio,
             duracion_en_meses: 12,
             activa: true,
+            renovacion_automatica: true,
+            periodos_facturados: 0,
+            cambio_desde: None,
+            suspendida: false,
+            fecha_baja: None,
+            prueba: false,
         }
     }
 
-    pub fn superior(&mut self) -> &TipoSuscripcion {
-        self.tipo_suscripcion.superior()
+    // Cierra esta suscripcion en `desde`, dentro de `periodo`, y devuelve la que la continua
+    // con el otro tipo, respetando el plazo y el dia de facturacion originales
+    fn dividir(
+        &mut self,
+        periodo: u8,
+        desde: Fecha,
+        tipo_suscripcion: TipoSuscripcion,
+        costo_mensual: f64,
+        medio_pago: TipoMedioPago<'c>,
+    ) -> Suscripcion<'c> {
+        let mut nueva = Suscripcion::new(
+            self.nombre_usuario,
+            tipo_suscripcion,
+            costo_mensual,
+            medio_pago,
+            desde,
+        );
+        nueva.inicio_periodos = self.inicio_periodo(periodo);
+        nueva.duracion_en_meses = self.duracion_en_meses - periodo;
+        nueva.renovacion_automatica = self.renovacion_automatica;
+        nueva.cambio_desde = Some(self.tipo_suscripcion);
+        self.cancelar();
+        self.fecha_baja = Some(nueva.fecha_inicio);
+        nueva
+    }
+
+    // Inicio del periodo mensual numero `periodo` (el primero es el 0)
+    pub fn inicio_periodo(&self, periodo: u8) -> Fecha {
+        self.inicio_periodos.sumar_meses(periodo as u32)
+    }
+
+    // Periodo en curso en `fecha`; la fecha tiene que estar dentro del plazo
+    fn periodo_en(&self, fecha: Fecha) -> u8 {
+        (0..self.duracion_en_meses)
+            .rev()
+            .find(|&p| self.inicio_periodo(p) <= fecha)
+            .unwrap_or(0)
+    }
+
+    pub fn fecha_fin(&self) -> Fecha {
+        self.inicio_periodo(self.duracion_en_meses)
+    }
+
+    pub fn esta_vigente(&self, hoy: Fecha) -> bool {
+        self.activa && self.fecha_inicio <= hoy && hoy < self.fecha_fin()
     }
 
-    pub fn inferior(&mut self) -> &TipoSuscripcion {
-        self.tipo_suscripcion.inferior()
+    // Vigencia historica: tiene en cuenta bajas, cambios de plan y vencimientos
+    pub fn vigente_en(&self, fecha: Fecha) -> bool {
+        let fin = self.fecha_baja.unwrap_or(self.fecha_fin());
+        self.fecha_inicio <= fecha && fecha < fin
     }
 
     pub fn cancelar(&mut self) {
@@ -307,6 +885,51 @@ impl<'c> Suscripcion<'c> {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct LineaFactura {
+    tipo: TipoLinea,
+    concepto: String,
+    monto: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum TipoLinea {
+    Suscripcion,
+    Cambio,
+    Perfil,
+    Promocion,
+    Credito,
+}
+
+#[allow(dead_code)]
+impl LineaFactura {
+    pub fn es_promocion(&self) -> bool {
+        self.tipo == TipoLinea::Promocion
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct Factura<'f> {
+    numero: u32,
+    nombre_usuario: &'f str,
+    tipo_suscripcion: TipoSuscripcion,
+    desde: Fecha,
+    hasta: Fecha,
+    lineas: Vec<LineaFactura>,
+    medio_pago: TipoMedioPago<'f>,
+    pagada: bool,
+    intentos_fallidos: u8,
+}
+#[allow(dead_code)]
+impl<'f> Factura<'f> {
+    pub fn total(&self) -> f64 {
+        self.lineas.iter().map(|l| l.monto).sum()
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 #[allow(dead_code)]
 pub struct PagoElectronico<'b> {
@@ -330,9 +953,20 @@ impl<'c> Usuario<'c> {
 }
 
 #[allow(dead_code)]
+#[derive(Default)]
 pub struct StreamingRust<'d> {
     usuarios: Vec<Usuario<'d>>,
     suscripciones: HashMap<&'d str, Vec<Suscripcion<'d>>>,
+    facturas: Vec<Factura<'d>>,
+    creditos: HashMap<&'d str, f64>,
+    cambios_programados: HashMap<&'d str, (TipoSuscripcion, TipoMedioPago<'d>, Fecha)>,
+    catalogo: CatalogoPlanes,
+    cuentas: HashMap<&'d str, CuentaFamiliar<'d>>,
+    sesiones: Vec<Sesion<'d>>,
+    proxima_sesion: u32,
+    promociones: HashMap<String, CodigoPromocional>,
+    canjes: Vec<(String, &'d str, Fecha)>,
+    descuentos_activos: HashMap<&'d str, DescuentoActivo>,
 }
 
 #[allow(dead_code)]
@@ -343,19 +977,51 @@ impl<'d> StreamingRust<'d> {
         nombre_usuario: &'d str,
         tipo_suscripcion: TipoSuscripcion,
         medio_pago: TipoMedioPago<'d>,
+    ) -> Result<&str, ErroresApp> {
+        self.crear_usuario_con_fecha(
+            id,
+            nombre_usuario,
+            tipo_suscripcion,
+            medio_pago,
+            Fecha::hoy(),
+        )
+    }
+
+    pub fn crear_usuario_con_fecha(
+        &mut self,
+        id: u128,
+        nombre_usuario: &'d str,
+        tipo_suscripcion: TipoSuscripcion,
+        medio_pago: TipoMedioPago<'d>,
+        fecha_inicio: Fecha,
     ) -> Result<&str, ErroresApp> {
         if self.suscripciones.contains_key(nombre_usuario) {
             return Err(ErroresApp::UsuarioExistente);
         }
 
-        let hoy = Fecha::hoy();
+        let costo_mensual = self
+            .catalogo
+            .precio_vigente(tipo_suscripcion, fecha_inicio)?;
         let nuevo_usuario: Usuario = Usuario::new(id, nombre_usuario);
-        let nueva_suscripcion: Suscripcion =
-            Suscripcion::new(nombre_usuario, tipo_suscripcion, medio_pago, hoy);
+        let nueva_suscripcion: Suscripcion = Suscripcion::new(
+            nombre_usuario,
+            tipo_suscripcion,
+            costo_mensual,
+            medio_pago,
+            fecha_inicio,
+        );
 
-        self.usuarios.push(nuevo_usuario);
+        // Puede estar registrado de antes, por ejemplo como miembro de otra cuenta
+        if !self
+            .usuarios
+            .iter()
+            .any(|u| u.nombre_usuario == nombre_usuario)
+        {
+            self.usuarios.push(nuevo_usuario);
+        }
         self.suscripciones
             .insert(nombre_usuario, vec![nueva_suscripcion]);
+        self.salir_de_cuentas(nombre_usuario);
 
         return Ok(nuevo_usuario.nombre_usuario);
     }
@@ -365,38 +1031,28 @@ impl<'d> StreamingRust<'d> {
         nombre_usuario: &'d str,
         medio_pago: TipoMedioPago<'d>,
     ) -> Result<TipoSuscripcion, ErroresApp> {
-        //Obtengo vector de las suscripciones del usuario o elevo error
-        let suscripciones = self
-            .suscripciones
-            .get_mut(nombre_usuario)
-            .ok_or(ErroresApp::UsuarioSinSuscripciones)?;
-
-        //Obtengo suscripcion activa
-        let suscripcion_actual = suscripciones.iter_mut().find(|x| x.activa);
-
-        match suscripcion_actual {
-            Some(suscripcion_actual) => {
-                suscripcion_actual.cancelar(); // Cancelo la suscripcion actual
-
-                if suscripcion_actual.tipo_suscripcion == TipoSuscripcion::SUPER {
-                    return Err(ErroresApp::MejorSuscripcionDisponible);
-                } //Si el usuario tiene la mayor suscripcion posible elevo error
-
-                let siguiente = suscripcion_actual.tipo_suscripcion.superior(); //Obtengo la siguiente suscripcion
-                let hoy = Fecha::hoy();
-                let nueva_suscripcion =
-                    Suscripcion::new(nombre_usuario, siguiente.clone(), medio_pago, hoy); // Instancio una nueva suscripcion activa con los datos locales
-                suscripciones.push(nueva_suscripcion); // Pusheo el vector con la nueva suscripcion
-                Ok(nueva_suscripcion.tipo_suscripcion.clone())
-            }
-            None => Err(ErroresApp::UsuarioSinSuscripcionActiva),
-        }
+        self.subir_suscripcion_con_fecha(nombre_usuario, medio_pago, Fecha::hoy())
     }
 
     pub fn bajar_suscripcion_a_usuario(
         &mut self,
         nombre_usuario: &'d str,
         medio_pago: TipoMedioPago<'d>,
+    ) -> Result<TipoSuscripcion, ErroresApp> {
+        self.bajar_suscripcion_con_fecha(nombre_usuario, medio_pago, Fecha::hoy(), false)
+    }
+
+    pub fn cancelar_suscripcion_a_usuario(
+        &mut self,
+        nombre_usuario: &'d str,
+    ) -> Result<TipoSuscripcion, ErroresApp> {
+        self.cancelar_suscripcion_con_fecha(nombre_usuario, Fecha::hoy())
+    }
+
+    pub fn cancelar_suscripcion_con_fecha(
+        &mut self,
+        nombre_usuario: &'d str,
+        fecha: Fecha,
     ) -> Result<TipoSuscripcion, ErroresApp> {
         //Obtengo vector de las suscripciones del usuario o elevo error
         let suscripciones = self
@@ -404,50 +1060,85 @@ impl<'d> StreamingRust<'d> {
             .get_mut(nombre_usuario)
             .ok_or(ErroresApp::UsuarioSinSuscripciones)?;
 
-        //Obtengo suscripcion activa
-        let suscripcion_actual = suscripciones.iter_mut().find(|x| x.activa);
+        //Obtengo suscripcion vigente a la fecha de la baja
+        let suscripcion_actual = suscripciones.iter_mut().find(|x| x.esta_vigente(fecha));
 
         match suscripcion_actual {
             Some(suscripcion_actual) => {
                 suscripcion_actual.cancelar(); // Cancelo la suscripcion actual
-                if suscripcion_actual.tipo_suscripcion == TipoSuscripcion::BASICA {
-                    return Ok(TipoSuscripcion::BASICA);
-                } //Si el usuario tiene la mayor suscripcion posible elevo error
-
-                let siguiente = suscripcion_actual.tipo_suscripcion.inferior(); //Obtengo la suscripcion inferior
-                let hoy = Fecha::hoy();
-                let nueva_suscripcion =
-                    Suscripcion::new(nombre_usuario, siguiente.clone(), medio_pago, hoy); // Instancio una nueva suscripcion activa con los datos locales
-                suscripciones.push(nueva_suscripcion); // Pusheo el vector con la nueva suscripcion
-                Ok(nueva_suscripcion.tipo_suscripcion.clone())
+                suscripcion_actual.fecha_baja = Some(fecha);
+                let tipo = suscripcion_actual.tipo_suscripcion;
+                // Una baja programada muere con la suscripcion que la pidio
+                self.cambios_programados.remove(nombre_usuario);
+                Ok(tipo)
             }
             None => Err(ErroresApp::UsuarioSinSuscripcionActiva),
         }
     }
 
-    pub fn cancelar_suscripcion_a_usuario(
+    pub fn volver_a_suscribir(
         &mut self,
         nombre_usuario: &'d str,
+        tipo_suscripcion: TipoSuscripcion,
+        medio_pago: TipoMedioPago<'d>,
     ) -> Result<TipoSuscripcion, ErroresApp> {
-        //Obtengo vector de las suscripciones del usuario o elevo error
+        self.volver_a_suscribir_con_fecha(
+            nombre_usuario,
+            tipo_suscripcion,
+            medio_pago,
+            Fecha::hoy(),
+        )
+    }
+
+    // Nueva suscripcion para un usuario que se dio de baja o al que se le vencio el plazo
+    pub fn volver_a_suscribir_con_fecha(
+        &mut self,
+        nombre_usuario: &'d str,
+        tipo_suscripcion: TipoSuscripcion,
+        medio_pago: TipoMedioPago<'d>,
+        fecha_inicio: Fecha,
+    ) -> Result<TipoSuscripcion, ErroresApp> {
+        let costo_mensual = self
+            .catalogo
+            .precio_vigente(tipo_suscripcion, fecha_inicio)?;
         let suscripciones = self
             .suscripciones
             .get_mut(nombre_usuario)
             .ok_or(ErroresApp::UsuarioSinSuscripciones)?;
 
-        //Obtengo suscripcion activa
-        let suscripcion_actual = suscripciones.iter_mut().find(|x| x.activa);
-
-        match suscripcion_actual {
-            Some(suscripcion_actual) => {
-                suscripcion_actual.cancelar(); // Cancelo la suscripcion actual
-                Ok(suscripcion_actual.tipo_suscripcion.clone())
-            }
-            None => Err(ErroresApp::UsuarioSinSuscripcionActiva),
+        if suscripciones.iter().any(|s| s.esta_vigente(fecha_inicio)) {
+            return Err(ErroresApp::UsuarioConSuscripcionPropia);
         }
+        // No se puede reescribir el historial: la vuelta tiene que ser posterior a todo lo anterior
+        if suscripciones
+            .iter()
+            .any(|s| s.fecha_inicio > fecha_inicio || s.vigente_en(fecha_inicio))
+        {
+            return Err(ErroresApp::DatosInvalidos);
+        }
+
+        suscripciones.push(Suscripcion::new(
+            nombre_usuario,
+            tipo_suscripcion,
+            costo_mensual,
+            medio_pago,
+            fecha_inicio,
+        ));
+        self.cambios_programados.remove(nombre_usuario);
+        self.salir_de_cuentas(nombre_usuario);
+        Ok(tipo_suscripcion)
     }
 
     pub fn mayor_medio_pago(&self, solo_activas: bool) -> Result<String, ErroresApp> {
+        self.mayor_medio_pago_al(solo_activas, Fecha::hoy())
+    }
+
+    // Con `solo_activas` cuenta las suscripciones vigentes a `hoy`, aunque no se haya facturado
+    pub fn mayor_medio_pago_al(
+        &self,
+        solo_activas: bool,
+        hoy: Fecha,
+    ) -> Result<String, ErroresApp> {
         // Compruebo que existan suscripciones en la plataforma o propago error custom
         if self.suscripciones.is_empty() {
             return Err(ErroresApp::SinSuscripciones);
@@ -458,15 +1149,9 @@ impl<'d> StreamingRust<'d> {
         self.suscripciones
             .values()
             .flat_map(|sus_vec| sus_vec.iter())
-            .filter(|s| !solo_activas || s.activa)
+            .filter(|s| !solo_activas || s.esta_vigente(hoy))
             .for_each(|s| {
-                let clave = match s.medio_pago {
-                    TipoMedioPago::EFECTIVO(_) => "EFECTIVO".to_string(),
-                    TipoMedioPago::MERCADOPAGO { .. } => "MERCADOPAGO".to_string(),
-                    TipoMedioPago::CREDITO { .. } => "CREDITO".to_string(),
-                    TipoMedioPago::TRANSFERENCIA { .. } => "TRANSFERENCIA".to_string(),
-                    TipoMedioPago::CRIPTO { .. } => "CRIPTO".to_string(),
-                };
+                let clave = s.medio_pago.nombre().to_string();
                 *conteo.entry(clave).or_insert(0) += 1;
             });
 
@@ -481,6 +1166,14 @@ impl<'d> StreamingRust<'d> {
     }
 
     pub fn mayor_suscripcion(&self, solo_activas: bool) -> Result<String, ErroresApp> {
+        self.mayor_suscripcion_al(solo_activas, Fecha::hoy())
+    }
+
+    pub fn mayor_suscripcion_al(
+        &self,
+        solo_activas: bool,
+        hoy: Fecha,
+    ) -> Result<String, ErroresApp> {
         // Compruebo que existan suscripciones en la plataforma o propago error custom
         if self.suscripciones.is_empty() {
             return Err(ErroresApp::SinSuscripciones);
@@ -491,7 +1184,7 @@ impl<'d> StreamingRust<'d> {
         self.suscripciones
             .values()
             .flat_map(|sus_vec| sus_vec.iter())
-            .filter(|s| !solo_activas || s.activa)
+            .filter(|s| !solo_activas || s.esta_vigente(hoy))
             .for_each(|s| {
                 let clave = s.tipo_suscripcion.to_string();
                 *conteo.entry(clave).or_insert(0) += 1;
@@ -514,11 +1207,21 @@ pub struct Informe<'g> {
     nombre_usuario: &'g str,
     usuario: Usuario<'g>,
     historial: Vec<Suscripcion<'g>>,
+    facturas: Vec<Factura<'g>>,
+    fecha_corte: Fecha,
 }
 
 #[allow(dead_code)]
 impl<'d> StreamingRust<'d> {
     pub fn get_historial(&self, nombre_usuario: &'d str) -> Result<Informe<'d>, ErroresApp> {
+        self.get_historial_al(nombre_usuario, Fecha::hoy())
+    }
+
+    pub fn get_historial_al(
+        &self,
+        nombre_usuario: &'d str,
+        fecha_corte: Fecha,
+    ) -> Result<Informe<'d>, ErroresApp> {
         // Compruebo que el usuario exista o arrojo error de usuario inexistente que declaro en ErroresApp
         // Si el usuario existe, recupero sus datos que luego podria llegar a usar para el informe
         let usuario = self
@@ -552,621 +1255,3454 @@ impl<'d> StreamingRust<'d> {
             nombre_usuario,
             usuario,
             historial,
+            facturas: self
+                .facturas_de(nombre_usuario)
+                .into_iter()
+                .cloned()
+                .collect(),
+            fecha_corte,
         };
 
         // Retorno informe
         Ok(informe)
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::collections::HashMap;
-
-    fn instanciar_plataforma() -> StreamingRust<'static> {
-        StreamingRust {
-            usuarios: vec![],
-            suscripciones: HashMap::new(),
-        }
-    }
 
-    fn mockear_medio_pago() -> TipoMedioPago<'static> {
-        TipoMedioPago::EFECTIVO(999.0)
+    // Informes de varios usuarios a la vez; los que no existen quedan como error
+    pub fn get_historiales_al(
+        &self,
+        nombres: &[&'d str],
+        fecha_corte: Fecha,
+    ) -> Vec<Result<Informe<'d>, ErroresApp>> {
+        nombres
+            .iter()
+            .map(|nombre| self.get_historial_al(nombre, fecha_corte))
+            .collect()
     }
+}
 
-    #[test]
-    fn test_crear_usuario_valido() {
-        let mut sistema = instanciar_plataforma();
-        let resultado =
-            sistema.crear_usuario(1, "valido", TipoSuscripcion::CLASICA, mockear_medio_pago());
-        assert_eq!(resultado.unwrap(), "valido");
-    }
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[allow(dead_code)]
+pub struct FilaInforme {
+    tipo: TipoSuscripcion,
+    desde: Fecha,
+    hasta: Fecha,
+    dias: i64,
+    costo_mensual: f64,
+    medio_pago: String,
+    estado: String,
+}
 
-    #[test]
-    fn test_crear_usuario_duplicado() {
-        let mut sistema = instanciar_plataforma();
-        sistema
-            .crear_usuario(1, "repetido", TipoSuscripcion::BASICA, mockear_medio_pago())
-            .unwrap();
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[allow(dead_code)]
+pub struct Hueco {
+    desde: Fecha,
+    hasta: Fecha,
+    dias: i64,
+}
 
-        let resultado =
-            sistema.crear_usuario(2, "repetido", TipoSuscripcion::SUPER, mockear_medio_pago());
-        assert_eq!(resultado.unwrap_err(), ErroresApp::UsuarioExistente);
-    }
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[allow(dead_code)]
+pub struct ResumenInforme {
+    usuario: String,
+    id: u128,
+    fecha_corte: Fecha,
+    total_facturado: f64,
+    total_pagado: f64,
+    total_descuentos: f64,
+    dias_por_tipo: Vec<(TipoSuscripcion, i64)>,
+    huecos: Vec<Hueco>,
+    suscripciones: Vec<FilaInforme>,
+}
 
-    #[test]
-    fn test_subir_suscripcion_valido() {
-        let mut sistema = instanciar_plataforma();
-        let usuario = "simon";
-        sistema
-            .crear_usuario(1, usuario, TipoSuscripcion::BASICA, mockear_medio_pago())
-            .unwrap();
+const ENCABEZADOS_INFORME: [&str; 8] = [
+    "usuario",
+    "tipo",
+    "desde",
+    "hasta",
+    "dias",
+    "costo_mensual",
+    "medio_pago",
+    "estado",
+];
 
-        let resultado = sistema.subir_suscripcion_a_usuario(usuario, mockear_medio_pago());
-        assert_eq!(resultado.unwrap(), TipoSuscripcion::CLASICA);
+#[allow(dead_code)]
+impl<'g> Informe<'g> {
+    // Suscripciones en orden cronologico, recortadas a la fecha de corte
+    pub fn filas(&self) -> Vec<FilaInforme> {
+        let mut historial = self.historial.clone();
+        historial.sort_by_key(|s| s.fecha_inicio);
+        historial
+            .iter()
+            .filter(|s| s.fecha_inicio <= self.fecha_corte)
+            .map(|s| {
+                let fin = s.fecha_baja.unwrap_or(s.fecha_fin());
+                let hasta = if fin < self.fecha_corte {
+                    fin
+                } else {
+                    self.fecha_corte
+                };
+                let estado = if s.suspendida {
+                    "SUSPENDIDA"
+                } else if s.prueba {
+                    "PRUEBA"
+                } else if s.activa {
+                    "ACTIVA"
+                } else {
+                    "FINALIZADA"
+                };
+                FilaInforme {
+                    tipo: s.tipo_suscripcion,
+                    desde: s.fecha_inicio,
+                    hasta,
+                    dias: s.fecha_inicio.dias_hasta(hasta),
+                    costo_mensual: s.costo_mensual,
+                    medio_pago: s.medio_pago.nombre().to_string(),
+                    estado: estado.to_string(),
+                }
+            })
+            .collect()
     }
 
-    #[test]
-    fn test_subir_suscripcion_con_superior_maxima() {
-        let mut sistema = instanciar_plataforma();
-        let usuario = "simon";
-        sistema
-            .crear_usuario(1, usuario, TipoSuscripcion::SUPER, mockear_medio_pago())
-            .unwrap();
-
-        let resultado = sistema.subir_suscripcion_a_usuario(usuario, mockear_medio_pago());
-        assert_eq!(
-            resultado.unwrap_err(),
-            ErroresApp::MejorSuscripcionDisponible
-        );
+    pub fn total_facturado(&self) -> f64 {
+        self.facturas.iter().map(|f| f.total()).sum()
     }
 
-    #[test]
-    fn test_bajar_suscripcion_valido() {
-        let mut sistema = instanciar_plataforma();
-        let usuario = "simon";
-        sistema
-            .crear_usuario(1, usuario, TipoSuscripcion::SUPER, mockear_medio_pago())
-            .unwrap();
-
-        let resultado = sistema.bajar_suscripcion_a_usuario(usuario, mockear_medio_pago());
-        assert_eq!(resultado.unwrap(), TipoSuscripcion::CLASICA);
+    pub fn total_descuentos(&self) -> f64 {
+        self.facturas
+            .iter()
+            .flat_map(|f| f.lineas.iter())
+            .filter(|l| l.es_promocion())
+            .map(|l| -l.monto)
+            .sum()
     }
 
-    #[test]
-    fn test_bajar_suscripcion_minima() {
-        let mut sistema = instanciar_plataforma();
-        let usuario = "simon";
-        sistema
-            .crear_usuario(1, usuario, TipoSuscripcion::BASICA, mockear_medio_pago())
-            .unwrap();
-
-        let resultado = sistema.bajar_suscripcion_a_usuario(usuario, mockear_medio_pago());
-        assert_eq!(resultado.unwrap(), TipoSuscripcion::BASICA);
+    pub fn total_pagado(&self) -> f64 {
+        self.facturas
+            .iter()
+            .filter(|f| f.pagada)
+            .map(|f| f.total())
+            .sum()
     }
 
-    #[test]
-    fn test_error_usuario_no_registrado() {
-        let mut sistema = instanciar_plataforma();
-        let resultado = sistema.subir_suscripcion_a_usuario("fantasma", mockear_medio_pago());
-        assert_eq!(resultado.unwrap_err(), ErroresApp::UsuarioSinSuscripciones);
+    pub fn dias_por_tipo(&self) -> Vec<(TipoSuscripcion, i64)> {
+        let mut dias: Vec<(TipoSuscripcion, i64)> = vec![];
+        for fila in self.filas() {
+            match dias.iter_mut().find(|(tipo, _)| *tipo == fila.tipo) {
+                Some((_, total)) => *total += fila.dias,
+                None => dias.push((fila.tipo, fila.dias)),
+            }
+        }
+        dias
     }
 
-    #[test]
-    fn test_cancelar_suscripcion_activa() {
-        let mut sistema = instanciar_plataforma();
-        let usuario = "lara";
-        sistema
-            .crear_usuario(1, usuario, TipoSuscripcion::CLASICA, mockear_medio_pago())
-            .unwrap();
+    // Periodos sin ninguna suscripcion vigente entre el primer alta y la fecha de corte
+    pub fn huecos(&self) -> Vec<Hueco> {
+        let mut huecos = vec![];
+        let mut cubierto_hasta: Option<Fecha> = None;
+        for fila in self.filas() {
+            if let Some(fin) = cubierto_hasta
+                && fila.desde > fin
+            {
+                huecos.push(Hueco {
+                    desde: fin,
+                    hasta: fila.desde,
+                    dias: fin.dias_hasta(fila.desde),
+                });
+            }
+            if cubierto_hasta.is_none_or(|fin| fila.hasta > fin) {
+                cubierto_hasta = Some(fila.hasta);
+            }
+        }
+        if let Some(fin) = cubierto_hasta
+            && fin < self.fecha_corte
+        {
+            huecos.push(Hueco {
+                desde: fin,
+                hasta: self.fecha_corte,
+                dias: fin.dias_hasta(self.fecha_corte),
+            });
+        }
+        huecos
+    }
 
-        let resultado = sistema.cancelar_suscripcion_a_usuario(usuario);
-        assert_eq!(resultado.unwrap(), TipoSuscripcion::CLASICA);
-        let resultado_error = sistema.cancelar_suscripcion_a_usuario(usuario);
-        assert_eq!(
-            resultado_error.unwrap_err(),
-            ErroresApp::UsuarioSinSuscripcionActiva
-        );
+    pub fn resumen(&self) -> ResumenInforme {
+        ResumenInforme {
+            usuario: self.nombre_usuario.to_string(),
+            id: self.usuario.id,
+            fecha_corte: self.fecha_corte,
+            total_facturado: self.total_facturado(),
+            total_pagado: self.total_pagado(),
+            total_descuentos: self.total_descuentos(),
+            dias_por_tipo: self.dias_por_tipo(),
+            huecos: self.huecos(),
+            suscripciones: self.filas(),
+        }
     }
 
-    #[test]
-    fn test_cancelar_suscripcion_sin_activa() {
-        let mut sistema = instanciar_plataforma();
-        let usuario = "maxi";
-        sistema
-            .crear_usuario(1, usuario, TipoSuscripcion::CLASICA, mockear_medio_pago())
-            .unwrap();
-        sistema.cancelar_suscripcion_a_usuario(usuario).unwrap(); // la cancela
+    fn filas_csv(&self) -> Vec<Vec<String>> {
+        self.filas()
+            .into_iter()
+            .map(|f| {
+                vec![
+                    self.nombre_usuario.to_string(),
+                    f.tipo.to_string(),
+                    f.desde.to_string(),
+                    f.hasta.to_string(),
+                    f.dias.to_string(),
+                    format!("{:.2}", f.costo_mensual),
+                    f.medio_pago,
+                    f.estado,
+                ]
+            })
+            .collect()
+    }
 
-        let resultado = sistema.cancelar_suscripcion_a_usuario(usuario);
-        assert_eq!(
-            resultado.unwrap_err(),
-            ErroresApp::UsuarioSinSuscripcionActiva
+    pub fn a_tabla(&self) -> String {
+        let mut salida = format!(
+            "Usuario: {} (id {}) - corte al {}\n",
+            self.nombre_usuario, self.usuario.id, self.fecha_corte
         );
+        salida.push_str(&armar_tabla(&ENCABEZADOS_INFORME, &self.filas_csv()));
+        salida.push_str(&format!(
+            "Total facturado: {:.2} | Total pagado: {:.2} | Descuentos: {:.2}\n",
+            self.total_facturado(),
+            self.total_pagado(),
+            self.total_descuentos()
+        ));
+        for (tipo, dias) in self.dias_por_tipo() {
+            salida.push_str(&format!("Dias en {}: {}\n", tipo.to_string(), dias));
+        }
+        for hueco in self.huecos() {
+            salida.push_str(&format!(
+                "Sin suscripcion del {} al {} ({} dias)\n",
+                hueco.desde, hueco.hasta, hueco.dias
+            ));
+        }
+        salida
     }
 
-    #[test]
-    fn test_subir_suscripcion_sin_activa() {
-        let mut sistema = instanciar_plataforma();
-        let usuario = "natalia";
-        sistema
-            .crear_usuario(1, usuario, TipoSuscripcion::BASICA, mockear_medio_pago())
-            .unwrap();
-        sistema.cancelar_suscripcion_a_usuario(usuario).unwrap(); // se queda sin activa
+    pub fn a_csv(&self) -> String {
+        informes_a_csv(std::slice::from_ref(self))
+    }
 
-        let resultado = sistema.subir_suscripcion_a_usuario(usuario, mockear_medio_pago());
-        assert_eq!(
-            resultado.unwrap_err(),
-            ErroresApp::UsuarioSinSuscripcionActiva
-        );
+    pub fn a_json(&self) -> Result<String, ErroresApp> {
+        serde_json::to_string_pretty(&self.resumen()).map_err(|_| ErroresApp::DatosInvalidos)
     }
+}
 
-    #[test]
-    fn test_bajar_suscripcion_sin_activa() {
-        let mut sistema = instanciar_plataforma();
-        let usuario = "enzo";
-        sistema
-            .crear_usuario(1, usuario, TipoSuscripcion::CLASICA, mockear_medio_pago())
-            .unwrap();
-        sistema.cancelar_suscripcion_a_usuario(usuario).unwrap();
+#[allow(dead_code)]
+pub fn informes_a_tabla(informes: &[Informe]) -> String {
+    informes
+        .iter()
+        .map(|i| i.a_tabla())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
 
-        let resultado = sistema.bajar_suscripcion_a_usuario(usuario, mockear_medio_pago());
-        assert_eq!(
-            resultado.unwrap_err(),
-            ErroresApp::UsuarioSinSuscripcionActiva
-        );
+// Un solo encabezado y una fila por suscripcion de cada usuario
+pub fn informes_a_csv(informes: &[Informe]) -> String {
+    let mut salida = ENCABEZADOS_INFORME.join(",");
+    salida.push('\n');
+    for informe in informes {
+        for fila in informe.filas_csv() {
+            let campos: Vec<String> = fila.iter().map(|c| escapar_csv(c)).collect();
+            salida.push_str(&campos.join(","));
+            salida.push('\n');
+        }
     }
+    salida
+}
 
-    #[test]
-    fn test_solo_una_activa_por_usuario() {
-        let mut sistema = instanciar_plataforma();
-        let usuario = "ramiro";
-        sistema
-            .crear_usuario(1, usuario, TipoSuscripcion::BASICA, mockear_medio_pago())
-            .unwrap();
+#[allow(dead_code)]
+pub fn informes_a_json(informes: &[Informe]) -> Result<String, ErroresApp> {
+    let resumenes: Vec<ResumenInforme> = informes.iter().map(|i| i.resumen()).collect();
+    serde_json::to_string_pretty(&resumenes).map_err(|_| ErroresApp::DatosInvalidos)
+}
 
-        sistema
-            .subir_suscripcion_a_usuario(usuario, mockear_medio_pago())
-            .unwrap();
-        sistema
-            .subir_suscripcion_a_usuario(usuario, mockear_medio_pago())
-            .unwrap();
+fn escapar_csv(campo: &str) -> String {
+    if campo.contains([',', '"', '\n']) {
+        format!("\"{}\"", campo.replace('"', "\"\""))
+    } else {
+        campo.to_string()
+    }
+}
 
-        let activas = sistema
-            .suscripciones
-            .get(usuario)
-            .unwrap()
+fn armar_tabla(encabezados: &[&str], filas: &[Vec<String>]) -> String {
+    let mut anchos: Vec<usize> = encabezados.iter().map(|e| e.chars().count()).collect();
+    for fila in filas {
+        for (ancho, celda) in anchos.iter_mut().zip(fila) {
+            *ancho = (*ancho).max(celda.chars().count());
+        }
+    }
+    let separador = format!(
+        "+{}+\n",
+        anchos
             .iter()
-            .filter(|s| s.activa)
-            .count();
-
-        assert_eq!(activas, 1);
+            .map(|a| "-".repeat(a + 2))
+            .collect::<Vec<String>>()
+            .join("+")
+    );
+    let linea = |celdas: Vec<&str>| {
+        let contenido: Vec<String> = celdas
+            .iter()
+            .zip(&anchos)
+            .map(|(c, a)| format!(" {:<a$} ", c, a = *a))
+            .collect();
+        format!("|{}|\n", contenido.join("|"))
+    };
+
+    let mut salida = separador.clone();
+    salida.push_str(&linea(encabezados.to_vec()));
+    salida.push_str(&separador);
+    for fila in filas {
+        salida.push_str(&linea(fila.iter().map(|c| c.as_str()).collect()));
     }
+    salida.push_str(&separador);
+    salida
+}
 
-    #[test]
-    fn test_tipo_suscripcion_superior_inferior() {
-        assert_eq!(
-            TipoSuscripcion::BASICA.superior(),
-            &TipoSuscripcion::CLASICA
-        );
-        assert_eq!(TipoSuscripcion::CLASICA.superior(), &TipoSuscripcion::SUPER);
-        assert_eq!(TipoSuscripcion::SUPER.superior(), &TipoSuscripcion::SUPER);
-
-        assert_eq!(TipoSuscripcion::SUPER.inferior(), &TipoSuscripcion::CLASICA);
-        assert_eq!(
-            TipoSuscripcion::CLASICA.inferior(),
-            &TipoSuscripcion::BASICA
-        );
-        assert_eq!(TipoSuscripcion::BASICA.inferior(), &TipoSuscripcion::BASICA);
+#[allow(dead_code)]
+impl<'d> StreamingRust<'d> {
+    pub fn set_renovacion_automatica(
+        &mut self,
+        nombre_usuario: &'d str,
+        renovar: bool,
+    ) -> Result<(), ErroresApp> {
+        let suscripcion = self
+            .suscripciones
+            .get_mut(nombre_usuario)
+            .ok_or(ErroresApp::UsuarioSinSuscripciones)?
+            .iter_mut()
+            .find(|s| s.activa)
+            .ok_or(ErroresApp::UsuarioSinSuscripcionActiva)?;
+        suscripcion.renovacion_automatica = renovar;
+        Ok(())
     }
 
-    #[test]
-    fn test_tipo_suscripcion_to_string_y_costo() {
-        assert_eq!(TipoSuscripcion::BASICA.to_string(), "BASICA");
-        assert_eq!(TipoSuscripcion::CLASICA.to_string(), "CLASICA");
-        assert_eq!(TipoSuscripcion::SUPER.to_string(), "SUPER");
+    // Factura todos los periodos que empezaron hasta `hoy` y cierra las suscripciones
+    // cuyo plazo termino, renovandolas si corresponde. Devuelve las facturas nuevas.
+    pub fn avanzar_facturacion(&mut self, hoy: Fecha) -> Vec<Factura<'d>> {
+        let mut nombres: Vec<&'d str> = self.suscripciones.keys().copied().collect();
+        nombres.sort();
 
-        assert_eq!(TipoSuscripcion::BASICA.get_costo(), 100.0);
-        assert_eq!(TipoSuscripcion::CLASICA.get_costo(), 200.0);
-        assert_eq!(TipoSuscripcion::SUPER.get_costo(), 300.0);
+        let mut nuevas = vec![];
+        for nombre in nombres {
+            nuevas.extend(self.facturar_usuario(nombre, hoy));
+        }
+        self.ajustar_cuentas(hoy);
+        nuevas
     }
 
-    #[test]
-    fn test_errores_app_display() {
-        assert_eq!(
-            ErroresApp::DatosInvalidos.to_string(),
-            "No fue posible validar sus datos"
-        );
-        assert_eq!(
+    fn facturar_usuario(&mut self, nombre: &'d str, hoy: Fecha) -> Vec<Factura<'d>> {
+        let Some(suscripciones) = self.suscripciones.get_mut(nombre) else {
+            return vec![];
+        };
+        let inicio = self.facturas.len();
+        let mut facturar_periodo = |actual: &mut Suscripcion<'d>| {
+            let periodo = actual.periodos_facturados;
+            let concepto = if actual.prueba {
+                "Prueba gratis"
+            } else {
+                "Suscripcion"
+            };
+            let mut lineas = vec![LineaFactura {
+                tipo: TipoLinea::Suscripcion,
+                concepto: format!(
+                    "{} {} ({}/{})",
+                    concepto,
+                    actual.to_string(),
+                    periodo + 1,
+                    actual.duracion_en_meses
+                ),
+                monto: actual.costo_mensual,
+            }];
+            if let Some(anterior) = actual.cambio_desde.take() {
+                lineas.push(LineaFactura {
+                    tipo: TipoLinea::Cambio,
+                    concepto: format!(
+                        "Cambio programado {} -> {}",
+                        anterior.to_string(),
+                        actual.to_string()
+                    ),
+                    monto: 0.0,
+                });
+            }
+            // La factura la paga el titular, con un detalle por cada perfil de la cuenta
+            if let Some(cuenta) = self.cuentas.get(nombre) {
+                for miembro in &cuenta.miembros {
+                    lineas.push(LineaFactura {
+                        tipo: TipoLinea::Perfil,
+                        concepto: format!("Perfil {}", miembro),
+                        monto: 0.0,
+                    });
+                }
+            }
+            if !actual.prueba
+                && let Some(descuento) = self.descuentos_activos.get_mut(nombre)
+            {
+                lineas.push(LineaFactura {
+                    tipo: TipoLinea::Promocion,
+                    concepto: format!("Promocion {}", descuento.codigo),
+                    monto: -descuento.tipo.calcular(actual.costo_mensual),
+                });
+                descuento.meses_restantes = descuento.meses_restantes.saturating_sub(1);
+                if descuento.meses_restantes == 0 {
+                    self.descuentos_activos.remove(nombre);
+                }
+            }
+            aplicar_credito(&mut self.creditos, nombre, &mut lineas);
+            self.facturas.push(Factura {
+                numero: self.facturas.len() as u32 + 1,
+                nombre_usuario: nombre,
+                tipo_suscripcion: actual.tipo_suscripcion,
+                desde: actual.inicio_periodo(periodo),
+                hasta: actual.inicio_periodo(periodo + 1),
+                lineas,
+                medio_pago: actual.medio_pago,
+                pagada: false,
+                intentos_fallidos: 0,
+            });
+            actual.periodos_facturados += 1;
+        };
+
+        // Una suscripcion cerrada a mitad de plazo todavia debe los periodos que
+        // empezaron antes de la baja y no se llegaron a facturar
+        for cerrada in suscripciones.iter_mut().filter(|s| !s.activa) {
+            while let Some(baja) = cerrada.fecha_baja
+                && cerrada.periodos_facturados < cerrada.duracion_en_meses
+                && cerrada.inicio_periodo(cerrada.periodos_facturados) <= hoy
+                && cerrada.inicio_periodo(cerrada.periodos_facturados) < baja
+            {
+                facturar_periodo(cerrada);
+            }
+        }
+
+        while let Some(actual) = suscripciones.iter_mut().find(|s| s.activa) {
+            let mut cambio = None;
+            while actual.periodos_facturados < actual.duracion_en_meses
+                && actual.inicio_periodo(actual.periodos_facturados) <= hoy
+            {
+                let periodo = actual.periodos_facturados;
+                // Un cambio programado entra en vigencia al empezar el periodo siguiente
+                // al que se pidio
+                let inicio_periodo = actual.inicio_periodo(periodo);
+                if periodo > 0
+                    && let Some(&(tipo, medio_pago, desde)) = self.cambios_programados.get(nombre)
+                    && inicio_periodo >= desde
+                {
+                    self.cambios_programados.remove(nombre);
+                    // Si el plan se quedo sin precio vigente el cambio se descarta
+                    if let Ok(costo) = self.catalogo.precio_vigente(tipo, inicio_periodo) {
+                        cambio =
+                            Some(actual.dividir(periodo, inicio_periodo, tipo, costo, medio_pago));
+                        break;
+                    }
+                }
+                facturar_periodo(actual);
+            }
+
+            if let Some(nueva) = cambio {
+                suscripciones.push(nueva);
+                continue;
+            }
+            if actual.fecha_fin() > hoy {
+                break;
+            }
+            // Termino el plazo: vence, y si renueva arranca una nueva el mismo dia
+            // manteniendo el precio con el que se suscribio
+            actual.activa = false;
+            actual.fecha_baja = Some(actual.fecha_fin());
+            let programado = self.cambios_programados.remove(nombre);
+            if !actual.renovacion_automatica {
+                break;
+            }
+            // Una baja pedida en el ultimo periodo entra en vigencia con la renovacion
+            if let Some((tipo, medio_pago, desde)) = programado
+                && desde <= actual.fecha_fin()
+                && let Ok(costo) = self.catalogo.precio_vigente(tipo, actual.fecha_fin())
+            {
+                let mut renovada =
+                    Suscripcion::new(nombre, tipo, costo, medio_pago, actual.fecha_fin());
+                renovada.cambio_desde = Some(actual.tipo_suscripcion);
+                suscripciones.push(renovada);
+                continue;
+            }
+            // Una prueba que convierte pasa a pagar el precio vigente del plan; si el plan
+            // ya no tiene precio, la prueba vence sin convertirse
+            let costo = if actual.prueba {
+                match self
+                    .catalogo
+                    .precio_vigente(actual.tipo_suscripcion, actual.fecha_fin())
+                {
+                    Ok(costo) => costo,
+                    Err(_) => break,
+                }
+            } else {
+                actual.costo_mensual
+            };
+            let renovada = Suscripcion::new(
+                nombre,
+                actual.tipo_suscripcion,
+                costo,
+                actual.medio_pago,
+                actual.fecha_fin(),
+            );
+            suscripciones.push(renovada);
+        }
+        if !suscripciones.iter().any(|s| s.activa) {
+            self.liberar_descuento(nombre);
+        }
+        self.facturas[inicio..].to_vec()
+    }
+
+    // Sin suscripcion activa el descuento pendiente se pierde; si no se llego a aplicar
+    // en ninguna factura, el canje no cuenta y el codigo recupera el uso
+    fn liberar_descuento(&mut self, nombre_usuario: &'d str) {
+        let Some(descuento) = self.descuentos_activos.remove(nombre_usuario) else {
+            return;
+        };
+        let Some(promocion) = self.promociones.get_mut(&descuento.codigo) else {
+            return;
+        };
+        if descuento.meses_restantes == promocion.meses {
+            promocion.usos = promocion.usos.saturating_sub(1);
+            self.canjes
+                .retain(|(c, u, _)| *c != descuento.codigo || *u != nombre_usuario);
+        }
+    }
+
+    pub fn subir_suscripcion_con_fecha(
+        &mut self,
+        nombre_usuario: &'d str,
+        medio_pago: TipoMedioPago<'d>,
+        fecha: Fecha,
+    ) -> Result<TipoSuscripcion, ErroresApp> {
+        let actual = self.suscripcion_activa_al(nombre_usuario, fecha)?;
+        let siguiente = self
+            .catalogo
+            .superior(actual.tipo_suscripcion)
+            .ok_or(ErroresApp::MejorSuscripcionDisponible)?;
+        self.cambiar_con_prorrateo(nombre_usuario, siguiente, medio_pago, fecha)
+    }
+
+    // Con `al_proximo_periodo` la baja se aplica cuando empieza el periodo siguiente,
+    // sin credito por lo que resta del actual
+    pub fn bajar_suscripcion_con_fecha(
+        &mut self,
+        nombre_usuario: &'d str,
+        medio_pago: TipoMedioPago<'d>,
+        fecha: Fecha,
+        al_proximo_periodo: bool,
+    ) -> Result<TipoSuscripcion, ErroresApp> {
+        let actual = self.suscripcion_activa_al(nombre_usuario, fecha)?;
+        let Some(siguiente) = self.catalogo.inferior(actual.tipo_suscripcion) else {
+            return Ok(actual.tipo_suscripcion);
+        };
+        if al_proximo_periodo {
+            let desde = actual.inicio_periodo(actual.periodo_en(fecha) + 1);
+            self.catalogo.precio_vigente(siguiente, desde)?;
+            self.cambios_programados
+                .insert(nombre_usuario, (siguiente, medio_pago, desde));
+            return Ok(siguiente);
+        }
+        let tipo = self.cambiar_con_prorrateo(nombre_usuario, siguiente, medio_pago, fecha)?;
+        self.ajustar_cuentas(fecha);
+        Ok(tipo)
+    }
+
+    fn suscripcion_activa_al(
+        &self,
+        nombre_usuario: &'d str,
+        fecha: Fecha,
+    ) -> Result<Suscripcion<'d>, ErroresApp> {
+        self.suscripciones
+            .get(nombre_usuario)
+            .ok_or(ErroresApp::UsuarioSinSuscripciones)?
+            .iter()
+            .find(|s| s.esta_vigente(fecha))
+            .copied()
+            .ok_or(ErroresApp::UsuarioSinSuscripcionActiva)
+    }
+
+    // Cobra (o acredita) la diferencia de precio por los dias que le quedan al periodo en curso.
+    // No factura nada mas: los periodos pendientes del plan anterior se facturan al avanzar
+    fn cambiar_con_prorrateo(
+        &mut self,
+        nombre_usuario: &'d str,
+        nuevo_tipo: TipoSuscripcion,
+        medio_pago: TipoMedioPago<'d>,
+        fecha: Fecha,
+    ) -> Result<TipoSuscripcion, ErroresApp> {
+        let nuevo_costo = self.catalogo.precio_vigente(nuevo_tipo, fecha)?;
+        let suscripciones = self.suscripciones.get_mut(nombre_usuario).unwrap();
+        let actual = suscripciones
+            .iter_mut()
+            .find(|s| s.esta_vigente(fecha))
+            .ok_or(ErroresApp::UsuarioSinSuscripcionActiva)?;
+        let periodo = actual.periodo_en(fecha);
+        // No se puede cambiar el plan de periodos que ya se facturaron
+        if actual.periodos_facturados > periodo + 1 {
+            return Err(ErroresApp::DatosInvalidos);
+        }
+        let desde = actual.inicio_periodo(periodo);
+        let hasta = actual.inicio_periodo(periodo + 1);
+        let dias_periodo = desde.dias_hasta(hasta);
+        let dias_restantes = fecha.dias_hasta(hasta);
+        let diferencia =
+            (nuevo_costo - actual.costo_mensual) * dias_restantes as f64 / dias_periodo as f64;
+        // El periodo en curso lo paga el plan anterior, salvo que el cambio sea justo al
+        // empezar un periodo todavia sin facturar: ese lo factura directamente el nuevo
+        let cubierto = actual.periodos_facturados > periodo || desde < fecha;
+
+        let mut nueva = actual.dividir(periodo, fecha, nuevo_tipo, nuevo_costo, medio_pago);
+        nueva.cambio_desde = None;
+        nueva.periodos_facturados = if cubierto { 1 } else { 0 };
+        let anterior = actual.tipo_suscripcion;
+        suscripciones.push(nueva);
+        self.cambios_programados.remove(nombre_usuario);
+        if !cubierto {
+            return Ok(nuevo_tipo);
+        }
+
+        let mut lineas = vec![LineaFactura {
+            tipo: TipoLinea::Cambio,
+            concepto: format!(
+                "Cambio {} -> {} ({}/{} dias)",
+                anterior.to_string(),
+                nuevo_tipo.to_string(),
+                dias_restantes,
+                dias_periodo
+            ),
+            monto: diferencia,
+        }];
+        if diferencia < 0.0 {
+            *self.creditos.entry(nombre_usuario).or_insert(0.0) -= diferencia;
+        } else {
+            aplicar_credito(&mut self.creditos, nombre_usuario, &mut lineas);
+        }
+        self.facturas.push(Factura {
+            numero: self.facturas.len() as u32 + 1,
+            nombre_usuario,
+            tipo_suscripcion: nuevo_tipo,
+            desde: fecha,
+            hasta,
+            lineas,
+            medio_pago,
+            pagada: false,
+            intentos_fallidos: 0,
+        });
+        Ok(nuevo_tipo)
+    }
+
+    // Intenta cobrar cada factura impaga con su medio de pago. Al llegar al maximo
+    // de intentos rechazados se suspende la suscripcion activa del usuario.
+    pub fn cobrar_facturas_pendientes(
+        &mut self,
+        pasarela: &mut dyn Pasarela,
+    ) -> Vec<(u32, Result<(), ErroresApp>)> {
+        let mut resultados = vec![];
+        let mut a_suspender = vec![];
+        for factura in self.facturas.iter_mut().filter(|f| !f.pagada) {
+            let total = factura.total();
+            // Las notas de credito y las facturas cubiertas con credito no se cobran
+            if total <= 0.0 {
+                factura.pagada = true;
+                continue;
+            }
+            let resultado = factura.medio_pago.procesador().cobrar(total, pasarela);
+            match resultado {
+                Ok(()) => factura.pagada = true,
+                Err(_) => {
+                    factura.intentos_fallidos += 1;
+                    if factura.intentos_fallidos >= MAX_INTENTOS_COBRO {
+                        a_suspender.push(factura.nombre_usuario);
+                    }
+                }
+            }
+            resultados.push((factura.numero, resultado));
+        }
+
+        for nombre in a_suspender {
+            if let Some(activa) = self.suscripcion_actual_mut(nombre).filter(|s| s.activa) {
+                activa.cancelar();
+                activa.suspendida = true;
+                // Deja de estar vigente cuando termina el ultimo periodo facturado
+                activa.fecha_baja = Some(activa.inicio_periodo(activa.periodos_facturados));
+                self.cambios_programados.remove(nombre);
+            }
+        }
+        resultados
+    }
+
+    // La suscripcion en curso es siempre la ultima del historial: los cambios de plan,
+    // las renovaciones y las altas nuevas se agregan al final
+    fn suscripcion_actual(&self, nombre_usuario: &str) -> Option<&Suscripcion<'d>> {
+        self.suscripciones.get(nombre_usuario)?.last()
+    }
+
+    fn suscripcion_actual_mut(&mut self, nombre_usuario: &str) -> Option<&mut Suscripcion<'d>> {
+        self.suscripciones.get_mut(nombre_usuario)?.last_mut()
+    }
+
+    pub fn esta_suspendido(&self, nombre_usuario: &str) -> bool {
+        self.suscripcion_actual(nombre_usuario)
+            .is_some_and(|s| s.suspendida)
+    }
+
+    // Con la deuda saldada se reactiva la suscripcion; los meses suspendidos no se facturan
+    pub fn reactivar_usuario(
+        &mut self,
+        nombre_usuario: &'d str,
+        hoy: Fecha,
+    ) -> Result<(), ErroresApp> {
+        if !self.esta_suspendido(nombre_usuario) {
+            return Err(ErroresApp::UsuarioNoSuspendido);
+        }
+        let con_deuda = self
+            .facturas
+            .iter()
+            .any(|f| f.nombre_usuario == nombre_usuario && !f.pagada && f.total() > 0.0);
+        if con_deuda {
+            return Err(ErroresApp::UsuarioConDeuda);
+        }
+
+        let suscripcion = self.suscripcion_actual_mut(nombre_usuario).unwrap();
+        suscripcion.activar();
+        suscripcion.suspendida = false;
+        suscripcion.fecha_baja = None;
+        while suscripcion.periodos_facturados < suscripcion.duracion_en_meses
+            && suscripcion.inicio_periodo(suscripcion.periodos_facturados + 1) <= hoy
+        {
+            suscripcion.periodos_facturados += 1;
+        }
+        Ok(())
+    }
+
+    pub fn cargar_catalogo(&mut self, catalogo: CatalogoPlanes) {
+        self.catalogo = catalogo;
+    }
+
+    pub fn credito_de(&self, nombre_usuario: &str) -> f64 {
+        self.creditos.get(nombre_usuario).copied().unwrap_or(0.0)
+    }
+
+    pub fn facturas_de(&self, nombre_usuario: &str) -> Vec<&Factura<'d>> {
+        self.facturas
+            .iter()
+            .filter(|f| f.nombre_usuario == nombre_usuario)
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[allow(dead_code)]
+pub struct CuentaFamiliar<'d> {
+    titular: &'d str,
+    miembros: Vec<&'d str>,
+    invitaciones: Vec<&'d str>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct Sesion<'d> {
+    id: u32,
+    cuenta: &'d str,
+    perfil: &'d str,
+    dispositivo: String,
+    vence: Fecha,
+}
+
+const DURACION_SESION_DIAS: u32 = 30;
+
+#[allow(dead_code)]
+impl<'d> StreamingRust<'d> {
+    pub fn registrar_usuario(
+        &mut self,
+        id: u128,
+        nombre_usuario: &'d str,
+    ) -> Result<(), ErroresApp> {
+        if self
+            .usuarios
+            .iter()
+            .any(|u| u.nombre_usuario == nombre_usuario)
+        {
+            return Err(ErroresApp::UsuarioExistente);
+        }
+        self.usuarios.push(Usuario::new(id, nombre_usuario));
+        Ok(())
+    }
+
+    fn plan_activo(&self, titular: &str) -> Result<(&Suscripcion<'d>, &Plan), ErroresApp> {
+        let activa = self
+            .suscripciones
+            .get(titular)
+            .and_then(|v| v.iter().find(|s| s.activa))
+            .ok_or(ErroresApp::UsuarioSinSuscripcionActiva)?;
+        let plan = self
+            .catalogo
+            .plan(activa.tipo_suscripcion)
+            .ok_or(ErroresApp::PlanInexistente)?;
+        Ok((activa, plan))
+    }
+
+    // Titular de la cuenta a la que pertenece el perfil (un titular es su propia cuenta)
+    pub fn cuenta_de(&self, perfil: &str) -> Option<&'d str> {
+        if let Some((titular, _)) = self.cuentas.get_key_value(perfil) {
+            return Some(*titular);
+        }
+        self.cuentas
+            .values()
+            .find(|c| c.miembros.contains(&perfil))
+            .map(|c| c.titular)
+            .or_else(|| self.suscripciones.get_key_value(perfil).map(|(t, _)| *t))
+    }
+
+    // Quien pasa a tener suscripcion propia deja la cuenta familiar en la que estaba
+    fn salir_de_cuentas(&mut self, nombre_usuario: &'d str) {
+        for cuenta in self.cuentas.values_mut() {
+            cuenta.miembros.retain(|m| *m != nombre_usuario);
+            cuenta.invitaciones.retain(|i| *i != nombre_usuario);
+        }
+        self.sesiones
+            .retain(|s| s.perfil != nombre_usuario || s.cuenta == nombre_usuario);
+    }
+
+    // Cierra las sesiones vencidas o de cuentas sin plan vigente y, si el plan bajo,
+    // saca los ultimos perfiles y sesiones que ya no entran en sus limites
+    pub fn ajustar_cuentas(&mut self, hoy: Fecha) {
+        self.sesiones.retain(|s| s.vence > hoy);
+        let mut titulares: Vec<&'d str> = self.cuentas.keys().copied().collect();
+        titulares.extend(self.sesiones.iter().map(|s| s.cuenta));
+        titulares.sort();
+        titulares.dedup();
+
+        for titular in titulares {
+            let plan = self
+                .suscripciones
+                .get(titular)
+                .and_then(|v| v.iter().find(|s| s.esta_vigente(hoy)))
+                .and_then(|s| self.catalogo.plan(s.tipo_suscripcion));
+            let Some(plan) = plan else {
+                self.sesiones.retain(|s| s.cuenta != titular);
+                continue;
+            };
+            let (perfiles, pantallas) = (plan.perfiles.max(1) as usize, plan.pantallas as usize);
+
+            if let Some(cuenta) = self.cuentas.get_mut(titular) {
+                let sobrantes = cuenta
+                    .miembros
+                    .split_off(cuenta.miembros.len().min(perfiles - 1));
+                let lugar = perfiles - 1 - cuenta.miembros.len();
+                cuenta.invitaciones.truncate(lugar);
+                self.sesiones.retain(|s| !sobrantes.contains(&s.perfil));
+            }
+            let mut en_uso = 0;
+            self.sesiones.retain(|s| {
+                if s.cuenta != titular {
+                    return true;
+                }
+                en_uso += 1;
+                en_uso <= pantallas
+            });
+        }
+    }
+
+    pub fn perfiles_de(&self, titular: &'d str) -> Vec<&'d str> {
+        let mut perfiles = vec![titular];
+        if let Some(cuenta) = self.cuentas.get(titular) {
+            perfiles.extend(cuenta.miembros.iter().copied());
+        }
+        perfiles
+    }
+
+    pub fn invitar_miembro(
+        &mut self,
+        titular: &'d str,
+        invitado: &'d str,
+    ) -> Result<(), ErroresApp> {
+        let (_, plan) = self.plan_activo(titular)?;
+        let limite = plan.perfiles.max(1) as usize;
+        if !self.usuarios.iter().any(|u| u.nombre_usuario == invitado) {
+            return Err(ErroresApp::UsuarioNoExiste);
+        }
+        if invitado == titular
+            || self
+                .cuentas
+                .values()
+                .any(|c| c.miembros.contains(&invitado))
+        {
+            return Err(ErroresApp::UsuarioYaEsMiembro);
+        }
+        if self.plan_activo(invitado).is_ok() {
+            return Err(ErroresApp::UsuarioConSuscripcionPropia);
+        }
+
+        let cuenta = self.cuentas.entry(titular).or_insert(CuentaFamiliar {
+            titular,
+            ..Default::default()
+        });
+        if cuenta.invitaciones.contains(&invitado) {
+            return Ok(());
+        }
+        // Las invitaciones pendientes reservan lugar para no pasarse del limite al aceptarlas
+        if 1 + cuenta.miembros.len() + cuenta.invitaciones.len() >= limite {
+            return Err(ErroresApp::LimiteDePerfilesAlcanzado);
+        }
+        cuenta.invitaciones.push(invitado);
+        Ok(())
+    }
+
+    pub fn aceptar_invitacion(
+        &mut self,
+        invitado: &'d str,
+        titular: &'d str,
+    ) -> Result<(), ErroresApp> {
+        if self.plan_activo(invitado).is_ok() {
+            return Err(ErroresApp::UsuarioConSuscripcionPropia);
+        }
+        let cuenta = self
+            .cuentas
+            .get_mut(titular)
+            .ok_or(ErroresApp::InvitacionInexistente)?;
+        let indice = cuenta
+            .invitaciones
+            .iter()
+            .position(|i| *i == invitado)
+            .ok_or(ErroresApp::InvitacionInexistente)?;
+        cuenta.invitaciones.remove(indice);
+        cuenta.miembros.push(invitado);
+        // Una persona pertenece a una sola cuenta
+        for otra in self.cuentas.values_mut() {
+            otra.invitaciones.retain(|i| *i != invitado);
+        }
+        Ok(())
+    }
+
+    pub fn quitar_miembro(&mut self, titular: &'d str, miembro: &'d str) -> Result<(), ErroresApp> {
+        let cuenta = self
+            .cuentas
+            .get_mut(titular)
+            .ok_or(ErroresApp::MiembroInexistente)?;
+        let indice = cuenta
+            .miembros
+            .iter()
+            .position(|m| *m == miembro)
+            .ok_or(ErroresApp::MiembroInexistente)?;
+        cuenta.miembros.remove(indice);
+        self.sesiones.retain(|s| s.perfil != miembro);
+        Ok(())
+    }
+
+    pub fn iniciar_sesion(
+        &mut self,
+        perfil: &'d str,
+        dispositivo: &str,
+        fecha: Fecha,
+    ) -> Result<u32, ErroresApp> {
+        let cuenta = self.cuenta_de(perfil).ok_or(ErroresApp::UsuarioNoExiste)?;
+        self.ajustar_cuentas(fecha);
+        let (activa, plan) = self.plan_activo(cuenta)?;
+        if !activa.esta_vigente(fecha) {
+            return Err(ErroresApp::UsuarioSinSuscripcionActiva);
+        }
+        let en_uso = self.sesiones.iter().filter(|s| s.cuenta == cuenta).count();
+        if en_uso >= plan.pantallas as usize {
+            return Err(ErroresApp::LimiteDePantallasAlcanzado);
+        }
+
+        let mut vence = fecha;
+        vence.sumar_dias(DURACION_SESION_DIAS);
+        self.proxima_sesion += 1;
+        self.sesiones.push(Sesion {
+            id: self.proxima_sesion,
+            cuenta,
+            perfil,
+            dispositivo: dispositivo.to_string(),
+            vence,
+        });
+        Ok(self.proxima_sesion)
+    }
+
+    pub fn cerrar_sesion(&mut self, id: u32) -> Result<(), ErroresApp> {
+        let indice = self
+            .sesiones
+            .iter()
+            .position(|s| s.id == id)
+            .ok_or(ErroresApp::SesionInexistente)?;
+        self.sesiones.remove(indice);
+        Ok(())
+    }
+
+    pub fn sesiones_de_cuenta(&self, titular: &str) -> Vec<&Sesion<'d>> {
+        self.sesiones
+            .iter()
+            .filter(|s| s.cuenta == titular)
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub enum TipoDescuento {
+    Porcentaje(f64),
+    Fijo(f64),
+}
+#[allow(dead_code)]
+impl TipoDescuento {
+    // Nunca descuenta mas que el precio del mes
+    pub fn calcular(&self, precio: f64) -> f64 {
+        match self {
+            TipoDescuento::Porcentaje(p) => precio * p.clamp(0.0, 100.0) / 100.0,
+            TipoDescuento::Fijo(monto) => monto.clamp(0.0, precio),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct CodigoPromocional {
+    codigo: String,
+    tipo: TipoDescuento,
+    meses: u8,
+    vigente_desde: Fecha,
+    vigente_hasta: Fecha,
+    usos_maximos: Option<u32>,
+    usos: u32,
+}
+#[allow(dead_code)]
+impl CodigoPromocional {
+    pub fn new(
+        codigo: &str,
+        tipo: TipoDescuento,
+        meses: u8,
+        vigente_desde: Fecha,
+        vigente_hasta: Fecha,
+    ) -> Self {
+        Self {
+            codigo: codigo.to_uppercase(),
+            tipo,
+            meses,
+            vigente_desde,
+            vigente_hasta,
+            usos_maximos: None,
+            usos: 0,
+        }
+    }
+
+    pub fn con_limite_de_usos(mut self, usos_maximos: u32) -> Self {
+        self.usos_maximos = Some(usos_maximos);
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct DescuentoActivo {
+    codigo: String,
+    tipo: TipoDescuento,
+    meses_restantes: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct PeriodoPrueba {
+    meses: u8,
+    convertir: bool,
+}
+#[allow(dead_code)]
+impl PeriodoPrueba {
+    // Si `convertir` es falso la prueba vence sola
+    pub fn new(meses: u8, convertir: bool) -> Self {
+        Self { meses, convertir }
+    }
+}
+
+#[allow(dead_code)]
+impl<'d> StreamingRust<'d> {
+    // Alta con un periodo de prueba gratis
+    pub fn iniciar_prueba(
+        &mut self,
+        id: u128,
+        nombre_usuario: &'d str,
+        tipo_suscripcion: TipoSuscripcion,
+        medio_pago: TipoMedioPago<'d>,
+        fecha_inicio: Fecha,
+        periodo: PeriodoPrueba,
+    ) -> Result<&str, ErroresApp> {
+        if periodo.meses == 0 {
+            return Err(ErroresApp::DatosInvalidos);
+        }
+        // Si convierte, el plan tiene que tener precio vigente al terminar la prueba
+        let mut prueba = Suscripcion::new(
+            nombre_usuario,
+            tipo_suscripcion,
+            0.0,
+            medio_pago,
+            fecha_inicio,
+        );
+        prueba.duracion_en_meses = periodo.meses;
+        if periodo.convertir {
+            self.catalogo
+                .precio_vigente(tipo_suscripcion, prueba.fecha_fin())?;
+        }
+        self.crear_usuario_con_fecha(
+            id,
+            nombre_usuario,
+            tipo_suscripcion,
+            medio_pago,
+            fecha_inicio,
+        )?;
+        let prueba = self
+            .suscripciones
+            .get_mut(nombre_usuario)
+            .and_then(|v| v.last_mut())
+            .unwrap();
+        prueba.prueba = true;
+        prueba.costo_mensual = 0.0;
+        prueba.duracion_en_meses = periodo.meses;
+        prueba.renovacion_automatica = periodo.convertir;
+        Ok(nombre_usuario)
+    }
+
+    pub fn crear_codigo_promocional(
+        &mut self,
+        promocion: CodigoPromocional,
+    ) -> Result<(), ErroresApp> {
+        if promocion.meses == 0 {
+            return Err(ErroresApp::DatosInvalidos);
+        }
+        if self.promociones.contains_key(&promocion.codigo) {
+            return Err(ErroresApp::CodigoExistente);
+        }
+        self.promociones.insert(promocion.codigo.clone(), promocion);
+        Ok(())
+    }
+
+    // El descuento se aplica en las proximas `meses` facturas pagas del usuario
+    pub fn canjear_codigo(
+        &mut self,
+        nombre_usuario: &'d str,
+        codigo: &str,
+        fecha: Fecha,
+    ) -> Result<(), ErroresApp> {
+        let codigo = codigo.to_uppercase();
+        let promocion = self
+            .promociones
+            .get(&codigo)
+            .ok_or(ErroresApp::CodigoInexistente)?;
+        if promocion.meses == 0 {
+            return Err(ErroresApp::DatosInvalidos);
+        }
+        if fecha < promocion.vigente_desde || fecha > promocion.vigente_hasta {
+            return Err(ErroresApp::CodigoFueraDeVigencia);
+        }
+        if promocion
+            .usos_maximos
+            .is_some_and(|max| promocion.usos >= max)
+        {
+            return Err(ErroresApp::CodigoAgotado);
+        }
+        if self
+            .canjes
+            .iter()
+            .any(|(c, u, _)| *c == codigo && *u == nombre_usuario)
+        {
+            return Err(ErroresApp::CodigoYaCanjeado);
+        }
+        let tiene_activa = self
+            .suscripciones
+            .get(nombre_usuario)
+            .is_some_and(|v| v.iter().any(|s| s.activa));
+        if !tiene_activa {
+            return Err(ErroresApp::UsuarioSinSuscripcionActiva);
+        }
+        if self.descuentos_activos.contains_key(nombre_usuario) {
+            return Err(ErroresApp::PromocionEnCurso);
+        }
+
+        self.descuentos_activos.insert(
+            nombre_usuario,
+            DescuentoActivo {
+                codigo: codigo.clone(),
+                tipo: promocion.tipo,
+                meses_restantes: promocion.meses,
+            },
+        );
+        self.promociones.get_mut(&codigo).unwrap().usos += 1;
+        self.canjes.push((codigo, nombre_usuario, fecha));
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct FlujoCambio {
+    desde: TipoSuscripcion,
+    hacia: TipoSuscripcion,
+    cantidad: usize,
+    es_mejora: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[allow(dead_code)]
+pub struct Cohorte {
+    mes: u32,
+    ano: u32,
+    usuarios: usize,
+    // retencion[k]: fraccion de la cohorte con suscripcion vigente k meses despues de empezar
+    retencion: Vec<f64>,
+}
+
+#[allow(dead_code)]
+impl<'d> StreamingRust<'d> {
+    pub fn mrr(&self, fecha: Fecha) -> f64 {
+        self.suscripciones
+            .values()
+            .flatten()
+            .filter(|s| s.vigente_en(fecha))
+            .map(|s| s.costo_mensual)
+            .sum()
+    }
+
+    fn tiene_vigente(&self, nombre_usuario: &str, fecha: Fecha) -> bool {
+        self.suscripciones
+            .get(nombre_usuario)
+            .is_some_and(|v| v.iter().any(|s| s.vigente_en(fecha)))
+    }
+
+    // Porcentaje de los usuarios vigentes en `desde` que ya no lo estan en `hasta`
+    pub fn churn(&self, desde: Fecha, hasta: Fecha) -> Option<f64> {
+        let al_inicio: Vec<&&str> = self
+            .suscripciones
+            .keys()
+            .filter(|u| self.tiene_vigente(u, desde))
+            .collect();
+        if al_inicio.is_empty() {
+            return None;
+        }
+        let perdidos = al_inicio
+            .iter()
+            .filter(|u| !self.tiene_vigente(u, hasta))
+            .count();
+        Some(perdidos as f64 * 100.0 / al_inicio.len() as f64)
+    }
+
+    // Cambios de plan sin corte entre una suscripcion y la siguiente; las renovaciones no cuentan
+    pub fn flujos_de_cambio(&self) -> Vec<FlujoCambio> {
+        let mut conteo: HashMap<(TipoSuscripcion, TipoSuscripcion), usize> = HashMap::new();
+        for historial in self.suscripciones.values() {
+            let mut ordenado: Vec<&Suscripcion> = historial.iter().collect();
+            ordenado.sort_by_key(|s| s.fecha_inicio);
+            for par in ordenado.windows(2) {
+                let (anterior, siguiente) = (par[0], par[1]);
+                let continua = anterior.fecha_baja == Some(siguiente.fecha_inicio);
+                if continua && anterior.tipo_suscripcion != siguiente.tipo_suscripcion {
+                    *conteo
+                        .entry((anterior.tipo_suscripcion, siguiente.tipo_suscripcion))
+                        .or_insert(0) += 1;
+                }
+            }
+        }
+
+        let orden = |tipo| self.catalogo.plan(tipo).map(|p| p.orden).unwrap_or(0);
+        let mut flujos: Vec<FlujoCambio> = conteo
+            .into_iter()
+            .map(|((desde, hacia), cantidad)| FlujoCambio {
+                desde,
+                hacia,
+                cantidad,
+                es_mejora: orden(hacia) > orden(desde),
+            })
+            .collect();
+        flujos.sort_by_key(|f| (orden(f.desde), orden(f.hacia)));
+        flujos
+    }
+
+    pub fn retencion_por_cohorte(&self, meses: u32) -> Vec<Cohorte> {
+        let mut cohortes: HashMap<(u32, u32), Vec<(&str, Fecha)>> = HashMap::new();
+        for (nombre, historial) in &self.suscripciones {
+            let primera = historial
+                .iter()
+                .map(|s| s.fecha_inicio)
+                .reduce(|a, b| if b < a { b } else { a });
+            if let Some(inicio) = primera {
+                cohortes
+                    .entry((inicio.ano, inicio.mes))
+                    .or_default()
+                    .push((nombre, inicio));
+            }
+        }
+
+        let mut resultado: Vec<Cohorte> = cohortes
+            .into_iter()
+            .map(|((ano, mes), usuarios)| {
+                let retencion = (0..=meses)
+                    .map(|k| {
+                        let retenidos = usuarios
+                            .iter()
+                            .filter(|(nombre, inicio)| {
+                                self.tiene_vigente(nombre, inicio.sumar_meses(k))
+                            })
+                            .count();
+                        retenidos as f64 / usuarios.len() as f64
+                    })
+                    .collect();
+                Cohorte {
+                    mes,
+                    ano,
+                    usuarios: usuarios.len(),
+                    retencion,
+                }
+            })
+            .collect();
+        resultado.sort_by_key(|c| (c.ano, c.mes));
+        resultado
+    }
+
+    // Porcentaje de suscripciones por medio de pago, de mayor a menor
+    pub fn participacion_medios_pago(&self, solo_activas: bool) -> Vec<(String, f64)> {
+        let mut conteo: HashMap<&str, usize> = HashMap::new();
+        let mut total = 0;
+        for s in self.suscripciones.values().flatten() {
+            if solo_activas && !s.activa {
+                continue;
+            }
+            *conteo.entry(s.medio_pago.nombre()).or_insert(0) += 1;
+            total += 1;
+        }
+        let mut participacion: Vec<(String, f64)> = conteo
+            .into_iter()
+            .map(|(medio, cantidad)| (medio.to_string(), cantidad as f64 * 100.0 / total as f64))
+            .collect();
+        participacion.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        participacion
+    }
+}
+
+// Descuenta de la factura el credito a favor del usuario, hasta cubrir su total
+fn aplicar_credito<'d>(
+    creditos: &mut HashMap<&'d str, f64>,
+    nombre_usuario: &'d str,
+    lineas: &mut Vec<LineaFactura>,
+) {
+    let Some(credito) = creditos.get_mut(nombre_usuario) else {
+        return;
+    };
+    let total: f64 = lineas.iter().map(|l| l.monto).sum();
+    let aplicado = credito.min(total);
+    if aplicado <= 0.0 {
+        return;
+    }
+    *credito -= aplicado;
+    lineas.push(LineaFactura {
+        tipo: TipoLinea::Credito,
+        concepto: "Credito aplicado".to_string(),
+        monto: -aplicado,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn instanciar_plataforma() -> StreamingRust<'static> {
+        StreamingRust {
+            usuarios: vec![],
+            suscripciones: HashMap::new(),
+            ..Default::default()
+        }
+    }
+
+    fn mockear_medio_pago() -> TipoMedioPago<'static> {
+        TipoMedioPago::EFECTIVO(999.0)
+    }
+
+    #[test]
+    fn test_crear_usuario_valido() {
+        let mut sistema = instanciar_plataforma();
+        let resultado =
+            sistema.crear_usuario(1, "valido", TipoSuscripcion::CLASICA, mockear_medio_pago());
+        assert_eq!(resultado.unwrap(), "valido");
+    }
+
+    #[test]
+    fn test_crear_usuario_duplicado() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario(1, "repetido", TipoSuscripcion::BASICA, mockear_medio_pago())
+            .unwrap();
+
+        let resultado =
+            sistema.crear_usuario(2, "repetido", TipoSuscripcion::SUPER, mockear_medio_pago());
+        assert_eq!(resultado.unwrap_err(), ErroresApp::UsuarioExistente);
+    }
+
+    #[test]
+    fn test_subir_suscripcion_valido() {
+        let mut sistema = instanciar_plataforma();
+        let usuario = "simon";
+        sistema
+            .crear_usuario(1, usuario, TipoSuscripcion::BASICA, mockear_medio_pago())
+            .unwrap();
+
+        let resultado = sistema.subir_suscripcion_a_usuario(usuario, mockear_medio_pago());
+        assert_eq!(resultado.unwrap(), TipoSuscripcion::CLASICA);
+    }
+
+    #[test]
+    fn test_subir_suscripcion_con_superior_maxima() {
+        let mut sistema = instanciar_plataforma();
+        let usuario = "simon";
+        sistema
+            .crear_usuario(1, usuario, TipoSuscripcion::SUPER, mockear_medio_pago())
+            .unwrap();
+
+        let resultado = sistema.subir_suscripcion_a_usuario(usuario, mockear_medio_pago());
+        assert_eq!(
+            resultado.unwrap_err(),
+            ErroresApp::MejorSuscripcionDisponible
+        );
+    }
+
+    #[test]
+    fn test_bajar_suscripcion_valido() {
+        let mut sistema = instanciar_plataforma();
+        let usuario = "simon";
+        sistema
+            .crear_usuario(1, usuario, TipoSuscripcion::SUPER, mockear_medio_pago())
+            .unwrap();
+
+        let resultado = sistema.bajar_suscripcion_a_usuario(usuario, mockear_medio_pago());
+        assert_eq!(resultado.unwrap(), TipoSuscripcion::CLASICA);
+    }
+
+    #[test]
+    fn test_bajar_suscripcion_minima() {
+        let mut sistema = instanciar_plataforma();
+        let usuario = "simon";
+        sistema
+            .crear_usuario(1, usuario, TipoSuscripcion::BASICA, mockear_medio_pago())
+            .unwrap();
+
+        let resultado = sistema.bajar_suscripcion_a_usuario(usuario, mockear_medio_pago());
+        assert_eq!(resultado.unwrap(), TipoSuscripcion::BASICA);
+    }
+
+    #[test]
+    fn test_error_usuario_no_registrado() {
+        let mut sistema = instanciar_plataforma();
+        let resultado = sistema.subir_suscripcion_a_usuario("fantasma", mockear_medio_pago());
+        assert_eq!(resultado.unwrap_err(), ErroresApp::UsuarioSinSuscripciones);
+    }
+
+    #[test]
+    fn test_cancelar_suscripcion_activa() {
+        let mut sistema = instanciar_plataforma();
+        let usuario = "lara";
+        sistema
+            .crear_usuario(1, usuario, TipoSuscripcion::CLASICA, mockear_medio_pago())
+            .unwrap();
+
+        let resultado = sistema.cancelar_suscripcion_a_usuario(usuario);
+        assert_eq!(resultado.unwrap(), TipoSuscripcion::CLASICA);
+        let resultado_error = sistema.cancelar_suscripcion_a_usuario(usuario);
+        assert_eq!(
+            resultado_error.unwrap_err(),
+            ErroresApp::UsuarioSinSuscripcionActiva
+        );
+    }
+
+    #[test]
+    fn test_cancelar_suscripcion_sin_activa() {
+        let mut sistema = instanciar_plataforma();
+        let usuario = "maxi";
+        sistema
+            .crear_usuario(1, usuario, TipoSuscripcion::CLASICA, mockear_medio_pago())
+            .unwrap();
+        sistema.cancelar_suscripcion_a_usuario(usuario).unwrap(); // la cancela
+
+        let resultado = sistema.cancelar_suscripcion_a_usuario(usuario);
+        assert_eq!(
+            resultado.unwrap_err(),
+            ErroresApp::UsuarioSinSuscripcionActiva
+        );
+    }
+
+    #[test]
+    fn test_subir_suscripcion_sin_activa() {
+        let mut sistema = instanciar_plataforma();
+        let usuario = "natalia";
+        sistema
+            .crear_usuario(1, usuario, TipoSuscripcion::BASICA, mockear_medio_pago())
+            .unwrap();
+        sistema.cancelar_suscripcion_a_usuario(usuario).unwrap(); // se queda sin activa
+
+        let resultado = sistema.subir_suscripcion_a_usuario(usuario, mockear_medio_pago());
+        assert_eq!(
+            resultado.unwrap_err(),
+            ErroresApp::UsuarioSinSuscripcionActiva
+        );
+    }
+
+    #[test]
+    fn test_bajar_suscripcion_sin_activa() {
+        let mut sistema = instanciar_plataforma();
+        let usuario = "enzo";
+        sistema
+            .crear_usuario(1, usuario, TipoSuscripcion::CLASICA, mockear_medio_pago())
+            .unwrap();
+        sistema.cancelar_suscripcion_a_usuario(usuario).unwrap();
+
+        let resultado = sistema.bajar_suscripcion_a_usuario(usuario, mockear_medio_pago());
+        assert_eq!(
+            resultado.unwrap_err(),
+            ErroresApp::UsuarioSinSuscripcionActiva
+        );
+    }
+
+    #[test]
+    fn test_solo_una_activa_por_usuario() {
+        let mut sistema = instanciar_plataforma();
+        let usuario = "ramiro";
+        sistema
+            .crear_usuario(1, usuario, TipoSuscripcion::BASICA, mockear_medio_pago())
+            .unwrap();
+
+        sistema
+            .subir_suscripcion_a_usuario(usuario, mockear_medio_pago())
+            .unwrap();
+        sistema
+            .subir_suscripcion_a_usuario(usuario, mockear_medio_pago())
+            .unwrap();
+
+        let activas = sistema
+            .suscripciones
+            .get(usuario)
+            .unwrap()
+            .iter()
+            .filter(|s| s.activa)
+            .count();
+
+        assert_eq!(activas, 1);
+    }
+
+    #[test]
+    fn test_tipo_suscripcion_to_string() {
+        assert_eq!(TipoSuscripcion::BASICA.to_string(), "BASICA");
+        assert_eq!(TipoSuscripcion::CLASICA.to_string(), "CLASICA");
+        assert_eq!(TipoSuscripcion::SUPER.to_string(), "SUPER");
+    }
+
+    #[test]
+    fn test_errores_app_display() {
+        assert_eq!(
+            ErroresApp::DatosInvalidos.to_string(),
+            "No fue posible validar sus datos"
+        );
+        assert_eq!(
             ErroresApp::MetodoNoDisponible.to_string(),
             "Método de pago no disponible"
         );
         assert_eq!(
-            ErroresApp::MontoInsuficiente.to_string(),
-            "Monto insuficiente para procesar el pago"
+            ErroresApp::MontoInsuficiente.to_string(),
+            "Monto insuficiente para procesar el pago"
+        );
+        assert_eq!(
+            ErroresApp::MejorSuscripcionDisponible.to_string(),
+            "Usted tiene la mejor suscripcion disponible."
+        );
+        assert_eq!(
+            ErroresApp::NoActiva.to_string(),
+            "No puede cancelar una suscripcion inactiva!"
+        );
+        assert_eq!(
+            ErroresApp::UsuarioNoExiste.to_string(),
+            "El usuario no existe"
+        );
+        assert_eq!(
+            ErroresApp::UsuarioSinSuscripciones.to_string(),
+            "El usuario no registra suscripciones"
+        );
+        assert_eq!(
+            ErroresApp::UsuarioSinSuscripcionActiva.to_string(),
+            "El usuario no registra suscripciones activas"
+        );
+        assert_eq!(
+            ErroresApp::UsuarioExistente.to_string(),
+            "Este nombre de usuario ya existe"
+        );
+    }
+
+    #[test]
+    fn test_suscripcion_new_y_to_string() {
+        let fecha = Fecha::hoy();
+        let sus = Suscripcion::new(
+            "leo",
+            TipoSuscripcion::CLASICA,
+            200.0,
+            mockear_medio_pago(),
+            fecha,
+        );
+
+        assert_eq!(sus.nombre_usuario, "leo");
+        assert_eq!(sus.tipo_suscripcion, TipoSuscripcion::CLASICA);
+        assert_eq!(sus.costo_mensual, 200.0);
+        assert_eq!(sus.duracion_en_meses, 12);
+        assert_eq!(sus.activa, true);
+        assert_eq!(sus.to_string(), "CLASICA");
+    }
+
+    #[test]
+    fn test_suscripcion_cancelar() {
+        let fecha = Fecha::hoy();
+        let mut sus = Suscripcion::new(
+            "ana",
+            TipoSuscripcion::BASICA,
+            100.0,
+            mockear_medio_pago(),
+            fecha,
+        );
+
+        assert!(sus.activa);
+        sus.cancelar();
+        assert!(!sus.activa);
+    }
+
+    #[test]
+    fn test_get_historial_usuario_existente_con_suscripciones() {
+        let mut sistema = instanciar_plataforma();
+        let usuario = "simon";
+        sistema
+            .crear_usuario(1, usuario, TipoSuscripcion::BASICA, mockear_medio_pago())
+            .unwrap();
+
+        let resultado = sistema.get_historial(usuario);
+        assert!(resultado.is_ok());
+        let informe = resultado.unwrap();
+        assert_eq!(informe.nombre_usuario, usuario);
+        assert_eq!(informe.usuario.nombre_usuario, usuario);
+        assert!(!informe.historial.is_empty());
+    }
+
+    #[test]
+    fn test_get_historial_usuario_inexistente() {
+        let sistema = instanciar_plataforma();
+        let resultado = sistema.get_historial("pablito");
+        assert_eq!(resultado.unwrap_err(), ErroresApp::UsuarioNoExiste);
+    }
+
+    #[test]
+    fn test_get_historial_usuario_existente_sin_suscripciones() {
+        let usuario = "basouuura";
+        let mut sistema = instanciar_plataforma();
+        sistema.usuarios.push(Usuario::new(1, usuario));
+        let resultado = sistema.get_historial(usuario);
+        assert_eq!(resultado.unwrap_err(), ErroresApp::UsuarioSinSuscripciones);
+    }
+
+    #[test]
+    fn test_get_historial_usuario_existente_con_varias_suscripciones() {
+        let mut sistema = instanciar_plataforma();
+        let usuario = "usuario_platudo";
+        sistema
+            .crear_usuario(1, usuario, TipoSuscripcion::BASICA, mockear_medio_pago())
+            .unwrap();
+        sistema
+            .subir_suscripcion_a_usuario(usuario, mockear_medio_pago())
+            .unwrap();
+        sistema
+            .subir_suscripcion_a_usuario(usuario, mockear_medio_pago())
+            .unwrap();
+
+        let resultado = sistema.get_historial(usuario);
+        assert!(resultado.is_ok());
+        let informe = resultado.unwrap();
+        assert_eq!(informe.nombre_usuario, usuario);
+        assert!(informe.historial.len() >= 3);
+
+        for i in 1..informe.historial.len() {
+            assert!(
+                !informe.historial[i]
+                    .fecha_inicio
+                    .es_mayor(informe.historial[i - 1].fecha_inicio),
+                "Historial no está ordenado cronológicamente"
+            );
+            println!("{:?}", informe)
+        }
+    }
+
+    #[test]
+    fn test_fecha_es_bisiesto() {
+        let fecha: Fecha = Fecha::new(29, 02, 2024);
+        assert_eq!(true, fecha.es_bisiesto());
+
+        let fecha: Fecha = Fecha::new(29, 02, 2020);
+        assert_eq!(true, fecha.es_bisiesto());
+
+        let fecha: Fecha = Fecha::new(29, 02, 1900);
+        assert_eq!(false, fecha.es_bisiesto());
+
+        let fecha: Fecha = Fecha::new(29, 02, 2021);
+        assert_eq!(false, fecha.es_bisiesto());
+    }
+
+    #[test]
+    fn test_fecha_es_mayor() {
+        // TEST 29-02-2024 > 28-02-2024
+        let fecha: Fecha = Fecha::new(29, 02, 2024);
+        let otra_fecha: Fecha = Fecha::new(28, 02, 2024);
+        assert_eq!(true, fecha.es_mayor(otra_fecha));
+
+        // TEST 01-02-2024 > 28-02-2024
+        let fecha: Fecha = Fecha::new(1, 02, 2024);
+        let otra_fecha: Fecha = Fecha::new(28, 02, 2024);
+        assert_eq!(false, fecha.es_mayor(otra_fecha));
+
+        // TEST 01-02-2024 > 28-02-1985
+        let fecha: Fecha = Fecha::new(1, 02, 2024);
+        let otra_fecha: Fecha = Fecha::new(28, 02, 1985);
+        assert_eq!(true, fecha.es_mayor(otra_fecha));
+
+        // TEST 01-02-2024 > 28-01-2024
+        let fecha: Fecha = Fecha::new(1, 02, 2024);
+        let otra_fecha: Fecha = Fecha::new(28, 01, 2024);
+        assert_eq!(true, fecha.es_mayor(otra_fecha));
+    }
+
+    #[test]
+    fn test_fecha_es_fecha_valida() {
+        let fecha: Fecha = Fecha::new(1, 1, 2025);
+        assert_eq!(true, fecha.es_fecha_valida());
+
+        let fecha: Fecha = Fecha::new(30, 4, 1985);
+        assert_eq!(true, fecha.es_fecha_valida());
+
+        let fecha: Fecha = Fecha::new(31, 12, 2001);
+        assert_eq!(true, fecha.es_fecha_valida());
+
+        let fecha: Fecha = Fecha::new(29, 2, 2024);
+        assert_eq!(true, fecha.es_fecha_valida());
+
+        let fecha: Fecha = Fecha::new(29, 02, 1900);
+        assert_eq!(false, fecha.es_fecha_valida());
+
+        let fecha: Fecha = Fecha::new(0, 1, 2025);
+        assert_eq!(false, fecha.es_fecha_valida());
+
+        let fecha: Fecha = Fecha::new(31, 2, 2025);
+        assert_eq!(false, fecha.es_fecha_valida());
+
+        let fecha: Fecha = Fecha::new(31, 4, 2025);
+        assert_eq!(false, fecha.es_fecha_valida());
+
+        let fecha: Fecha = Fecha::new(1, 1, 2026);
+        assert_eq!(true, fecha.es_fecha_valida());
+    }
+
+    #[test]
+    fn test_fecha_to_string() {
+        let fecha: Fecha = Fecha::new(1, 1, 2025);
+        assert_eq!("1/1/2025".to_string(), fecha.to_string());
+    }
+
+    #[test]
+    fn test_fecha_restar_dias() {
+        let mut fecha: Fecha = Fecha::new(1, 02, 2024);
+        fecha.restar_dias(5);
+        assert_eq!("27/1/2024", fecha.to_string());
+
+        let mut fecha: Fecha = Fecha::new(1, 02, 2024);
+        fecha.restar_dias(600);
+        assert_eq!("11/6/2022", fecha.to_string());
+
+        let mut fecha: Fecha = Fecha::new(4, 05, 2025);
+        fecha.restar_dias(5498);
+        assert_eq!("15/4/2010", fecha.to_string());
+
+        let mut fecha: Fecha = Fecha::new(1, 02, 2024);
+        fecha.restar_dias(0);
+        assert_eq!("1/2/2024", fecha.to_string());
+    }
+
+    #[test]
+    fn test_fecha_sumar_dias() {
+        let mut fecha: Fecha = Fecha::new(1, 02, 2024);
+        fecha.sumar_dias(5);
+        assert_eq!("6/2/2024", fecha.to_string());
+
+        let mut fecha: Fecha = Fecha::new(4, 05, 2025);
+        fecha.sumar_dias(600);
+        assert_eq!("25/12/2026", fecha.to_string());
+
+        let mut fecha: Fecha = Fecha::new(4, 05, 2025);
+        fecha.sumar_dias(5498);
+        assert_eq!("23/5/2040", fecha.to_string());
+
+        let mut fecha: Fecha = Fecha::new(1, 02, 2024);
+        fecha.sumar_dias(0);
+        assert_eq!("1/2/2024", fecha.to_string());
+    }
+    #[test]
+
+    fn test_mayor_suscripcion_con_varias_suscripciones() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario(1, "pepe", TipoSuscripcion::BASICA, mockear_medio_pago())
+            .unwrap();
+        sistema
+            .crear_usuario(2, "pipi", TipoSuscripcion::CLASICA, mockear_medio_pago())
+            .unwrap();
+        sistema
+            .crear_usuario(3, "popo", TipoSuscripcion::CLASICA, mockear_medio_pago())
+            .unwrap();
+
+        let resultado = sistema.mayor_suscripcion(false);
+        assert_eq!(resultado.unwrap(), "CLASICA");
+    }
+
+    #[test]
+    fn test_mayor_suscripcion_sin_suscripciones() {
+        let sistema = instanciar_plataforma();
+        let resultado = sistema.mayor_suscripcion(false);
+        assert_eq!(resultado.unwrap_err(), ErroresApp::SinSuscripciones);
+    }
+
+    #[test]
+    fn test_mayor_suscripcion_activa_con_suscripciones_activas() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario(1, "pepe", TipoSuscripcion::BASICA, mockear_medio_pago())
+            .unwrap();
+        sistema
+            .crear_usuario(2, "pipi", TipoSuscripcion::CLASICA, mockear_medio_pago())
+            .unwrap();
+        sistema
+            .crear_usuario(3, "popo", TipoSuscripcion::CLASICA, mockear_medio_pago())
+            .unwrap();
+
+        let resultado = sistema.mayor_suscripcion(true);
+        assert_eq!(resultado.unwrap(), "CLASICA");
+    }
+
+    #[test]
+    fn test_mayor_suscripcion_activa_sin_suscripciones_activas() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario(1, "pepe", TipoSuscripcion::BASICA, mockear_medio_pago())
+            .unwrap();
+        sistema
+            .crear_usuario(2, "pipi", TipoSuscripcion::CLASICA, mockear_medio_pago())
+            .unwrap();
+
+        // Cancelar todas las suscripciones activas
+        for usuario in &["pepe", "pipi"] {
+            sistema.cancelar_suscripcion_a_usuario(usuario).unwrap();
+        }
+
+        let resultado = sistema.mayor_suscripcion(true);
+        assert_eq!(resultado.unwrap_err(), ErroresApp::SinSuscripcionesActivas);
+    }
+
+    #[test]
+    fn test_mayor_medio_pago_con_suscripciones() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario(
+                1,
+                "elber_galarga",
+                TipoSuscripcion::BASICA,
+                TipoMedioPago::EFECTIVO(100.0),
+            )
+            .unwrap();
+        sistema
+            .crear_usuario(
+                2,
+                "elber_galarga2",
+                TipoSuscripcion::CLASICA,
+                TipoMedioPago::CREDITO { nro_cuenta: "123" },
+            )
+            .unwrap();
+        sistema
+            .crear_usuario(
+                3,
+                "elber_galarga3",
+                TipoSuscripcion::SUPER,
+                TipoMedioPago::CREDITO { nro_cuenta: "456" },
+            )
+            .unwrap();
+
+        let resultado = sistema.mayor_medio_pago(false);
+        assert_eq!(resultado.unwrap(), "CREDITO");
+    }
+
+    #[test]
+    fn test_mayor_medio_pago_sin_suscripciones() {
+        let sistema = instanciar_plataforma();
+        let resultado = sistema.mayor_medio_pago(false);
+        assert_eq!(resultado.unwrap_err(), ErroresApp::SinSuscripciones);
+    }
+
+    #[test]
+    fn test_mayor_medio_pago_activo_con_suscripciones_activas() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario(
+                1,
+                "elber_galarga",
+                TipoSuscripcion::BASICA,
+                TipoMedioPago::EFECTIVO(100.0),
+            )
+            .unwrap();
+        sistema
+            .crear_usuario(
+                2,
+                "elber_galarga2",
+                TipoSuscripcion::CLASICA,
+                TipoMedioPago::CREDITO { nro_cuenta: "123" },
+            )
+            .unwrap();
+        sistema
+            .crear_usuario(
+                3,
+                "elber_galarga3",
+                TipoSuscripcion::SUPER,
+                TipoMedioPago::CREDITO { nro_cuenta: "456" },
+            )
+            .unwrap();
+
+        let resultado = sistema.mayor_medio_pago(true);
+        assert_eq!(resultado.unwrap(), "CREDITO");
+    }
+
+    #[test]
+    fn test_mayor_medio_pago_activo_sin_suscripciones_activas() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario(
+                1,
+                "elber_galarga",
+                TipoSuscripcion::BASICA,
+                TipoMedioPago::EFECTIVO(100.0),
+            )
+            .unwrap();
+        sistema
+            .crear_usuario(
+                2,
+                "elber_galarga2",
+                TipoSuscripcion::CLASICA,
+                TipoMedioPago::CREDITO { nro_cuenta: "123" },
+            )
+            .unwrap();
+
+        // Cancelar todas las suscripciones activas
+        for usuario in &["elber_galarga", "elber_galarga2"] {
+            sistema.cancelar_suscripcion_a_usuario(usuario).unwrap();
+        }
+
+        let resultado = sistema.mayor_medio_pago(true);
+        assert_eq!(resultado.unwrap_err(), ErroresApp::SinSuscripcionesActivas);
+    }
+
+    #[test]
+    fn test_suscripcion_vencida_sin_facturar_no_cuenta_como_activa() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario_con_fecha(
+                1,
+                "pepe",
+                TipoSuscripcion::SUPER,
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2020),
+            )
+            .unwrap();
+        sistema
+            .crear_usuario_con_fecha(
+                2,
+                "pipi",
+                TipoSuscripcion::BASICA,
+                TipoMedioPago::CREDITO { nro_cuenta: "123" },
+                Fecha::new(1, 6, 2020),
+            )
+            .unwrap();
+
+        // Nunca se facturo, pero el plazo de pepe termino el 1/1/2021
+        let hoy = Fecha::new(1, 3, 2021);
+        assert_eq!(sistema.mayor_suscripcion_al(true, hoy).unwrap(), "BASICA");
+        assert_eq!(sistema.mayor_medio_pago_al(true, hoy).unwrap(), "CREDITO");
+        assert_eq!(
+            sistema
+                .cancelar_suscripcion_con_fecha("pepe", hoy)
+                .unwrap_err(),
+            ErroresApp::UsuarioSinSuscripcionActiva
+        );
+        assert_eq!(
+            sistema.cancelar_suscripcion_con_fecha("pipi", hoy).unwrap(),
+            TipoSuscripcion::BASICA
         );
         assert_eq!(
-            ErroresApp::MejorSuscripcionDisponible.to_string(),
-            "Usted tiene la mejor suscripcion disponible."
+            sistema.mayor_suscripcion_al(true, hoy).unwrap_err(),
+            ErroresApp::SinSuscripcionesActivas
+        );
+    }
+
+    #[test]
+    fn test_fecha_sumar_meses() {
+        assert_eq!(
+            Fecha::new(15, 1, 2025).sumar_meses(1),
+            Fecha::new(15, 2, 2025)
         );
         assert_eq!(
-            ErroresApp::NoActiva.to_string(),
-            "No puede cancelar una suscripcion inactiva!"
+            Fecha::new(31, 1, 2024).sumar_meses(1),
+            Fecha::new(29, 2, 2024)
         );
         assert_eq!(
-            ErroresApp::UsuarioNoExiste.to_string(),
-            "El usuario no existe"
+            Fecha::new(10, 11, 2025).sumar_meses(14),
+            Fecha::new(10, 1, 2027)
+        );
+        assert!(Fecha::new(1, 2, 2025) > Fecha::new(31, 1, 2025));
+    }
+
+    #[test]
+    fn test_facturacion_mensual() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario_con_fecha(
+                1,
+                "simon",
+                TipoSuscripcion::CLASICA,
+                mockear_medio_pago(),
+                Fecha::new(10, 1, 2025),
+            )
+            .unwrap();
+
+        let facturas = sistema.avanzar_facturacion(Fecha::new(15, 3, 2025));
+        assert_eq!(facturas.len(), 3);
+        assert_eq!(facturas[2].desde, Fecha::new(10, 3, 2025));
+        assert_eq!(facturas[2].hasta, Fecha::new(10, 4, 2025));
+        assert_eq!(facturas[2].total(), 200.0);
+        assert_eq!(facturas[2].numero, 3);
+
+        // Volver a procesar la misma fecha no duplica facturas
+        assert!(
+            sistema
+                .avanzar_facturacion(Fecha::new(15, 3, 2025))
+                .is_empty()
         );
+        assert_eq!(sistema.facturas_de("simon").len(), 3);
+    }
+
+    #[test]
+    fn test_facturacion_renovacion_automatica() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario_con_fecha(
+                1,
+                "simon",
+                TipoSuscripcion::BASICA,
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+            )
+            .unwrap();
+
+        sistema.avanzar_facturacion(Fecha::new(1, 2, 2026));
+        assert_eq!(sistema.facturas_de("simon").len(), 14);
+
+        let suscripciones = sistema.suscripciones.get("simon").unwrap();
+        assert_eq!(suscripciones.len(), 2);
+        assert!(!suscripciones[0].activa);
+        assert!(suscripciones[1].activa);
+        assert_eq!(suscripciones[1].fecha_inicio, Fecha::new(1, 1, 2026));
+        assert!(suscripciones[1].esta_vigente(Fecha::new(1, 2, 2026)));
+    }
+
+    #[test]
+    fn test_facturacion_vencimiento_sin_renovacion() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario_con_fecha(
+                1,
+                "simon",
+                TipoSuscripcion::SUPER,
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+            )
+            .unwrap();
+        sistema.set_renovacion_automatica("simon", false).unwrap();
+
+        sistema.avanzar_facturacion(Fecha::new(31, 12, 2025));
+        assert!(sistema.suscripciones.get("simon").unwrap()[0].activa);
+
+        sistema.avanzar_facturacion(Fecha::new(1, 1, 2026));
+        let facturas = sistema.facturas_de("simon");
+        assert_eq!(facturas.len(), 12);
+        assert_eq!(facturas.iter().map(|f| f.total()).sum::<f64>(), 3600.0);
+        assert!(!sistema.suscripciones.get("simon").unwrap()[0].activa);
         assert_eq!(
-            ErroresApp::UsuarioSinSuscripciones.to_string(),
-            "El usuario no registra suscripciones"
+            sistema.cancelar_suscripcion_a_usuario("simon").unwrap_err(),
+            ErroresApp::UsuarioSinSuscripcionActiva
+        );
+    }
+
+    fn crear_usuario_en_enero(sistema: &mut StreamingRust<'static>, tipo: TipoSuscripcion) {
+        sistema
+            .crear_usuario_con_fecha(
+                1,
+                "simon",
+                tipo,
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_subir_suscripcion_con_prorrateo() {
+        let mut sistema = instanciar_plataforma();
+        crear_usuario_en_enero(&mut sistema, TipoSuscripcion::BASICA);
+        sistema.avanzar_facturacion(Fecha::new(15, 4, 2025));
+
+        // Quedan 16 de los 30 dias de abril
+        let resultado = sistema.subir_suscripcion_con_fecha(
+            "simon",
+            mockear_medio_pago(),
+            Fecha::new(15, 4, 2025),
         );
+        assert_eq!(resultado.unwrap(), TipoSuscripcion::CLASICA);
+
+        let facturas = sistema.facturas_de("simon");
+        assert_eq!(facturas.len(), 5);
+        let ajuste = facturas[4];
         assert_eq!(
-            ErroresApp::UsuarioSinSuscripcionActiva.to_string(),
-            "El usuario no registra suscripciones activas"
+            ajuste.lineas[0].concepto,
+            "Cambio BASICA -> CLASICA (16/30 dias)"
+        );
+        assert!((ajuste.total() - 100.0 * 16.0 / 30.0).abs() < 1e-9);
+
+        // La nueva suscripcion sigue facturando el 1 de cada mes, al precio nuevo
+        let nuevas = sistema.avanzar_facturacion(Fecha::new(1, 5, 2025));
+        assert_eq!(nuevas.len(), 1);
+        assert_eq!(nuevas[0].desde, Fecha::new(1, 5, 2025));
+        assert_eq!(nuevas[0].total(), 200.0);
+        let activa = sistema.suscripciones["simon"]
+            .iter()
+            .find(|s| s.activa)
+            .unwrap();
+        assert_eq!(activa.fecha_fin(), Fecha::new(1, 1, 2026));
+    }
+
+    #[test]
+    fn test_cambio_de_plan_no_factura_periodos_pendientes() {
+        let mut sistema = instanciar_plataforma();
+        crear_usuario_en_enero(&mut sistema, TipoSuscripcion::BASICA);
+
+        // Sin facturar antes: solo se emite el ajuste por el cambio
+        sistema
+            .subir_suscripcion_con_fecha("simon", mockear_medio_pago(), Fecha::new(15, 4, 2025))
+            .unwrap();
+        let facturas = sistema.facturas_de("simon");
+        assert_eq!(facturas.len(), 1);
+        assert!((facturas[0].total() - 100.0 * 16.0 / 30.0).abs() < 1e-9);
+
+        // La nueva arranca el dia del cambio y la anterior termina ese mismo dia
+        let historial = &sistema.suscripciones["simon"];
+        assert_eq!(historial[0].fecha_baja, Some(Fecha::new(15, 4, 2025)));
+        assert_eq!(historial[1].fecha_inicio, Fecha::new(15, 4, 2025));
+        assert_eq!(historial[1].inicio_periodo(0), Fecha::new(1, 4, 2025));
+
+        // Enero a abril los factura el plan anterior y mayo el nuevo
+        let nuevas = sistema.avanzar_facturacion(Fecha::new(1, 5, 2025));
+        let totales: Vec<f64> = nuevas.iter().map(|f| f.total()).collect();
+        assert_eq!(totales, vec![100.0, 100.0, 100.0, 100.0, 200.0]);
+        assert_eq!(nuevas[4].desde, Fecha::new(1, 5, 2025));
+    }
+
+    #[test]
+    fn test_cambio_de_plan_al_empezar_un_periodo_sin_facturar() {
+        let mut sistema = instanciar_plataforma();
+        crear_usuario_en_enero(&mut sistema, TipoSuscripcion::BASICA);
+        sistema
+            .subir_suscripcion_con_fecha("simon", mockear_medio_pago(), Fecha::new(1, 4, 2025))
+            .unwrap();
+        assert!(sistema.facturas_de("simon").is_empty());
+
+        let nuevas = sistema.avanzar_facturacion(Fecha::new(1, 4, 2025));
+        let totales: Vec<f64> = nuevas.iter().map(|f| f.total()).collect();
+        assert_eq!(totales, vec![100.0, 100.0, 100.0, 200.0]);
+        assert_eq!(nuevas[3].tipo_suscripcion, TipoSuscripcion::CLASICA);
+    }
+
+    #[test]
+    fn test_bajar_suscripcion_genera_credito() {
+        let mut sistema = instanciar_plataforma();
+        crear_usuario_en_enero(&mut sistema, TipoSuscripcion::SUPER);
+        sistema.avanzar_facturacion(Fecha::new(16, 6, 2025));
+
+        // Quedan 15 de los 30 dias de junio: se acreditan 50
+        sistema
+            .bajar_suscripcion_con_fecha(
+                "simon",
+                mockear_medio_pago(),
+                Fecha::new(16, 6, 2025),
+                false,
+            )
+            .unwrap();
+        assert!((sistema.credito_de("simon") - 50.0).abs() < 1e-9);
+        let ajuste = *sistema.facturas_de("simon").last().unwrap();
+        assert!((ajuste.total() + 50.0).abs() < 1e-9);
+
+        // El credito se descuenta de la proxima factura
+        let nuevas = sistema.avanzar_facturacion(Fecha::new(1, 7, 2025));
+        assert_eq!(nuevas[0].lineas[1].concepto, "Credito aplicado");
+        assert!((nuevas[0].total() - 150.0).abs() < 1e-9);
+        assert_eq!(sistema.credito_de("simon"), 0.0);
+    }
+
+    #[test]
+    fn test_bajar_suscripcion_al_proximo_periodo() {
+        let mut sistema = instanciar_plataforma();
+        crear_usuario_en_enero(&mut sistema, TipoSuscripcion::CLASICA);
+        sistema.avanzar_facturacion(Fecha::new(10, 3, 2025));
+
+        let resultado = sistema.bajar_suscripcion_con_fecha(
+            "simon",
+            mockear_medio_pago(),
+            Fecha::new(10, 3, 2025),
+            true,
+        );
+        assert_eq!(resultado.unwrap(), TipoSuscripcion::BASICA);
+        assert_eq!(sistema.credito_de("simon"), 0.0);
+        assert_eq!(sistema.facturas_de("simon").len(), 3);
+
+        let nuevas = sistema.avanzar_facturacion(Fecha::new(1, 5, 2025));
+        assert_eq!(nuevas.len(), 2);
+        assert_eq!(nuevas[0].tipo_suscripcion, TipoSuscripcion::BASICA);
+        assert_eq!(nuevas[0].total(), 100.0);
+        assert_eq!(
+            nuevas[0].lineas[1].concepto,
+            "Cambio programado CLASICA -> BASICA"
+        );
+        assert_eq!(nuevas[1].lineas.len(), 1);
+
+        let suscripciones = &sistema.suscripciones["simon"];
+        assert_eq!(suscripciones.len(), 2);
+        assert_eq!(suscripciones[1].fecha_inicio, Fecha::new(1, 4, 2025));
+        assert_eq!(suscripciones[1].duracion_en_meses, 9);
+    }
+
+    #[test]
+    fn test_baja_programada_en_el_ultimo_periodo_aplica_en_la_renovacion() {
+        let mut sistema = instanciar_plataforma();
+        crear_usuario_en_enero(&mut sistema, TipoSuscripcion::CLASICA);
+        sistema.avanzar_facturacion(Fecha::new(10, 12, 2025));
+
+        sistema
+            .bajar_suscripcion_con_fecha(
+                "simon",
+                mockear_medio_pago(),
+                Fecha::new(10, 12, 2025),
+                true,
+            )
+            .unwrap();
+
+        let nuevas = sistema.avanzar_facturacion(Fecha::new(1, 1, 2026));
+        assert_eq!(nuevas.len(), 1);
+        assert_eq!(nuevas[0].tipo_suscripcion, TipoSuscripcion::BASICA);
+        assert_eq!(nuevas[0].desde, Fecha::new(1, 1, 2026));
+        assert_eq!(nuevas[0].total(), 100.0);
+        assert_eq!(
+            nuevas[0].lineas[1].concepto,
+            "Cambio programado CLASICA -> BASICA"
+        );
+        assert!(sistema.cambios_programados.is_empty());
+    }
+
+    #[test]
+    fn test_baja_programada_no_sobrevive_a_la_cancelacion() {
+        let mut sistema = instanciar_plataforma();
+        crear_usuario_en_enero(&mut sistema, TipoSuscripcion::SUPER);
+        sistema.avanzar_facturacion(Fecha::new(10, 2, 2025));
+        sistema
+            .bajar_suscripcion_con_fecha(
+                "simon",
+                mockear_medio_pago(),
+                Fecha::new(10, 2, 2025),
+                true,
+            )
+            .unwrap();
+        sistema
+            .cancelar_suscripcion_con_fecha("simon", Fecha::new(11, 2, 2025))
+            .unwrap();
+        assert!(sistema.cambios_programados.is_empty());
+
+        sistema
+            .volver_a_suscribir_con_fecha(
+                "simon",
+                TipoSuscripcion::SUPER,
+                mockear_medio_pago(),
+                Fecha::new(1, 6, 2025),
+            )
+            .unwrap();
+        let nuevas = sistema.avanzar_facturacion(Fecha::new(1, 7, 2025));
+        assert_eq!(nuevas.len(), 2);
+        assert!(
+            nuevas
+                .iter()
+                .all(|f| f.tipo_suscripcion == TipoSuscripcion::SUPER && f.total() == 300.0)
+        );
+    }
+
+    const TARJETA: &str = "4539578763621486";
+    const CBU: &str = "2850590940090418135201";
+    const CVU: &str = "0000003100010000000009";
+
+    #[test]
+    fn test_validaciones_de_medios_de_pago() {
+        assert!(luhn_es_valido(TARJETA));
+        assert!(luhn_es_valido("4111 1111 1111 1111"));
+        assert!(!luhn_es_valido("4111111111111112"));
+        assert!(!luhn_es_valido("41111"));
+        assert!(cbu_es_valido(CBU));
+        assert!(cbu_es_valido(CVU));
+        assert!(cvu_es_valido(CVU));
+        assert!(!cvu_es_valido(CBU));
+        assert!(!cvu_es_valido("0000003100010000000008"));
+        assert!(!cbu_es_valido("2850590940090418135202"));
+        assert!(!cbu_es_valido("285059094009041813520"));
+
+        assert!(
+            TipoMedioPago::CREDITO {
+                nro_cuenta: TARJETA
+            }
+            .procesador()
+            .validar()
+            .is_ok()
+        );
+        assert!(
+            TipoMedioPago::MERCADOPAGO { cvu: CVU }
+                .procesador()
+                .validar()
+                .is_ok()
+        );
+        assert_eq!(
+            TipoMedioPago::MERCADOPAGO { cvu: CBU }
+                .procesador()
+                .validar()
+                .unwrap_err(),
+            ErroresApp::DatosInvalidos
+        );
+        assert_eq!(
+            TipoMedioPago::TRANSFERENCIA { cbu: "123" }
+                .procesador()
+                .validar()
+                .unwrap_err(),
+            ErroresApp::DatosInvalidos
         );
         assert_eq!(
-            ErroresApp::UsuarioExistente.to_string(),
-            "Este nombre de usuario ya existe"
+            TipoMedioPago::CRIPTO { wallet: "corta" }
+                .procesador()
+                .validar()
+                .unwrap_err(),
+            ErroresApp::DatosInvalidos
         );
     }
 
     #[test]
-    fn test_suscripcion_new_y_to_string() {
-        let fecha = Fecha::hoy();
-        let sus = Suscripcion::new("leo", TipoSuscripcion::CLASICA, mockear_medio_pago(), fecha);
+    fn test_procesador_con_pasarela_simulada() {
+        let mut pasarela = PasarelaSimulada::new(RespuestaPasarela::Aprobado);
+        let credito = TipoMedioPago::CREDITO {
+            nro_cuenta: TARJETA,
+        }
+        .procesador();
+        assert!(credito.cobrar(100.0, &mut pasarela).is_ok());
 
-        assert_eq!(sus.nombre_usuario, "leo");
-        assert_eq!(sus.tipo_suscripcion, TipoSuscripcion::CLASICA);
-        assert_eq!(sus.costo_mensual, 200.0);
-        assert_eq!(sus.duracion_en_meses, 12);
-        assert_eq!(sus.activa, true);
-        assert_eq!(sus.to_string(), "CLASICA");
-    }
+        // Sin respuesta se reintenta en el momento
+        pasarela.encolar(RespuestaPasarela::SinRespuesta);
+        pasarela.encolar(RespuestaPasarela::SinRespuesta);
+        assert!(credito.cobrar(100.0, &mut pasarela).is_ok());
+        assert_eq!(pasarela.intentos(), 4);
 
-    #[test]
-    fn test_suscripcion_cancelar() {
-        let fecha = Fecha::hoy();
-        let mut sus = Suscripcion::new("ana", TipoSuscripcion::BASICA, mockear_medio_pago(), fecha);
+        let mut caida = PasarelaSimulada::new(RespuestaPasarela::SinRespuesta);
+        assert_eq!(
+            credito.cobrar(100.0, &mut caida).unwrap_err(),
+            ErroresApp::TiempoAgotado
+        );
+        let mut rechaza = PasarelaSimulada::new(RespuestaPasarela::Rechazado);
+        assert_eq!(
+            credito.cobrar(100.0, &mut rechaza).unwrap_err(),
+            ErroresApp::PagoRechazado
+        );
 
-        assert!(sus.activa);
-        sus.cancelar();
-        assert!(!sus.activa);
+        // El efectivo no usa la pasarela
+        let efectivo = TipoMedioPago::EFECTIVO(50.0).procesador();
+        assert_eq!(
+            efectivo.cobrar(100.0, &mut rechaza).unwrap_err(),
+            ErroresApp::MontoInsuficiente
+        );
+        assert_eq!(rechaza.intentos(), 1);
     }
 
     #[test]
-    fn test_get_historial_usuario_existente_con_suscripciones() {
+    fn test_cobranza_suspende_y_reactiva() {
         let mut sistema = instanciar_plataforma();
-        let usuario = "simon";
         sistema
-            .crear_usuario(1, usuario, TipoSuscripcion::BASICA, mockear_medio_pago())
+            .crear_usuario_con_fecha(
+                1,
+                "simon",
+                TipoSuscripcion::BASICA,
+                TipoMedioPago::CREDITO {
+                    nro_cuenta: TARJETA,
+                },
+                Fecha::new(1, 1, 2025),
+            )
             .unwrap();
+        sistema.avanzar_facturacion(Fecha::new(1, 1, 2025));
 
-        let resultado = sistema.get_historial(usuario);
-        assert!(resultado.is_ok());
-        let informe = resultado.unwrap();
-        assert_eq!(informe.nombre_usuario, usuario);
-        assert_eq!(informe.usuario.nombre_usuario, usuario);
-        assert!(!informe.historial.is_empty());
-    }
+        let mut rechaza = PasarelaSimulada::new(RespuestaPasarela::Rechazado);
+        for _ in 0..2 {
+            let resultados = sistema.cobrar_facturas_pendientes(&mut rechaza);
+            assert_eq!(resultados, vec![(1, Err(ErroresApp::PagoRechazado))]);
+            assert!(!sistema.esta_suspendido("simon"));
+        }
+        sistema.cobrar_facturas_pendientes(&mut rechaza);
+        assert!(sistema.esta_suspendido("simon"));
+        assert!(
+            sistema
+                .avanzar_facturacion(Fecha::new(1, 3, 2025))
+                .is_empty()
+        );
+        assert_eq!(
+            sistema
+                .reactivar_usuario("simon", Fecha::new(10, 3, 2025))
+                .unwrap_err(),
+            ErroresApp::UsuarioConDeuda
+        );
 
-    #[test]
-    fn test_get_historial_usuario_inexistente() {
-        let sistema = instanciar_plataforma();
-        let resultado = sistema.get_historial("pablito");
-        assert_eq!(resultado.unwrap_err(), ErroresApp::UsuarioNoExiste);
-    }
+        let mut aprueba = PasarelaSimulada::new(RespuestaPasarela::Aprobado);
+        assert_eq!(
+            sistema.cobrar_facturas_pendientes(&mut aprueba),
+            vec![(1, Ok(()))]
+        );
+        sistema
+            .reactivar_usuario("simon", Fecha::new(10, 3, 2025))
+            .unwrap();
+        assert!(!sistema.esta_suspendido("simon"));
 
-    #[test]
-    fn test_get_historial_usuario_existente_sin_suscripciones() {
-        let usuario = "basouuura";
-        let mut sistema = instanciar_plataforma();
-        sistema.usuarios.push(Usuario::new(1, usuario));
-        let resultado = sistema.get_historial(usuario);
-        assert_eq!(resultado.unwrap_err(), ErroresApp::UsuarioSinSuscripciones);
+        // Febrero no se factura; se retoma en el periodo en curso
+        let nuevas = sistema.avanzar_facturacion(Fecha::new(10, 3, 2025));
+        assert_eq!(nuevas.len(), 1);
+        assert_eq!(nuevas[0].desde, Fecha::new(1, 3, 2025));
+        assert_eq!(
+            sistema
+                .reactivar_usuario("simon", Fecha::new(10, 3, 2025))
+                .unwrap_err(),
+            ErroresApp::UsuarioNoSuspendido
+        );
     }
 
     #[test]
-    fn test_get_historial_usuario_existente_con_varias_suscripciones() {
+    fn test_suspension_despues_de_un_cambio_de_plan() {
         let mut sistema = instanciar_plataforma();
-        let usuario = "usuario_platudo";
-        sistema
-            .crear_usuario(1, usuario, TipoSuscripcion::BASICA, mockear_medio_pago())
-            .unwrap();
+        let credito = TipoMedioPago::CREDITO {
+            nro_cuenta: TARJETA,
+        };
         sistema
-            .subir_suscripcion_a_usuario(usuario, mockear_medio_pago())
+            .crear_usuario_con_fecha(
+                1,
+                "simon",
+                TipoSuscripcion::BASICA,
+                credito,
+                Fecha::new(1, 1, 2025),
+            )
             .unwrap();
+        sistema.avanzar_facturacion(Fecha::new(1, 1, 2025));
         sistema
-            .subir_suscripcion_a_usuario(usuario, mockear_medio_pago())
+            .subir_suscripcion_con_fecha("simon", credito, Fecha::new(16, 1, 2025))
             .unwrap();
 
-        let resultado = sistema.get_historial(usuario);
-        assert!(resultado.is_ok());
-        let informe = resultado.unwrap();
-        assert_eq!(informe.nombre_usuario, usuario);
-        assert!(informe.historial.len() >= 3);
-
-        for i in 1..informe.historial.len() {
-            assert!(
-                !informe.historial[i]
-                    .fecha_inicio
-                    .es_mayor(informe.historial[i - 1].fecha_inicio),
-                "Historial no está ordenado cronológicamente"
-            );
-            println!("{:?}", informe)
+        // La deuda es del plan anterior, pero se suspende el plan en curso
+        let mut rechaza = PasarelaSimulada::new(RespuestaPasarela::Rechazado);
+        for _ in 0..MAX_INTENTOS_COBRO {
+            sistema.cobrar_facturas_pendientes(&mut rechaza);
         }
+        assert!(sistema.esta_suspendido("simon"));
+        let historial = &sistema.suscripciones["simon"];
+        assert!(!historial[0].suspendida);
+        assert!(historial[1].suspendida);
+
+        let mut aprueba = PasarelaSimulada::new(RespuestaPasarela::Aprobado);
+        sistema.cobrar_facturas_pendientes(&mut aprueba);
+        sistema
+            .reactivar_usuario("simon", Fecha::new(20, 1, 2025))
+            .unwrap();
+        assert!(sistema.suscripciones["simon"][1].activa);
     }
 
+    const CATALOGO_JSON: &str = r#"{
+        "planes": [
+            {"tipo": "SUPER", "nombre": "Premium", "orden": 2, "pantallas": 4, "hd": true, "descargas": true,
+             "precios": [{"desde": {"dia": 1, "mes": 1, "ano": 2025}, "precio": 500.0}]},
+            {"tipo": "BASICA", "nombre": "Esencial", "orden": 1, "pantallas": 1, "hd": false, "descargas": false,
+             "precios": [
+                {"desde": {"dia": 1, "mes": 6, "ano": 2025}, "precio": 150.0},
+                {"desde": {"dia": 1, "mes": 1, "ano": 2025}, "precio": 120.0}
+             ]}
+        ]
+    }"#;
+
     #[test]
-    fn test_fecha_es_bisiesto() {
-        let fecha: Fecha = Fecha::new(29, 02, 2024);
-        assert_eq!(true, fecha.es_bisiesto());
+    fn test_catalogo_con_plan_nuevo_y_repetidos() {
+        let json = r#"{
+            "planes": [
+                {"tipo": "BASICA", "nombre": "Esencial", "orden": 1, "pantallas": 1, "hd": false, "descargas": false,
+                 "precios": [{"desde": {"dia": 1, "mes": 1, "ano": 2025}, "precio": 100.0}]},
+                {"tipo": "FAMILIAR_4K", "nombre": "Familiar 4K", "orden": 2, "pantallas": 6, "hd": true, "descargas": true,
+                 "perfiles": 6, "precios": [{"desde": {"dia": 1, "mes": 1, "ano": 2025}, "precio": 450.0}]}
+            ]
+        }"#;
+        let familiar = TipoSuscripcion::new("FAMILIAR_4K").unwrap();
+        let mut sistema = instanciar_plataforma();
+        sistema.cargar_catalogo(CatalogoPlanes::desde_json(json).unwrap());
+        crear_usuario_en_enero(&mut sistema, TipoSuscripcion::BASICA);
+        sistema.avanzar_facturacion(Fecha::new(1, 1, 2025));
+        assert_eq!(
+            sistema
+                .subir_suscripcion_con_fecha("simon", mockear_medio_pago(), Fecha::new(1, 2, 2025))
+                .unwrap(),
+            familiar
+        );
+        let nuevas = sistema.avanzar_facturacion(Fecha::new(1, 2, 2025));
+        assert_eq!(nuevas[0].total(), 450.0);
+        assert_eq!(familiar.to_string(), "FAMILIAR_4K");
 
-        let fecha: Fecha = Fecha::new(29, 02, 2020);
-        assert_eq!(true, fecha.es_bisiesto());
+        assert_eq!(
+            TipoSuscripcion::new("familiar").unwrap_err(),
+            ErroresApp::ConfiguracionInvalida
+        );
+        let tipo_repetido = json.replace("FAMILIAR_4K", "BASICA");
+        assert_eq!(
+            CatalogoPlanes::desde_json(&tipo_repetido).unwrap_err(),
+            ErroresApp::ConfiguracionInvalida
+        );
+        let orden_repetido = json.replace("\"orden\": 2", "\"orden\": 1");
+        assert_eq!(
+            CatalogoPlanes::desde_json(&orden_repetido).unwrap_err(),
+            ErroresApp::ConfiguracionInvalida
+        );
+    }
 
-        let fecha: Fecha = Fecha::new(29, 02, 1900);
-        assert_eq!(false, fecha.es_bisiesto());
+    #[test]
+    fn test_catalogo_desde_json() {
+        let catalogo = CatalogoPlanes::desde_json(CATALOGO_JSON).unwrap();
+        let basica = catalogo.plan(TipoSuscripcion::BASICA).unwrap();
+        assert_eq!(basica.nombre, "Esencial");
+        assert!(!basica.hd);
+        assert_eq!(
+            catalogo.precio_vigente(TipoSuscripcion::BASICA, Fecha::new(31, 5, 2025)),
+            Ok(120.0)
+        );
+        assert_eq!(
+            catalogo.precio_vigente(TipoSuscripcion::BASICA, Fecha::new(1, 6, 2025)),
+            Ok(150.0)
+        );
+        assert_eq!(
+            catalogo.precio_vigente(TipoSuscripcion::CLASICA, Fecha::new(1, 6, 2025)),
+            Err(ErroresApp::PlanInexistente)
+        );
+        // Sin el plan CLASICA, BASICA pasa directo a SUPER
+        assert_eq!(
+            catalogo.superior(TipoSuscripcion::BASICA),
+            Some(TipoSuscripcion::SUPER)
+        );
+        assert_eq!(catalogo.superior(TipoSuscripcion::SUPER), None);
+        assert_eq!(catalogo.inferior(TipoSuscripcion::BASICA), None);
+        assert_eq!(
+            CatalogoPlanes::desde_json("{\"planes\": 3}").unwrap_err(),
+            ErroresApp::ConfiguracionInvalida
+        );
+    }
 
-        let fecha: Fecha = Fecha::new(29, 02, 2021);
-        assert_eq!(false, fecha.es_bisiesto());
+    #[test]
+    fn test_catalogo_por_defecto_respeta_precios_de_lista() {
+        let catalogo = CatalogoPlanes::por_defecto();
+        for (tipo, precio) in [
+            (TipoSuscripcion::BASICA, 100.0),
+            (TipoSuscripcion::CLASICA, 200.0),
+            (TipoSuscripcion::SUPER, 300.0),
+        ] {
+            assert_eq!(catalogo.precio_vigente(tipo, Fecha::hoy()), Ok(precio));
+        }
+        assert_eq!(catalogo.plan(TipoSuscripcion::SUPER).unwrap().pantallas, 4);
     }
 
     #[test]
-    fn test_fecha_es_mayor() {
-        // TEST 29-02-2024 > 28-02-2024
-        let fecha: Fecha = Fecha::new(29, 02, 2024);
-        let otra_fecha: Fecha = Fecha::new(28, 02, 2024);
-        assert_eq!(true, fecha.es_mayor(otra_fecha));
+    fn test_precios_congelados_para_suscriptores_existentes() {
+        let mut sistema = instanciar_plataforma();
+        sistema.cargar_catalogo(CatalogoPlanes::desde_json(CATALOGO_JSON).unwrap());
+        sistema
+            .crear_usuario_con_fecha(
+                1,
+                "antiguo",
+                TipoSuscripcion::BASICA,
+                mockear_medio_pago(),
+                Fecha::new(1, 2, 2025),
+            )
+            .unwrap();
+        sistema
+            .crear_usuario_con_fecha(
+                2,
+                "nuevo",
+                TipoSuscripcion::BASICA,
+                mockear_medio_pago(),
+                Fecha::new(1, 7, 2025),
+            )
+            .unwrap();
 
-        // TEST 01-02-2024 > 28-02-2024
-        let fecha: Fecha = Fecha::new(1, 02, 2024);
-        let otra_fecha: Fecha = Fecha::new(28, 02, 2024);
-        assert_eq!(false, fecha.es_mayor(otra_fecha));
+        sistema.avanzar_facturacion(Fecha::new(1, 7, 2025));
+        let antiguo = sistema.facturas_de("antiguo");
+        assert!(antiguo.iter().all(|f| f.total() == 120.0));
+        assert_eq!(sistema.facturas_de("nuevo")[0].total(), 150.0);
 
-        // TEST 01-02-2024 > 28-02-1985
-        let fecha: Fecha = Fecha::new(1, 02, 2024);
-        let otra_fecha: Fecha = Fecha::new(28, 02, 1985);
-        assert_eq!(true, fecha.es_mayor(otra_fecha));
+        // El cambio de plan toma el precio vigente del plan nuevo
+        sistema
+            .subir_suscripcion_con_fecha("antiguo", mockear_medio_pago(), Fecha::new(1, 8, 2025))
+            .unwrap();
+        let activa = sistema.suscripciones["antiguo"]
+            .iter()
+            .find(|s| s.activa)
+            .unwrap();
+        assert_eq!(activa.tipo_suscripcion, TipoSuscripcion::SUPER);
+        assert_eq!(activa.costo_mensual, 500.0);
 
-        // TEST 01-02-2024 > 28-01-2024
-        let fecha: Fecha = Fecha::new(1, 02, 2024);
-        let otra_fecha: Fecha = Fecha::new(28, 01, 2024);
-        assert_eq!(true, fecha.es_mayor(otra_fecha));
+        assert_eq!(
+            sistema
+                .crear_usuario_con_fecha(
+                    3,
+                    "sin_plan",
+                    TipoSuscripcion::CLASICA,
+                    mockear_medio_pago(),
+                    Fecha::new(1, 7, 2025)
+                )
+                .unwrap_err(),
+            ErroresApp::PlanInexistente
+        );
     }
 
     #[test]
-    fn test_fecha_es_fecha_valida() {
-        let fecha: Fecha = Fecha::new(1, 1, 2025);
-        assert_eq!(true, fecha.es_fecha_valida());
-
-        let fecha: Fecha = Fecha::new(30, 4, 1985);
-        assert_eq!(true, fecha.es_fecha_valida());
-
-        let fecha: Fecha = Fecha::new(31, 12, 2001);
-        assert_eq!(true, fecha.es_fecha_valida());
+    fn test_metricas_de_la_plataforma() {
+        let mut sistema = instanciar_plataforma();
+        let credito = TipoMedioPago::CREDITO {
+            nro_cuenta: TARJETA,
+        };
+        sistema
+            .crear_usuario_con_fecha(
+                1,
+                "ana",
+                TipoSuscripcion::BASICA,
+                credito,
+                Fecha::new(5, 1, 2025),
+            )
+            .unwrap();
+        sistema
+            .crear_usuario_con_fecha(
+                2,
+                "beto",
+                TipoSuscripcion::CLASICA,
+                mockear_medio_pago(),
+                Fecha::new(20, 1, 2025),
+            )
+            .unwrap();
+        sistema
+            .crear_usuario_con_fecha(
+                3,
+                "caro",
+                TipoSuscripcion::SUPER,
+                credito,
+                Fecha::new(1, 2, 2025),
+            )
+            .unwrap();
+        sistema
+            .crear_usuario_con_fecha(
+                4,
+                "dani",
+                TipoSuscripcion::BASICA,
+                credito,
+                Fecha::new(10, 2, 2025),
+            )
+            .unwrap();
+        sistema
+            .subir_suscripcion_con_fecha("ana", credito, Fecha::new(15, 3, 2025))
+            .unwrap();
+        sistema
+            .bajar_suscripcion_con_fecha("caro", credito, Fecha::new(1, 3, 2025), true)
+            .unwrap();
+        sistema
+            .cancelar_suscripcion_con_fecha("beto", Fecha::new(10, 3, 2025))
+            .unwrap();
+        sistema.avanzar_facturacion(Fecha::new(1, 5, 2025));
 
-        let fecha: Fecha = Fecha::new(29, 2, 2024);
-        assert_eq!(true, fecha.es_fecha_valida());
+        // MRR
+        assert_eq!(sistema.mrr(Fecha::new(1, 1, 2025)), 0.0);
+        assert_eq!(sistema.mrr(Fecha::new(25, 1, 2025)), 300.0);
+        assert_eq!(sistema.mrr(Fecha::new(1, 3, 2025)), 700.0);
+        // ana paso a CLASICA, beto se dio de baja y caro bajo a CLASICA en abril
+        assert_eq!(sistema.mrr(Fecha::new(15, 4, 2025)), 500.0);
 
-        let fecha: Fecha = Fecha::new(29, 02, 1900);
-        assert_eq!(false, fecha.es_fecha_valida());
+        // Churn
+        assert_eq!(
+            sistema.churn(Fecha::new(1, 1, 2025), Fecha::new(1, 4, 2025)),
+            None
+        );
+        assert_eq!(
+            sistema.churn(Fecha::new(15, 2, 2025), Fecha::new(15, 4, 2025)),
+            Some(25.0)
+        );
 
-        let fecha: Fecha = Fecha::new(0, 1, 2025);
-        assert_eq!(false, fecha.es_fecha_valida());
+        // Flujos de cambio de plan
+        let flujos = sistema.flujos_de_cambio();
+        assert_eq!(flujos.len(), 2);
+        assert_eq!(flujos[0].desde, TipoSuscripcion::BASICA);
+        assert_eq!(flujos[0].hacia, TipoSuscripcion::CLASICA);
+        assert!(flujos[0].es_mejora);
+        assert_eq!(flujos[1].desde, TipoSuscripcion::SUPER);
+        assert!(!flujos[1].es_mejora);
+
+        // Retencion por cohorte
+        let cohortes = sistema.retencion_por_cohorte(3);
+        assert_eq!(cohortes.len(), 2);
+        assert_eq!((cohortes[0].mes, cohortes[0].usuarios), (1, 2));
+        // beto (enero) se dio de baja en marzo
+        assert_eq!(cohortes[0].retencion, vec![1.0, 1.0, 0.5, 0.5]);
+        assert_eq!(cohortes[1].retencion, vec![1.0, 1.0, 1.0, 1.0]);
+
+        // Participacion de medios de pago
+        let participacion = sistema.participacion_medios_pago(true);
+        assert_eq!(participacion, vec![("CREDITO".to_string(), 100.0)]);
+        let historica = sistema.participacion_medios_pago(false);
+        assert_eq!(historica[0].0, "CREDITO");
+        assert!((historica[1].1 - 100.0 / 6.0).abs() < 1e-9);
+    }
 
-        let fecha: Fecha = Fecha::new(31, 2, 2025);
-        assert_eq!(false, fecha.es_fecha_valida());
+    #[test]
+    fn test_informes_de_historial() {
+        let mut sistema = instanciar_plataforma();
+        let credito = TipoMedioPago::CREDITO {
+            nro_cuenta: TARJETA,
+        };
+        sistema
+            .crear_usuario_con_fecha(
+                7,
+                "ana",
+                TipoSuscripcion::BASICA,
+                credito,
+                Fecha::new(1, 1, 2025),
+            )
+            .unwrap();
+        sistema
+            .crear_usuario_con_fecha(
+                8,
+                "beto, el grande",
+                TipoSuscripcion::SUPER,
+                credito,
+                Fecha::new(1, 3, 2025),
+            )
+            .unwrap();
+        sistema
+            .subir_suscripcion_con_fecha("ana", credito, Fecha::new(1, 2, 2025))
+            .unwrap();
+        sistema
+            .cancelar_suscripcion_con_fecha("ana", Fecha::new(1, 3, 2025))
+            .unwrap();
+        // Vuelve a suscribirse despues de un mes sin servicio
+        sistema
+            .volver_a_suscribir_con_fecha(
+                "ana",
+                TipoSuscripcion::BASICA,
+                credito,
+                Fecha::new(1, 4, 2025),
+            )
+            .unwrap();
 
-        let fecha: Fecha = Fecha::new(31, 4, 2025);
-        assert_eq!(false, fecha.es_fecha_valida());
+        sistema.avanzar_facturacion(Fecha::new(1, 3, 2025));
+        let mut aprueba = PasarelaSimulada::new(RespuestaPasarela::Aprobado);
+        sistema.cobrar_facturas_pendientes(&mut aprueba);
+        sistema.avanzar_facturacion(Fecha::new(1, 5, 2025));
 
-        let fecha: Fecha = Fecha::new(1, 1, 2026);
-        assert_eq!(true, fecha.es_fecha_valida());
-    }
+        // Totales, tiempos y huecos
+        let informe = sistema
+            .get_historial_al("ana", Fecha::new(15, 5, 2025))
+            .unwrap();
 
-    #[test]
-    fn test_fecha_to_string() {
-        let fecha: Fecha = Fecha::new(1, 1, 2025);
-        assert_eq!("1/1/2025".to_string(), fecha.to_string());
-    }
+        // enero y febrero BASICA, el ajuste por pasar a CLASICA todo febrero, abril y mayo BASICA
+        assert_eq!(
+            informe.total_facturado(),
+            100.0 + 100.0 + 100.0 + 100.0 + 100.0
+        );
+        assert_eq!(informe.total_pagado(), 300.0);
+        assert_eq!(
+            informe.dias_por_tipo(),
+            vec![
+                (TipoSuscripcion::BASICA, 31 + 44),
+                (TipoSuscripcion::CLASICA, 28)
+            ]
+        );
+        assert_eq!(
+            informe.huecos(),
+            vec![Hueco {
+                desde: Fecha::new(1, 3, 2025),
+                hasta: Fecha::new(1, 4, 2025),
+                dias: 31
+            }]
+        );
+        let filas = informe.filas();
+        assert_eq!(filas[1].estado, "FINALIZADA");
+        assert_eq!(filas[2].estado, "ACTIVA");
 
-    #[test]
-    fn test_fecha_restar_dias() {
-        let mut fecha: Fecha = Fecha::new(1, 02, 2024);
-        fecha.restar_dias(5);
-        assert_eq!("27/1/2024", fecha.to_string());
+        // Formatos
+        let informe = sistema
+            .get_historial_al("ana", Fecha::new(15, 5, 2025))
+            .unwrap();
 
-        let mut fecha: Fecha = Fecha::new(1, 02, 2024);
-        fecha.restar_dias(600);
-        assert_eq!("11/6/2022", fecha.to_string());
+        let tabla = informe.a_tabla();
+        assert!(tabla.contains("| usuario | tipo    |"));
+        assert!(tabla.contains("Sin suscripcion del 1/3/2025 al 1/4/2025 (31 dias)"));
 
-        let mut fecha: Fecha = Fecha::new(4, 05, 2025);
-        fecha.restar_dias(5498);
-        assert_eq!("15/4/2010", fecha.to_string());
+        let csv = informe.a_csv();
+        let lineas: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lineas[0],
+            "usuario,tipo,desde,hasta,dias,costo_mensual,medio_pago,estado"
+        );
+        assert_eq!(
+            lineas[1],
+            "ana,BASICA,1/1/2025,1/2/2025,31,100.00,CREDITO,FINALIZADA"
+        );
+        assert_eq!(lineas.len(), 4);
+
+        let json: serde_json::Value = serde_json::from_str(&informe.a_json().unwrap()).unwrap();
+        assert_eq!(json["usuario"], "ana");
+        assert_eq!(json["total_pagado"], 300.0);
+        assert_eq!(json["huecos"][0]["dias"], 31);
+        assert_eq!(json["suscripciones"][1]["tipo"], "CLASICA");
+
+        // Informes en lote
+        let resultados = sistema.get_historiales_al(
+            &["ana", "fantasma", "beto, el grande"],
+            Fecha::new(15, 5, 2025),
+        );
+        assert_eq!(
+            resultados[1].as_ref().unwrap_err(),
+            &ErroresApp::UsuarioNoExiste
+        );
 
-        let mut fecha: Fecha = Fecha::new(1, 02, 2024);
-        fecha.restar_dias(0);
-        assert_eq!("1/2/2024", fecha.to_string());
+        let informes: Vec<Informe> = resultados.into_iter().filter_map(|r| r.ok()).collect();
+        let csv = informes_a_csv(&informes);
+        assert_eq!(csv.lines().count(), 5);
+        assert!(csv.contains("\"beto, el grande\",SUPER"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&informes_a_json(&informes).unwrap()).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json[1]["id"], 8);
+        assert!(json[1]["huecos"].as_array().unwrap().is_empty());
+        assert_eq!(informes_a_tabla(&informes).matches("Usuario: ").count(), 2);
     }
 
     #[test]
-    fn test_fecha_sumar_dias() {
-        let mut fecha: Fecha = Fecha::new(1, 02, 2024);
-        fecha.sumar_dias(5);
-        assert_eq!("6/2/2024", fecha.to_string());
+    fn test_volver_a_suscribir() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario_con_fecha(
+                1,
+                "simon",
+                TipoSuscripcion::CLASICA,
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+            )
+            .unwrap();
+        assert_eq!(
+            sistema
+                .volver_a_suscribir_con_fecha(
+                    "simon",
+                    TipoSuscripcion::SUPER,
+                    mockear_medio_pago(),
+                    Fecha::new(1, 2, 2025)
+                )
+                .unwrap_err(),
+            ErroresApp::UsuarioConSuscripcionPropia
+        );
+        sistema
+            .cancelar_suscripcion_con_fecha("simon", Fecha::new(15, 2, 2025))
+            .unwrap();
+        // No puede volver antes de la baja
+        assert_eq!(
+            sistema
+                .volver_a_suscribir_con_fecha(
+                    "simon",
+                    TipoSuscripcion::SUPER,
+                    mockear_medio_pago(),
+                    Fecha::new(10, 2, 2025)
+                )
+                .unwrap_err(),
+            ErroresApp::DatosInvalidos
+        );
+        assert_eq!(
+            sistema.volver_a_suscribir_con_fecha(
+                "simon",
+                TipoSuscripcion::SUPER,
+                mockear_medio_pago(),
+                Fecha::new(1, 3, 2025)
+            ),
+            Ok(TipoSuscripcion::SUPER)
+        );
+        assert!(
+            sistema
+                .suscripcion_activa_al("simon", Fecha::new(15, 3, 2025))
+                .is_ok()
+        );
+        assert_eq!(
+            sistema
+                .volver_a_suscribir_con_fecha(
+                    "nadie",
+                    TipoSuscripcion::SUPER,
+                    mockear_medio_pago(),
+                    Fecha::new(1, 3, 2025)
+                )
+                .unwrap_err(),
+            ErroresApp::UsuarioSinSuscripciones
+        );
+    }
 
-        let mut fecha: Fecha = Fecha::new(4, 05, 2025);
-        fecha.sumar_dias(600);
-        assert_eq!("25/12/2026", fecha.to_string());
+    #[test]
+    fn test_invitar_y_quitar_miembros() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario_con_fecha(
+                1,
+                "titular",
+                TipoSuscripcion::CLASICA,
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+            )
+            .unwrap();
+        for (id, nombre) in [(2, "hija"), (3, "hijo"), (4, "abuela")] {
+            sistema.registrar_usuario(id, nombre).unwrap();
+        }
+        sistema.invitar_miembro("titular", "hija").unwrap();
+        sistema.invitar_miembro("titular", "hijo").unwrap();
+        // CLASICA admite 3 perfiles y las invitaciones pendientes ocupan lugar
+        assert_eq!(
+            sistema.invitar_miembro("titular", "abuela").unwrap_err(),
+            ErroresApp::LimiteDePerfilesAlcanzado
+        );
+        assert_eq!(
+            sistema.aceptar_invitacion("abuela", "titular").unwrap_err(),
+            ErroresApp::InvitacionInexistente
+        );
 
-        let mut fecha: Fecha = Fecha::new(4, 05, 2025);
-        fecha.sumar_dias(5498);
-        assert_eq!("23/5/2040", fecha.to_string());
+        sistema.aceptar_invitacion("hija", "titular").unwrap();
+        assert_eq!(sistema.perfiles_de("titular"), vec!["titular", "hija"]);
+        assert_eq!(sistema.cuenta_de("hija"), Some("titular"));
+        assert_eq!(
+            sistema.invitar_miembro("titular", "fantasma").unwrap_err(),
+            ErroresApp::UsuarioNoExiste
+        );
 
-        let mut fecha: Fecha = Fecha::new(1, 02, 2024);
-        fecha.sumar_dias(0);
-        assert_eq!("1/2/2024", fecha.to_string());
+        sistema.quitar_miembro("titular", "hija").unwrap();
+        assert_eq!(sistema.cuenta_de("hija"), None);
+        assert_eq!(
+            sistema.quitar_miembro("titular", "hija").unwrap_err(),
+            ErroresApp::MiembroInexistente
+        );
     }
-    #[test]
 
-    fn test_mayor_suscripcion_con_varias_suscripciones() {
+    #[test]
+    fn test_invitar_con_plan_individual_o_suscripcion_propia() {
         let mut sistema = instanciar_plataforma();
         sistema
-            .crear_usuario(1, "pepe", TipoSuscripcion::BASICA, mockear_medio_pago())
+            .crear_usuario_con_fecha(
+                1,
+                "titular",
+                TipoSuscripcion::BASICA,
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+            )
             .unwrap();
+        sistema.registrar_usuario(2, "hija").unwrap();
+        assert_eq!(
+            sistema.invitar_miembro("titular", "hija").unwrap_err(),
+            ErroresApp::LimiteDePerfilesAlcanzado
+        );
         sistema
-            .crear_usuario(2, "pipi", TipoSuscripcion::CLASICA, mockear_medio_pago())
+            .crear_usuario_con_fecha(
+                5,
+                "vecino",
+                TipoSuscripcion::SUPER,
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+            )
             .unwrap();
+        assert_eq!(
+            sistema.invitar_miembro("vecino", "titular").unwrap_err(),
+            ErroresApp::UsuarioConSuscripcionPropia
+        );
+    }
+
+    #[test]
+    fn test_factura_compartida_del_titular() {
+        let mut sistema = instanciar_plataforma();
         sistema
-            .crear_usuario(3, "popo", TipoSuscripcion::CLASICA, mockear_medio_pago())
+            .crear_usuario_con_fecha(
+                1,
+                "titular",
+                TipoSuscripcion::SUPER,
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+            )
             .unwrap();
-
-        let resultado = sistema.mayor_suscripcion(false);
-        assert_eq!(resultado.unwrap(), "CLASICA");
+        for (id, nombre) in [(2, "hija"), (3, "hijo")] {
+            sistema.registrar_usuario(id, nombre).unwrap();
+        }
+        for miembro in ["hija", "hijo"] {
+            sistema.invitar_miembro("titular", miembro).unwrap();
+            sistema.aceptar_invitacion(miembro, "titular").unwrap();
+        }
+        let facturas = sistema.avanzar_facturacion(Fecha::new(1, 1, 2025));
+        assert_eq!(facturas.len(), 1);
+        assert_eq!(facturas[0].nombre_usuario, "titular");
+        assert_eq!(facturas[0].lineas[1].concepto, "Perfil hija");
+        assert_eq!(facturas[0].total(), 300.0);
     }
 
     #[test]
-    fn test_mayor_suscripcion_sin_suscripciones() {
-        let sistema = instanciar_plataforma();
-        let resultado = sistema.mayor_suscripcion(false);
-        assert_eq!(resultado.unwrap_err(), ErroresApp::SinSuscripciones);
+    fn test_limite_de_pantallas_simultaneas() {
+        let mut sistema = instanciar_plataforma();
+        sistema
+            .crear_usuario_con_fecha(
+                1,
+                "titular",
+                TipoSuscripcion::CLASICA,
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+            )
+            .unwrap();
+        sistema.registrar_usuario(2, "hija").unwrap();
+        sistema.invitar_miembro("titular", "hija").unwrap();
+        sistema.aceptar_invitacion("hija", "titular").unwrap();
+        let hoy = Fecha::new(10, 1, 2025);
+
+        let tv = sistema.iniciar_sesion("titular", "tv", hoy).unwrap();
+        sistema.iniciar_sesion("hija", "celular", hoy).unwrap();
+        assert_eq!(
+            sistema
+                .iniciar_sesion("titular", "notebook", hoy)
+                .unwrap_err(),
+            ErroresApp::LimiteDePantallasAlcanzado
+        );
+        sistema.cerrar_sesion(tv).unwrap();
+        sistema.iniciar_sesion("titular", "notebook", hoy).unwrap();
+        assert_eq!(
+            sistema.cerrar_sesion(tv).unwrap_err(),
+            ErroresApp::SesionInexistente
+        );
+
+        // Al quitar un miembro se cierran sus sesiones
+        sistema.quitar_miembro("titular", "hija").unwrap();
+        assert_eq!(sistema.sesiones_de_cuenta("titular").len(), 1);
+        assert_eq!(
+            sistema.iniciar_sesion("hija", "celular", hoy).unwrap_err(),
+            ErroresApp::UsuarioNoExiste
+        );
+        // Fuera de la vigencia del plan no se puede ver nada
+        assert_eq!(
+            sistema
+                .iniciar_sesion("titular", "tv", Fecha::new(1, 1, 2026))
+                .unwrap_err(),
+            ErroresApp::UsuarioSinSuscripcionActiva
+        );
     }
 
     #[test]
-    fn test_mayor_suscripcion_activa_con_suscripciones_activas() {
+    fn test_bajar_de_plan_respeta_perfiles_y_pantallas() {
         let mut sistema = instanciar_plataforma();
         sistema
-            .crear_usuario(1, "pepe", TipoSuscripcion::BASICA, mockear_medio_pago())
+            .crear_usuario_con_fecha(
+                1,
+                "titular",
+                TipoSuscripcion::SUPER,
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+            )
             .unwrap();
+        for (id, nombre) in [(2, "hija"), (3, "hijo"), (4, "abuela")] {
+            sistema.registrar_usuario(id, nombre).unwrap();
+        }
+        for miembro in ["hija", "hijo", "abuela"] {
+            sistema.invitar_miembro("titular", miembro).unwrap();
+            sistema.aceptar_invitacion(miembro, "titular").unwrap();
+        }
+        let hoy = Fecha::new(10, 1, 2025);
+        for perfil in ["titular", "hija", "hijo", "abuela"] {
+            sistema.iniciar_sesion(perfil, "tv", hoy).unwrap();
+        }
+        sistema.avanzar_facturacion(hoy);
+
+        // CLASICA admite 3 perfiles y 2 pantallas: salen los ultimos en entrar
         sistema
-            .crear_usuario(2, "pipi", TipoSuscripcion::CLASICA, mockear_medio_pago())
+            .bajar_suscripcion_con_fecha("titular", mockear_medio_pago(), hoy, false)
             .unwrap();
+        assert_eq!(
+            sistema.perfiles_de("titular"),
+            vec!["titular", "hija", "hijo"]
+        );
+        assert_eq!(sistema.cuenta_de("abuela"), None);
+        let perfiles: Vec<&str> = sistema
+            .sesiones_de_cuenta("titular")
+            .iter()
+            .map(|s| s.perfil)
+            .collect();
+        assert_eq!(perfiles, vec!["titular", "hija"]);
+
+        // La baja programada se aplica al facturar el periodo siguiente
         sistema
-            .crear_usuario(3, "popo", TipoSuscripcion::CLASICA, mockear_medio_pago())
+            .bajar_suscripcion_con_fecha("titular", mockear_medio_pago(), hoy, true)
             .unwrap();
-
-        let resultado = sistema.mayor_suscripcion(true);
-        assert_eq!(resultado.unwrap(), "CLASICA");
+        sistema.avanzar_facturacion(Fecha::new(1, 2, 2025));
+        assert_eq!(sistema.perfiles_de("titular"), vec!["titular"]);
+        assert_eq!(sistema.sesiones_de_cuenta("titular").len(), 1);
     }
 
     #[test]
-    fn test_mayor_suscripcion_activa_sin_suscripciones_activas() {
+    fn test_miembro_con_suscripcion_propia_deja_la_cuenta() {
         let mut sistema = instanciar_plataforma();
         sistema
-            .crear_usuario(1, "pepe", TipoSuscripcion::BASICA, mockear_medio_pago())
+            .crear_usuario_con_fecha(
+                1,
+                "titular",
+                TipoSuscripcion::CLASICA,
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+            )
             .unwrap();
+        sistema.registrar_usuario(2, "hija").unwrap();
+        sistema.invitar_miembro("titular", "hija").unwrap();
+        sistema.aceptar_invitacion("hija", "titular").unwrap();
         sistema
-            .crear_usuario(2, "pipi", TipoSuscripcion::CLASICA, mockear_medio_pago())
+            .iniciar_sesion("hija", "celular", Fecha::new(10, 1, 2025))
             .unwrap();
 
-        // Cancelar todas las suscripciones activas
-        for usuario in &["pepe", "pipi"] {
-            sistema.cancelar_suscripcion_a_usuario(usuario).unwrap();
-        }
-
-        let resultado = sistema.mayor_suscripcion(true);
-        assert_eq!(resultado.unwrap_err(), ErroresApp::SinSuscripcionesActivas);
+        sistema
+            .crear_usuario_con_fecha(
+                2,
+                "hija",
+                TipoSuscripcion::BASICA,
+                mockear_medio_pago(),
+                Fecha::new(15, 1, 2025),
+            )
+            .unwrap();
+        assert_eq!(sistema.cuenta_de("hija"), Some("hija"));
+        assert_eq!(sistema.perfiles_de("titular"), vec!["titular"]);
+        assert!(sistema.sesiones_de_cuenta("titular").is_empty());
     }
 
     #[test]
-    fn test_mayor_medio_pago_con_suscripciones() {
+    fn test_sesiones_vencen_y_no_sobreviven_al_plan() {
         let mut sistema = instanciar_plataforma();
         sistema
-            .crear_usuario(
+            .crear_usuario_con_fecha(
                 1,
-                "elber_galarga",
+                "titular",
                 TipoSuscripcion::BASICA,
-                TipoMedioPago::EFECTIVO(100.0),
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
             )
             .unwrap();
+        sistema.registrar_usuario(2, "hija").unwrap();
         sistema
-            .crear_usuario(
-                2,
-                "elber_galarga2",
-                TipoSuscripcion::CLASICA,
-                TipoMedioPago::CREDITO { nro_cuenta: "123" },
-            )
+            .iniciar_sesion("titular", "tv", Fecha::new(10, 1, 2025))
+            .unwrap();
+        assert_eq!(
+            sistema
+                .iniciar_sesion("titular", "notebook", Fecha::new(20, 1, 2025))
+                .unwrap_err(),
+            ErroresApp::LimiteDePantallasAlcanzado
+        );
+        // A los 30 dias la sesion vence y libera la pantalla
+        let notebook = sistema
+            .iniciar_sesion("titular", "notebook", Fecha::new(9, 2, 2025))
             .unwrap();
+        assert_eq!(sistema.sesiones_de_cuenta("titular")[0].id, notebook);
+
         sistema
-            .crear_usuario(
-                3,
-                "elber_galarga3",
-                TipoSuscripcion::SUPER,
-                TipoMedioPago::CREDITO { nro_cuenta: "456" },
-            )
+            .cancelar_suscripcion_con_fecha("titular", Fecha::new(15, 2, 2025))
             .unwrap();
+        sistema.avanzar_facturacion(Fecha::new(15, 2, 2025));
+        assert!(sistema.sesiones_de_cuenta("titular").is_empty());
+    }
 
-        let resultado = sistema.mayor_medio_pago(false);
-        assert_eq!(resultado.unwrap(), "CREDITO");
+    #[test]
+    fn test_canjear_codigo_promocional() {
+        let mut sistema = instanciar_plataforma();
+        let promo = CodigoPromocional::new(
+            "verano",
+            TipoDescuento::Porcentaje(50.0),
+            2,
+            Fecha::new(1, 1, 2025),
+            Fecha::new(31, 3, 2025),
+        )
+        .con_limite_de_usos(2);
+        sistema.crear_codigo_promocional(promo).unwrap();
+        for (id, nombre) in [(1, "ana"), (2, "beto"), (3, "caro")] {
+            sistema
+                .crear_usuario_con_fecha(
+                    id,
+                    nombre,
+                    TipoSuscripcion::CLASICA,
+                    mockear_medio_pago(),
+                    Fecha::new(1, 1, 2025),
+                )
+                .unwrap();
+        }
+        sistema
+            .canjear_codigo("ana", "VERANO", Fecha::new(1, 1, 2025))
+            .unwrap();
+        let facturas = sistema.avanzar_facturacion(Fecha::new(1, 3, 2025));
+        let de_ana: Vec<&Factura> = facturas
+            .iter()
+            .filter(|f| f.nombre_usuario == "ana")
+            .collect();
+        // Dos meses a mitad de precio y despues precio completo
+        assert_eq!(de_ana[0].lineas[1].concepto, "Promocion VERANO");
+        assert_eq!(de_ana[0].total(), 100.0);
+        assert_eq!(de_ana[1].total(), 100.0);
+        assert_eq!(de_ana[2].total(), 200.0);
+
+        let informe = sistema
+            .get_historial_al("ana", Fecha::new(15, 3, 2025))
+            .unwrap();
+        assert_eq!(informe.total_descuentos(), 200.0);
+        assert!(informe.a_tabla().contains("Descuentos: 200.00"));
     }
 
     #[test]
-    fn test_mayor_medio_pago_sin_suscripciones() {
-        let sistema = instanciar_plataforma();
-        let resultado = sistema.mayor_medio_pago(false);
-        assert_eq!(resultado.unwrap_err(), ErroresApp::SinSuscripciones);
+    fn test_restricciones_de_codigo_promocional() {
+        let mut sistema = instanciar_plataforma();
+        let promo = CodigoPromocional::new(
+            "verano",
+            TipoDescuento::Porcentaje(50.0),
+            2,
+            Fecha::new(1, 1, 2025),
+            Fecha::new(31, 3, 2025),
+        )
+        .con_limite_de_usos(2);
+        sistema.crear_codigo_promocional(promo).unwrap();
+        for (id, nombre) in [(1, "ana"), (2, "beto"), (3, "caro")] {
+            sistema
+                .crear_usuario_con_fecha(
+                    id,
+                    nombre,
+                    TipoSuscripcion::CLASICA,
+                    mockear_medio_pago(),
+                    Fecha::new(1, 1, 2025),
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            sistema
+                .canjear_codigo("ana", "otono", Fecha::new(1, 1, 2025))
+                .unwrap_err(),
+            ErroresApp::CodigoInexistente
+        );
+        assert_eq!(
+            sistema
+                .canjear_codigo("ana", "verano", Fecha::new(1, 4, 2025))
+                .unwrap_err(),
+            ErroresApp::CodigoFueraDeVigencia
+        );
+        sistema
+            .canjear_codigo("ana", "verano", Fecha::new(1, 2, 2025))
+            .unwrap();
+        assert_eq!(
+            sistema
+                .canjear_codigo("ana", "verano", Fecha::new(2, 2, 2025))
+                .unwrap_err(),
+            ErroresApp::CodigoYaCanjeado
+        );
+        sistema
+            .canjear_codigo("beto", "verano", Fecha::new(1, 2, 2025))
+            .unwrap();
+        assert_eq!(
+            sistema
+                .canjear_codigo("caro", "verano", Fecha::new(1, 2, 2025))
+                .unwrap_err(),
+            ErroresApp::CodigoAgotado
+        );
+
+        let fijo = CodigoPromocional::new(
+            "MIL",
+            TipoDescuento::Fijo(1000.0),
+            1,
+            Fecha::new(1, 1, 2025),
+            Fecha::new(1, 1, 2026),
+        );
+        sistema.crear_codigo_promocional(fijo.clone()).unwrap();
+        assert_eq!(
+            sistema.crear_codigo_promocional(fijo).unwrap_err(),
+            ErroresApp::CodigoExistente
+        );
+        let sin_meses = CodigoPromocional::new(
+            "NADA",
+            TipoDescuento::Fijo(10.0),
+            0,
+            Fecha::new(1, 1, 2025),
+            Fecha::new(1, 1, 2026),
+        );
+        assert_eq!(
+            sistema.crear_codigo_promocional(sin_meses).unwrap_err(),
+            ErroresApp::DatosInvalidos
+        );
+        assert_eq!(
+            sistema
+                .canjear_codigo("ana", "mil", Fecha::new(1, 2, 2025))
+                .unwrap_err(),
+            ErroresApp::PromocionEnCurso
+        );
+        // El descuento fijo no deja la factura en negativo
+        sistema
+            .canjear_codigo("caro", "mil", Fecha::new(1, 2, 2025))
+            .unwrap();
+        let facturas = sistema.avanzar_facturacion(Fecha::new(1, 1, 2025));
+        let de_caro = facturas
+            .iter()
+            .find(|f| f.nombre_usuario == "caro")
+            .unwrap();
+        assert_eq!(de_caro.total(), 0.0);
     }
 
     #[test]
-    fn test_mayor_medio_pago_activo_con_suscripciones_activas() {
+    fn test_prueba_gratis_que_convierte() {
         let mut sistema = instanciar_plataforma();
         sistema
-            .crear_usuario(
+            .iniciar_prueba(
                 1,
-                "elber_galarga",
-                TipoSuscripcion::BASICA,
-                TipoMedioPago::EFECTIVO(100.0),
+                "ana",
+                TipoSuscripcion::SUPER,
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+                PeriodoPrueba::new(1, true),
             )
             .unwrap();
-        sistema
-            .crear_usuario(
-                2,
-                "elber_galarga2",
-                TipoSuscripcion::CLASICA,
-                TipoMedioPago::CREDITO { nro_cuenta: "123" },
-            )
+        let facturas = sistema.avanzar_facturacion(Fecha::new(1, 2, 2025));
+        assert_eq!(facturas.len(), 2);
+        assert_eq!(facturas[0].lineas[0].concepto, "Prueba gratis SUPER (1/1)");
+        assert_eq!(facturas[0].total(), 0.0);
+        assert_eq!(facturas[1].total(), 300.0);
+
+        let informe = sistema
+            .get_historial_al("ana", Fecha::new(2, 2, 2025))
             .unwrap();
+        let filas = informe.filas();
+        assert_eq!(filas[0].estado, "PRUEBA");
+        assert_eq!(filas[1].estado, "ACTIVA");
+    }
+
+    #[test]
+    fn test_prueba_sin_precio_al_convertir() {
+        let solo_basica = r#"{
+            "planes": [
+                {"tipo": "BASICA", "nombre": "Esencial", "orden": 1, "pantallas": 1, "hd": false, "descargas": false,
+                 "precios": [{"desde": {"dia": 1, "mes": 1, "ano": 2025}, "precio": 100.0}]}
+            ]
+        }"#;
+        let mut sistema = instanciar_plataforma();
         sistema
-            .crear_usuario(
-                3,
-                "elber_galarga3",
+            .iniciar_prueba(
+                1,
+                "ana",
                 TipoSuscripcion::SUPER,
-                TipoMedioPago::CREDITO { nro_cuenta: "456" },
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+                PeriodoPrueba::new(1, true),
             )
             .unwrap();
+        sistema.cargar_catalogo(CatalogoPlanes::desde_json(solo_basica).unwrap());
+
+        // El plan se retiro durante la prueba: vence en lugar de facturarse gratis
+        let facturas = sistema.avanzar_facturacion(Fecha::new(1, 3, 2025));
+        assert_eq!(facturas.len(), 1);
+        assert!(facturas[0].lineas[0].concepto.starts_with("Prueba gratis"));
+        assert!(
+            sistema
+                .suscripcion_activa_al("ana", Fecha::new(1, 3, 2025))
+                .is_err()
+        );
 
-        let resultado = sistema.mayor_medio_pago(true);
-        assert_eq!(resultado.unwrap(), "CREDITO");
+        assert_eq!(
+            sistema
+                .iniciar_prueba(
+                    2,
+                    "leo",
+                    TipoSuscripcion::SUPER,
+                    mockear_medio_pago(),
+                    Fecha::new(1, 3, 2025),
+                    PeriodoPrueba::new(1, true),
+                )
+                .unwrap_err(),
+            ErroresApp::PlanInexistente
+        );
     }
 
     #[test]
-    fn test_mayor_medio_pago_activo_sin_suscripciones_activas() {
+    fn test_prueba_gratis_que_vence() {
         let mut sistema = instanciar_plataforma();
         sistema
-            .crear_usuario(
+            .iniciar_prueba(
                 1,
-                "elber_galarga",
+                "ana",
                 TipoSuscripcion::BASICA,
-                TipoMedioPago::EFECTIVO(100.0),
+                mockear_medio_pago(),
+                Fecha::new(1, 1, 2025),
+                PeriodoPrueba::new(2, false),
             )
             .unwrap();
+        // Durante la prueba no se aplican promociones
+        let promo = CodigoPromocional::new(
+            "HOLA",
+            TipoDescuento::Fijo(10.0),
+            3,
+            Fecha::new(1, 1, 2025),
+            Fecha::new(1, 1, 2026),
+        );
+        sistema.crear_codigo_promocional(promo).unwrap();
         sistema
-            .crear_usuario(
-                2,
-                "elber_galarga2",
-                TipoSuscripcion::CLASICA,
-                TipoMedioPago::CREDITO { nro_cuenta: "123" },
-            )
+            .canjear_codigo("ana", "hola", Fecha::new(1, 1, 2025))
             .unwrap();
 
-        // Cancelar todas las suscripciones activas
-        for usuario in &["elber_galarga", "elber_galarga2"] {
-            sistema.cancelar_suscripcion_a_usuario(usuario).unwrap();
-        }
-
-        let resultado = sistema.mayor_medio_pago(true);
-        assert_eq!(resultado.unwrap_err(), ErroresApp::SinSuscripcionesActivas);
+        let facturas = sistema.avanzar_facturacion(Fecha::new(1, 4, 2025));
+        assert_eq!(facturas.len(), 2);
+        assert!(
+            facturas
+                .iter()
+                .all(|f| f.total() == 0.0 && f.lineas.len() == 1)
+        );
+        assert_eq!(sistema.mrr(Fecha::new(15, 1, 2025)), 0.0);
+        assert!(!sistema.suscripciones["ana"].iter().any(|s| s.activa));
+        // Al vencer la prueba el descuento sin usar se descarta y el codigo recupera el uso
+        assert!(sistema.descuentos_activos.is_empty());
+        assert!(sistema.canjes.is_empty());
+        assert_eq!(sistema.promociones["HOLA"].usos, 0);
+        assert_eq!(
+            sistema
+                .iniciar_prueba(
+                    2,
+                    "beto",
+                    TipoSuscripcion::BASICA,
+                    mockear_medio_pago(),
+                    Fecha::new(1, 1, 2025),
+                    PeriodoPrueba::new(0, true),
+                )
+                .unwrap_err(),
+            ErroresApp::DatosInvalidos
+        );
     }
 }